@@ -0,0 +1,512 @@
+//! Streaming anomaly detection over behavior attestations
+//!
+//! For every (target, epoch) the detector keeps the latest report of each
+//! prober and takes the component-wise median of their metrics as the
+//! robust consensus. A prober's residual is its signed distance from that
+//! consensus, computed as for prober trust (see
+//! [`terrain_gossip_belief::residual`]). Probers that sit far from
+//! consensus across several targets are flagged as liar sensors; pairs
+//! whose residuals point the same way across shared targets are linked,
+//! and linked probers form a suspected poisoning cluster.
+
+use parking_lot::Mutex;
+use std::collections::{HashMap, HashSet, VecDeque};
+use terrain_gossip_belief::residual::{self, components, CorrelationConfig, COMPONENTS};
+use terrain_gossip_core::types::*;
+
+type Prober = Vec<u8>;
+type GroupKey = (TargetRef, u64);
+
+/// Detection thresholds
+#[derive(Debug, Clone)]
+pub struct AnomalyConfig {
+    /// Mean deviation from consensus that flags a prober
+    pub deviation_threshold: f64,
+    /// Residual correlation that links two probers
+    pub correlation_threshold: f64,
+    /// Reports needed on a target before consensus is taken
+    pub min_probers: usize,
+    /// Targets a prober must report on before it is judged
+    pub min_samples: usize,
+    /// Targets two probers must share before they are compared
+    pub min_shared: usize,
+    /// Epochs of reports kept
+    pub window_epochs: u64,
+    /// Recent alerts kept for inspection
+    pub max_alerts: usize,
+}
+
+impl Default for AnomalyConfig {
+    fn default() -> Self {
+        Self {
+            deviation_threshold: 0.3,
+            correlation_threshold: 0.9,
+            min_probers: 3,
+            min_samples: 3,
+            min_shared: 3,
+            window_epochs: 24,
+            max_alerts: 256,
+        }
+    }
+}
+
+impl AnomalyConfig {
+    /// Thresholds taken from a world's rule bundle
+    ///
+    /// A median of fewer than three reports cannot single out a liar, so
+    /// `min_diverse_probers` is raised to three.
+    pub fn from_rule_bundle(bundle: &RuleBundle) -> Self {
+        Self {
+            deviation_threshold: bundle.disagreement_quarantine_threshold,
+            min_probers: (bundle.min_diverse_probers as usize).max(3),
+            ..Self::default()
+        }
+    }
+}
+
+/// What an alert is about
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AnomalyKind {
+    /// One prober persistently far from consensus
+    Deviation,
+    /// Probers whose deviations move together
+    Correlation,
+}
+
+/// A local anomaly alert
+#[derive(Debug, Clone)]
+pub struct AnomalyAlert {
+    pub kind: AnomalyKind,
+    /// Flagged probers' transport keys
+    pub probers: Vec<Vec<u8>>,
+    /// Mean deviation, or residual correlation for a cluster
+    pub score: f64,
+    /// Targets the score is based on
+    pub samples: usize,
+    /// Epoch of the cited reports
+    pub epoch_id: u64,
+    /// Flagged report
+    pub event_a: EventId,
+    /// Report it is contrasted with
+    pub event_b: EventId,
+}
+
+impl AnomalyAlert {
+    /// Human-readable reason, used in disputes
+    pub fn reason(&self) -> String {
+        match self.kind {
+            AnomalyKind::Deviation => format!(
+                "prober deviates from consensus: mean deviation {:.3} over {} targets",
+                self.score, self.samples
+            ),
+            AnomalyKind::Correlation => format!(
+                "correlated probers ({} in cluster): residual correlation {:.3} over {} targets",
+                self.probers.len(),
+                self.score,
+                self.samples
+            ),
+        }
+    }
+
+    /// Unsigned dispute citing the alert's evidence
+    pub fn to_dispute(&self, world: WorldId) -> EventBody {
+        EventBody::Dispute(DisputeEvent {
+            world,
+            epoch_id: self.epoch_id,
+            event_a: self.event_a,
+            event_b: self.event_b,
+            reason: self.reason(),
+            disputer_transport_pubkey: vec![],
+            signature: vec![],
+        })
+    }
+}
+
+struct Report {
+    prober: Prober,
+    event_id: EventId,
+    values: [f64; COMPONENTS],
+}
+
+/// Latest report of each prober on one (target, epoch)
+#[derive(Default)]
+struct Group {
+    reports: Vec<Report>,
+}
+
+impl Group {
+    fn residuals(&self) -> Vec<(Prober, Residual)> {
+        let values: Vec<_> = self.reports.iter().map(|r| r.values).collect();
+        self.reports
+            .iter()
+            .zip(residual::residuals(&values))
+            .map(|(report, signed)| {
+                let residual = Residual {
+                    event_id: report.event_id,
+                    signed,
+                    deviation: residual::deviation(&signed),
+                };
+                (report.prober.clone(), residual)
+            })
+            .collect()
+    }
+}
+
+struct Residual {
+    event_id: EventId,
+    signed: residual::Residual,
+    /// Largest component distance from consensus
+    deviation: f64,
+}
+
+#[derive(Default)]
+struct State {
+    groups: HashMap<GroupKey, Group>,
+    residuals: HashMap<Prober, HashMap<GroupKey, Residual>>,
+    /// Probers with an outstanding deviation alert
+    flagged: HashSet<Prober>,
+    /// Correlated prober pairs, smaller key first
+    linked: HashSet<(Prober, Prober)>,
+    alerts: VecDeque<AnomalyAlert>,
+}
+
+impl State {
+    fn mean_deviation(&self, prober: &Prober) -> Option<(f64, usize)> {
+        let records = self.residuals.get(prober)?;
+        if records.is_empty() {
+            return None;
+        }
+        let total: f64 = records.values().map(|r| r.deviation).sum();
+        Some((total / records.len() as f64, records.len()))
+    }
+
+    fn check_deviation(&mut self, prober: &Prober, config: &AnomalyConfig) -> Option<AnomalyAlert> {
+        let (mean, samples) = self.mean_deviation(prober)?;
+        if mean < config.deviation_threshold / 2.0 {
+            // Back in line: a later relapse alerts again
+            self.flagged.remove(prober);
+        }
+        if samples < config.min_samples || mean < config.deviation_threshold {
+            return None;
+        }
+        if self.flagged.contains(prober) {
+            return None;
+        }
+
+        let records = self.residuals.get(prober)?;
+        let (key, worst) = records
+            .iter()
+            .max_by(|a, b| a.1.deviation.total_cmp(&b.1.deviation))?;
+        let closest = self
+            .groups
+            .get(key)?
+            .reports
+            .iter()
+            .filter(|r| &r.prober != prober)
+            .filter_map(|r| Some((r, self.residuals.get(&r.prober)?.get(key)?.deviation)))
+            .min_by(|a, b| a.1.total_cmp(&b.1))?
+            .0;
+        let alert = AnomalyAlert {
+            kind: AnomalyKind::Deviation,
+            probers: vec![prober.clone()],
+            score: mean,
+            samples,
+            epoch_id: key.1,
+            event_a: worst.event_id,
+            event_b: closest.event_id,
+        };
+        self.flagged.insert(prober.clone());
+        Some(alert)
+    }
+
+    fn check_correlation(&mut self, prober: &Prober, config: &AnomalyConfig) -> Vec<AnomalyAlert> {
+        let Some(mine) = self.residuals.get(prober) else {
+            return Vec::new();
+        };
+        let linking = CorrelationConfig {
+            threshold: config.correlation_threshold,
+            min_shared: config.min_shared,
+            noise_floor: config.deviation_threshold / 2.0,
+        };
+
+        let mut found = Vec::new();
+        for (other, theirs) in &self.residuals {
+            if other == prober || self.linked.contains(&ordered(prober, other)) {
+                continue;
+            }
+            let shared: Vec<&GroupKey> = mine.keys().filter(|k| theirs.contains_key(*k)).collect();
+            let signed: Vec<_> = shared
+                .iter()
+                .map(|k| (mine[*k].signed, theirs[*k].signed))
+                .collect();
+            let Some(correlation) = linking.correlation(&signed) else {
+                continue;
+            };
+
+            let evidence = shared
+                .iter()
+                .max_by(|x, y| {
+                    let score = |k: &GroupKey| mine[k].deviation + theirs[k].deviation;
+                    score(x).total_cmp(&score(y))
+                })
+                .expect("shared is not empty");
+            found.push((
+                other.clone(),
+                correlation,
+                shared.len(),
+                evidence.1,
+                mine[*evidence].event_id,
+                theirs[*evidence].event_id,
+            ));
+        }
+
+        found
+            .into_iter()
+            .map(
+                |(other, correlation, samples, epoch_id, event_a, event_b)| {
+                    self.linked.insert(ordered(prober, &other));
+                    AnomalyAlert {
+                        kind: AnomalyKind::Correlation,
+                        probers: self.cluster(prober),
+                        score: correlation,
+                        samples,
+                        epoch_id,
+                        event_a,
+                        event_b,
+                    }
+                },
+            )
+            .collect()
+    }
+
+    /// Probers connected to `prober` by correlation links, sorted
+    fn cluster(&self, prober: &Prober) -> Vec<Prober> {
+        self.clusters()
+            .into_iter()
+            .find(|members| members.contains(prober))
+            .unwrap_or_else(|| vec![prober.clone()])
+    }
+
+    /// Clusters of correlated probers, each sorted, in order
+    fn clusters(&self) -> Vec<Vec<Prober>> {
+        let links: Vec<(Prober, Prober)> = self.linked.iter().cloned().collect();
+        residual::clusters(&links)
+    }
+}
+
+fn ordered(a: &Prober, b: &Prober) -> (Prober, Prober) {
+    if a <= b {
+        (a.clone(), b.clone())
+    } else {
+        (b.clone(), a.clone())
+    }
+}
+
+/// Per-prober residual tracking and correlation clustering
+pub struct AnomalyDetector {
+    config: AnomalyConfig,
+    state: Mutex<State>,
+}
+
+impl AnomalyDetector {
+    /// Create a detector
+    pub fn new(config: AnomalyConfig) -> Self {
+        Self {
+            config,
+            state: Mutex::new(State::default()),
+        }
+    }
+
+    /// Detection thresholds
+    pub fn config(&self) -> &AnomalyConfig {
+        &self.config
+    }
+
+    /// Ingest an event, returning any new alerts
+    ///
+    /// Only attestations are considered. A prober's later report on the
+    /// same target and epoch replaces its earlier one.
+    pub fn observe(&self, event: &Event) -> Vec<AnomalyAlert> {
+        let EventBody::Attestation(attestation) = &event.body else {
+            return Vec::new();
+        };
+        let key = (attestation.target_ref, attestation.epoch_id);
+        let prober = attestation.prober_transport_pubkey.clone();
+        let report = Report {
+            prober: prober.clone(),
+            event_id: event.event_id,
+            values: components(&attestation.metrics),
+        };
+
+        let mut state = self.state.lock();
+        let group = state.groups.entry(key).or_default();
+        match group.reports.iter_mut().find(|r| r.prober == prober) {
+            Some(existing) => *existing = report,
+            None => group.reports.push(report),
+        }
+        if group.reports.len() < self.config.min_probers {
+            return Vec::new();
+        }
+
+        // The median moved, so every member's residual on this target did
+        let residuals = group.residuals();
+        let members: Vec<Prober> = residuals.iter().map(|(p, _)| p.clone()).collect();
+        for (member, residual) in residuals {
+            state
+                .residuals
+                .entry(member)
+                .or_default()
+                .insert(key, residual);
+        }
+
+        let mut alerts: Vec<AnomalyAlert> = members
+            .iter()
+            .filter_map(|member| state.check_deviation(member, &self.config))
+            .collect();
+        alerts.extend(state.check_correlation(&prober, &self.config));
+
+        for alert in &alerts {
+            if state.alerts.len() >= self.config.max_alerts {
+                state.alerts.pop_front();
+            }
+            state.alerts.push_back(alert.clone());
+        }
+        alerts
+    }
+
+    /// Mean deviation of a prober and the number of targets it covers
+    pub fn deviation(&self, prober: &[u8]) -> Option<(f64, usize)> {
+        self.state.lock().mean_deviation(&prober.to_vec())
+    }
+
+    /// Clusters of correlated probers
+    pub fn clusters(&self) -> Vec<Vec<Vec<u8>>> {
+        self.state.lock().clusters()
+    }
+
+    /// Most recent alerts, oldest first
+    pub fn recent_alerts(&self) -> Vec<AnomalyAlert> {
+        self.state.lock().alerts.iter().cloned().collect()
+    }
+
+    /// Forget reports older than the window ending at `current_epoch`
+    pub fn prune(&self, current_epoch: u64) {
+        let oldest = current_epoch.saturating_sub(self.config.window_epochs);
+        let mut state = self.state.lock();
+        state.groups.retain(|key, _| key.1 >= oldest);
+        state.residuals.retain(|_, records| {
+            records.retain(|key, _| key.1 >= oldest);
+            !records.is_empty()
+        });
+        let State {
+            residuals,
+            flagged,
+            linked,
+            ..
+        } = &mut *state;
+        flagged.retain(|p| residuals.contains_key(p));
+        linked.retain(|(a, b)| residuals.contains_key(a) && residuals.contains_key(b));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn attestation(id: u8, prober: u8, target: u8, success: f64, fidelity: f64) -> Event {
+        let mut event_id = [0; 32];
+        event_id[0] = id;
+        event_id[1] = prober;
+        event_id[2] = target;
+        Event {
+            event_id: EventId(event_id),
+            world: WorldId([0; 32]),
+            epoch_id: 1,
+            event_type: EventType::Attestation,
+            body: EventBody::Attestation(BehaviorAttestation {
+                attestation_id: AttestationId(event_id),
+                world: WorldId([0; 32]),
+                epoch_id: 1,
+                challenge_id: ChallengeId([id; 32]),
+                target_ref: TargetRef([target; 32]),
+                target_fah: None,
+                metrics: MetricsVector {
+                    success_rate: success,
+                    refusal_consistency: 0.9,
+                    tool_fidelity: fidelity,
+                    latency_p50_ms: 200,
+                    latency_p95_ms: 400,
+                    robustness_score: 0.8,
+                    drift_indicator: 0.1,
+                    freshness: FreshnessStrength::None,
+                },
+                evidence_commitment: [0; 32],
+                freshness_anchor: None,
+                prober_transport_pubkey: vec![prober],
+                signature: vec![],
+            }),
+        }
+    }
+
+    /// Three honest probers agree on every target
+    fn honest(detector: &AnomalyDetector, target: u8) {
+        for (prober, jitter) in [(1, 0.0), (2, 0.02), (3, -0.02)] {
+            detector.observe(&attestation(1, prober, target, 0.9 + jitter, 0.85 - jitter));
+        }
+    }
+
+    #[test]
+    fn test_liar_sensor_flagged() {
+        let detector = AnomalyDetector::new(AnomalyConfig::default());
+        let mut alerts = Vec::new();
+        for target in 1..=3 {
+            honest(&detector, target);
+            let lie = if target == 3 { 0.0 } else { 0.1 };
+            alerts.extend(detector.observe(&attestation(2, 9, target, lie, 0.85)));
+        }
+
+        assert_eq!(alerts.len(), 1);
+        let alert = &alerts[0];
+        assert_eq!(alert.kind, AnomalyKind::Deviation);
+        assert_eq!(alert.probers, vec![vec![9]]);
+        assert_eq!(alert.samples, 3);
+        assert_eq!(alert.event_a, attestation(2, 9, 3, 0.0, 0.85).event_id);
+        assert_eq!(alert.event_b, attestation(1, 1, 3, 0.9, 0.85).event_id);
+        assert!(matches!(
+            alert.to_dispute(WorldId([0; 32])),
+            EventBody::Dispute(DisputeEvent { event_a, .. }) if event_a == alert.event_a
+        ));
+        assert!(detector.deviation(&[1]).unwrap().0 < 0.05);
+
+        // Already flagged: a fourth lie does not repeat the alert
+        honest(&detector, 4);
+        assert!(detector
+            .observe(&attestation(2, 9, 4, 0.1, 0.85))
+            .is_empty());
+        assert_eq!(detector.recent_alerts().len(), 1);
+
+        detector.prune(100);
+        assert!(detector.deviation(&[9]).is_none());
+    }
+
+    #[test]
+    fn test_colluding_probers_clustered() {
+        let detector = AnomalyDetector::new(AnomalyConfig::default());
+        let mut alerts = Vec::new();
+        for target in 1..=3 {
+            honest(&detector, target);
+            // Two probers push the same target down the same way
+            alerts.extend(detector.observe(&attestation(2, 8, target, 0.3, 0.4)));
+            alerts.extend(detector.observe(&attestation(2, 9, target, 0.32, 0.38)));
+        }
+
+        let correlated: Vec<_> = alerts
+            .iter()
+            .filter(|a| a.kind == AnomalyKind::Correlation)
+            .collect();
+        assert_eq!(correlated.len(), 1);
+        assert_eq!(correlated[0].probers, vec![vec![8], vec![9]]);
+        assert!(correlated[0].score > 0.9);
+        assert_eq!(detector.clusters(), vec![vec![vec![8], vec![9]]]);
+    }
+}
@@ -0,0 +1,1243 @@
+//! Local API for co-located daemons
+//!
+//! routerd, prober and infernode talk to gossipd over a loopback TCP
+//! connection using the regular frame codec. A connection carries
+//! `ApiRequest`/`ApiResponse` pairs until it subscribes; from then on it
+//! only carries `ApiEvent` frames. Subscriptions resume from the local
+//! sequence number of the last event a client saw. A node hosting several
+//! worlds serves each connection from its primary world until the client
+//! selects another.
+
+use crate::event_log::EventLog;
+use crate::endorsement::{Activation, EndorsementTally, EndorsementTotal, ForkChoice};
+use crate::membership::{AuditRecord, BanList, MembershipManager};
+use crate::quarantine::QuarantineEntry;
+use crate::query::{EventQuery, QueryPage, MAX_QUERY_LIMIT};
+use crate::semantic::SemanticHit;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use terrain_gossip_core::crypto::EventSigner;
+use terrain_gossip_core::types::*;
+use terrain_gossip_net::framing::{read_frame, write_frame, Frame, FrameError, FrameType};
+use thiserror::Error;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::{TcpListener, TcpStream, ToSocketAddrs};
+use tokio::sync::{broadcast, mpsc};
+use tracing::{debug, error, info, warn};
+
+/// Events read from storage per replay batch
+const REPLAY_BATCH: usize = 256;
+
+/// Initial and maximum reconnect delay for the `follow` functions
+const FOLLOW_BACKOFF_MIN: Duration = Duration::from_secs(1);
+const FOLLOW_BACKOFF_MAX: Duration = Duration::from_secs(30);
+
+/// How often `follow_descriptors` refetches without a descriptor event, so
+/// descriptors past their TTL are dropped
+const DESCRIPTOR_REFRESH: Duration = Duration::from_secs(60);
+
+/// API errors
+#[derive(Debug, Error)]
+pub enum ApiError {
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Frame error: {0}")]
+    Frame(#[from] FrameError),
+    #[error("Codec error: {0}")]
+    Codec(#[from] postcard::Error),
+    #[error("Request rejected: {0}")]
+    Remote(String),
+    #[error("Unexpected frame: {0:?}")]
+    UnexpectedFrame(FrameType),
+    #[error("Unexpected response")]
+    UnexpectedResponse,
+    #[error("Connection closed")]
+    Closed,
+}
+
+/// Request from a local client
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[allow(clippy::large_enum_variant)]
+pub enum ApiRequest {
+    /// Node status
+    Status,
+    /// Append a signed event to the log and gossip it
+    Publish(Event),
+    /// Query stored events
+    Query(EventQuery),
+    /// Switch the connection to an event stream
+    Subscribe(SubscribeRequest),
+    /// Inspect up to `limit` quarantined events, oldest first
+    Quarantined { limit: usize },
+    /// Drop one quarantined event, or all of them
+    PurgeQuarantine(Option<EventId>),
+    /// Export this node's bans
+    ExportBans,
+    /// Apply bans exported by another node
+    ImportBans(BanList),
+    /// Up to `limit` most recent membership audit records, optionally for
+    /// one peer
+    MembershipAudit {
+        pubkey: Option<[u8; 32]>,
+        limit: usize,
+    },
+    /// Nearest events to a caller-computed embedding
+    SemanticQuery(SemanticQuery),
+    /// Nearest events to text embedded by the node
+    SemanticSearch(SemanticSearch),
+    /// Each provider's current descriptor
+    CurrentDescriptors,
+    /// Trust-weighted endorsement of each rule bundle in an epoch
+    Endorsements { epoch_id: u64 },
+    /// Rule bundles that have activated
+    Activations,
+    /// Follow an activated bundle's fork, or stay with `None`; applied at
+    /// the next start
+    ChooseFork(Option<Bytes32>),
+    /// Worlds this node hosts, primary first
+    Worlds,
+    /// Direct the rest of the connection at a hosted world
+    SelectWorld(WorldId),
+}
+
+/// Text search over event summaries
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SemanticSearch {
+    pub text: String,
+    pub top_k: usize,
+    pub filter_type: Option<EventType>,
+}
+
+/// Subscription parameters
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SubscribeRequest {
+    /// Event types to stream (empty = all)
+    pub event_types: Vec<EventType>,
+    /// Replay stored events after this sequence number first
+    /// (`None` = only events arriving from now on)
+    pub after_seq: Option<u64>,
+}
+
+/// Node status
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiStatus {
+    pub world: WorldId,
+    pub current_epoch: u64,
+    /// Sequence number of the newest event
+    pub head_seq: u64,
+    pub event_count: u64,
+}
+
+/// Response to an `ApiRequest`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum ApiResponse {
+    Status(ApiStatus),
+    Published,
+    Page(QueryPage),
+    /// The connection now streams events
+    Subscribed {
+        head_seq: u64,
+    },
+    Quarantined(Vec<QuarantineEntry>),
+    /// Number of quarantine entries removed
+    Purged(usize),
+    Bans(BanList),
+    /// Number of bans that were new
+    ImportedBans(usize),
+    Audit(Vec<AuditRecord>),
+    SemanticResult(SemanticQueryResult),
+    /// Matches with scores, best first
+    SemanticHits(Vec<SemanticHit>),
+    Descriptors(Vec<ProviderDescriptor>),
+    Endorsements(Vec<EndorsementTotal>),
+    Activations(Vec<Activation>),
+    ForkChoice(ForkChoice),
+    Worlds(Vec<WorldId>),
+    Error(String),
+}
+
+/// Event streamed to a subscriber
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiEvent {
+    /// Local sequence number; pass as `after_seq` to resume
+    pub seq: u64,
+    pub event: Event,
+}
+
+fn encode<T: Serialize>(frame_type: FrameType, message: &T) -> Result<Frame, ApiError> {
+    Ok(Frame::new(frame_type, postcard::to_allocvec(message)?))
+}
+
+fn decode<T: DeserializeOwned>(frame: Frame, expected: FrameType) -> Result<T, ApiError> {
+    if frame.frame_type != expected {
+        return Err(ApiError::UnexpectedFrame(frame.frame_type));
+    }
+    Ok(postcard::from_bytes(&frame.payload)?)
+}
+
+/// Serves the local API from the event log
+pub struct ApiServer {
+    event_log: Arc<EventLog>,
+    /// Membership for ban and audit requests, with the node's public key
+    membership: Option<(Arc<MembershipManager>, [u8; 32])>,
+    /// Endorsement tally for bundle and fork requests
+    endorsements: Option<Arc<EndorsementTally>>,
+}
+
+impl ApiServer {
+    /// Create an API server over an event log
+    pub fn new(event_log: Arc<EventLog>) -> Self {
+        Self {
+            event_log,
+            membership: None,
+            endorsements: None,
+        }
+    }
+
+    /// Serve ban and audit requests from `membership`
+    pub fn with_membership(
+        mut self,
+        membership: Arc<MembershipManager>,
+        node_key: [u8; 32],
+    ) -> Self {
+        self.membership = Some((membership, node_key));
+        self
+    }
+
+    /// Serve endorsement and fork requests from `endorsements`
+    pub fn with_endorsements(mut self, endorsements: Arc<EndorsementTally>) -> Self {
+        self.endorsements = Some(endorsements);
+        self
+    }
+
+    /// Accept API connections until shutdown
+    pub async fn serve(
+        self: Arc<Self>,
+        listener: TcpListener,
+        shutdown_rx: broadcast::Receiver<()>,
+    ) {
+        let world = self.event_log.world_id();
+        Arc::new(ApiHost::new(world, self))
+            .serve(listener, shutdown_rx)
+            .await
+    }
+
+    /// Node status for this world
+    fn status(&self) -> ApiStatus {
+        ApiStatus {
+            world: self.event_log.world_id(),
+            current_epoch: self.event_log.current_epoch(),
+            head_seq: self.event_log.head_seq(),
+            event_count: self.event_log.event_count() as u64,
+        }
+    }
+
+    /// Answer a request/response call
+    pub fn handle_request(&self, request: ApiRequest) -> ApiResponse {
+        let result = match request {
+            ApiRequest::Status => Ok(ApiResponse::Status(self.status())),
+            // Only reached without an `ApiHost`: this is the only world
+            ApiRequest::Worlds => Ok(ApiResponse::Worlds(vec![self.event_log.world_id()])),
+            ApiRequest::SelectWorld(world) if world == self.event_log.world_id() => {
+                Ok(ApiResponse::Status(self.status()))
+            }
+            ApiRequest::SelectWorld(_) => return ApiResponse::Error("unknown world".into()),
+            ApiRequest::Publish(event) => {
+                self.event_log.append(event).map(|_| ApiResponse::Published)
+            }
+            ApiRequest::Query(query) => self.event_log.query(&query).map(ApiResponse::Page),
+            ApiRequest::Quarantined { limit } => self
+                .event_log
+                .quarantine()
+                .list(limit)
+                .map(ApiResponse::Quarantined)
+                .map_err(Into::into),
+            ApiRequest::PurgeQuarantine(event_id) => {
+                let quarantine = self.event_log.quarantine();
+                match event_id {
+                    Some(id) => quarantine.remove(&id).map(|e| e.is_some() as usize),
+                    None => quarantine.purge(),
+                }
+                .map(ApiResponse::Purged)
+                .map_err(Into::into)
+            }
+            ApiRequest::ExportBans
+            | ApiRequest::ImportBans(_)
+            | ApiRequest::MembershipAudit { .. } => return self.handle_membership(request),
+            ApiRequest::SemanticQuery(_) | ApiRequest::SemanticSearch(_) => {
+                return self.handle_semantic(request)
+            }
+            ApiRequest::CurrentDescriptors => self
+                .event_log
+                .current_descriptors()
+                .map(ApiResponse::Descriptors),
+            ApiRequest::Endorsements { .. }
+            | ApiRequest::Activations
+            | ApiRequest::ChooseFork(_) => return self.handle_endorsements(request),
+            ApiRequest::Subscribe(_) => {
+                return ApiResponse::Error("subscribe must be streamed".into())
+            }
+        };
+        result.unwrap_or_else(|e| ApiResponse::Error(e.to_string()))
+    }
+
+    fn handle_endorsements(&self, request: ApiRequest) -> ApiResponse {
+        let Some(endorsements) = &self.endorsements else {
+            return ApiResponse::Error("endorsements are not available".into());
+        };
+        match request {
+            ApiRequest::Endorsements { epoch_id } => {
+                ApiResponse::Endorsements(endorsements.totals(epoch_id))
+            }
+            ApiRequest::Activations => ApiResponse::Activations(endorsements.activations()),
+            ApiRequest::ChooseFork(bundle) => endorsements
+                .choose(bundle)
+                .map(ApiResponse::ForkChoice)
+                .unwrap_or_else(|e| ApiResponse::Error(e.to_string())),
+            _ => ApiResponse::Error("not an endorsement request".into()),
+        }
+    }
+
+    fn handle_semantic(&self, request: ApiRequest) -> ApiResponse {
+        let Some(index) = self.event_log.semantic_index() else {
+            return ApiResponse::Error("semantic index is disabled".into());
+        };
+        match request {
+            ApiRequest::SemanticQuery(query) => {
+                if query.world != self.event_log.world_id() {
+                    return ApiResponse::Error("query is for another world".into());
+                }
+                index
+                    .query(&query)
+                    .map(ApiResponse::SemanticResult)
+                    .unwrap_or_else(|e| ApiResponse::Error(e.to_string()))
+            }
+            ApiRequest::SemanticSearch(search) => ApiResponse::SemanticHits(index.search_text(
+                &search.text,
+                search.top_k,
+                search.filter_type,
+            )),
+            _ => ApiResponse::Error("not a semantic request".into()),
+        }
+    }
+
+    fn handle_membership(&self, request: ApiRequest) -> ApiResponse {
+        let Some((membership, node_key)) = &self.membership else {
+            return ApiResponse::Error("membership is not available".into());
+        };
+        let result = match request {
+            ApiRequest::ExportBans => Ok(ApiResponse::Bans(membership.export_bans(*node_key))),
+            ApiRequest::ImportBans(list) => {
+                membership.import_bans(&list).map(ApiResponse::ImportedBans)
+            }
+            ApiRequest::MembershipAudit { pubkey, limit } => membership
+                .audit_log(pubkey.as_ref(), limit)
+                .map(ApiResponse::Audit),
+            _ => return ApiResponse::Error("not a membership request".into()),
+        };
+        result.unwrap_or_else(|e| ApiResponse::Error(e.to_string()))
+    }
+
+    /// Stream stored events after the cursor, then live events, until the
+    /// client disconnects
+    async fn stream_events<R, W>(
+        &self,
+        mut reader: R,
+        mut writer: W,
+        request: SubscribeRequest,
+        mut shutdown_rx: broadcast::Receiver<()>,
+    ) -> Result<(), ApiError>
+    where
+        R: AsyncRead + Unpin,
+        W: AsyncWrite + Unpin,
+    {
+        // Subscribe before reading the head so nothing falls in between
+        let mut notify_rx = self.event_log.subscribe();
+        let head_seq = self.event_log.head_seq();
+        let mut cursor = request.after_seq.unwrap_or(head_seq);
+        let wanted = |event: &Event| {
+            request.event_types.is_empty() || request.event_types.contains(&event.event_type)
+        };
+
+        let response = ApiResponse::Subscribed { head_seq };
+        write_frame(&mut writer, encode(FrameType::ApiResponse, &response)?).await?;
+        cursor = self.replay(&mut writer, cursor, &wanted).await?;
+
+        loop {
+            tokio::select! {
+                result = notify_rx.recv() => match result {
+                    Ok(new_event) => {
+                        if new_event.seq <= cursor {
+                            continue;
+                        }
+                        cursor = new_event.seq;
+                        if wanted(&new_event.event) {
+                            let event = ApiEvent { seq: new_event.seq, event: new_event.event };
+                            write_frame(&mut writer, encode(FrameType::ApiEvent, &event)?).await?;
+                        }
+                    }
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        debug!("API subscriber lagged by {} events; replaying", skipped);
+                        cursor = self.replay(&mut writer, cursor, &wanted).await?;
+                    }
+                    Err(broadcast::error::RecvError::Closed) => return Ok(()),
+                },
+                // Subscribed connections send nothing; any frame or EOF ends them
+                _ = read_frame(&mut reader) => return Ok(()),
+                _ = shutdown_rx.recv() => return Ok(()),
+            }
+        }
+    }
+
+    /// Send stored events after `cursor`, returning the new cursor
+    async fn replay<W: AsyncWrite + Unpin>(
+        &self,
+        writer: &mut W,
+        mut cursor: u64,
+        wanted: &impl Fn(&Event) -> bool,
+    ) -> Result<u64, ApiError> {
+        loop {
+            let batch = self
+                .event_log
+                .events_after(cursor, REPLAY_BATCH)
+                .map_err(|e| ApiError::Remote(e.to_string()))?;
+            let done = batch.len() < REPLAY_BATCH;
+            for (seq, event) in batch {
+                cursor = seq;
+                if wanted(&event) {
+                    write_frame(
+                        writer,
+                        encode(FrameType::ApiEvent, &ApiEvent { seq, event })?,
+                    )
+                    .await?;
+                }
+            }
+            if done {
+                return Ok(cursor);
+            }
+        }
+    }
+}
+
+/// Local API over every world a node hosts
+///
+/// Each connection starts on the primary world; `SelectWorld` moves it to
+/// another one, and the requests that follow are scoped to that world.
+pub struct ApiHost {
+    primary: WorldId,
+    worlds: HashMap<WorldId, Arc<ApiServer>>,
+}
+
+impl ApiHost {
+    /// Serve `primary` by default
+    pub fn new(world: WorldId, api: Arc<ApiServer>) -> Self {
+        Self {
+            primary: world,
+            worlds: HashMap::from([(world, api)]),
+        }
+    }
+
+    /// Also serve `world`
+    pub fn with_world(mut self, world: WorldId, api: Arc<ApiServer>) -> Self {
+        self.worlds.insert(world, api);
+        self
+    }
+
+    /// Hosted worlds, primary first
+    pub fn world_ids(&self) -> Vec<WorldId> {
+        let mut worlds: Vec<WorldId> = self.worlds.keys().copied().collect();
+        worlds.sort_by_key(|world| (*world != self.primary, world.0));
+        worlds
+    }
+
+    /// Accept API connections until shutdown
+    pub async fn serve(
+        self: Arc<Self>,
+        listener: TcpListener,
+        mut shutdown_rx: broadcast::Receiver<()>,
+    ) {
+        loop {
+            tokio::select! {
+                result = listener.accept() => match result {
+                    Ok((stream, addr)) => {
+                        debug!("API client connected from {}", addr);
+                        let host = self.clone();
+                        let shutdown_rx = shutdown_rx.resubscribe();
+                        tokio::spawn(async move {
+                            if let Err(e) = host.handle_connection(stream, shutdown_rx).await {
+                                warn!("API connection error from {}: {}", addr, e);
+                            }
+                        });
+                    }
+                    Err(e) => error!("API accept error: {}", e),
+                },
+                _ = shutdown_rx.recv() => break,
+            }
+        }
+    }
+
+    async fn handle_connection(
+        &self,
+        stream: TcpStream,
+        shutdown_rx: broadcast::Receiver<()>,
+    ) -> Result<(), ApiError> {
+        let (mut reader, mut writer) = stream.into_split();
+        let mut api = &self.worlds[&self.primary];
+
+        while let Some(frame) = read_frame(&mut reader).await? {
+            let response = match decode(frame, FrameType::ApiRequest)? {
+                ApiRequest::Subscribe(request) => {
+                    return api.stream_events(reader, writer, request, shutdown_rx).await;
+                }
+                ApiRequest::Worlds => ApiResponse::Worlds(self.world_ids()),
+                ApiRequest::SelectWorld(world) => match self.worlds.get(&world) {
+                    Some(selected) => {
+                        api = selected;
+                        ApiResponse::Status(api.status())
+                    }
+                    None => ApiResponse::Error("unknown world".into()),
+                },
+                request => api.handle_request(request),
+            };
+            write_frame(&mut writer, encode(FrameType::ApiResponse, &response)?).await?;
+        }
+        Ok(())
+    }
+}
+
+/// Client for the local API
+pub struct ApiClient {
+    stream: TcpStream,
+}
+
+impl ApiClient {
+    /// Connect to gossipd's API address
+    pub async fn connect(addr: impl ToSocketAddrs) -> Result<Self, ApiError> {
+        Ok(Self {
+            stream: TcpStream::connect(addr).await?,
+        })
+    }
+
+    async fn call(&mut self, request: &ApiRequest) -> Result<ApiResponse, ApiError> {
+        write_frame(&mut self.stream, encode(FrameType::ApiRequest, request)?).await?;
+        let frame = read_frame(&mut self.stream)
+            .await?
+            .ok_or(ApiError::Closed)?;
+        match decode(frame, FrameType::ApiResponse)? {
+            ApiResponse::Error(message) => Err(ApiError::Remote(message)),
+            response => Ok(response),
+        }
+    }
+
+    /// Node status
+    pub async fn status(&mut self) -> Result<ApiStatus, ApiError> {
+        match self.call(&ApiRequest::Status).await? {
+            ApiResponse::Status(status) => Ok(status),
+            _ => Err(ApiError::UnexpectedResponse),
+        }
+    }
+
+    /// Publish a signed event
+    pub async fn publish(&mut self, event: Event) -> Result<(), ApiError> {
+        match self.call(&ApiRequest::Publish(event)).await? {
+            ApiResponse::Published => Ok(()),
+            _ => Err(ApiError::UnexpectedResponse),
+        }
+    }
+
+    /// Query stored events
+    pub async fn query(&mut self, query: EventQuery) -> Result<QueryPage, ApiError> {
+        match self.call(&ApiRequest::Query(query)).await? {
+            ApiResponse::Page(page) => Ok(page),
+            _ => Err(ApiError::UnexpectedResponse),
+        }
+    }
+
+    /// Inspect quarantined events
+    pub async fn quarantined(&mut self, limit: usize) -> Result<Vec<QuarantineEntry>, ApiError> {
+        match self.call(&ApiRequest::Quarantined { limit }).await? {
+            ApiResponse::Quarantined(entries) => Ok(entries),
+            _ => Err(ApiError::UnexpectedResponse),
+        }
+    }
+
+    /// Drop one quarantined event, or all of them; returns the number removed
+    pub async fn purge_quarantine(&mut self, event_id: Option<EventId>) -> Result<usize, ApiError> {
+        match self.call(&ApiRequest::PurgeQuarantine(event_id)).await? {
+            ApiResponse::Purged(count) => Ok(count),
+            _ => Err(ApiError::UnexpectedResponse),
+        }
+    }
+
+    /// Export the node's bans
+    pub async fn export_bans(&mut self) -> Result<BanList, ApiError> {
+        match self.call(&ApiRequest::ExportBans).await? {
+            ApiResponse::Bans(list) => Ok(list),
+            _ => Err(ApiError::UnexpectedResponse),
+        }
+    }
+
+    /// Import bans exported by another node; returns how many were new
+    pub async fn import_bans(&mut self, list: BanList) -> Result<usize, ApiError> {
+        match self.call(&ApiRequest::ImportBans(list)).await? {
+            ApiResponse::ImportedBans(count) => Ok(count),
+            _ => Err(ApiError::UnexpectedResponse),
+        }
+    }
+
+    /// Most recent membership audit records
+    pub async fn membership_audit(
+        &mut self,
+        pubkey: Option<[u8; 32]>,
+        limit: usize,
+    ) -> Result<Vec<AuditRecord>, ApiError> {
+        match self.call(&ApiRequest::MembershipAudit { pubkey, limit }).await? {
+            ApiResponse::Audit(records) => Ok(records),
+            _ => Err(ApiError::UnexpectedResponse),
+        }
+    }
+
+    /// Each provider's current descriptor: superseded, expired and revoked
+    /// descriptors are left out
+    pub async fn current_descriptors(&mut self) -> Result<Vec<ProviderDescriptor>, ApiError> {
+        match self.call(&ApiRequest::CurrentDescriptors).await? {
+            ApiResponse::Descriptors(descriptors) => Ok(descriptors),
+            _ => Err(ApiError::UnexpectedResponse),
+        }
+    }
+
+    /// Trust-weighted endorsement of each rule bundle in an epoch
+    pub async fn endorsements(&mut self, epoch_id: u64) -> Result<Vec<EndorsementTotal>, ApiError> {
+        match self.call(&ApiRequest::Endorsements { epoch_id }).await? {
+            ApiResponse::Endorsements(totals) => Ok(totals),
+            _ => Err(ApiError::UnexpectedResponse),
+        }
+    }
+
+    /// Rule bundles that have activated
+    pub async fn activations(&mut self) -> Result<Vec<Activation>, ApiError> {
+        match self.call(&ApiRequest::Activations).await? {
+            ApiResponse::Activations(activations) => Ok(activations),
+            _ => Err(ApiError::UnexpectedResponse),
+        }
+    }
+
+    /// Follow an activated bundle's fork, or stay with `None`
+    pub async fn choose_fork(&mut self, bundle: Option<Bytes32>) -> Result<ForkChoice, ApiError> {
+        match self.call(&ApiRequest::ChooseFork(bundle)).await? {
+            ApiResponse::ForkChoice(choice) => Ok(choice),
+            _ => Err(ApiError::UnexpectedResponse),
+        }
+    }
+
+    /// Nearest events to a caller-computed embedding
+    pub async fn semantic_query(
+        &mut self,
+        query: SemanticQuery,
+    ) -> Result<SemanticQueryResult, ApiError> {
+        match self.call(&ApiRequest::SemanticQuery(query)).await? {
+            ApiResponse::SemanticResult(result) => Ok(result),
+            _ => Err(ApiError::UnexpectedResponse),
+        }
+    }
+
+    /// Nearest events to `text`, embedded by the node
+    pub async fn semantic_search(
+        &mut self,
+        text: impl Into<String>,
+        top_k: usize,
+        filter_type: Option<EventType>,
+    ) -> Result<Vec<SemanticHit>, ApiError> {
+        let search = SemanticSearch {
+            text: text.into(),
+            top_k,
+            filter_type,
+        };
+        match self.call(&ApiRequest::SemanticSearch(search)).await? {
+            ApiResponse::SemanticHits(hits) => Ok(hits),
+            _ => Err(ApiError::UnexpectedResponse),
+        }
+    }
+
+    /// Worlds the node hosts, primary first
+    pub async fn worlds(&mut self) -> Result<Vec<WorldId>, ApiError> {
+        match self.call(&ApiRequest::Worlds).await? {
+            ApiResponse::Worlds(worlds) => Ok(worlds),
+            _ => Err(ApiError::UnexpectedResponse),
+        }
+    }
+
+    /// Scope the rest of this connection to a hosted world
+    pub async fn select_world(&mut self, world: WorldId) -> Result<ApiStatus, ApiError> {
+        match self.call(&ApiRequest::SelectWorld(world)).await? {
+            ApiResponse::Status(status) => Ok(status),
+            _ => Err(ApiError::UnexpectedResponse),
+        }
+    }
+
+    /// Turn the connection into an event stream
+    pub async fn subscribe(mut self, request: SubscribeRequest) -> Result<Subscription, ApiError> {
+        match self.call(&ApiRequest::Subscribe(request)).await? {
+            ApiResponse::Subscribed { head_seq } => Ok(Subscription {
+                stream: self.stream,
+                head_seq,
+            }),
+            _ => Err(ApiError::UnexpectedResponse),
+        }
+    }
+}
+
+/// Event stream from a subscribed connection
+pub struct Subscription {
+    stream: TcpStream,
+    head_seq: u64,
+}
+
+impl Subscription {
+    /// Head sequence number when the subscription started
+    pub fn head_seq(&self) -> u64 {
+        self.head_seq
+    }
+
+    /// Next event, or `None` when gossipd closes the stream
+    pub async fn next(&mut self) -> Result<Option<ApiEvent>, ApiError> {
+        match read_frame(&mut self.stream).await? {
+            Some(frame) => Ok(Some(decode(frame, FrameType::ApiEvent)?)),
+            None => Ok(None),
+        }
+    }
+}
+
+/// Follow gossipd's event stream forever
+///
+/// Replays every stored event of the requested types, then streams new ones.
+/// Reconnects with backoff on failure and resumes after the last event seen.
+pub async fn follow<F>(addr: String, event_types: Vec<EventType>, mut on_event: F)
+where
+    F: FnMut(ApiEvent),
+{
+    let mut after_seq = 0;
+    let mut backoff = FOLLOW_BACKOFF_MIN;
+
+    loop {
+        let request = SubscribeRequest {
+            event_types: event_types.clone(),
+            after_seq: Some(after_seq),
+        };
+        let result = async {
+            let mut subscription = ApiClient::connect(addr.as_str())
+                .await?
+                .subscribe(request)
+                .await?;
+            info!(
+                "Subscribed to gossipd at {} (head {})",
+                addr,
+                subscription.head_seq()
+            );
+            while let Some(event) = subscription.next().await? {
+                after_seq = event.seq;
+                backoff = FOLLOW_BACKOFF_MIN;
+                on_event(event);
+            }
+            Ok::<_, ApiError>(())
+        }
+        .await;
+
+        match result {
+            Ok(()) => warn!("gossipd at {} closed the event stream", addr),
+            Err(e) => warn!("gossipd at {} unavailable: {}", addr, e),
+        }
+        tokio::time::sleep(backoff).await;
+        backoff = (backoff * 2).min(FOLLOW_BACKOFF_MAX);
+    }
+}
+
+/// Follow recent events of the requested types forever
+///
+/// Unlike [`follow`], which replays the whole log, the first connection
+/// fetches only events from the last `window_epochs` epochs through the
+/// query API, then streams new ones. Events stored during the fetch may be
+/// passed twice, so `on_event` must be idempotent. Reconnects with backoff
+/// on failure and resumes after the last event seen.
+pub async fn follow_recent<F>(
+    addr: String,
+    event_types: Vec<EventType>,
+    window_epochs: u64,
+    mut on_event: F,
+) where
+    F: FnMut(&Event),
+{
+    let mut after_seq = None;
+    let mut backoff = FOLLOW_BACKOFF_MIN;
+
+    loop {
+        let result = async {
+            // Subscribe before fetching so no event is missed
+            let mut subscription = ApiClient::connect(addr.as_str())
+                .await?
+                .subscribe(SubscribeRequest {
+                    event_types: event_types.clone(),
+                    after_seq,
+                })
+                .await?;
+            info!(
+                "Subscribed to gossipd at {} (head {})",
+                addr,
+                subscription.head_seq()
+            );
+            if after_seq.is_none() {
+                let mut client = ApiClient::connect(addr.as_str()).await?;
+                let status = client.status().await?;
+                let from = status.current_epoch.saturating_sub(window_epochs);
+                let types: Vec<Option<EventType>> = if event_types.is_empty() {
+                    vec![None]
+                } else {
+                    event_types.iter().copied().map(Some).collect()
+                };
+                for event_type in types {
+                    let mut query = EventQuery {
+                        event_type,
+                        ..EventQuery::new()
+                            .epochs(from, u64::MAX)
+                            .limit(MAX_QUERY_LIMIT)
+                    };
+                    loop {
+                        let page = client.query(query.clone()).await?;
+                        page.events.iter().for_each(&mut on_event);
+                        match page.next_cursor {
+                            Some(cursor) => query = query.after(cursor),
+                            None => break,
+                        }
+                    }
+                }
+                after_seq = Some(subscription.head_seq());
+                backoff = FOLLOW_BACKOFF_MIN;
+            }
+            while let Some(event) = subscription.next().await? {
+                after_seq = Some(event.seq);
+                backoff = FOLLOW_BACKOFF_MIN;
+                on_event(&event.event);
+            }
+            Ok::<_, ApiError>(())
+        }
+        .await;
+
+        match result {
+            Ok(()) => warn!("gossipd at {} closed the event stream", addr),
+            Err(e) => warn!("gossipd at {} unavailable: {}", addr, e),
+        }
+        tokio::time::sleep(backoff).await;
+        backoff = (backoff * 2).min(FOLLOW_BACKOFF_MAX);
+    }
+}
+
+/// Follow the current provider descriptors forever
+///
+/// Calls `on_update` with every provider's current descriptor on connect,
+/// after each publication or revocation and every `DESCRIPTOR_REFRESH`.
+/// Superseded, expired and revoked descriptors are never passed, so the
+/// caller replaces its set rather than replaying history. Reconnects with
+/// backoff on failure.
+pub async fn follow_descriptors<F>(addr: String, mut on_update: F)
+where
+    F: FnMut(Vec<ProviderDescriptor>),
+{
+    let mut backoff = FOLLOW_BACKOFF_MIN;
+    let mut watcher: Option<tokio::task::JoinHandle<()>> = None;
+
+    loop {
+        let result = async {
+            // Subscribe before the first fetch so no change is missed
+            let mut subscription = ApiClient::connect(addr.as_str())
+                .await?
+                .subscribe(SubscribeRequest {
+                    event_types: vec![EventType::DescriptorPublish, EventType::DescriptorRevoke],
+                    after_seq: None,
+                })
+                .await?;
+            let mut client = ApiClient::connect(addr.as_str()).await?;
+            info!("Following descriptors from gossipd at {}", addr);
+
+            // Reading a frame is not cancel safe, so changes are watched in
+            // a task and coalesced into one pending refetch
+            let (changed_tx, mut changed) = mpsc::channel(1);
+            watcher = Some(tokio::spawn(async move {
+                while let Ok(Some(_)) = subscription.next().await {
+                    let _ = changed_tx.try_send(());
+                }
+            }));
+            let start = tokio::time::Instant::now() + DESCRIPTOR_REFRESH;
+            let mut ticker = tokio::time::interval_at(start, DESCRIPTOR_REFRESH);
+            loop {
+                on_update(client.current_descriptors().await?);
+                backoff = FOLLOW_BACKOFF_MIN;
+                tokio::select! {
+                    change = changed.recv() => {
+                        if change.is_none() {
+                            return Ok::<_, ApiError>(());
+                        }
+                    }
+                    _ = ticker.tick() => {}
+                }
+            }
+        }
+        .await;
+        if let Some(watcher) = watcher.take() {
+            watcher.abort();
+        }
+
+        match result {
+            Ok(()) => warn!("gossipd at {} closed the event stream", addr),
+            Err(e) => warn!("gossipd at {} unavailable: {}", addr, e),
+        }
+        tokio::time::sleep(backoff).await;
+        backoff = (backoff * 2).min(FOLLOW_BACKOFF_MAX);
+    }
+}
+
+/// Sign and publish locally produced events every `period`, forever
+///
+/// `produce` is given gossipd's status and returns the unsigned bodies to
+/// publish. Connects when needed; rounds while gossipd is unreachable are
+/// skipped, and the rest of a round is dropped if the connection fails.
+pub async fn publish_every<F>(addr: String, signer: EventSigner, period: Duration, mut produce: F)
+where
+    F: FnMut(&ApiStatus) -> Vec<EventBody>,
+{
+    let mut client: Option<ApiClient> = None;
+    let mut ticker = tokio::time::interval(period);
+
+    loop {
+        ticker.tick().await;
+        if client.is_none() {
+            match ApiClient::connect(addr.as_str()).await {
+                Ok(connected) => client = Some(connected),
+                Err(e) => {
+                    debug!(
+                        "Skipping publication: gossipd at {} unavailable: {}",
+                        addr, e
+                    );
+                    continue;
+                }
+            }
+        }
+        let Some(api) = client.as_mut() else {
+            continue;
+        };
+
+        let status = match api.status().await {
+            Ok(status) => status,
+            Err(e) => {
+                warn!("Failed to query gossipd at {}: {}", addr, e);
+                client = None;
+                continue;
+            }
+        };
+        for body in produce(&status) {
+            let event = match signer.sign(body) {
+                Ok(event) => event,
+                Err(e) => {
+                    warn!("Failed to sign event: {}", e);
+                    continue;
+                }
+            };
+            let event_type = event.event_type;
+            match api.publish(event).await {
+                Ok(()) => debug!("Published {:?} event", event_type),
+                Err(ApiError::Remote(reason)) => {
+                    warn!("gossipd rejected {:?} event: {}", event_type, reason)
+                }
+                Err(e) => {
+                    warn!("Failed to publish {:?} event: {}", event_type, e);
+                    client = None;
+                    break;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::semantic::{HashingEmbedder, SemanticIndex};
+    use crate::storage::Storage;
+    use tempfile::tempdir;
+
+    fn endorsement(id: u8) -> Event {
+        Event {
+            event_id: EventId([id; 32]),
+            world: WorldId([0; 32]),
+            epoch_id: 1,
+            event_type: EventType::RuleEndorsement,
+            body: EventBody::RuleEndorsement(RuleEndorsementEvent {
+                world: WorldId([0; 32]),
+                epoch_id: 1,
+                rule_bundle_hash: [0; 32],
+                weight: 1.0,
+                signer_transport_pubkey: vec![id; 32],
+                signature: vec![],
+            }),
+        }
+    }
+
+    fn attestation(prober: u8, target: u8, epoch_id: u64, quality: f64) -> Event {
+        let mut id = [prober; 32];
+        id[1] = target;
+        id[2..10].copy_from_slice(&epoch_id.to_le_bytes());
+        Event {
+            event_id: EventId(id),
+            world: WorldId([0; 32]),
+            epoch_id,
+            event_type: EventType::Attestation,
+            body: EventBody::Attestation(BehaviorAttestation {
+                attestation_id: AttestationId(id),
+                world: WorldId([0; 32]),
+                epoch_id,
+                challenge_id: ChallengeId(id),
+                target_ref: TargetRef([target; 32]),
+                target_fah: None,
+                metrics: MetricsVector {
+                    success_rate: quality,
+                    refusal_consistency: quality,
+                    tool_fidelity: quality,
+                    latency_p50_ms: 0,
+                    latency_p95_ms: 0,
+                    robustness_score: quality,
+                    drift_indicator: 0.0,
+                    freshness: FreshnessStrength::None,
+                },
+                evidence_commitment: [0; 32],
+                freshness_anchor: None,
+                prober_transport_pubkey: vec![prober; 32],
+                signature: vec![],
+            }),
+        }
+    }
+
+    fn link_hint(signer: u8, a: u8, b: u8, epoch_id: u64) -> Event {
+        Event {
+            event_id: EventId([100 + signer; 32]),
+            world: WorldId([0; 32]),
+            epoch_id,
+            event_type: EventType::LinkHint,
+            body: EventBody::LinkHint(LinkHintEvent {
+                world: WorldId([0; 32]),
+                epoch_id,
+                target_a: TargetRef([a; 32]),
+                target_b: TargetRef([b; 32]),
+                evidence_commitment: [0; 32],
+                compatibility_score: 1.0,
+                signer_transport_pubkey: vec![signer; 32],
+                signature: vec![],
+            }),
+        }
+    }
+
+    async fn start() -> (
+        std::net::SocketAddr,
+        broadcast::Sender<()>,
+        tempfile::TempDir,
+    ) {
+        let dir = tempdir().unwrap();
+        let storage = Arc::new(Storage::open(dir.path()).unwrap());
+        let index = Arc::new(SemanticIndex::new(Arc::new(HashingEmbedder::default())));
+        let log = Arc::new(
+            EventLog::new(storage, WorldId([0; 32]), [1; 32])
+                .with_semantic_index(index)
+                .unwrap(),
+        );
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (shutdown_tx, shutdown_rx) = broadcast::channel(1);
+        tokio::spawn(Arc::new(ApiServer::new(log)).serve(listener, shutdown_rx));
+        (addr, shutdown_tx, dir)
+    }
+
+    #[tokio::test]
+    async fn test_publish_and_query() {
+        let (addr, _shutdown, _dir) = start().await;
+        let mut client = ApiClient::connect(addr).await.unwrap();
+
+        client.publish(endorsement(1)).await.unwrap();
+        // Duplicates are rejected with the log's error
+        assert!(matches!(
+            client.publish(endorsement(1)).await,
+            Err(ApiError::Remote(_))
+        ));
+
+        let status = client.status().await.unwrap();
+        assert_eq!(status.head_seq, 1);
+        assert_eq!(status.event_count, 1);
+
+        let page = client
+            .query(EventQuery::new().signer(vec![1; 32]))
+            .await
+            .unwrap();
+        assert_eq!(page.events.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_semantic_search() {
+        let (addr, _shutdown, _dir) = start().await;
+        let mut client = ApiClient::connect(addr).await.unwrap();
+        for id in 1..=3 {
+            client.publish(endorsement(id)).await.unwrap();
+        }
+
+        let hits = client
+            .semantic_search("rule bundle endorsement", 2, Some(EventType::RuleEndorsement))
+            .await
+            .unwrap();
+        assert_eq!(hits.len(), 2);
+        assert!(client
+            .semantic_search("rule bundle endorsement", 2, Some(EventType::Dispute))
+            .await
+            .unwrap()
+            .is_empty());
+
+        let query = SemanticQuery {
+            world: WorldId([9; 32]),
+            embedding: vec![],
+            top_k: 1,
+            filter_type: None,
+        };
+        assert!(matches!(
+            client.semantic_query(query).await,
+            Err(ApiError::Remote(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_subscribe_replays_then_streams() {
+        let (addr, _shutdown, _dir) = start().await;
+        let mut publisher = ApiClient::connect(addr).await.unwrap();
+        publisher.publish(endorsement(1)).await.unwrap();
+        publisher.publish(endorsement(2)).await.unwrap();
+
+        // Resume after the first event
+        let mut subscription = ApiClient::connect(addr)
+            .await
+            .unwrap()
+            .subscribe(SubscribeRequest {
+                event_types: vec![EventType::RuleEndorsement],
+                after_seq: Some(1),
+            })
+            .await
+            .unwrap();
+        assert_eq!(subscription.head_seq(), 2);
+
+        let replayed = subscription.next().await.unwrap().unwrap();
+        assert_eq!(
+            (replayed.seq, replayed.event.event_id),
+            (2, EventId([2; 32]))
+        );
+
+        publisher.publish(endorsement(3)).await.unwrap();
+        let live = tokio::time::timeout(Duration::from_secs(5), subscription.next())
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+        assert_eq!((live.seq, live.event.event_id), (3, EventId([3; 32])));
+    }
+
+    #[tokio::test]
+    async fn test_follow_recent_queries_then_streams() {
+        let (addr, _shutdown, _dir) = start().await;
+        let mut publisher = ApiClient::connect(addr).await.unwrap();
+        publisher.publish(endorsement(1)).await.unwrap();
+        publisher.publish(endorsement(2)).await.unwrap();
+
+        let (seen_tx, mut seen) = mpsc::unbounded_channel();
+        let follower = tokio::spawn(follow_recent(
+            addr.to_string(),
+            vec![EventType::RuleEndorsement],
+            u64::MAX,
+            move |event| {
+                let _ = seen_tx.send(event.event_id);
+            },
+        ));
+        for id in 1..=2 {
+            assert_eq!(seen.recv().await, Some(EventId([id; 32])));
+        }
+
+        publisher.publish(endorsement(3)).await.unwrap();
+        let live = tokio::time::timeout(Duration::from_secs(5), seen.recv())
+            .await
+            .unwrap();
+        assert_eq!(live, Some(EventId([3; 32])));
+        follower.abort();
+    }
+
+    #[tokio::test]
+    async fn test_published_hints_link_with_earned_trust() {
+        use terrain_gossip_belief::{
+            BeliefConfig, BeliefField, LinkConfig, LinkGraph, ObservationWeights, TrustConfig,
+            TrustEngine,
+        };
+
+        let (addr, _shutdown, _dir) = start().await;
+        let mut publisher = ApiClient::connect(addr).await.unwrap();
+        let now = publisher.status().await.unwrap().current_epoch;
+
+        // Probers 1-4 agree on every target, so their reports are scored
+        // and earn trust
+        let mut published = 0;
+        for epoch_id in now - 8..now {
+            for target in 1..=4u8 {
+                for prober in 1..=4u8 {
+                    let noise = ((prober as u64 * 7 + epoch_id) % 5) as f64 / 100.0;
+                    let quality = 0.4 + 0.1 * target as f64 + noise;
+                    publisher
+                        .publish(attestation(prober, target, epoch_id, quality))
+                        .await
+                        .unwrap();
+                    published += 1;
+                }
+            }
+        }
+        // Two of them hint 5 and 6 linked; two unscored keys hint 7 and 8
+        for (signer, a, b) in [(1, 5, 6), (2, 5, 6), (8, 7, 8), (9, 7, 8)] {
+            publisher
+                .publish(link_hint(signer, a, b, now))
+                .await
+                .unwrap();
+            published += 1;
+        }
+
+        let beliefs = Arc::new(BeliefField::new(
+            ObservationWeights::default(),
+            BeliefConfig::default(),
+        ));
+        let links = Arc::new(LinkGraph::new(LinkConfig::default()));
+        let (follow_beliefs, follow_links) = (beliefs.clone(), links.clone());
+        let (seen_tx, mut seen) = mpsc::unbounded_channel();
+        let follower = tokio::spawn(follow_recent(
+            addr.to_string(),
+            vec![EventType::Attestation, EventType::LinkHint],
+            100,
+            move |event| {
+                follow_beliefs.apply(event);
+                follow_links.apply(event);
+                let _ = seen_tx.send(());
+            },
+        ));
+        for _ in 0..published {
+            tokio::time::timeout(Duration::from_secs(5), seen.recv())
+                .await
+                .unwrap();
+        }
+        follower.abort();
+
+        let engine = TrustEngine::new(TrustConfig::default());
+        assert!(engine.update(&beliefs).scored > 0);
+        let earned = engine.earned_trust();
+        links.recluster(|signer| earned.get(signer).copied().unwrap_or(0.0));
+        assert_eq!(links.cluster_of(&TargetRef([6; 32])), TargetRef([5; 32]));
+        assert_eq!(links.cluster_of(&TargetRef([8; 32])), TargetRef([8; 32]));
+    }
+}
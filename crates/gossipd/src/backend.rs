@@ -0,0 +1,409 @@
+//! Key-value backends for `Storage`
+//!
+//! `Storage` keeps all typed encoding, indexing and bookkeeping; a backend
+//! only provides named ordered trees of byte keys and values. sled is the
+//! persistent backend; the in-memory backend is for tests and for running
+//! many simulated nodes in one process.
+
+use crate::index::IndexKind;
+use crate::storage::StorageError;
+use parking_lot::RwLock;
+use std::collections::{BTreeMap, HashMap};
+use std::ops::Bound;
+use std::path::Path;
+
+/// Key-value pair as returned by backend scans
+pub type KvPair = (Vec<u8>, Vec<u8>);
+
+/// Iterator over a backend scan
+pub type KvIter<'a> = Box<dyn Iterator<Item = Result<KvPair, StorageError>> + 'a>;
+
+/// Logical trees of the store
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Tree {
+    /// event_id -> Event
+    Events,
+    /// descriptor_id -> ProviderDescriptor
+    Descriptors,
+    /// replica_id -> counter
+    VersionVectors,
+    /// key -> value
+    Metadata,
+    /// transport_pubkey -> PeerRecord
+    PeerBook,
+    /// event_id -> epoch_id of a pruned event
+    Tombstones,
+    /// event_id -> QuarantineEntry
+    Quarantine,
+    /// dependency key || event_id -> ()
+    QuarantineDeps,
+    /// local sequence number (BE) -> event_id
+    EventSeq,
+    /// pubkey -> Member
+    Members,
+    /// pubkey -> BanRecord
+    Bans,
+    /// audit sequence number (BE) -> AuditRecord
+    MembershipAudit,
+    /// Secondary index (see `index`)
+    Index(IndexKind),
+}
+
+impl Tree {
+    /// Every tree, including one per index kind
+    pub fn all() -> Vec<Tree> {
+        let mut trees = vec![
+            Tree::Events,
+            Tree::Descriptors,
+            Tree::VersionVectors,
+            Tree::Metadata,
+            Tree::PeerBook,
+            Tree::Tombstones,
+            Tree::Quarantine,
+            Tree::QuarantineDeps,
+            Tree::EventSeq,
+            Tree::Members,
+            Tree::Bans,
+            Tree::MembershipAudit,
+        ];
+        trees.extend(IndexKind::ALL.into_iter().map(Tree::Index));
+        trees
+    }
+
+    /// Stable tree name
+    pub fn name(&self) -> &'static str {
+        match self {
+            Tree::Events => "events",
+            Tree::Descriptors => "descriptors",
+            Tree::VersionVectors => "version_vectors",
+            Tree::Metadata => "metadata",
+            Tree::PeerBook => "peer_book",
+            Tree::Tombstones => "tombstones",
+            Tree::Quarantine => "quarantine",
+            Tree::QuarantineDeps => "quarantine_deps",
+            Tree::EventSeq => "event_seq",
+            Tree::Members => "members",
+            Tree::Bans => "bans",
+            Tree::MembershipAudit => "membership_audit",
+            Tree::Index(kind) => kind.tree_name(),
+        }
+    }
+}
+
+/// Ordered key-value store with named trees
+///
+/// Keys within a tree are ordered bytewise. Scans may be lazy or snapshot
+/// the range; callers must not rely on seeing writes made during a scan.
+pub trait StorageBackend: Send + Sync {
+    /// Value stored under `key`
+    fn get(&self, tree: Tree, key: &[u8]) -> Result<Option<Vec<u8>>, StorageError>;
+
+    /// Store `value` under `key`, replacing any previous value
+    fn insert(&self, tree: Tree, key: &[u8], value: &[u8]) -> Result<(), StorageError>;
+
+    /// Remove `key`, returning its previous value
+    fn remove(&self, tree: Tree, key: &[u8]) -> Result<Option<Vec<u8>>, StorageError>;
+
+    /// Entries with keys between `lower` and `upper`, in key order
+    fn range(&self, tree: Tree, lower: Bound<Vec<u8>>, upper: Bound<Vec<u8>>) -> KvIter<'_>;
+
+    /// Entries whose key starts with `prefix`, in key order
+    fn scan_prefix(&self, tree: Tree, prefix: &[u8]) -> KvIter<'_>;
+
+    /// Entry with the greatest key
+    fn last(&self, tree: Tree) -> Result<Option<KvPair>, StorageError>;
+
+    /// Number of entries in a tree
+    fn len(&self, tree: Tree) -> usize;
+
+    /// Remove every entry from a tree
+    fn clear(&self, tree: Tree) -> Result<(), StorageError>;
+
+    /// Persist pending writes
+    fn flush(&self) -> Result<(), StorageError>;
+
+    /// Bytes used on disk (0 for in-memory backends)
+    fn size_on_disk(&self) -> Result<u64, StorageError>;
+
+    /// Whether `key` is present
+    fn contains(&self, tree: Tree, key: &[u8]) -> Result<bool, StorageError> {
+        Ok(self.get(tree, key)?.is_some())
+    }
+
+    /// Whether a tree is empty
+    fn is_empty(&self, tree: Tree) -> bool {
+        self.len(tree) == 0
+    }
+}
+
+/// Persistent backend on sled
+pub struct SledBackend {
+    db: sled::Db,
+    trees: HashMap<Tree, sled::Tree>,
+}
+
+impl SledBackend {
+    /// Open (or create) a database at `path`
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, StorageError> {
+        Self::with_db(sled::open(path)?)
+    }
+
+    /// Database removed when dropped
+    pub fn temporary() -> Result<Self, StorageError> {
+        Self::with_db(sled::Config::new().temporary(true).open()?)
+    }
+
+    fn with_db(db: sled::Db) -> Result<Self, StorageError> {
+        let mut trees = HashMap::new();
+        for tree in Tree::all() {
+            trees.insert(tree, db.open_tree(tree.name())?);
+        }
+        Ok(Self { db, trees })
+    }
+
+    fn tree(&self, tree: Tree) -> &sled::Tree {
+        &self.trees[&tree]
+    }
+}
+
+fn sled_pair(result: sled::Result<(sled::IVec, sled::IVec)>) -> Result<KvPair, StorageError> {
+    let (key, value) = result?;
+    Ok((key.to_vec(), value.to_vec()))
+}
+
+impl StorageBackend for SledBackend {
+    fn get(&self, tree: Tree, key: &[u8]) -> Result<Option<Vec<u8>>, StorageError> {
+        Ok(self.tree(tree).get(key)?.map(|v| v.to_vec()))
+    }
+
+    fn insert(&self, tree: Tree, key: &[u8], value: &[u8]) -> Result<(), StorageError> {
+        self.tree(tree).insert(key, value)?;
+        Ok(())
+    }
+
+    fn remove(&self, tree: Tree, key: &[u8]) -> Result<Option<Vec<u8>>, StorageError> {
+        Ok(self.tree(tree).remove(key)?.map(|v| v.to_vec()))
+    }
+
+    fn range(&self, tree: Tree, lower: Bound<Vec<u8>>, upper: Bound<Vec<u8>>) -> KvIter<'_> {
+        if is_empty_range(&lower, &upper) {
+            return Box::new(std::iter::empty());
+        }
+        Box::new(self.tree(tree).range((lower, upper)).map(sled_pair))
+    }
+
+    fn scan_prefix(&self, tree: Tree, prefix: &[u8]) -> KvIter<'_> {
+        Box::new(self.tree(tree).scan_prefix(prefix).map(sled_pair))
+    }
+
+    fn last(&self, tree: Tree) -> Result<Option<KvPair>, StorageError> {
+        self.tree(tree).last()?.map(Ok).map(sled_pair).transpose()
+    }
+
+    fn len(&self, tree: Tree) -> usize {
+        self.tree(tree).len()
+    }
+
+    fn clear(&self, tree: Tree) -> Result<(), StorageError> {
+        self.tree(tree).clear()?;
+        Ok(())
+    }
+
+    fn flush(&self) -> Result<(), StorageError> {
+        self.db.flush()?;
+        Ok(())
+    }
+
+    fn size_on_disk(&self) -> Result<u64, StorageError> {
+        Ok(self.db.size_on_disk()?)
+    }
+}
+
+/// One in-memory tree
+type MemoryTree = BTreeMap<Vec<u8>, Vec<u8>>;
+
+/// Volatile backend on ordered maps
+#[derive(Default)]
+pub struct MemoryBackend {
+    trees: RwLock<HashMap<Tree, MemoryTree>>,
+}
+
+impl MemoryBackend {
+    /// Empty store
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Snapshot the entries selected by `select` from a tree
+    fn snapshot<'a>(
+        &self,
+        tree: Tree,
+        select: impl FnOnce(&MemoryTree) -> Vec<KvPair>,
+    ) -> KvIter<'a> {
+        let trees = self.trees.read();
+        let entries = trees.get(&tree).map(select).unwrap_or_default();
+        Box::new(entries.into_iter().map(Ok))
+    }
+}
+
+impl StorageBackend for MemoryBackend {
+    fn get(&self, tree: Tree, key: &[u8]) -> Result<Option<Vec<u8>>, StorageError> {
+        Ok(self
+            .trees
+            .read()
+            .get(&tree)
+            .and_then(|t| t.get(key).cloned()))
+    }
+
+    fn insert(&self, tree: Tree, key: &[u8], value: &[u8]) -> Result<(), StorageError> {
+        self.trees
+            .write()
+            .entry(tree)
+            .or_default()
+            .insert(key.to_vec(), value.to_vec());
+        Ok(())
+    }
+
+    fn remove(&self, tree: Tree, key: &[u8]) -> Result<Option<Vec<u8>>, StorageError> {
+        Ok(self
+            .trees
+            .write()
+            .get_mut(&tree)
+            .and_then(|t| t.remove(key)))
+    }
+
+    fn range(&self, tree: Tree, lower: Bound<Vec<u8>>, upper: Bound<Vec<u8>>) -> KvIter<'_> {
+        if is_empty_range(&lower, &upper) {
+            return Box::new(std::iter::empty());
+        }
+        self.snapshot(tree, |t| {
+            t.range((lower, upper))
+                .map(|(k, v)| (k.clone(), v.clone()))
+                .collect()
+        })
+    }
+
+    fn scan_prefix(&self, tree: Tree, prefix: &[u8]) -> KvIter<'_> {
+        self.snapshot(tree, |t| {
+            t.range(prefix.to_vec()..)
+                .take_while(|(k, _)| k.starts_with(prefix))
+                .map(|(k, v)| (k.clone(), v.clone()))
+                .collect()
+        })
+    }
+
+    fn last(&self, tree: Tree) -> Result<Option<KvPair>, StorageError> {
+        Ok(self
+            .trees
+            .read()
+            .get(&tree)
+            .and_then(|t| t.last_key_value())
+            .map(|(k, v)| (k.clone(), v.clone())))
+    }
+
+    fn len(&self, tree: Tree) -> usize {
+        self.trees.read().get(&tree).map_or(0, |t| t.len())
+    }
+
+    fn clear(&self, tree: Tree) -> Result<(), StorageError> {
+        self.trees.write().remove(&tree);
+        Ok(())
+    }
+
+    fn flush(&self) -> Result<(), StorageError> {
+        Ok(())
+    }
+
+    fn size_on_disk(&self) -> Result<u64, StorageError> {
+        Ok(0)
+    }
+}
+
+/// Whether bounds select nothing (ordered maps panic on inverted ranges)
+fn is_empty_range(lower: &Bound<Vec<u8>>, upper: &Bound<Vec<u8>>) -> bool {
+    match (lower, upper) {
+        (Bound::Included(l), Bound::Included(u)) => l > u,
+        (Bound::Included(l), Bound::Excluded(u))
+        | (Bound::Excluded(l), Bound::Included(u))
+        | (Bound::Excluded(l), Bound::Excluded(u)) => l >= u,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Run a check against every backend
+    fn for_each_backend(check: impl Fn(&dyn StorageBackend)) {
+        check(&SledBackend::temporary().unwrap());
+        check(&MemoryBackend::new());
+    }
+
+    #[test]
+    fn test_get_insert_remove() {
+        for_each_backend(|backend| {
+            assert_eq!(backend.get(Tree::Metadata, b"k").unwrap(), None);
+            backend.insert(Tree::Metadata, b"k", b"v1").unwrap();
+            backend.insert(Tree::Metadata, b"k", b"v2").unwrap();
+            assert_eq!(
+                backend.get(Tree::Metadata, b"k").unwrap(),
+                Some(b"v2".to_vec())
+            );
+            assert!(backend.contains(Tree::Metadata, b"k").unwrap());
+
+            // Trees are independent
+            assert!(!backend.contains(Tree::Events, b"k").unwrap());
+            assert!(backend.is_empty(Tree::Events));
+
+            assert_eq!(backend.len(Tree::Metadata), 1);
+            assert_eq!(
+                backend.remove(Tree::Metadata, b"k").unwrap(),
+                Some(b"v2".to_vec())
+            );
+            assert_eq!(backend.remove(Tree::Metadata, b"k").unwrap(), None);
+            assert_eq!(backend.len(Tree::Metadata), 0);
+        });
+    }
+
+    #[test]
+    fn test_ordered_scans() {
+        for_each_backend(|backend| {
+            let tree = Tree::Index(IndexKind::Target);
+            for key in [&b"b2"[..], b"a1", b"b1", b"c1", b"b"] {
+                backend.insert(tree, key, &[]).unwrap();
+            }
+            let keys = |iter: KvIter<'_>| -> Vec<Vec<u8>> { iter.map(|r| r.unwrap().0).collect() };
+
+            assert_eq!(
+                keys(backend.scan_prefix(tree, b"b")),
+                vec![b"b".to_vec(), b"b1".to_vec(), b"b2".to_vec()]
+            );
+            assert_eq!(
+                keys(backend.range(
+                    tree,
+                    Bound::Excluded(b"a1".to_vec()),
+                    Bound::Included(b"b1".to_vec())
+                )),
+                vec![b"b".to_vec(), b"b1".to_vec()]
+            );
+            assert!(keys(backend.range(
+                tree,
+                Bound::Included(b"c".to_vec()),
+                Bound::Included(b"a".to_vec())
+            ))
+            .is_empty());
+            assert!(keys(backend.range(
+                tree,
+                Bound::Excluded(b"b".to_vec()),
+                Bound::Included(b"b".to_vec())
+            ))
+            .is_empty());
+            assert_eq!(backend.last(tree).unwrap().unwrap().0, b"c1".to_vec());
+
+            backend.clear(tree).unwrap();
+            assert!(backend.is_empty(tree));
+            assert!(backend.last(tree).unwrap().is_none());
+        });
+    }
+}
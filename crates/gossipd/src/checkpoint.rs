@@ -0,0 +1,374 @@
+//! Signed log checkpoints
+//!
+//! A checkpoint is a snapshot of the retained event log: the events are
+//! encoded in sequence order into an LZ4-compressed segment, and a header
+//! commits to them with a Merkle root over the sorted event IDs. Members sign
+//! the header, the producer first and others by co-signing the file after
+//! checking its segment; a new node imports a checkpoint once enough trusted
+//! members have signed it, then delta-syncs from the header's version vector.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::Path;
+use terrain_gossip_core::types::*;
+use terrain_gossip_net::crypto::KeyPair;
+use thiserror::Error;
+
+/// Domain separator for checkpoint header signatures
+const DOMAIN_CHECKPOINT_SIG: &[u8] = b"checkpoint-sig";
+
+/// Domain separator for Merkle leaves
+const DOMAIN_LEAF: &[u8] = b"checkpoint-leaf";
+
+/// Domain separator for Merkle interior nodes
+const DOMAIN_NODE: &[u8] = b"checkpoint-node";
+
+/// Checkpoint format version
+pub const CHECKPOINT_VERSION: u8 = 1;
+
+/// Checkpoint errors
+#[derive(Debug, Error)]
+pub enum CheckpointError {
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Codec error: {0}")]
+    Codec(#[from] postcard::Error),
+    #[error("Decompression failed: {0}")]
+    Decompress(String),
+    #[error("Unsupported checkpoint version {0}")]
+    Version(u8),
+    #[error("Checkpoint is for another world")]
+    WorldMismatch,
+    #[error("Segment hash does not match header")]
+    SegmentMismatch,
+    #[error("Merkle root does not match header")]
+    RootMismatch,
+    #[error("Segment has {actual} events, header says {expected}")]
+    CountMismatch { expected: u64, actual: u64 },
+    #[error("{valid} trusted signatures, {required} required")]
+    NotEnoughSignatures { valid: usize, required: usize },
+}
+
+/// Signed part of a checkpoint
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CheckpointHeader {
+    pub version: u8,
+    pub world: WorldId,
+    /// Epoch the snapshot was taken in
+    pub epoch_id: u64,
+    /// Producer's sequence number at the snapshot
+    pub head_seq: u64,
+    pub event_count: u64,
+    /// Merkle root over the sorted event IDs
+    pub merkle_root: [u8; 32],
+    /// BLAKE3 of the compressed segment
+    pub segment_hash: [u8; 32],
+    /// Producer's version vector at the snapshot
+    pub version_vector: Vec<VersionVectorEntry>,
+    /// Unix time in milliseconds
+    pub created_at: u64,
+}
+
+impl CheckpointHeader {
+    /// Bytes covered by member signatures
+    pub fn sign_bytes(&self) -> Result<Vec<u8>, CheckpointError> {
+        let mut bytes = DOMAIN_CHECKPOINT_SIG.to_vec();
+        bytes.extend_from_slice(&postcard::to_allocvec(self)?);
+        Ok(bytes)
+    }
+}
+
+/// A member's signature over a checkpoint header
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CheckpointSignature {
+    /// Transport public key of the signer
+    pub signer: [u8; 32],
+    pub signature: Vec<u8>,
+}
+
+/// Signed, compressed snapshot of the event log
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Checkpoint {
+    pub header: CheckpointHeader,
+    /// LZ4-compressed postcard `Vec<Event>` in sequence order
+    pub segment: Vec<u8>,
+    pub signatures: Vec<CheckpointSignature>,
+}
+
+impl Checkpoint {
+    /// Build an unsigned checkpoint from events in sequence order
+    pub fn build(
+        world: WorldId,
+        epoch_id: u64,
+        head_seq: u64,
+        version_vector: Vec<VersionVectorEntry>,
+        created_at: u64,
+        events: &[Event],
+    ) -> Result<Self, CheckpointError> {
+        let ids: Vec<EventId> = events.iter().map(|e| e.event_id).collect();
+        let segment = lz4_flex::compress_prepend_size(&postcard::to_allocvec(events)?);
+        let header = CheckpointHeader {
+            version: CHECKPOINT_VERSION,
+            world,
+            epoch_id,
+            head_seq,
+            event_count: events.len() as u64,
+            merkle_root: merkle_root(&ids),
+            segment_hash: *blake3::hash(&segment).as_bytes(),
+            version_vector,
+            created_at,
+        };
+        Ok(Self {
+            header,
+            segment,
+            signatures: Vec::new(),
+        })
+    }
+
+    /// Add (or replace) a signature from `keypair`
+    pub fn sign(&mut self, keypair: &KeyPair) -> Result<(), CheckpointError> {
+        let signer = keypair.public_key();
+        let signature = keypair.sign(&self.header.sign_bytes()?).to_vec();
+        self.signatures.retain(|s| s.signer != signer);
+        self.signatures
+            .push(CheckpointSignature { signer, signature });
+        Ok(())
+    }
+
+    /// Check a checkpoint produced by another member and add a signature
+    /// from `keypair`
+    ///
+    /// The header must be for `world` and the segment must match its hash,
+    /// event count and Merkle root.
+    pub fn cosign(&mut self, world: WorldId, keypair: &KeyPair) -> Result<(), CheckpointError> {
+        if self.header.version != CHECKPOINT_VERSION {
+            return Err(CheckpointError::Version(self.header.version));
+        }
+        if self.header.world != world {
+            return Err(CheckpointError::WorldMismatch);
+        }
+        self.events()?;
+        self.sign(keypair)
+    }
+
+    /// Number of distinct trusted signers with a valid signature
+    pub fn trusted_signatures(&self, trusted: &[[u8; 32]]) -> Result<usize, CheckpointError> {
+        let message = self.header.sign_bytes()?;
+        let mut valid = HashSet::new();
+        for sig in &self.signatures {
+            if !trusted.contains(&sig.signer) {
+                continue;
+            }
+            let Ok(signature) = <[u8; 64]>::try_from(sig.signature.as_slice()) else {
+                continue;
+            };
+            if KeyPair::verify(&sig.signer, &message, &signature).is_ok() {
+                valid.insert(sig.signer);
+            }
+        }
+        Ok(valid.len())
+    }
+
+    /// Check the header against `world` and require `threshold` trusted
+    /// signatures
+    pub fn verify(
+        &self,
+        world: WorldId,
+        trusted: &[[u8; 32]],
+        threshold: usize,
+    ) -> Result<(), CheckpointError> {
+        if self.header.version != CHECKPOINT_VERSION {
+            return Err(CheckpointError::Version(self.header.version));
+        }
+        if self.header.world != world {
+            return Err(CheckpointError::WorldMismatch);
+        }
+        let required = threshold.max(1);
+        let valid = self.trusted_signatures(trusted)?;
+        if valid < required {
+            return Err(CheckpointError::NotEnoughSignatures { valid, required });
+        }
+        Ok(())
+    }
+
+    /// Decompress the segment and check it against the header
+    pub fn events(&self) -> Result<Vec<Event>, CheckpointError> {
+        if *blake3::hash(&self.segment).as_bytes() != self.header.segment_hash {
+            return Err(CheckpointError::SegmentMismatch);
+        }
+        let bytes = lz4_flex::decompress_size_prepended(&self.segment)
+            .map_err(|e| CheckpointError::Decompress(e.to_string()))?;
+        let events: Vec<Event> = postcard::from_bytes(&bytes)?;
+        if events.len() as u64 != self.header.event_count {
+            return Err(CheckpointError::CountMismatch {
+                expected: self.header.event_count,
+                actual: events.len() as u64,
+            });
+        }
+        let ids: Vec<EventId> = events.iter().map(|e| e.event_id).collect();
+        if merkle_root(&ids) != self.header.merkle_root {
+            return Err(CheckpointError::RootMismatch);
+        }
+        Ok(events)
+    }
+
+    /// Encode for storage or transfer
+    pub fn to_bytes(&self) -> Result<Vec<u8>, CheckpointError> {
+        Ok(postcard::to_allocvec(self)?)
+    }
+
+    /// Decode from `to_bytes`
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, CheckpointError> {
+        Ok(postcard::from_bytes(bytes)?)
+    }
+
+    /// Write to a file, replacing it atomically
+    pub fn write_to(&self, path: &Path) -> Result<(), CheckpointError> {
+        let tmp = path.with_extension("tmp");
+        std::fs::write(&tmp, self.to_bytes()?)?;
+        std::fs::rename(&tmp, path)?;
+        Ok(())
+    }
+
+    /// Read from a file
+    pub fn read_from(path: &Path) -> Result<Self, CheckpointError> {
+        Self::from_bytes(&std::fs::read(path)?)
+    }
+}
+
+/// Merkle root over event IDs in sorted order
+///
+/// An odd node at the end of a level is promoted unchanged. The root of an
+/// empty set is all zeros.
+pub fn merkle_root(ids: &[EventId]) -> [u8; 32] {
+    let mut sorted: Vec<&EventId> = ids.iter().collect();
+    sorted.sort_by_key(|id| id.0);
+
+    let mut level: Vec<[u8; 32]> = sorted
+        .into_iter()
+        .map(|id| {
+            let mut hasher = blake3::Hasher::new();
+            hasher.update(DOMAIN_LEAF);
+            hasher.update(&id.0);
+            *hasher.finalize().as_bytes()
+        })
+        .collect();
+    if level.is_empty() {
+        return [0; 32];
+    }
+
+    while level.len() > 1 {
+        level = level
+            .chunks(2)
+            .map(|pair| match pair {
+                [left, right] => {
+                    let mut hasher = blake3::Hasher::new();
+                    hasher.update(DOMAIN_NODE);
+                    hasher.update(left);
+                    hasher.update(right);
+                    *hasher.finalize().as_bytes()
+                }
+                [single] => *single,
+                _ => unreachable!(),
+            })
+            .collect();
+    }
+    level[0]
+}
+
+/// Parse a hex-encoded 32-byte signer key
+pub fn parse_signer(s: &str) -> Result<[u8; 32], String> {
+    let bytes = hex::decode(s.trim()).map_err(|e| format!("invalid hex '{}': {}", s, e))?;
+    bytes
+        .try_into()
+        .map_err(|_| format!("signer key '{}' is not 32 bytes", s))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn endorsement(id: u8) -> Event {
+        Event {
+            event_id: EventId([id; 32]),
+            world: WorldId([0; 32]),
+            epoch_id: 1,
+            event_type: EventType::RuleEndorsement,
+            body: EventBody::RuleEndorsement(RuleEndorsementEvent {
+                world: WorldId([0; 32]),
+                epoch_id: 1,
+                rule_bundle_hash: [0; 32],
+                weight: 1.0,
+                signer_transport_pubkey: vec![],
+                signature: vec![],
+            }),
+        }
+    }
+
+    #[test]
+    fn test_merkle_root_is_order_independent() {
+        let ids: Vec<_> = (1..=5).map(|i| EventId([i; 32])).collect();
+        let mut reversed = ids.clone();
+        reversed.reverse();
+        assert_eq!(merkle_root(&ids), merkle_root(&reversed));
+        assert_ne!(merkle_root(&ids), merkle_root(&ids[..4]));
+        assert_eq!(merkle_root(&[]), [0; 32]);
+    }
+
+    #[test]
+    fn test_checkpoint_roundtrip_and_signatures() {
+        let events: Vec<_> = (1..=3).map(endorsement).collect();
+        let mut checkpoint = Checkpoint::build(WorldId([0; 32]), 1, 3, vec![], 0, &events).unwrap();
+
+        let producer = KeyPair::from_seed(&[1; 32]);
+        let member = KeyPair::from_seed(&[2; 32]);
+        let outsider = KeyPair::from_seed(&[3; 32]);
+        checkpoint.sign(&producer).unwrap();
+        checkpoint.sign(&outsider).unwrap();
+
+        let trusted = [producer.public_key(), member.public_key()];
+        let decoded = Checkpoint::from_bytes(&checkpoint.to_bytes().unwrap()).unwrap();
+        decoded.verify(WorldId([0; 32]), &trusted, 1).unwrap();
+        assert!(matches!(
+            decoded.verify(WorldId([0; 32]), &trusted, 2),
+            Err(CheckpointError::NotEnoughSignatures {
+                valid: 1,
+                required: 2
+            })
+        ));
+        assert!(matches!(
+            decoded.verify(WorldId([9; 32]), &trusted, 1),
+            Err(CheckpointError::WorldMismatch)
+        ));
+        assert_eq!(decoded.events().unwrap(), events);
+
+        // Any change to the header invalidates signatures
+        let mut tampered = decoded.clone();
+        tampered.header.head_seq += 1;
+        assert_eq!(tampered.trusted_signatures(&trusted).unwrap(), 0);
+
+        // A member co-signs after checking the segment
+        let mut cosigned = decoded.clone();
+        cosigned.cosign(WorldId([0; 32]), &member).unwrap();
+        cosigned.verify(WorldId([0; 32]), &trusted, 2).unwrap();
+        assert!(matches!(
+            decoded.clone().cosign(WorldId([9; 32]), &member),
+            Err(CheckpointError::WorldMismatch)
+        ));
+
+        // A segment swapped under a valid header is rejected
+        let mut swapped = decoded;
+        swapped.segment = Checkpoint::build(WorldId([0; 32]), 1, 3, vec![], 0, &events[..2])
+            .unwrap()
+            .segment;
+        assert!(matches!(
+            swapped.events(),
+            Err(CheckpointError::SegmentMismatch)
+        ));
+        assert!(matches!(
+            swapped.cosign(WorldId([0; 32]), &member),
+            Err(CheckpointError::SegmentMismatch)
+        ));
+        assert_eq!(swapped.signatures.len(), 2);
+    }
+}
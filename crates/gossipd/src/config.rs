@@ -1,9 +1,14 @@
 //! Configuration for gossipd
 
+use crate::checkpoint::parse_signer;
+use crate::membership::MembershipManager;
+use crate::retention::RetentionRule;
 use clap::Parser;
 use serde::{Deserialize, Serialize};
 use std::net::SocketAddr;
 use std::path::PathBuf;
+use std::str::FromStr;
+use terrain_gossip_core::types::WorldId;
 
 /// gossipd - TerrainGossip Event Log Daemon
 #[derive(Parser, Debug, Clone)]
@@ -14,6 +19,10 @@ pub struct Config {
     #[arg(short, long, default_value = "0.0.0.0:9100")]
     pub listen: SocketAddr,
 
+    /// Listen address for the local API used by routerd, prober and infernode
+    #[arg(long, default_value = "127.0.0.1:9001")]
+    pub api_listen: SocketAddr,
+
     /// Data directory for persistent storage
     #[arg(short, long, default_value = "./data/gossipd")]
     pub data_dir: PathBuf,
@@ -26,10 +35,30 @@ pub struct Config {
     #[arg(long)]
     pub rule_bundle: Option<PathBuf>,
 
+    /// JSON file listing further worlds to host alongside the primary one
+    #[arg(long)]
+    pub worlds: Option<PathBuf>,
+
     /// Bootstrap peers (comma-separated addresses)
     #[arg(long, value_delimiter = ',')]
     pub bootstrap: Vec<SocketAddr>,
 
+    /// Address advertised to peers for inbound connections (defaults to --listen)
+    #[arg(long)]
+    pub advertise_addr: Option<SocketAddr>,
+
+    /// Target number of outbound peer connections
+    #[arg(long, default_value = "8")]
+    pub target_outbound: usize,
+
+    /// Maximum number of inbound peer connections
+    #[arg(long, default_value = "32")]
+    pub max_inbound: usize,
+
+    /// Interval between outbound dial rounds in seconds
+    #[arg(long, default_value = "10")]
+    pub dial_interval_secs: u64,
+
     /// Maximum events per delta sync response
     #[arg(long, default_value = "1000")]
     pub max_sync_events: u32,
@@ -38,6 +67,138 @@ pub struct Config {
     #[arg(long, default_value = "30")]
     pub sync_interval_secs: u64,
 
+    /// Number of peers that receive new events by eager push
+    #[arg(long, default_value = "4")]
+    pub push_fanout: usize,
+
+    /// Maximum number of peers that receive lazy IHAVE announcements per event
+    #[arg(long, default_value = "8")]
+    pub lazy_fanout: usize,
+
+    /// Number of recently seen event IDs kept for push dedup
+    #[arg(long, default_value = "65536")]
+    pub seen_cache_size: usize,
+
+    /// How long to wait for an announced event before requesting it (ms)
+    #[arg(long, default_value = "500")]
+    pub ihave_timeout_ms: u64,
+
+    /// How long to wait for a peer to answer an event fetch before asking another (ms)
+    #[arg(long, default_value = "2000")]
+    pub fetch_timeout_ms: u64,
+
+    /// Retention overrides as TYPE=EPOCHS or TYPE=forever (e.g. receipt=288,dispute=forever)
+    #[arg(long, value_delimiter = ',')]
+    pub retention: Vec<RetentionRule>,
+
+    /// Prune interval in seconds
+    #[arg(long, default_value = "3600")]
+    pub prune_interval_secs: u64,
+
+    /// Forget disconnected peers idle for this many seconds
+    #[arg(long, default_value = "86400")]
+    pub stale_peer_secs: u64,
+
+    /// How long events failing validation are kept in quarantine (seconds)
+    #[arg(long, default_value = "3600")]
+    pub quarantine_ttl_secs: u64,
+
+    /// Maximum number of quarantined events
+    #[arg(long, default_value = "10000")]
+    pub max_quarantined: usize,
+
+    /// Interval between checkpoints in seconds (0 disables)
+    #[arg(long, default_value = "3600")]
+    pub checkpoint_interval_secs: u64,
+
+    /// Directory for checkpoint files (defaults to <data-dir>/checkpoints)
+    #[arg(long)]
+    pub checkpoint_dir: Option<PathBuf>,
+
+    /// Import this checkpoint on startup before syncing
+    #[arg(long)]
+    pub import_checkpoint: Option<PathBuf>,
+
+    /// Hex public keys of members trusted to sign checkpoints (comma-separated)
+    #[arg(long, value_delimiter = ',', value_parser = parse_signer)]
+    pub trusted_checkpoint_signers: Vec<[u8; 32]>,
+
+    /// Trusted signatures required to import a checkpoint
+    #[arg(long, default_value = "1")]
+    pub checkpoint_threshold: usize,
+
+    /// Check this checkpoint file on startup and add this node's signature
+    #[arg(long)]
+    pub cosign_checkpoint: Option<PathBuf>,
+
+    /// Import a JSON ban list exported by another node on startup
+    #[arg(long)]
+    pub import_bans: Option<PathBuf>,
+
+    /// Frames per minute accepted from one peer before it is penalized
+    #[arg(long, default_value = "6000")]
+    pub peer_rate_limit_rpm: u32,
+
+    /// Suspend peers whose reputation falls to this (0.0-1.0)
+    #[arg(long, default_value = "0.5")]
+    pub suspend_below_reputation: f64,
+
+    /// Ban peers whose reputation falls to this (0.0-1.0)
+    #[arg(long, default_value = "0.1")]
+    pub ban_below_reputation: f64,
+
+    /// Length of a misbehavior suspension in seconds
+    #[arg(long, default_value = "600")]
+    pub suspension_secs: u64,
+
+    /// Reputation regained per hour without offenses
+    #[arg(long, default_value = "0.05")]
+    pub reputation_recovery_per_hour: f64,
+
+    /// Dimension of the semantic event index (0 disables it)
+    #[arg(long, default_value = "256")]
+    pub semantic_dim: usize,
+
+    /// Residual correlation at which probers are clustered as colluding
+    #[arg(long, default_value = "0.9")]
+    pub anomaly_correlation_threshold: f64,
+
+    /// Sign and publish a dispute for each anomaly alert
+    #[arg(long)]
+    pub anomaly_disputes: bool,
+
+    /// Log conflicting and equivocating attestations without publishing
+    /// disputes for them
+    #[arg(long)]
+    pub no_auto_disputes: bool,
+
+    /// Trust-weighted endorsement a rule bundle needs in each epoch
+    #[arg(long, default_value = "3.0")]
+    pub activation_threshold: f64,
+
+    /// Consecutive epochs a bundle's endorsement must hold to activate
+    #[arg(long, default_value = "3")]
+    pub activation_epochs: u64,
+
+    /// Trust in endorsement signers that are not known members; above 0,
+    /// fresh keys can add up to activate a bundle
+    #[arg(long, default_value = "0.0")]
+    pub unknown_signer_trust: f64,
+
+    /// Seal control-plane frames: off, epoch or session
+    #[arg(long, default_value = "off")]
+    pub seal: SealMode,
+
+    /// Master key for epoch control-plane keys (hex), needed for
+    /// `--seal epoch`
+    #[arg(long, env = "GOSSIP_CONTROL_PLANE_MASTER_KEY", hide_env_values = true)]
+    #[arg(value_parser = parse_key)]
+    pub control_plane_master_key: Option<[u8; 32]>,
+
+    /// Accept events without checking IDs and signatures (testing only)
+    #[arg(long)]
+    pub insecure_skip_verify: bool,
+
     /// Enable verbose logging
     #[arg(short, long)]
     pub verbose: bool,
@@ -56,8 +217,119 @@ impl Config {
         if self.world_phrase.split_whitespace().count() < 2 {
             anyhow::bail!("World phrase should contain at least 2 words");
         }
+        if self.push_fanout == 0 {
+            anyhow::bail!("Push fanout must be at least 1");
+        }
+        if !self.api_listen.ip().is_loopback() {
+            anyhow::bail!("API listen address must be a loopback address");
+        }
+        if !(0.0..=1.0).contains(&self.ban_below_reputation)
+            || self.ban_below_reputation > self.suspend_below_reputation
+            || self.suspend_below_reputation > 1.0
+        {
+            anyhow::bail!("Reputation thresholds must satisfy 0 <= ban <= suspend <= 1");
+        }
+        if !(-1.0..=1.0).contains(&self.anomaly_correlation_threshold) {
+            anyhow::bail!("Anomaly correlation threshold must be between -1 and 1");
+        }
+        if self.activation_threshold <= 0.0 || self.activation_epochs == 0 {
+            anyhow::bail!("Bundle activation needs a positive threshold and epoch count");
+        }
+        if !(0.0..=1.0).contains(&self.unknown_signer_trust) {
+            anyhow::bail!("Unknown signer trust must be between 0 and 1");
+        }
+        if self.seal == SealMode::Epoch && self.control_plane_master_key.is_none() {
+            anyhow::bail!("--seal epoch needs --control-plane-master-key");
+        }
+        if self.import_checkpoint.is_some()
+            && self.trusted_checkpoint_signers.len() < self.checkpoint_threshold.max(1)
+        {
+            anyhow::bail!(
+                "Checkpoint import needs at least --checkpoint-threshold trusted signers"
+            );
+        }
         Ok(())
     }
+
+    /// Directory checkpoints are written to
+    pub fn checkpoint_dir(&self) -> PathBuf {
+        self.checkpoint_dir
+            .clone()
+            .unwrap_or_else(|| self.data_dir.join("checkpoints"))
+    }
+
+    /// Storage directory for the events of a followed fork's world
+    pub fn fork_dir(&self, world: &WorldId) -> PathBuf {
+        self.data_dir.join("forks").join(hex::encode(world.0))
+    }
+
+    /// Configuration for an additional hosted world
+    ///
+    /// The world gets its own storage under `worlds/` in the data directory,
+    /// named by its hex world ID so the phrase never appears on disk, and
+    /// shares listeners, bootstrap peers and limits with the primary.
+    /// Ban and checkpoint imports and co-signing apply to the primary world
+    /// only.
+    pub fn for_world(&self, spec: &WorldSpec) -> Config {
+        let name = hex::encode(MembershipManager::derive_world_id(&spec.world_phrase));
+        Config {
+            world_phrase: spec.world_phrase.clone(),
+            rule_bundle: spec.rule_bundle.clone(),
+            worlds: None,
+            data_dir: self.data_dir.join("worlds").join(&name),
+            sync_interval_secs: spec.sync_interval_secs.unwrap_or(self.sync_interval_secs),
+            checkpoint_dir: self.checkpoint_dir.as_ref().map(|dir| dir.join(&name)),
+            import_checkpoint: None,
+            cosign_checkpoint: None,
+            import_bans: None,
+            ..self.clone()
+        }
+    }
+}
+
+/// A further world hosted by the same process
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WorldSpec {
+    pub world_phrase: String,
+    /// Path to the world's rule bundle JSON file
+    #[serde(default)]
+    pub rule_bundle: Option<PathBuf>,
+    /// Sync interval in seconds (defaults to the primary world's)
+    #[serde(default)]
+    pub sync_interval_secs: Option<u64>,
+}
+
+/// How control-plane frames are sealed after the handshake
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SealMode {
+    /// Plaintext frames
+    #[default]
+    Off,
+    /// Sealed under a key derived from the epoch control-plane key
+    Epoch,
+    /// Sealed under per-peer session keys
+    Session,
+}
+
+impl FromStr for SealMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "off" => Ok(SealMode::Off),
+            "epoch" => Ok(SealMode::Epoch),
+            "session" => Ok(SealMode::Session),
+            other => Err(format!("unknown seal mode '{}' (off, epoch, session)", other)),
+        }
+    }
+}
+
+/// Parse a hex-encoded 32-byte key
+fn parse_key(s: &str) -> Result<[u8; 32], String> {
+    let bytes = hex::decode(s.trim()).map_err(|e| format!("invalid hex key: {}", e))?;
+    bytes
+        .try_into()
+        .map_err(|_| "key is not 32 bytes".to_string())
 }
 
 /// Persisted node state
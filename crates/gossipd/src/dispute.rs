@@ -0,0 +1,312 @@
+//! Automatic dispute generation for conflicting attestations
+//!
+//! Attestations are grouped by (target, epoch, challenge). Two reports from
+//! different probers conflict when some metric differs by more than the
+//! rule bundle's `disagreement_quarantine_threshold`; one prober signing two
+//! different outcomes for the same challenge is equivocation, which is
+//! provable fraud. Each pair of events is disputed at most once, including
+//! pairs already disputed by other nodes.
+
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use terrain_gossip_belief::residual::{components, COMPONENTS};
+use terrain_gossip_core::types::*;
+
+type ChallengeKey = (TargetRef, u64, ChallengeId);
+
+/// Dispute thresholds
+#[derive(Debug, Clone)]
+pub struct DisputeConfig {
+    /// Largest metric difference tolerated between probers
+    pub tolerance: f64,
+    /// Epochs of attestations kept
+    pub window_epochs: u64,
+}
+
+impl Default for DisputeConfig {
+    fn default() -> Self {
+        Self {
+            tolerance: 0.3,
+            window_epochs: 24,
+        }
+    }
+}
+
+impl DisputeConfig {
+    /// Tolerance taken from a world's rule bundle
+    pub fn from_rule_bundle(bundle: &RuleBundle) -> Self {
+        Self {
+            tolerance: bundle.disagreement_quarantine_threshold,
+            ..Self::default()
+        }
+    }
+}
+
+/// Why two attestations are disputed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DisputeKind {
+    /// Different probers disagree beyond tolerance
+    Conflict,
+    /// One prober signed two outcomes for the same challenge
+    Equivocation,
+}
+
+/// A pair of attestations to dispute
+#[derive(Debug, Clone)]
+pub struct DisputeFinding {
+    pub kind: DisputeKind,
+    pub epoch_id: u64,
+    pub event_a: EventId,
+    pub event_b: EventId,
+    /// Largest metric difference between the two
+    pub distance: f64,
+}
+
+impl DisputeFinding {
+    /// Human-readable reason
+    pub fn reason(&self) -> String {
+        match self.kind {
+            DisputeKind::Conflict => format!(
+                "conflicting attestations: metrics differ by {:.3}",
+                self.distance
+            ),
+            DisputeKind::Equivocation => format!(
+                "equivocation: prober signed two outcomes for one challenge (differ by {:.3})",
+                self.distance
+            ),
+        }
+    }
+
+    /// Unsigned dispute for this finding
+    pub fn to_dispute(&self, world: WorldId) -> EventBody {
+        EventBody::Dispute(DisputeEvent {
+            world,
+            epoch_id: self.epoch_id,
+            event_a: self.event_a,
+            event_b: self.event_b,
+            reason: self.reason(),
+            disputer_transport_pubkey: vec![],
+            signature: vec![],
+        })
+    }
+}
+
+struct Report {
+    prober: Vec<u8>,
+    event_id: EventId,
+    values: [f64; COMPONENTS],
+    evidence_commitment: Bytes32,
+}
+
+impl Report {
+    fn distance(&self, other: &Report) -> f64 {
+        self.values
+            .iter()
+            .zip(&other.values)
+            .fold(0.0f64, |max, (a, b)| max.max((a - b).abs()))
+    }
+}
+
+fn pair(a: EventId, b: EventId) -> (EventId, EventId) {
+    if a.0 <= b.0 {
+        (a, b)
+    } else {
+        (b, a)
+    }
+}
+
+#[derive(Default)]
+struct State {
+    reports: HashMap<ChallengeKey, Vec<Report>>,
+    /// Pairs already disputed, by us or others, smaller ID first
+    disputed: HashMap<(EventId, EventId), u64>,
+}
+
+/// Finds conflicting and equivocating attestations
+pub struct DisputeDetector {
+    config: DisputeConfig,
+    state: Mutex<State>,
+}
+
+impl DisputeDetector {
+    /// Create a detector
+    pub fn new(config: DisputeConfig) -> Self {
+        Self {
+            config,
+            state: Mutex::new(State::default()),
+        }
+    }
+
+    /// Ingest an event, returning pairs newly worth disputing
+    ///
+    /// Disputes seen here mark their pair as handled. A new attestation
+    /// yields at most one conflict, against the report farthest from it.
+    pub fn observe(&self, event: &Event) -> Vec<DisputeFinding> {
+        let attestation = match &event.body {
+            EventBody::Attestation(attestation) => attestation,
+            EventBody::Dispute(dispute) => {
+                self.claim(dispute.event_a, dispute.event_b, dispute.epoch_id);
+                return Vec::new();
+            }
+            _ => return Vec::new(),
+        };
+        let report = Report {
+            prober: attestation.prober_transport_pubkey.clone(),
+            event_id: event.event_id,
+            values: components(&attestation.metrics),
+            evidence_commitment: attestation.evidence_commitment,
+        };
+        let key = (
+            attestation.target_ref,
+            attestation.epoch_id,
+            attestation.challenge_id,
+        );
+
+        let mut state = self.state.lock();
+        let reports = state.reports.entry(key).or_default();
+        if reports.iter().any(|r| r.event_id == report.event_id) {
+            return Vec::new();
+        }
+
+        let mut candidates: Vec<_> = reports
+            .iter()
+            .filter(|other| other.prober == report.prober)
+            .map(|other| (other, report.distance(other)))
+            .filter(|(other, distance)| {
+                *distance > 0.0 || other.evidence_commitment != report.evidence_commitment
+            })
+            .map(|(other, distance)| (DisputeKind::Equivocation, other.event_id, distance))
+            .collect();
+        let farthest = reports
+            .iter()
+            .filter(|other| other.prober != report.prober)
+            .map(|other| (other.event_id, report.distance(other)))
+            .max_by(|a, b| a.1.total_cmp(&b.1));
+        if let Some((other, distance)) = farthest {
+            if distance > self.config.tolerance {
+                candidates.push((DisputeKind::Conflict, other, distance));
+            }
+        }
+        reports.push(report);
+
+        candidates
+            .into_iter()
+            .filter_map(|(kind, other, distance)| {
+                let (event_a, event_b) = pair(event.event_id, other);
+                let fresh = state
+                    .disputed
+                    .insert((event_a, event_b), attestation.epoch_id)
+                    .is_none();
+                fresh.then_some(DisputeFinding {
+                    kind,
+                    epoch_id: attestation.epoch_id,
+                    event_a,
+                    event_b,
+                    distance,
+                })
+            })
+            .collect()
+    }
+
+    /// Record a pair as disputed; false if it already was
+    pub fn claim(&self, a: EventId, b: EventId, epoch_id: u64) -> bool {
+        self.state
+            .lock()
+            .disputed
+            .insert(pair(a, b), epoch_id)
+            .is_none()
+    }
+
+    /// Forget attestations and disputes older than the window ending at
+    /// `current_epoch`
+    pub fn prune(&self, current_epoch: u64) {
+        let oldest = current_epoch.saturating_sub(self.config.window_epochs);
+        let mut state = self.state.lock();
+        state.reports.retain(|key, _| key.1 >= oldest);
+        state.disputed.retain(|_, epoch| *epoch >= oldest);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn attestation(id: u8, prober: u8, challenge: u8, success: f64, evidence: u8) -> Event {
+        Event {
+            event_id: EventId([id; 32]),
+            world: WorldId([0; 32]),
+            epoch_id: 1,
+            event_type: EventType::Attestation,
+            body: EventBody::Attestation(BehaviorAttestation {
+                attestation_id: AttestationId([id; 32]),
+                world: WorldId([0; 32]),
+                epoch_id: 1,
+                challenge_id: ChallengeId([challenge; 32]),
+                target_ref: TargetRef([1; 32]),
+                target_fah: None,
+                metrics: MetricsVector {
+                    success_rate: success,
+                    refusal_consistency: 0.9,
+                    tool_fidelity: 0.9,
+                    latency_p50_ms: 200,
+                    latency_p95_ms: 400,
+                    robustness_score: 0.8,
+                    drift_indicator: 0.1,
+                    freshness: FreshnessStrength::None,
+                },
+                evidence_commitment: [evidence; 32],
+                freshness_anchor: None,
+                prober_transport_pubkey: vec![prober],
+                signature: vec![],
+            }),
+        }
+    }
+
+    #[test]
+    fn test_conflict_disputed_once() {
+        let detector = DisputeDetector::new(DisputeConfig::default());
+        assert!(detector.observe(&attestation(1, 1, 1, 0.9, 0)).is_empty());
+        // Within tolerance
+        assert!(detector.observe(&attestation(2, 2, 1, 0.8, 0)).is_empty());
+        // Another challenge is judged separately
+        assert!(detector.observe(&attestation(3, 3, 2, 0.1, 0)).is_empty());
+
+        let findings = detector.observe(&attestation(4, 3, 1, 0.1, 0));
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].kind, DisputeKind::Conflict);
+        assert_eq!(
+            (findings[0].event_a, findings[0].event_b),
+            (EventId([1; 32]), EventId([4; 32]))
+        );
+        assert!((findings[0].distance - 0.8).abs() < 1e-9);
+
+        // Re-delivery and an existing dispute for the pair are both no-ops
+        assert!(detector.observe(&attestation(4, 3, 1, 0.1, 0)).is_empty());
+        assert!(!detector.claim(EventId([4; 32]), EventId([1; 32]), 1));
+
+        detector.prune(100);
+        assert!(detector.claim(EventId([4; 32]), EventId([1; 32]), 100));
+    }
+
+    #[test]
+    fn test_equivocation() {
+        let detector = DisputeDetector::new(DisputeConfig::default());
+        assert!(detector.observe(&attestation(1, 1, 1, 0.9, 0)).is_empty());
+
+        // Same metrics, different evidence: still two outcomes
+        let findings = detector.observe(&attestation(2, 1, 1, 0.9, 7));
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].kind, DisputeKind::Equivocation);
+
+        let findings = detector.observe(&attestation(3, 1, 1, 0.2, 0));
+        let kinds: Vec<_> = findings.iter().map(|f| f.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![DisputeKind::Equivocation, DisputeKind::Equivocation]
+        );
+        assert!(matches!(
+            findings[0].to_dispute(WorldId([0; 32])),
+            EventBody::Dispute(DisputeEvent { epoch_id: 1, .. })
+        ));
+    }
+}
@@ -0,0 +1,420 @@
+//! Rule bundle endorsement tallies, activation and world forks
+//!
+//! Endorsements are tallied per bundle hash and epoch with one vote per
+//! signer (its highest weight), scaled by how much this node trusts the
+//! signer. A bundle activates once its tally stays at or above the policy
+//! threshold for `epochs` consecutive epochs, naming a successor world
+//! `BLAKE3("world" || phrase || bundle_hash)`. The operator decides whether
+//! the node follows the fork; the choice is persisted and applied at the
+//! next start.
+
+use crate::storage::{Storage, StorageError};
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use terrain_gossip_core::crypto::derive_world_id_from_hash;
+use terrain_gossip_core::types::*;
+use thiserror::Error;
+
+/// Metadata key of the operator's fork choice
+const FORK_CHOICE_KEY: &str = "fork_choice";
+
+/// Metadata key of the activations seen so far
+const ACTIVATIONS_KEY: &str = "bundle_activations";
+
+/// Trust in a signer's transport key, `None` if unknown
+pub type TrustFn = Arc<dyn Fn(&[u8]) -> Option<f64> + Send + Sync>;
+
+/// Endorsement errors
+#[derive(Debug, Error)]
+pub enum EndorsementError {
+    #[error("Storage error: {0}")]
+    Storage(#[from] StorageError),
+    #[error("Codec error: {0}")]
+    Codec(#[from] postcard::Error),
+    #[error("Rule bundle {} has not activated", hex::encode(.0))]
+    NotActivated(Bytes32),
+}
+
+/// When a bundle activates
+#[derive(Debug, Clone)]
+pub struct ActivationPolicy {
+    /// Trust-weighted endorsement needed in each epoch
+    pub threshold: f64,
+    /// Consecutive epochs the threshold must hold
+    pub epochs: u64,
+    /// Trust in signers the node knows nothing about; keys are free, so
+    /// this is 0 unless endorsements are meant to be open
+    pub unknown_trust: f64,
+}
+
+impl Default for ActivationPolicy {
+    fn default() -> Self {
+        Self {
+            threshold: 3.0,
+            epochs: 3,
+            unknown_trust: 0.0,
+        }
+    }
+}
+
+/// Endorsement of one bundle in one epoch
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EndorsementTotal {
+    pub rule_bundle_hash: Bytes32,
+    pub epoch_id: u64,
+    /// Sum of trust-weighted votes
+    pub weight: f64,
+    /// Distinct signers
+    pub signers: usize,
+}
+
+/// An activated bundle and the world it forks to
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Activation {
+    pub rule_bundle_hash: Bytes32,
+    /// Last epoch of the window that activated it
+    pub epoch_id: u64,
+    pub successor: WorldId,
+}
+
+/// Operator decision on forks
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum ForkChoice {
+    /// Stay on the world derived from the phrase
+    #[default]
+    Stay,
+    /// Move to an activated bundle's successor world
+    Follow {
+        rule_bundle_hash: Bytes32,
+        world: WorldId,
+    },
+}
+
+impl ForkChoice {
+    /// The operator's persisted choice
+    pub fn load(storage: &Storage) -> Result<Self, EndorsementError> {
+        match storage.get_metadata(FORK_CHOICE_KEY)? {
+            Some(bytes) => Ok(postcard::from_bytes(&bytes)?),
+            None => Ok(Self::Stay),
+        }
+    }
+}
+
+/// Signer votes for one (bundle, epoch)
+type Votes = HashMap<Vec<u8>, f64>;
+
+/// Tallies endorsements and tracks bundle activations
+pub struct EndorsementTally {
+    /// Phrase successor worlds are derived from
+    world_phrase: String,
+    policy: ActivationPolicy,
+    trust: Option<TrustFn>,
+    storage: Arc<Storage>,
+    votes: RwLock<HashMap<(Bytes32, u64), Votes>>,
+    activations: RwLock<HashMap<Bytes32, Activation>>,
+}
+
+impl EndorsementTally {
+    /// Create a tally with in-memory state
+    pub fn new(world_phrase: impl Into<String>, policy: ActivationPolicy) -> Self {
+        Self {
+            world_phrase: world_phrase.into(),
+            policy,
+            trust: None,
+            storage: Arc::new(Storage::memory()),
+            votes: RwLock::new(HashMap::new()),
+            activations: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Weight votes by `trust` instead of the policy's unknown trust
+    pub fn with_trust(mut self, trust: TrustFn) -> Self {
+        self.trust = Some(trust);
+        self
+    }
+
+    /// Persist activations and the fork choice in `storage`, loading any
+    /// existing activations
+    pub fn with_storage(mut self, storage: Arc<Storage>) -> Result<Self, EndorsementError> {
+        if let Some(bytes) = storage.get_metadata(ACTIVATIONS_KEY)? {
+            let activations: Vec<Activation> = postcard::from_bytes(&bytes)?;
+            self.activations = RwLock::new(
+                activations
+                    .into_iter()
+                    .map(|a| (a.rule_bundle_hash, a))
+                    .collect(),
+            );
+        }
+        self.storage = storage;
+        Ok(self)
+    }
+
+    /// Activation policy
+    pub fn policy(&self) -> &ActivationPolicy {
+        &self.policy
+    }
+
+    fn trust_of(&self, signer: &[u8]) -> f64 {
+        self.trust
+            .as_ref()
+            .and_then(|trust| trust(signer))
+            .unwrap_or(self.policy.unknown_trust)
+            .clamp(0.0, 1.0)
+    }
+
+    /// Count a `RuleEndorsementEvent`; other events are ignored
+    ///
+    /// Returns whether the signer's vote for the bundle and epoch changed.
+    pub fn record(&self, event: &Event) -> bool {
+        let EventBody::RuleEndorsement(endorsement) = &event.body else {
+            return false;
+        };
+        let weight = if endorsement.weight.is_finite() {
+            endorsement.weight.clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+        let mut votes = self.votes.write();
+        let vote = votes
+            .entry((endorsement.rule_bundle_hash, endorsement.epoch_id))
+            .or_default()
+            .entry(endorsement.signer_transport_pubkey.clone())
+            .or_insert(-1.0);
+        if weight <= *vote {
+            return false;
+        }
+        *vote = weight;
+        true
+    }
+
+    fn total(&self, bundle: &Bytes32, epoch_id: u64, votes: &Votes) -> EndorsementTotal {
+        EndorsementTotal {
+            rule_bundle_hash: *bundle,
+            epoch_id,
+            weight: votes
+                .iter()
+                .map(|(signer, weight)| weight * self.trust_of(signer))
+                .sum(),
+            signers: votes.len(),
+        }
+    }
+
+    /// Endorsement of every bundle in `epoch_id`, heaviest first
+    pub fn totals(&self, epoch_id: u64) -> Vec<EndorsementTotal> {
+        let mut totals: Vec<EndorsementTotal> = self
+            .votes
+            .read()
+            .iter()
+            .filter(|((_, epoch), _)| *epoch == epoch_id)
+            .map(|((bundle, epoch), votes)| self.total(bundle, *epoch, votes))
+            .collect();
+        totals.sort_by(|a, b| {
+            b.weight
+                .total_cmp(&a.weight)
+                .then(a.rule_bundle_hash.cmp(&b.rule_bundle_hash))
+        });
+        totals
+    }
+
+    /// Activate bundles whose endorsement held for the policy's window
+    /// ending at `current_epoch`, returning the new activations
+    pub fn evaluate(&self, current_epoch: u64) -> Result<Vec<Activation>, EndorsementError> {
+        let window = self.policy.epochs.max(1);
+        let Some(first) = (current_epoch + 1).checked_sub(window) else {
+            return Ok(Vec::new());
+        };
+
+        let mut held: HashMap<Bytes32, u64> = HashMap::new();
+        for ((bundle, epoch), votes) in self.votes.read().iter() {
+            if (first..=current_epoch).contains(epoch)
+                && self.total(bundle, *epoch, votes).weight >= self.policy.threshold
+            {
+                *held.entry(*bundle).or_default() += 1;
+            }
+        }
+
+        let mut activations = self.activations.write();
+        let mut activated: Vec<Activation> = held
+            .into_iter()
+            .filter(|(bundle, epochs)| *epochs == window && !activations.contains_key(bundle))
+            .map(|(bundle, _)| Activation {
+                rule_bundle_hash: bundle,
+                epoch_id: current_epoch,
+                successor: derive_world_id_from_hash(&self.world_phrase, &bundle),
+            })
+            .collect();
+        if activated.is_empty() {
+            return Ok(activated);
+        }
+        activated.sort_by_key(|a| a.rule_bundle_hash);
+        for activation in &activated {
+            activations.insert(activation.rule_bundle_hash, activation.clone());
+        }
+        let all: Vec<&Activation> = activations.values().collect();
+        self.storage
+            .put_metadata(ACTIVATIONS_KEY, &postcard::to_allocvec(&all)?)?;
+        Ok(activated)
+    }
+
+    /// Every activation seen, oldest first
+    pub fn activations(&self) -> Vec<Activation> {
+        let mut activations: Vec<Activation> = self.activations.read().values().cloned().collect();
+        activations.sort_by_key(|a| (a.epoch_id, a.rule_bundle_hash));
+        activations
+    }
+
+    /// The operator's persisted choice
+    pub fn fork_choice(&self) -> Result<ForkChoice, EndorsementError> {
+        ForkChoice::load(&self.storage)
+    }
+
+    /// Follow the fork of an activated bundle, or stay with `None`
+    ///
+    /// The choice is persisted and applied at the next start.
+    pub fn choose(&self, bundle: Option<Bytes32>) -> Result<ForkChoice, EndorsementError> {
+        let choice = match bundle {
+            None => ForkChoice::Stay,
+            Some(hash) => {
+                let activations = self.activations.read();
+                let activation = activations
+                    .get(&hash)
+                    .ok_or(EndorsementError::NotActivated(hash))?;
+                ForkChoice::Follow {
+                    rule_bundle_hash: hash,
+                    world: activation.successor,
+                }
+            }
+        };
+        self.storage
+            .put_metadata(FORK_CHOICE_KEY, &postcard::to_allocvec(&choice)?)?;
+        Ok(choice)
+    }
+
+    /// Forget votes from before the activation window ending at
+    /// `current_epoch`
+    pub fn prune(&self, current_epoch: u64) {
+        let oldest = current_epoch.saturating_sub(self.policy.epochs.max(1));
+        self.votes.write().retain(|(_, epoch), _| *epoch >= oldest);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn endorsement(bundle: u8, epoch_id: u64, signer: u8, weight: f64) -> Event {
+        Event {
+            event_id: EventId([signer; 32]),
+            world: WorldId([0; 32]),
+            epoch_id,
+            event_type: EventType::RuleEndorsement,
+            body: EventBody::RuleEndorsement(RuleEndorsementEvent {
+                world: WorldId([0; 32]),
+                epoch_id,
+                rule_bundle_hash: [bundle; 32],
+                weight,
+                signer_transport_pubkey: vec![signer],
+                signature: vec![],
+            }),
+        }
+    }
+
+    fn policy() -> ActivationPolicy {
+        ActivationPolicy {
+            threshold: 1.5,
+            epochs: 2,
+            unknown_trust: 0.5,
+        }
+    }
+
+    #[test]
+    fn test_tally_dedups_and_weights_by_trust() {
+        let tally = EndorsementTally::new("test world", policy())
+            .with_trust(Arc::new(|signer: &[u8]| (signer == [1]).then_some(1.0)));
+        assert!(tally.record(&endorsement(7, 1, 1, 0.5)));
+        assert!(tally.record(&endorsement(7, 1, 1, 1.0)));
+        // A lower or repeated vote changes nothing
+        assert!(!tally.record(&endorsement(7, 1, 1, 0.4)));
+        assert!(tally.record(&endorsement(7, 1, 2, 1.0)));
+        assert!(tally.record(&endorsement(8, 1, 2, 0.2)));
+
+        let totals = tally.totals(1);
+        assert_eq!(totals.len(), 2);
+        assert_eq!(totals[0].rule_bundle_hash, [7; 32]);
+        assert_eq!(totals[0].signers, 2);
+        assert!((totals[0].weight - 1.5).abs() < 1e-9);
+        assert!((totals[1].weight - 0.1).abs() < 1e-9);
+        assert!(tally.totals(2).is_empty());
+    }
+
+    #[test]
+    fn test_non_members_cannot_activate() {
+        let tally = EndorsementTally::new("test world", ActivationPolicy::default())
+            .with_trust(Arc::new(|signer: &[u8]| (signer[0] >= 10).then_some(1.0)));
+        for epoch_id in 1..=3 {
+            for signer in 1..=6 {
+                tally.record(&endorsement(7, epoch_id, signer, 1.0));
+            }
+        }
+        assert_eq!(tally.totals(1)[0].signers, 6);
+        assert_eq!(tally.totals(1)[0].weight, 0.0);
+        assert!(tally.evaluate(3).unwrap().is_empty());
+
+        // Members do
+        for epoch_id in 1..=3 {
+            for signer in 10..=12 {
+                tally.record(&endorsement(8, epoch_id, signer, 1.0));
+            }
+        }
+        let activated = tally.evaluate(3).unwrap();
+        assert_eq!(activated.len(), 1);
+        assert_eq!(activated[0].rule_bundle_hash, [8; 32]);
+    }
+
+    #[test]
+    fn test_activation_and_fork_choice() {
+        let storage = Arc::new(Storage::memory());
+        let tally = EndorsementTally::new("test world", policy())
+            .with_storage(storage.clone())
+            .unwrap();
+        for signer in 1..=3 {
+            tally.record(&endorsement(7, 1, signer, 1.0));
+        }
+        assert!(tally.evaluate(1).unwrap().is_empty());
+        assert!(matches!(
+            tally.choose(Some([7; 32])),
+            Err(EndorsementError::NotActivated(_))
+        ));
+
+        // Held for a second epoch
+        for signer in 1..=3 {
+            tally.record(&endorsement(7, 2, signer, 1.0));
+        }
+        let activated = tally.evaluate(2).unwrap();
+        assert_eq!(activated.len(), 1);
+        assert_eq!(
+            activated[0].successor,
+            derive_world_id_from_hash("Test  World", &[7; 32])
+        );
+        assert!(tally.evaluate(3).unwrap().is_empty());
+
+        let choice = tally.choose(Some([7; 32])).unwrap();
+        assert_eq!(
+            choice,
+            ForkChoice::Follow {
+                rule_bundle_hash: [7; 32],
+                world: activated[0].successor
+            }
+        );
+        assert_eq!(ForkChoice::load(&storage).unwrap(), choice);
+
+        let reloaded = EndorsementTally::new("test world", policy())
+            .with_storage(storage.clone())
+            .unwrap();
+        assert_eq!(reloaded.activations(), activated);
+        assert_eq!(reloaded.choose(None).unwrap(), ForkChoice::Stay);
+        assert_eq!(ForkChoice::load(&storage).unwrap(), ForkChoice::Stay);
+    }
+}
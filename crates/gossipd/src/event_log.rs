@@ -1,11 +1,32 @@
 //! Append-only event log with version vectors
 
+use crate::checkpoint::{Checkpoint, CheckpointError};
+use crate::index::{self, IndexKind};
+use crate::peer_book::now_millis;
+use crate::query::{EventQuery, QueryPage};
+use crate::quarantine::{
+    Dependency, Quarantine, QuarantineReason, DEFAULT_MAX_QUARANTINED, DEFAULT_QUARANTINE_TTL,
+};
+use crate::retention::{RetentionPolicy, DEFAULT_EPOCH_LEN_MS};
+use crate::semantic::SemanticIndex;
 use crate::storage::{Storage, StorageError};
-use parking_lot::RwLock;
-use std::collections::HashMap;
+use parking_lot::{Mutex, RwLock};
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
+use std::time::Duration;
+use terrain_gossip_core::crypto::{
+    event_type_of, event_world_epoch, verify_event_id, verify_event_signature,
+};
 use terrain_gossip_core::types::*;
 use thiserror::Error;
+use tokio::sync::broadcast;
+use tracing::debug;
+
+/// Capacity of the new-event notification channel
+const NOTIFY_CAPACITY: usize = 1024;
+
+/// Epochs an event may be ahead of the local clock
+const MAX_EPOCH_SKEW: u64 = 2;
 
 /// Event log errors
 #[derive(Debug, Error)]
@@ -18,6 +39,20 @@ pub enum EventLogError {
     InvalidSignature,
     #[error("World mismatch")]
     WorldMismatch,
+    #[error("Invalid event: {0}")]
+    Invalid(QuarantineReason),
+    #[error("Checkpoint error: {0}")]
+    Checkpoint(#[from] CheckpointError),
+}
+
+/// Notification for an event that entered the log
+#[derive(Debug, Clone)]
+pub struct NewEvent {
+    /// Local sequence number, usable as a resume cursor
+    pub seq: u64,
+    pub event: Event,
+    /// Replica the event was merged from (`None` for local appends)
+    pub source: Option<[u8; 32]>,
 }
 
 /// Append-only event log with delta-state CRDT semantics
@@ -26,8 +61,119 @@ pub struct EventLog {
     world_id: WorldId,
     /// Local replica ID (our node's identity)
     replica_id: [u8; 32],
-    /// Cached version vector
+    /// Per other replica, the sequence number in its log up to which its
+    /// events were synced; our own entry is `head_seq`
     version_vector: RwLock<HashMap<[u8; 32], u64>>,
+    /// New-event notifications for push gossip and API subscribers
+    notify_tx: broadcast::Sender<NewEvent>,
+    /// Last assigned sequence number; held while notifying so subscribers
+    /// see sequence numbers in order
+    last_seq: Mutex<u64>,
+    /// Retention policy applied by `prune` and `merge`
+    retention: RetentionPolicy,
+    /// Epoch length for computing the current epoch
+    epoch_len_ms: u64,
+    /// Events held back by validation
+    quarantine: Quarantine,
+    /// Whether event IDs and signatures are checked
+    verify_signatures: bool,
+    /// Embedding index updated as events are stored
+    semantic: Option<Arc<SemanticIndex>>,
+    /// Epochs a descriptor stays current after publication
+    descriptor_ttl: Option<u64>,
+}
+
+/// A provider's latest descriptor publication
+struct CurrentDescriptor {
+    /// (descriptor epoch, publication epoch, descriptor ID)
+    rank: (u64, u64, [u8; 32]),
+    event_id: EventId,
+    descriptor: ProviderDescriptor,
+}
+
+/// Descriptor lifecycle state at one epoch
+struct DescriptorView {
+    /// Latest publication per provider key
+    latest: HashMap<Vec<u8>, CurrentDescriptor>,
+    revoked: HashSet<DescriptorId>,
+    current_epoch: u64,
+    ttl: Option<u64>,
+}
+
+impl DescriptorView {
+    /// Latest publications that are neither revoked nor expired
+    fn into_current(self) -> impl Iterator<Item = CurrentDescriptor> {
+        let Self {
+            latest,
+            revoked,
+            current_epoch,
+            ttl,
+        } = self;
+        latest.into_values().filter(move |entry| {
+            let published = entry.rank.1;
+            let expired = ttl.is_some_and(|ttl| published.saturating_add(ttl) < current_epoch);
+            !expired && !revoked.contains(&entry.descriptor.descriptor_id)
+        })
+    }
+}
+
+/// Result of a quarantine sweep
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct QuarantineSweep {
+    /// Entries dropped after their TTL
+    pub expired: usize,
+    /// Early events re-validated once their epoch came due
+    pub released: usize,
+}
+
+/// Result of merging one remote event
+#[derive(Debug, Clone, PartialEq)]
+pub enum MergeOutcome {
+    /// Stored in the log
+    Merged,
+    /// Already stored
+    Duplicate,
+    /// Pruned earlier or outside the retention horizon
+    Expired,
+    /// Failed validation and was quarantined
+    Quarantined(QuarantineReason),
+}
+
+impl MergeOutcome {
+    /// Whether the event was stored
+    pub fn is_merged(&self) -> bool {
+        matches!(self, MergeOutcome::Merged)
+    }
+}
+
+/// A page of the log after a peer's cursor
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Delta {
+    pub events: Vec<Event>,
+    /// Sequence number up to which the page covers the log
+    pub cursor: u64,
+    /// Whether events after `cursor` remain
+    pub has_more: bool,
+}
+
+/// Result of a checkpoint import
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CheckpointImport {
+    /// Events stored
+    pub imported: usize,
+    /// Events already present, expired or quarantined
+    pub skipped: usize,
+}
+
+/// Result of a prune pass
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PruneStats {
+    /// Events scanned
+    pub scanned: usize,
+    /// Events removed
+    pub pruned: usize,
+    /// Expired descriptors kept because they are the provider's latest
+    pub kept_descriptors: usize,
 }
 
 impl EventLog {
@@ -41,12 +187,87 @@ impl EventLog {
             }
         }
 
+        let (notify_tx, _) = broadcast::channel(NOTIFY_CAPACITY);
+        let last_seq = storage.last_seq().unwrap_or(0);
+        let quarantine = Quarantine::new(
+            storage.clone(),
+            DEFAULT_QUARANTINE_TTL,
+            DEFAULT_MAX_QUARANTINED,
+        );
+
         Self {
             storage,
             world_id,
             replica_id,
             version_vector: RwLock::new(vv),
+            notify_tx,
+            last_seq: Mutex::new(last_seq),
+            retention: RetentionPolicy::keep_all(),
+            epoch_len_ms: DEFAULT_EPOCH_LEN_MS,
+            quarantine,
+            verify_signatures: false,
+            semantic: None,
+            descriptor_ttl: None,
+        }
+    }
+
+    /// Set the retention policy and the epoch length it is measured in
+    pub fn with_retention(mut self, retention: RetentionPolicy, epoch_len_ms: u64) -> Self {
+        self.retention = retention;
+        self.epoch_len_ms = epoch_len_ms.max(1);
+        self
+    }
+
+    /// Check event IDs and signatures on append and merge
+    pub fn with_verification(mut self, verify_signatures: bool) -> Self {
+        self.verify_signatures = verify_signatures;
+        self
+    }
+
+    /// Expire descriptors `ttl` epochs after publication (the rule bundle's
+    /// `descriptor_ttl_epochs`)
+    pub fn with_descriptor_ttl(mut self, ttl: Option<u64>) -> Self {
+        self.descriptor_ttl = ttl;
+        self
+    }
+
+    /// Index every stored event in `index`, starting with those already
+    /// in storage
+    pub fn with_semantic_index(
+        mut self,
+        index: Arc<SemanticIndex>,
+    ) -> Result<Self, EventLogError> {
+        for result in self.storage.all_events() {
+            index.insert(&result?);
         }
+        self.semantic = Some(index);
+        Ok(self)
+    }
+
+    /// Embedding index over stored events, if enabled
+    pub fn semantic_index(&self) -> Option<&Arc<SemanticIndex>> {
+        self.semantic.as_ref()
+    }
+
+    /// Set how long quarantined events are kept and how many
+    pub fn with_quarantine(mut self, ttl: Duration, max_entries: usize) -> Self {
+        self.quarantine = Quarantine::new(self.storage.clone(), ttl, max_entries);
+        self
+    }
+
+    /// Events held back by validation
+    pub fn quarantine(&self) -> &Quarantine {
+        &self.quarantine
+    }
+
+    /// Current epoch by wall clock
+    pub fn current_epoch(&self) -> u64 {
+        now_millis() / self.epoch_len_ms
+    }
+
+    /// Subscribe to events newly appended or merged into the log
+    pub fn subscribe(&self) -> broadcast::Receiver<NewEvent> {
+        self.notify_tx.subscribe()
     }
 
     /// Append a new event locally
@@ -61,20 +282,73 @@ impl EventLog {
             return Err(EventLogError::DuplicateEvent(event.event_id));
         }
 
+        // Local events are rejected outright rather than quarantined
+        self.validate(&event).map_err(EventLogError::Invalid)?;
+
         // Store event
         self.storage.put_event(&event)?;
 
-        // Update version vector
-        let mut vv = self.version_vector.write();
-        let counter = vv.entry(self.replica_id).or_insert(0);
-        *counter += 1;
-        self.storage.put_version(&self.replica_id, *counter)?;
+        let provided = Self::provided_dependencies(&event);
+        self.sequence(event, None)?;
+        self.release(provided)?;
 
         Ok(())
     }
 
     /// Merge a remote event (from delta sync)
+    ///
+    /// Events that fail validation are quarantined and `Ok(false)` is
+    /// returned. Quarantined events waiting on this one are re-validated.
     pub fn merge(&self, event: Event, source_replica: [u8; 32]) -> Result<bool, EventLogError> {
+        Ok(self.merge_checked(event, source_replica)?.is_merged())
+    }
+
+    /// Merge a remote event, reporting why it was not stored
+    pub fn merge_checked(
+        &self,
+        event: Event,
+        source_replica: [u8; 32],
+    ) -> Result<MergeOutcome, EventLogError> {
+        let provided = Self::provided_dependencies(&event);
+        let outcome = self.merge_one(event, source_replica)?;
+        if outcome.is_merged() {
+            self.release(provided)?;
+        }
+        Ok(outcome)
+    }
+
+    /// Re-merge quarantined events waiting on `dependencies`, following
+    /// chains of dependents
+    fn release(&self, mut dependencies: Vec<Dependency>) -> Result<(), EventLogError> {
+        while let Some(dependency) = dependencies.pop() {
+            for entry in self.quarantine.take_waiting(&dependency)? {
+                let provided = Self::provided_dependencies(&entry.event);
+                if self.merge_one(entry.event, entry.source)?.is_merged() {
+                    dependencies.extend(provided);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// What other events may be waiting on once `event` is stored
+    fn provided_dependencies(event: &Event) -> Vec<Dependency> {
+        let mut provided = vec![Dependency::Event(event.event_id)];
+        if let EventBody::DescriptorPublish(publish) = &event.body {
+            provided.push(Dependency::Descriptor(publish.descriptor.descriptor_id));
+            if let Some(fah) = index::descriptor_fah(&publish.descriptor) {
+                provided.push(Dependency::Fah(fah));
+            }
+        }
+        provided
+    }
+
+    /// Validate and store one remote event, quarantining it on failure
+    fn merge_one(
+        &self,
+        event: Event,
+        source_replica: [u8; 32],
+    ) -> Result<MergeOutcome, EventLogError> {
         // Validate world
         if event.world.0 != self.world_id.0 {
             return Err(EventLogError::WorldMismatch);
@@ -82,57 +356,286 @@ impl EventLog {
 
         // Skip if already present
         if self.storage.has_event(&event.event_id)? {
-            return Ok(false);
+            return Ok(MergeOutcome::Duplicate);
+        }
+
+        // Skip pruned events so anti-entropy does not resurrect them. Expired
+        // descriptors are still taken unless tombstoned: one may be the
+        // provider's latest, and the next prune settles it.
+        if self.storage.has_tombstone(&event.event_id)? {
+            return Ok(MergeOutcome::Expired);
+        }
+        if event.event_type != EventType::DescriptorPublish
+            && self.retention.is_expired(&event, self.current_epoch())
+        {
+            return Ok(MergeOutcome::Expired);
+        }
+
+        if let Err(reason) = self.validate(&event) {
+            debug!(
+                "Quarantining event {:02x?}: {}",
+                &event.event_id.0[..8],
+                reason
+            );
+            self.quarantine.insert(event, reason.clone(), source_replica)?;
+            return Ok(MergeOutcome::Quarantined(reason));
         }
 
         // Store event
         self.storage.put_event(&event)?;
 
-        // Update source's version in our vector
-        let mut vv = self.version_vector.write();
-        let counter = vv.entry(source_replica).or_insert(0);
-        *counter += 1;
-        self.storage.put_version(&source_replica, *counter)?;
+        self.sequence(event, Some(source_replica))?;
+
+        Ok(MergeOutcome::Merged)
+    }
+
+    /// Check an event before it enters the log
+    fn validate(&self, event: &Event) -> Result<(), QuarantineReason> {
+        if event_type_of(&event.body) != event.event_type {
+            return Err(QuarantineReason::Malformed(
+                "event type does not match body".into(),
+            ));
+        }
+        if event_world_epoch(&event.body) != (event.world, event.epoch_id) {
+            return Err(QuarantineReason::Malformed(
+                "header does not match body".into(),
+            ));
+        }
+
+        if self.verify_signatures {
+            verify_event_id(event).map_err(|e| QuarantineReason::Malformed(e.to_string()))?;
+            verify_event_signature(event)
+                .map_err(|e| QuarantineReason::BadSignature(e.to_string()))?;
+        }
+
+        let current_epoch = self.current_epoch();
+        if event.epoch_id > current_epoch.saturating_add(MAX_EPOCH_SKEW) {
+            return Err(QuarantineReason::EpochOutOfRange {
+                epoch_id: event.epoch_id,
+                current_epoch,
+            });
+        }
+
+        let target_fah = match &event.body {
+            EventBody::Dispute(dispute) => {
+                // Cited attestations may have been pruned here while the
+                // dispute still travels: tombstoned ones, and any from a
+                // dispute older than attestations are kept
+                let past_retention = self
+                    .retention
+                    .horizon(EventType::Attestation, current_epoch)
+                    .is_some_and(|horizon| dispute.epoch_id < horizon);
+                for id in [dispute.event_a, dispute.event_b] {
+                    if !past_retention
+                        && !self.storage.has_event(&id).unwrap_or(false)
+                        && !self.storage.has_tombstone(&id).unwrap_or(false)
+                    {
+                        return Err(QuarantineReason::MissingDependency(id));
+                    }
+                }
+                None
+            }
+            EventBody::DescriptorRevoke(revoke) => {
+                let descriptor = self
+                    .get_descriptor(&revoke.descriptor_id)
+                    .ok()
+                    .flatten()
+                    .ok_or(QuarantineReason::MissingDescriptor(revoke.descriptor_id))?;
+                if descriptor.provider_transport_pubkey != revoke.provider_transport_pubkey {
+                    return Err(QuarantineReason::BadSignature(
+                        "revocation not signed by the descriptor's provider".into(),
+                    ));
+                }
+                None
+            }
+            EventBody::Receipt(receipt) => receipt.target_fah,
+            EventBody::Attestation(attestation) => attestation.target_fah,
+            _ => None,
+        };
+        if let Some(fah) = target_fah {
+            let known = self
+                .storage
+                .has_index_prefix(IndexKind::Fah, &fah.0)
+                .unwrap_or(false);
+            if !known {
+                return Err(QuarantineReason::UnknownDescriptor(fah));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Drop expired quarantine entries and re-validate early events whose
+    /// epoch has come due
+    pub fn sweep_quarantine(&self) -> Result<QuarantineSweep, EventLogError> {
+        let expired = self.quarantine.take_expired(now_millis())?.len();
+        let mut released = 0;
+        for entry in self
+            .quarantine
+            .take_due(self.current_epoch(), MAX_EPOCH_SKEW)?
+        {
+            if self.merge(entry.event, entry.source)? {
+                released += 1;
+            }
+        }
+        Ok(QuarantineSweep { expired, released })
+    }
+
+    /// Assign the next sequence number to a stored event and notify
+    fn sequence(&self, event: Event, source: Option<[u8; 32]>) -> Result<(), EventLogError> {
+        let mut last_seq = self.last_seq.lock();
+        let seq = *last_seq + 1;
+        self.storage.put_seq(seq, &event.event_id)?;
+        *last_seq = seq;
+        if let Some(semantic) = &self.semantic {
+            semantic.insert(&event);
+        }
+        let _ = self.notify_tx.send(NewEvent { seq, event, source });
+        Ok(())
+    }
+
+    /// Last assigned sequence number (0 if the log is empty)
+    pub fn head_seq(&self) -> u64 {
+        *self.last_seq.lock()
+    }
+
+    /// Events stored after sequence number `seq`, up to `limit`
+    pub fn events_after(&self, seq: u64, limit: usize) -> Result<Vec<(u64, Event)>, EventLogError> {
+        let mut events = Vec::new();
+        for result in self.storage.seq_after(seq) {
+            let (seq, event_id) = result?;
+            if let Some(event) = self.storage.get_event(&event_id)? {
+                events.push((seq, event));
+                if events.len() >= limit {
+                    break;
+                }
+            }
+        }
+        Ok(events)
+    }
 
-        Ok(true)
+    /// World this log belongs to
+    pub fn world_id(&self) -> WorldId {
+        self.world_id
     }
 
     /// Get current version vector
     pub fn get_version_vector(&self) -> Vec<VersionVectorEntry> {
-        self.version_vector
+        self.version_vector_at(self.head_seq())
+    }
+
+    /// Version vector with our own entry at `head_seq`, e.g. as of the
+    /// cursor of a delta
+    pub fn version_vector_at(&self, head_seq: u64) -> Vec<VersionVectorEntry> {
+        let mut vector: Vec<VersionVectorEntry> = self
+            .version_vector
             .read()
             .iter()
+            .filter(|(replica_id, _)| **replica_id != self.replica_id)
             .map(|(replica_id, counter)| VersionVectorEntry {
                 replica_id: *replica_id,
                 counter: *counter,
             })
-            .collect()
+            .collect();
+        vector.push(VersionVectorEntry {
+            replica_id: self.replica_id,
+            counter: head_seq,
+        });
+        vector
+    }
+
+    /// Record that `replica_id`'s events up to its sequence number
+    /// `counter` were synced
+    pub fn advance_version(&self, replica_id: [u8; 32], counter: u64) -> Result<(), EventLogError> {
+        if replica_id == self.replica_id {
+            return Ok(());
+        }
+        let mut vv = self.version_vector.write();
+        let current = vv.entry(replica_id).or_insert(0);
+        if counter > *current {
+            *current = counter;
+            self.storage.put_version(&replica_id, counter)?;
+        }
+        Ok(())
     }
 
-    /// Compute delta: events we have that peer doesn't
-    pub fn compute_delta(&self, peer_vector: &[VersionVectorEntry]) -> Result<Vec<Event>, EventLogError> {
-        let peer_vv: HashMap<[u8; 32], u64> = peer_vector
+    /// Compute delta: events we stored after the peer's entry for us in
+    /// `peer_vector`, in sequence order, up to `limit`
+    pub fn compute_delta(
+        &self,
+        peer_vector: &[VersionVectorEntry],
+        limit: usize,
+    ) -> Result<Delta, EventLogError> {
+        let head_seq = self.head_seq();
+        let mut cursor = peer_vector
             .iter()
-            .map(|e| (e.replica_id, e.counter))
-            .collect();
+            .find(|entry| entry.replica_id == self.replica_id)
+            .map_or(0, |entry| entry.counter);
+        // A cursor past our head predates a reset of our log
+        if cursor > head_seq {
+            cursor = 0;
+        }
+
+        let mut events = Vec::new();
+        for result in self.storage.seq_after(cursor) {
+            if events.len() >= limit.max(1) {
+                break;
+            }
+            let (seq, event_id) = result?;
+            // Pruned events leave gaps, which the cursor skips
+            if let Some(event) = self.storage.get_event(&event_id)? {
+                events.push(event);
+            }
+            cursor = seq;
+        }
+
+        Ok(Delta {
+            events,
+            cursor,
+            has_more: cursor < head_seq,
+        })
+    }
+
+    /// Remove events outside the retention policy at `current_epoch`.
+    ///
+    /// Each provider's current descriptor is always kept. Pruned descriptors
+    /// are tombstoned; other types are refused by `merge` via the retention
+    /// horizon.
+    pub fn prune(&self, current_epoch: u64) -> Result<PruneStats, EventLogError> {
+        let mut stats = PruneStats::default();
 
-        let mut delta = Vec::new();
+        let keep: HashSet<EventId> = self
+            .descriptor_view(current_epoch)?
+            .into_current()
+            .map(|entry| entry.event_id)
+            .collect();
 
-        // Iterate all events and include those peer doesn't have
-        // This is a simplified approach - in production, we'd track events per replica
+        let mut expired = Vec::new();
         for result in self.storage.all_events() {
             let event = result?;
-            // Include if peer version is behind
-            // Note: proper implementation would track event->replica mapping
-            delta.push(event);
+            stats.scanned += 1;
+            if !self.retention.is_expired(&event, current_epoch) {
+                continue;
+            }
+            if keep.contains(&event.event_id) {
+                stats.kept_descriptors += 1;
+                continue;
+            }
+            expired.push((event.event_id, event.epoch_id, event.event_type));
         }
 
-        // Limit batch size
-        if delta.len() > 1000 {
-            delta.truncate(1000);
+        for (event_id, epoch_id, event_type) in expired {
+            if event_type == EventType::DescriptorPublish {
+                self.storage.put_tombstone(&event_id, epoch_id)?;
+            }
+            self.storage.remove_event(&event_id)?;
+            if let Some(semantic) = &self.semantic {
+                semantic.remove(&event_id);
+            }
+            stats.pruned += 1;
         }
 
-        Ok(delta)
+        Ok(stats)
     }
 
     /// Get an event by ID
@@ -150,22 +653,181 @@ impl EventLog {
         self.storage.event_count()
     }
 
-    /// Get all descriptor publish events
+    /// Latest descriptor per provider and revoked descriptor IDs
+    fn descriptor_view(&self, current_epoch: u64) -> Result<DescriptorView, EventLogError> {
+        let mut view = DescriptorView {
+            latest: HashMap::new(),
+            revoked: HashSet::new(),
+            current_epoch,
+            ttl: self.descriptor_ttl,
+        };
+        let keys = self
+            .storage
+            .scan_index(IndexKind::Descriptor, vec![], vec![0xff; 72], None);
+        for result in keys {
+            let Some(event_id) = index::event_id_from_key(&result?) else {
+                continue;
+            };
+            let Some(event) = self.storage.get_event(&event_id)? else {
+                continue;
+            };
+            match event.body {
+                EventBody::DescriptorPublish(publish) => {
+                    let descriptor = publish.descriptor;
+                    let rank = (
+                        descriptor.unsigned.descriptor_epoch,
+                        event.epoch_id,
+                        descriptor.descriptor_id.0,
+                    );
+                    let superseded = matches!(
+                        view.latest.get(&descriptor.provider_transport_pubkey),
+                        Some(entry) if entry.rank >= rank
+                    );
+                    if !superseded {
+                        let entry = CurrentDescriptor {
+                            rank,
+                            event_id,
+                            descriptor,
+                        };
+                        view.latest
+                            .insert(entry.descriptor.provider_transport_pubkey.clone(), entry);
+                    }
+                }
+                EventBody::DescriptorRevoke(revoke) => {
+                    view.revoked.insert(revoke.descriptor_id);
+                }
+                _ => {}
+            }
+        }
+        Ok(view)
+    }
+
+    /// The current descriptor set: each provider's highest
+    /// `descriptor_epoch`, unless revoked or past the descriptor TTL
+    pub fn current_descriptors(&self) -> Result<Vec<ProviderDescriptor>, EventLogError> {
+        let mut descriptors: Vec<ProviderDescriptor> = self
+            .descriptor_view(self.current_epoch())?
+            .into_current()
+            .map(|entry| entry.descriptor)
+            .collect();
+        descriptors.sort_by_key(|d| d.descriptor_id.0);
+        Ok(descriptors)
+    }
+
+    /// Get all retained descriptor publications, including superseded,
+    /// expired and revoked ones
     pub fn get_descriptors(&self) -> Result<Vec<ProviderDescriptor>, EventLogError> {
         let mut descriptors = Vec::new();
-        for result in self.storage.all_events() {
-            let event = result?;
-            if let EventBody::DescriptorPublish(desc_event) = event.body {
+        let keys = self
+            .storage
+            .scan_index(IndexKind::Descriptor, vec![], vec![0xff; 72], None);
+        for result in keys {
+            let key = result?;
+            let Some(event_id) = index::event_id_from_key(&key) else {
+                continue;
+            };
+            if let Some(Event {
+                body: EventBody::DescriptorPublish(desc_event),
+                ..
+            }) = self.storage.get_event(&event_id)?
+            {
                 descriptors.push(desc_event.descriptor);
             }
         }
         Ok(descriptors)
     }
+
+    /// Latest retained descriptor with the given ID
+    pub fn get_descriptor(
+        &self,
+        descriptor_id: &DescriptorId,
+    ) -> Result<Option<ProviderDescriptor>, EventLogError> {
+        let (lower, upper) = index::prefix_range(&descriptor_id.0, 0, u64::MAX);
+        let mut latest = None;
+        for result in self.storage.scan_index(IndexKind::Descriptor, lower, upper, None) {
+            let Some(event_id) = index::event_id_from_key(&result?) else {
+                continue;
+            };
+            if let Some(Event {
+                body: EventBody::DescriptorPublish(desc_event),
+                ..
+            }) = self.storage.get_event(&event_id)?
+            {
+                latest = Some(desc_event.descriptor);
+            }
+        }
+        Ok(latest)
+    }
+
+    /// Snapshot the retained log into an unsigned checkpoint
+    pub fn export_checkpoint(&self) -> Result<Checkpoint, EventLogError> {
+        // Hold the sequence lock so the snapshot matches `head_seq`
+        let last_seq = self.last_seq.lock();
+        let mut events = Vec::new();
+        for result in self.storage.seq_after(0) {
+            let (_, event_id) = result?;
+            if let Some(event) = self.storage.get_event(&event_id)? {
+                events.push(event);
+            }
+        }
+        let checkpoint = Checkpoint::build(
+            self.world_id,
+            self.current_epoch(),
+            *last_seq,
+            self.version_vector_at(*last_seq),
+            now_millis(),
+            &events,
+        )?;
+        Ok(checkpoint)
+    }
+
+    /// Import a checkpoint signed by at least `threshold` of `trusted`
+    ///
+    /// Events are merged in the producer's sequence order, so they pass the
+    /// same validation as synced events. Afterwards the version vector is
+    /// raised to the checkpoint's, so delta sync with the producer resumes
+    /// after the checkpoint's last event.
+    pub fn import_checkpoint(
+        &self,
+        checkpoint: &Checkpoint,
+        trusted: &[[u8; 32]],
+        threshold: usize,
+    ) -> Result<CheckpointImport, EventLogError> {
+        checkpoint.verify(self.world_id, trusted, threshold)?;
+        let source = checkpoint
+            .signatures
+            .first()
+            .map(|s| s.signer)
+            .unwrap_or_default();
+
+        let mut stats = CheckpointImport::default();
+        for event in checkpoint.events()? {
+            let provided = Self::provided_dependencies(&event);
+            if self.merge_one(event, source)?.is_merged() {
+                self.release(provided)?;
+                stats.imported += 1;
+            } else {
+                stats.skipped += 1;
+            }
+        }
+
+        for entry in &checkpoint.header.version_vector {
+            self.advance_version(entry.replica_id, entry.counter)?;
+        }
+
+        Ok(stats)
+    }
+
+    /// Run a typed query against the secondary indexes
+    pub fn query(&self, query: &EventQuery) -> Result<QueryPage, EventLogError> {
+        Ok(query.execute(&self.storage)?)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::retention::RetentionRule;
     use tempfile::tempdir;
 
     fn create_test_log() -> (EventLog, tempfile::TempDir) {
@@ -265,4 +927,334 @@ mod tests {
         assert_eq!(vv.len(), 1);
         assert_eq!(vv[0].counter, 2);
     }
+
+    fn receipt_event(id: u8, epoch_id: u64) -> Event {
+        Event {
+            event_id: EventId([id; 32]),
+            world: WorldId([0; 32]),
+            epoch_id,
+            event_type: EventType::Receipt,
+            body: EventBody::Receipt(ProbeReceipt {
+                receipt_id: ReceiptId([id; 32]),
+                world: WorldId([0; 32]),
+                epoch_id,
+                challenge_id: ChallengeId([0; 32]),
+                target_ref: TargetRef([0; 32]),
+                target_fah: None,
+                outcome_commitment: [0; 32],
+                ticket: None,
+                prober_transport_pubkey: vec![],
+                signature: vec![],
+            }),
+        }
+    }
+
+    #[test]
+    fn test_prune_and_refuse_expired() {
+        let dir = tempdir().unwrap();
+        let storage = Arc::new(Storage::open(dir.path()).unwrap());
+        let policy = RetentionPolicy::keep_all().with_rule(RetentionRule {
+            event_type: EventType::Receipt,
+            epochs: Some(10),
+        });
+        let log = EventLog::new(storage, WorldId([0; 32]), [1; 32]).with_retention(policy, 1);
+        let now = log.current_epoch();
+
+        log.append(receipt_event(1, now - 100)).unwrap();
+        log.append(receipt_event(2, now)).unwrap();
+
+        let stats = log.prune(now).unwrap();
+        assert_eq!(stats.pruned, 1);
+        assert!(!log.has_event(&EventId([1; 32])).unwrap());
+        assert!(log.has_event(&EventId([2; 32])).unwrap());
+
+        // A peer offering the pruned event again is ignored
+        assert!(!log.merge(receipt_event(1, now - 100), [9; 32]).unwrap());
+        assert!(!log.has_event(&EventId([1; 32])).unwrap());
+    }
+
+    fn descriptor_event(id: u8, provider: u8, descriptor_epoch: u64, epoch_id: u64) -> Event {
+        Event {
+            event_id: EventId([id; 32]),
+            world: WorldId([0; 32]),
+            epoch_id,
+            event_type: EventType::DescriptorPublish,
+            body: EventBody::DescriptorPublish(DescriptorPublishEvent {
+                world: WorldId([0; 32]),
+                epoch_id,
+                descriptor: ProviderDescriptor {
+                    descriptor_id: DescriptorId([id; 32]),
+                    unsigned: ProviderDescriptorUnsigned {
+                        world: WorldId([0; 32]),
+                        descriptor_epoch,
+                        contact_points: vec![],
+                        capability: DescriptorCapability::Fah(Fah([0; 32])),
+                    },
+                    provider_transport_pubkey: vec![provider; 32],
+                    signature: vec![],
+                },
+            }),
+        }
+    }
+
+    #[test]
+    fn test_prune_keeps_latest_descriptor() {
+        let dir = tempdir().unwrap();
+        let storage = Arc::new(Storage::open(dir.path()).unwrap());
+        let policy = RetentionPolicy::keep_all().with_rule(RetentionRule {
+            event_type: EventType::DescriptorPublish,
+            epochs: Some(10),
+        });
+        let log = EventLog::new(storage, WorldId([0; 32]), [1; 32]).with_retention(policy, 1);
+        let now = log.current_epoch();
+
+        // Provider 7 published twice, both long ago
+        log.append(descriptor_event(1, 7, 1, now - 200)).unwrap();
+        log.append(descriptor_event(2, 7, 2, now - 100)).unwrap();
+
+        let stats = log.prune(now).unwrap();
+        assert_eq!(stats.pruned, 1);
+        assert_eq!(stats.kept_descriptors, 1);
+        assert!(log.has_event(&EventId([2; 32])).unwrap());
+
+        // The superseded descriptor is tombstoned and not merged back
+        assert!(!log.merge(descriptor_event(1, 7, 1, now - 200), [9; 32]).unwrap());
+    }
+
+    fn revoke_event(id: u8, descriptor: u8, provider: u8, epoch_id: u64) -> Event {
+        Event {
+            event_id: EventId([id; 32]),
+            world: WorldId([0; 32]),
+            epoch_id,
+            event_type: EventType::DescriptorRevoke,
+            body: EventBody::DescriptorRevoke(DescriptorRevokeEvent {
+                world: WorldId([0; 32]),
+                epoch_id,
+                descriptor_id: DescriptorId([descriptor; 32]),
+                provider_transport_pubkey: vec![provider; 32],
+                signature: vec![],
+            }),
+        }
+    }
+
+    #[test]
+    fn test_current_descriptor_set() {
+        let dir = tempdir().unwrap();
+        let storage = Arc::new(Storage::open(dir.path()).unwrap());
+        let log = EventLog::new(storage, WorldId([0; 32]), [1; 32])
+            .with_retention(RetentionPolicy::keep_all(), 1)
+            .with_descriptor_ttl(Some(50));
+        let now = log.current_epoch();
+        let current = |log: &EventLog| -> Vec<u8> {
+            let descriptors = log.current_descriptors().unwrap();
+            descriptors.iter().map(|d| d.descriptor_id.0[0]).collect()
+        };
+
+        // Provider 7 superseded descriptor 1 with 2, published earlier
+        log.append(descriptor_event(2, 7, 5, now - 10)).unwrap();
+        log.append(descriptor_event(1, 7, 4, now)).unwrap();
+        // Provider 8's only descriptor is past the TTL
+        log.append(descriptor_event(3, 8, 1, now - 100)).unwrap();
+        log.append(descriptor_event(4, 9, 1, now)).unwrap();
+        assert_eq!(current(&log), vec![2, 4]);
+
+        // Only the descriptor's provider may revoke it
+        assert!(matches!(
+            log.append(revoke_event(10, 4, 7, now)),
+            Err(EventLogError::Invalid(QuarantineReason::BadSignature(_)))
+        ));
+        log.append(revoke_event(11, 4, 9, now)).unwrap();
+        assert_eq!(current(&log), vec![2]);
+
+        // A revocation that arrives first waits for its descriptor
+        assert!(!log.merge(revoke_event(12, 5, 6, now), [9; 32]).unwrap());
+        log.append(descriptor_event(5, 6, 1, now)).unwrap();
+        assert!(log.has_event(&EventId([12; 32])).unwrap());
+        assert_eq!(current(&log), vec![2]);
+
+        // Revoked and expired descriptors are no longer kept by prune
+        let policy = RetentionPolicy::keep_all().with_rule(RetentionRule {
+            event_type: EventType::DescriptorPublish,
+            epochs: Some(1),
+        });
+        let log = log.with_retention(policy, 1);
+        log.prune(now + 2).unwrap();
+        assert_eq!(current(&log), vec![2]);
+        assert!(!log.has_event(&EventId([4; 32])).unwrap());
+    }
+
+    #[test]
+    fn test_new_event_notifications() {
+        let (log, _dir) = create_test_log();
+        let mut rx = log.subscribe();
+
+        let event = Event {
+            event_id: EventId([5; 32]),
+            world: WorldId([0; 32]),
+            epoch_id: 1,
+            event_type: EventType::RuleEndorsement,
+            body: EventBody::RuleEndorsement(RuleEndorsementEvent {
+                world: WorldId([0; 32]),
+                epoch_id: 1,
+                rule_bundle_hash: [0; 32],
+                weight: 1.0,
+                signer_transport_pubkey: vec![],
+                signature: vec![],
+            }),
+        };
+
+        assert!(log.merge(event.clone(), [7; 32]).unwrap());
+        let notified = rx.try_recv().unwrap();
+        assert_eq!(notified.event.event_id, event.event_id);
+        assert_eq!(notified.source, Some([7; 32]));
+
+        // Merging a known event does not notify again
+        assert!(!log.merge(event, [8; 32]).unwrap());
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_sequence_numbers() {
+        let dir = tempdir().unwrap();
+        let storage = Arc::new(Storage::open(dir.path()).unwrap());
+        let log = EventLog::new(storage.clone(), WorldId([0; 32]), [1; 32]);
+        let mut rx = log.subscribe();
+
+        log.append(receipt_event(1, 1)).unwrap();
+        log.merge(receipt_event(2, 1), [7; 32]).unwrap();
+        log.append(receipt_event(3, 1)).unwrap();
+        assert_eq!(rx.try_recv().unwrap().seq, 1);
+        assert_eq!(rx.try_recv().unwrap().seq, 2);
+        assert_eq!(log.head_seq(), 3);
+
+        let after: Vec<_> = log.events_after(1, 10).unwrap();
+        assert_eq!(after.len(), 2);
+        assert_eq!(after[0].0, 2);
+        assert_eq!(after[0].1.event_id, EventId([2; 32]));
+
+        // Sequence numbers continue across restarts
+        drop(log);
+        let log = EventLog::new(storage, WorldId([0; 32]), [1; 32]);
+        assert_eq!(log.head_seq(), 3);
+    }
+
+    fn dispute_event(id: u8, event_a: u8, event_b: u8, epoch_id: u64) -> Event {
+        Event {
+            event_id: EventId([id; 32]),
+            world: WorldId([0; 32]),
+            epoch_id,
+            event_type: EventType::Dispute,
+            body: EventBody::Dispute(DisputeEvent {
+                world: WorldId([0; 32]),
+                epoch_id,
+                event_a: EventId([event_a; 32]),
+                event_b: EventId([event_b; 32]),
+                reason: "conflicting receipts".into(),
+                disputer_transport_pubkey: vec![],
+                signature: vec![],
+            }),
+        }
+    }
+
+    #[test]
+    fn test_quarantine_until_dependencies_arrive() {
+        let (log, _dir) = create_test_log();
+
+        assert!(!log.merge(dispute_event(9, 1, 2, 1), [7; 32]).unwrap());
+        let entry = log.quarantine().get(&EventId([9; 32])).unwrap().unwrap();
+        assert_eq!(
+            entry.reason,
+            QuarantineReason::MissingDependency(EventId([1; 32]))
+        );
+        assert_eq!(entry.source, [7; 32]);
+
+        // First dependency arrives: re-validated, now waiting on the second
+        log.merge(receipt_event(1, 1), [8; 32]).unwrap();
+        let entry = log.quarantine().get(&EventId([9; 32])).unwrap().unwrap();
+        assert_eq!(
+            entry.reason,
+            QuarantineReason::MissingDependency(EventId([2; 32]))
+        );
+
+        log.append(receipt_event(2, 1)).unwrap();
+        assert!(log.has_event(&EventId([9; 32])).unwrap());
+        assert!(log.quarantine().is_empty());
+    }
+
+    #[test]
+    fn test_dispute_of_pruned_events_accepted() {
+        let dir = tempdir().unwrap();
+        let storage = Arc::new(Storage::open(dir.path()).unwrap());
+        let policy = RetentionPolicy::keep_all()
+            .with_rule(RetentionRule {
+                event_type: EventType::Receipt,
+                epochs: Some(10),
+            })
+            .with_rule(RetentionRule {
+                event_type: EventType::Attestation,
+                epochs: Some(1000),
+            });
+        let log =
+            EventLog::new(storage.clone(), WorldId([0; 32]), [1; 32]).with_retention(policy, 1);
+        let now = log.current_epoch();
+
+        // Attestations 1 and 2 were pruned here long ago
+        assert!(log
+            .merge(dispute_event(9, 1, 2, now - 2000), [7; 32])
+            .unwrap());
+
+        // A recent dispute still waits for what it cites, unless tombstoned
+        assert!(!log.merge(dispute_event(10, 3, 4, now), [7; 32]).unwrap());
+        assert!(log.quarantine().get(&EventId([10; 32])).unwrap().is_some());
+        storage.put_tombstone(&EventId([5; 32]), now).unwrap();
+        storage.put_tombstone(&EventId([6; 32]), now).unwrap();
+        assert!(log.merge(dispute_event(11, 5, 6, now), [7; 32]).unwrap());
+    }
+
+    #[test]
+    fn test_backdated_dispute_of_unknown_events_quarantined() {
+        let dir = tempdir().unwrap();
+        let storage = Arc::new(Storage::open(dir.path()).unwrap());
+        let policy = RetentionPolicy::keep_all().with_rule(RetentionRule {
+            event_type: EventType::Receipt,
+            epochs: Some(10),
+        });
+        let log = EventLog::new(storage, WorldId([0; 32]), [1; 32]).with_retention(policy, 1);
+        let now = log.current_epoch();
+
+        // Older than the receipt horizon, but attestations are kept forever:
+        // the cited events must exist
+        assert!(!log
+            .merge(dispute_event(9, 1, 2, now - 100), [7; 32])
+            .unwrap());
+        assert!(log.quarantine().get(&EventId([9; 32])).unwrap().is_some());
+    }
+
+    #[test]
+    fn test_quarantine_bad_signature_and_expiry() {
+        let dir = tempdir().unwrap();
+        let storage = Arc::new(Storage::open(dir.path()).unwrap());
+        let log = EventLog::new(storage, WorldId([0; 32]), [1; 32])
+            .with_verification(true)
+            .with_quarantine(Duration::ZERO, 16);
+
+        let signer = terrain_gossip_core::crypto::EventSigner::from_seed(&[3; 32]);
+        let mut event = signer.sign(receipt_event(1, 1).body).unwrap();
+        if let EventBody::Receipt(receipt) = &mut event.body {
+            receipt.signature[0] ^= 1;
+        }
+        event.event_id = terrain_gossip_core::crypto::compute_event_id(&event.body).unwrap();
+
+        // Local publishes are refused, remote ones quarantined
+        assert!(matches!(
+            log.append(event.clone()),
+            Err(EventLogError::Invalid(QuarantineReason::BadSignature(_)))
+        ));
+        assert!(!log.merge(event, [7; 32]).unwrap());
+        assert_eq!(log.quarantine().len(), 1);
+
+        let sweep = log.sweep_quarantine().unwrap();
+        assert_eq!(sweep.expired, 1);
+        assert!(log.quarantine().is_empty());
+    }
 }
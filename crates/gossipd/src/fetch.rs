@@ -0,0 +1,252 @@
+//! Want-list fetch of specific events by ID
+//!
+//! Delta sync moves everything after a version vector, but some events point
+//! at others directly: a dispute names the pair it contests, and quarantined
+//! events wait on missing dependencies. Those IDs go on a want-list and are
+//! requested with `EventFetch` from one connected peer at a time. Whatever a
+//! peer does not return is asked of the next untried peer; an ID is dropped
+//! once every connected peer has been tried or `max_attempts` is reached.
+
+use parking_lot::Mutex;
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
+use terrain_gossip_core::types::*;
+
+/// Want-list configuration
+#[derive(Debug, Clone)]
+pub struct FetchConfig {
+    /// Most IDs asked of one peer in one request
+    pub max_ids_per_request: usize,
+    /// How long to wait for a response before trying another peer
+    pub timeout: Duration,
+    /// Peers asked for one ID before giving up
+    pub max_attempts: usize,
+    /// Most IDs wanted at once
+    pub max_wanted: usize,
+}
+
+impl Default for FetchConfig {
+    fn default() -> Self {
+        Self {
+            max_ids_per_request: 256,
+            timeout: Duration::from_secs(2),
+            max_attempts: 8,
+            max_wanted: 4096,
+        }
+    }
+}
+
+struct Wanted {
+    /// Peers already asked
+    tried: HashSet<[u8; 32]>,
+    /// Peer currently asked, and when the request times out
+    in_flight: Option<([u8; 32], Instant)>,
+}
+
+/// Tracks wanted events and which peers have been asked for them
+pub struct FetchManager {
+    config: FetchConfig,
+    wanted: Mutex<HashMap<EventId, Wanted>>,
+}
+
+impl FetchManager {
+    /// Create an empty want-list
+    pub fn new(config: FetchConfig) -> Self {
+        Self {
+            config,
+            wanted: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Add IDs to the want-list, returning how many were new
+    pub fn want(&self, ids: impl IntoIterator<Item = EventId>) -> usize {
+        let mut wanted = self.wanted.lock();
+        let mut added = 0;
+        for id in ids {
+            if wanted.len() >= self.config.max_wanted {
+                break;
+            }
+            if let Entry::Vacant(entry) = wanted.entry(id) {
+                entry.insert(Wanted {
+                    tried: HashSet::new(),
+                    in_flight: None,
+                });
+                added += 1;
+            }
+        }
+        added
+    }
+
+    /// Whether an ID is on the want-list
+    pub fn is_wanted(&self, id: &EventId) -> bool {
+        self.wanted.lock().contains_key(id)
+    }
+
+    /// Number of wanted IDs
+    pub fn wanted_count(&self) -> usize {
+        self.wanted.lock().len()
+    }
+
+    /// Drop an ID that arrived by any path
+    pub fn fulfilled(&self, id: &EventId) {
+        self.wanted.lock().remove(id);
+    }
+
+    /// Assign wanted IDs to untried `peers`, returning the requests to send
+    ///
+    /// Requests that timed out count their peer as tried. IDs no connected
+    /// peer is left to ask for are dropped; with no peers at all, nothing
+    /// changes.
+    pub fn tick(&self, peers: &[[u8; 32]], now: Instant) -> Vec<([u8; 32], Vec<EventId>)> {
+        if peers.is_empty() {
+            return Vec::new();
+        }
+        let mut wanted = self.wanted.lock();
+        let mut requests: HashMap<[u8; 32], Vec<EventId>> = HashMap::new();
+        let mut exhausted = Vec::new();
+
+        for (id, want) in wanted.iter_mut() {
+            match want.in_flight {
+                Some((_, deadline)) if deadline > now => continue,
+                Some((peer, _)) => {
+                    want.tried.insert(peer);
+                    want.in_flight = None;
+                }
+                None => {}
+            }
+            if want.tried.len() >= self.config.max_attempts {
+                exhausted.push(*id);
+                continue;
+            }
+            // Spread IDs across the least loaded untried peers
+            let peer = peers
+                .iter()
+                .filter(|peer| !want.tried.contains(*peer))
+                .min_by_key(|peer| requests.get(*peer).map_or(0, Vec::len));
+            let Some(peer) = peer else {
+                exhausted.push(*id);
+                continue;
+            };
+            let request = requests.entry(*peer).or_default();
+            if request.len() < self.config.max_ids_per_request {
+                request.push(*id);
+                want.in_flight = Some((*peer, now + self.config.timeout));
+            }
+        }
+        for id in exhausted {
+            wanted.remove(&id);
+        }
+
+        requests.retain(|_, ids| !ids.is_empty());
+        requests.into_iter().collect()
+    }
+
+    /// Handle a peer's response, returning the events that were asked of it
+    ///
+    /// IDs asked of `peer` but not returned become eligible for the next
+    /// peer on the following tick.
+    pub fn on_response(&self, peer: [u8; 32], events: Vec<Event>) -> Vec<Event> {
+        let mut wanted = self.wanted.lock();
+        let asked = |want: &Wanted| matches!(want.in_flight, Some((p, _)) if p == peer);
+        let returned: Vec<Event> = events
+            .into_iter()
+            .filter(|event| wanted.get(&event.event_id).is_some_and(asked))
+            .collect();
+        for event in &returned {
+            wanted.remove(&event.event_id);
+        }
+        for want in wanted.values_mut().filter(|want| asked(want)) {
+            want.tried.insert(peer);
+            want.in_flight = None;
+        }
+        returned
+    }
+
+    /// Re-queue requests in flight to a disconnected peer
+    pub fn remove_peer(&self, peer: &[u8; 32]) {
+        for want in self.wanted.lock().values_mut() {
+            if matches!(want.in_flight, Some((p, _)) if p == *peer) {
+                want.tried.insert(*peer);
+                want.in_flight = None;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(id: u8) -> Event {
+        Event {
+            event_id: EventId([id; 32]),
+            world: WorldId([0; 32]),
+            epoch_id: 1,
+            event_type: EventType::RuleEndorsement,
+            body: EventBody::RuleEndorsement(RuleEndorsementEvent {
+                world: WorldId([0; 32]),
+                epoch_id: 1,
+                rule_bundle_hash: [0; 32],
+                weight: 1.0,
+                signer_transport_pubkey: vec![],
+                signature: vec![],
+            }),
+        }
+    }
+
+    #[test]
+    fn test_missing_ids_move_to_next_peer() {
+        let fetch = FetchManager::new(FetchConfig::default());
+        let now = Instant::now();
+        assert_eq!(fetch.want([EventId([1; 32]), EventId([2; 32])]), 2);
+        assert_eq!(fetch.want([EventId([1; 32])]), 0);
+
+        // Without peers nothing is asked or dropped
+        assert!(fetch.tick(&[], now).is_empty());
+        assert_eq!(fetch.wanted_count(), 2);
+
+        let requests = fetch.tick(&[[10; 32]], now);
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].1.len(), 2);
+        // In flight: not asked again before the timeout
+        assert!(fetch.tick(&[[10; 32], [11; 32]], now).is_empty());
+
+        // The first peer has only event 1; unsolicited events are ignored
+        let returned = fetch.on_response([10; 32], vec![event(1), event(3)]);
+        assert_eq!(returned.len(), 1);
+        assert!(!fetch.is_wanted(&EventId([1; 32])));
+
+        let requests = fetch.tick(&[[10; 32], [11; 32]], now);
+        assert_eq!(requests, vec![([11; 32], vec![EventId([2; 32])])]);
+
+        // Timed out with every peer tried: given up
+        assert!(fetch
+            .tick(&[[10; 32], [11; 32]], now + Duration::from_secs(3))
+            .is_empty());
+        assert_eq!(fetch.wanted_count(), 0);
+    }
+
+    #[test]
+    fn test_requests_spread_and_capped() {
+        let fetch = FetchManager::new(FetchConfig {
+            max_ids_per_request: 2,
+            ..FetchConfig::default()
+        });
+        let now = Instant::now();
+        fetch.want((1..=5).map(|i| EventId([i; 32])));
+
+        let requests = fetch.tick(&[[10; 32], [11; 32]], now);
+        assert_eq!(requests.len(), 2);
+        assert!(requests.iter().all(|(_, ids)| ids.len() == 2));
+
+        // A disconnect frees its IDs for the other peer
+        fetch.remove_peer(&[10; 32]);
+        fetch.on_response([11; 32], vec![]);
+        let requests = fetch.tick(&[[12; 32]], now);
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].1.len(), 2);
+        fetch.fulfilled(&EventId([5; 32]));
+        assert_eq!(fetch.wanted_count(), 4);
+    }
+}
@@ -0,0 +1,460 @@
+//! Epidemic push gossip (Plumtree-style eager/lazy push)
+//!
+//! Freshly appended or merged events are pushed in full to a small set of
+//! eager peers and announced by ID to lazy peers. A peer that delivers a
+//! duplicate is pruned to lazy; a peer whose announcement is not satisfied
+//! by an eager push within `ihave_timeout` is asked for the event and
+//! grafted back to eager. Periodic pull anti-entropy remains the safety net
+//! for anything this layer drops.
+
+use parking_lot::RwLock;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::time::{Duration, Instant};
+use terrain_gossip_core::types::*;
+
+/// Push gossip configuration
+#[derive(Debug, Clone)]
+pub struct GossipConfig {
+    /// Target number of eager (full push) peers
+    pub eager_fanout: usize,
+    /// Maximum number of lazy peers announced to per event
+    pub lazy_fanout: usize,
+    /// Number of recently seen event IDs kept for dedup
+    pub seen_cache_size: usize,
+    /// How long to wait for an announced event before requesting it
+    pub ihave_timeout: Duration,
+}
+
+impl Default for GossipConfig {
+    fn default() -> Self {
+        Self {
+            eager_fanout: 4,
+            lazy_fanout: 8,
+            seen_cache_size: 65536,
+            ihave_timeout: Duration::from_millis(500),
+        }
+    }
+}
+
+/// Outbound gossip action for the transport to carry out
+#[derive(Debug, Clone, PartialEq)]
+pub enum GossipAction {
+    /// Push full events to a peer
+    Push { peer: [u8; 32], events: Vec<Event> },
+    /// Announce event IDs to a peer
+    IHave {
+        peer: [u8; 32],
+        event_ids: Vec<EventId>,
+    },
+    /// Request announced events from a peer
+    IWant {
+        peer: [u8; 32],
+        event_ids: Vec<EventId>,
+    },
+    /// Ask a peer to stop eagerly pushing to us
+    Prune { peer: [u8; 32] },
+}
+
+/// Bounded FIFO set of recently seen event IDs
+struct SeenCache {
+    capacity: usize,
+    order: VecDeque<EventId>,
+    ids: HashSet<EventId>,
+}
+
+impl SeenCache {
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            order: VecDeque::new(),
+            ids: HashSet::new(),
+        }
+    }
+
+    fn contains(&self, id: &EventId) -> bool {
+        self.ids.contains(id)
+    }
+
+    /// Insert an ID, returning false if it was already present
+    fn insert(&mut self, id: EventId) -> bool {
+        if !self.ids.insert(id) {
+            return false;
+        }
+        self.order.push_back(id);
+        while self.order.len() > self.capacity {
+            if let Some(old) = self.order.pop_front() {
+                self.ids.remove(&old);
+            }
+        }
+        true
+    }
+}
+
+/// An announced event we have not received yet
+struct MissingEvent {
+    /// Peers that announced it, in arrival order
+    announcers: VecDeque<[u8; 32]>,
+    /// When to request it from the next announcer
+    deadline: Instant,
+}
+
+struct GossipState {
+    eager: HashSet<[u8; 32]>,
+    lazy: HashSet<[u8; 32]>,
+    seen: SeenCache,
+    missing: HashMap<EventId, MissingEvent>,
+}
+
+/// Push gossip overlay state
+pub struct PushGossip {
+    config: GossipConfig,
+    state: RwLock<GossipState>,
+}
+
+impl PushGossip {
+    /// Create a new push gossip overlay
+    pub fn new(config: GossipConfig) -> Self {
+        let seen = SeenCache::new(config.seen_cache_size);
+        Self {
+            config,
+            state: RwLock::new(GossipState {
+                eager: HashSet::new(),
+                lazy: HashSet::new(),
+                seen,
+                missing: HashMap::new(),
+            }),
+        }
+    }
+
+    /// Add a connected peer, filling the eager set first
+    pub fn add_peer(&self, peer: [u8; 32]) {
+        let mut state = self.state.write();
+        if state.eager.contains(&peer) || state.lazy.contains(&peer) {
+            return;
+        }
+        if state.eager.len() < self.config.eager_fanout {
+            state.eager.insert(peer);
+        } else {
+            state.lazy.insert(peer);
+        }
+    }
+
+    /// Remove a disconnected peer, promoting a lazy peer if eager drops below fanout
+    pub fn remove_peer(&self, peer: &[u8; 32]) {
+        let mut state = self.state.write();
+        state.lazy.remove(peer);
+        if state.eager.remove(peer) && state.eager.len() < self.config.eager_fanout {
+            if let Some(promoted) = state.lazy.iter().next().copied() {
+                state.lazy.remove(&promoted);
+                state.eager.insert(promoted);
+            }
+        }
+        for missing in state.missing.values_mut() {
+            missing.announcers.retain(|p| p != peer);
+        }
+        state.missing.retain(|_, m| !m.announcers.is_empty());
+    }
+
+    /// Check whether an event ID has been seen recently
+    pub fn is_seen(&self, event_id: &EventId) -> bool {
+        self.state.read().seen.contains(event_id)
+    }
+
+    /// Whether a peer is currently in the eager set
+    pub fn is_eager(&self, peer: &[u8; 32]) -> bool {
+        self.state.read().eager.contains(peer)
+    }
+
+    /// Eager and lazy peer counts
+    pub fn peer_counts(&self) -> (usize, usize) {
+        let state = self.state.read();
+        (state.eager.len(), state.lazy.len())
+    }
+
+    /// Start disseminating an event that entered the local log.
+    ///
+    /// `from` is the peer it came from, if any; it is excluded from the push.
+    /// Returns no actions if the event was already seen.
+    pub fn broadcast(&self, event: &Event, from: Option<[u8; 32]>) -> Vec<GossipAction> {
+        let mut state = self.state.write();
+        if !state.seen.insert(event.event_id) {
+            return Vec::new();
+        }
+        state.missing.remove(&event.event_id);
+        self.forward(&state, event, from)
+    }
+
+    /// Handle an eagerly pushed event before it is validated.
+    ///
+    /// Returns whether the event is new. A duplicate prunes the sender, and
+    /// the returned actions say so. A new event changes nothing yet: once
+    /// it has been merged into the log, [`PushGossip::on_merged`] records
+    /// it and forwards it.
+    pub fn on_push(&self, from: [u8; 32], event: &Event) -> (bool, Vec<GossipAction>) {
+        let mut state = self.state.write();
+        if !state.seen.contains(&event.event_id) {
+            return (true, Vec::new());
+        }
+        // Redundant path: demote the sender to lazy
+        state.eager.remove(&from);
+        state.lazy.insert(from);
+        (false, vec![GossipAction::Prune { peer: from }])
+    }
+
+    /// Handle a pushed event that was merged into the log.
+    ///
+    /// Marks it seen, grafts the sender to eager and returns the actions
+    /// forwarding it, unless its log notification already did.
+    pub fn on_merged(&self, from: [u8; 32], event: &Event) -> Vec<GossipAction> {
+        let mut state = self.state.write();
+        state.lazy.remove(&from);
+        state.eager.insert(from);
+        if !state.seen.insert(event.event_id) {
+            return Vec::new();
+        }
+        state.missing.remove(&event.event_id);
+        self.forward(&state, event, Some(from))
+    }
+
+    /// Record an announcement of event IDs from a lazy peer
+    pub fn on_ihave(&self, from: [u8; 32], event_ids: &[EventId], now: Instant) {
+        let mut state = self.state.write();
+        let deadline = now + self.config.ihave_timeout;
+        for id in event_ids {
+            if state.seen.contains(id) {
+                continue;
+            }
+            let missing = state.missing.entry(*id).or_insert_with(|| MissingEvent {
+                announcers: VecDeque::new(),
+                deadline,
+            });
+            if !missing.announcers.contains(&from) {
+                missing.announcers.push_back(from);
+            }
+        }
+    }
+
+    /// Handle a request for events; grafts the requester back to eager
+    pub fn on_iwant(&self, from: [u8; 32]) {
+        let mut state = self.state.write();
+        state.lazy.remove(&from);
+        state.eager.insert(from);
+    }
+
+    /// Handle a prune; demotes the sender to lazy
+    pub fn on_prune(&self, from: [u8; 32]) {
+        let mut state = self.state.write();
+        if state.eager.remove(&from) {
+            state.lazy.insert(from);
+        }
+    }
+
+    /// Request announced events whose eager push did not arrive in time.
+    ///
+    /// Each expired event is requested from its next announcer, which is
+    /// grafted to eager so the tree repairs itself.
+    pub fn tick(&self, now: Instant) -> Vec<GossipAction> {
+        let mut state = self.state.write();
+        let state = &mut *state;
+        let mut wants: HashMap<[u8; 32], Vec<EventId>> = HashMap::new();
+
+        for (id, missing) in state.missing.iter_mut() {
+            if missing.deadline > now || state.seen.contains(id) {
+                continue;
+            }
+            if let Some(peer) = missing.announcers.pop_front() {
+                wants.entry(peer).or_default().push(*id);
+                missing.deadline = now + self.config.ihave_timeout;
+            }
+        }
+        // Keep entries that still have announcers or an outstanding request
+        let seen = &state.seen;
+        state
+            .missing
+            .retain(|id, m| !seen.contains(id) && (!m.announcers.is_empty() || m.deadline > now));
+
+        let mut actions = Vec::with_capacity(wants.len());
+        for (peer, event_ids) in wants {
+            state.lazy.remove(&peer);
+            state.eager.insert(peer);
+            actions.push(GossipAction::IWant { peer, event_ids });
+        }
+        actions
+    }
+
+    fn forward(
+        &self,
+        state: &GossipState,
+        event: &Event,
+        from: Option<[u8; 32]>,
+    ) -> Vec<GossipAction> {
+        let mut actions = Vec::new();
+        for peer in state.eager.iter().filter(|p| Some(**p) != from) {
+            actions.push(GossipAction::Push {
+                peer: *peer,
+                events: vec![event.clone()],
+            });
+        }
+        for peer in state
+            .lazy
+            .iter()
+            .filter(|p| Some(**p) != from)
+            .take(self.config.lazy_fanout)
+        {
+            actions.push(GossipAction::IHave {
+                peer: *peer,
+                event_ids: vec![event.event_id],
+            });
+        }
+        actions
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_event(id: u8) -> Event {
+        Event {
+            event_id: EventId([id; 32]),
+            world: WorldId([0; 32]),
+            epoch_id: 1,
+            event_type: EventType::RuleEndorsement,
+            body: EventBody::RuleEndorsement(RuleEndorsementEvent {
+                world: WorldId([0; 32]),
+                epoch_id: 1,
+                rule_bundle_hash: [0; 32],
+                weight: 1.0,
+                signer_transport_pubkey: vec![],
+                signature: vec![],
+            }),
+        }
+    }
+
+    fn test_gossip(eager_fanout: usize) -> PushGossip {
+        PushGossip::new(GossipConfig {
+            eager_fanout,
+            lazy_fanout: 8,
+            seen_cache_size: 16,
+            ihave_timeout: Duration::from_millis(100),
+        })
+    }
+
+    #[test]
+    fn test_broadcast_eager_and_lazy() {
+        let gossip = test_gossip(2);
+        for i in 1..=4 {
+            gossip.add_peer([i; 32]);
+        }
+        assert_eq!(gossip.peer_counts(), (2, 2));
+
+        let actions = gossip.broadcast(&test_event(1), None);
+        let pushes = actions
+            .iter()
+            .filter(|a| matches!(a, GossipAction::Push { .. }))
+            .count();
+        let ihaves = actions
+            .iter()
+            .filter(|a| matches!(a, GossipAction::IHave { .. }))
+            .count();
+        assert_eq!(pushes, 2);
+        assert_eq!(ihaves, 2);
+
+        // Second broadcast of the same event is deduplicated
+        assert!(gossip.broadcast(&test_event(1), None).is_empty());
+    }
+
+    #[test]
+    fn test_duplicate_push_prunes_sender() {
+        let gossip = test_gossip(4);
+        gossip.add_peer([1; 32]);
+        gossip.add_peer([2; 32]);
+
+        let (fresh, actions) = gossip.on_push([1; 32], &test_event(7));
+        assert!(fresh);
+        assert!(actions.is_empty());
+        let actions = gossip.on_merged([1; 32], &test_event(7));
+        // Forwarded to the other eager peer only
+        assert_eq!(actions.len(), 1);
+        assert!(matches!(&actions[0], GossipAction::Push { peer, .. } if *peer == [2; 32]));
+
+        let (fresh, actions) = gossip.on_push([2; 32], &test_event(7));
+        assert!(!fresh);
+        assert_eq!(actions, vec![GossipAction::Prune { peer: [2; 32] }]);
+        assert!(!gossip.is_eager(&[2; 32]));
+    }
+
+    #[test]
+    fn test_rejected_push_is_not_seen() {
+        let gossip = test_gossip(0);
+        gossip.add_peer([1; 32]);
+        gossip.add_peer([2; 32]);
+        gossip.add_peer([3; 32]);
+
+        // An invalid push is dropped without marking its ID or grafting
+        let (fresh, _) = gossip.on_push([1; 32], &test_event(4));
+        assert!(fresh);
+        assert!(!gossip.is_seen(&EventId([4; 32])));
+        assert!(!gossip.is_eager(&[1; 32]));
+
+        // A valid push of the same ID is still new and forwarded
+        let (fresh, _) = gossip.on_push([2; 32], &test_event(4));
+        assert!(fresh);
+        let actions = gossip.on_merged([2; 32], &test_event(4));
+        assert!(gossip.is_seen(&EventId([4; 32])));
+        assert!(gossip.is_eager(&[2; 32]));
+        assert!(!actions.is_empty());
+
+        // An event released later is broadcast from its log notification
+        let (fresh, _) = gossip.on_push([3; 32], &test_event(5));
+        assert!(fresh);
+        assert!(!gossip.broadcast(&test_event(5), Some([3; 32])).is_empty());
+    }
+
+    #[test]
+    fn test_ihave_timeout_requests_and_grafts() {
+        let gossip = test_gossip(0);
+        gossip.add_peer([3; 32]);
+        assert!(!gossip.is_eager(&[3; 32]));
+
+        let now = Instant::now();
+        gossip.on_ihave([3; 32], &[EventId([9; 32])], now);
+
+        // Nothing before the timeout
+        assert!(gossip.tick(now).is_empty());
+
+        let actions = gossip.tick(now + Duration::from_millis(150));
+        assert_eq!(
+            actions,
+            vec![GossipAction::IWant {
+                peer: [3; 32],
+                event_ids: vec![EventId([9; 32])],
+            }]
+        );
+        assert!(gossip.is_eager(&[3; 32]));
+    }
+
+    #[test]
+    fn test_ihave_satisfied_by_push() {
+        let gossip = test_gossip(1);
+        gossip.add_peer([1; 32]);
+        gossip.add_peer([2; 32]);
+
+        let now = Instant::now();
+        gossip.on_ihave([2; 32], &[EventId([5; 32])], now);
+        gossip.on_merged([1; 32], &test_event(5));
+
+        assert!(gossip.tick(now + Duration::from_secs(1)).is_empty());
+    }
+
+    #[test]
+    fn test_seen_cache_eviction() {
+        let gossip = test_gossip(1);
+        for i in 0..20 {
+            gossip.broadcast(&test_event(i), None);
+        }
+        // Capacity is 16, so the oldest IDs were evicted
+        assert!(!gossip.is_seen(&EventId([0; 32])));
+        assert!(gossip.is_seen(&EventId([19; 32])));
+    }
+}
@@ -0,0 +1,189 @@
+//! Several worlds hosted by one process
+//!
+//! Each world runs its own `Server` with its own rule bundle, storage,
+//! membership and sync schedule. The worlds share one control-plane
+//! listener, where inbound connections are routed by the world in the
+//! peer's HELLO, and one local API listener, where each connection is
+//! scoped to a world (see [`ApiHost`]).
+
+use crate::api::ApiHost;
+use crate::config::{Config, WorldSpec};
+use crate::server::{read_hello, Server, ServerError};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use terrain_gossip_core::types::WorldId;
+use terrain_gossip_net::framing::FrameType;
+use terrain_gossip_net::transport::messages;
+use tokio::net::{TcpListener, TcpStream};
+use tracing::{debug, error, info, warn};
+
+/// Hosted worlds sharing listeners
+pub struct Host {
+    /// World new API connections start in
+    primary: WorldId,
+    worlds: HashMap<WorldId, Arc<Server>>,
+}
+
+impl Host {
+    /// Host the primary world from `config` and any listed in `--worlds`
+    pub fn new(config: Config) -> Result<Self, ServerError> {
+        let specs = match &config.worlds {
+            Some(path) => Self::load_worlds(path)?,
+            None => Vec::new(),
+        };
+        let mut servers = vec![Arc::new(Server::new(config.clone())?)];
+        for spec in &specs {
+            let world_config = config.for_world(spec);
+            world_config
+                .validate()
+                .map_err(|e| ServerError::Worlds(format!("{}: {}", spec.world_phrase, e)))?;
+            servers.push(Arc::new(Server::new(world_config)?));
+        }
+        Self::from_servers(servers)
+    }
+
+    /// Host already constructed servers; the first is the primary world
+    pub fn from_servers(servers: Vec<Arc<Server>>) -> Result<Self, ServerError> {
+        let primary = servers
+            .first()
+            .map(|server| WorldId(server.world_id()))
+            .ok_or_else(|| ServerError::Worlds("no worlds to host".into()))?;
+        let mut worlds = HashMap::new();
+        for server in servers {
+            let world = WorldId(server.world_id());
+            if worlds.insert(world, server).is_some() {
+                return Err(ServerError::Worlds(format!(
+                    "world {} is listed twice",
+                    hex::encode(&world.0[..8])
+                )));
+            }
+        }
+        Ok(Self { primary, worlds })
+    }
+
+    /// Load additional world specs from a JSON file
+    fn load_worlds(path: &std::path::Path) -> Result<Vec<WorldSpec>, ServerError> {
+        let bytes = std::fs::read(path)?;
+        serde_json::from_slice(&bytes)
+            .map_err(|e| ServerError::Worlds(format!("{}: {}", path.display(), e)))
+    }
+
+    /// Hosted world IDs, primary first
+    pub fn world_ids(&self) -> Vec<WorldId> {
+        let mut worlds: Vec<WorldId> = self.worlds.keys().copied().collect();
+        worlds.sort_by_key(|world| (*world != self.primary, world.0));
+        worlds
+    }
+
+    /// Server for a hosted world
+    pub fn world(&self, world: &WorldId) -> Option<Arc<Server>> {
+        self.worlds.get(world).cloned()
+    }
+
+    fn primary(&self) -> &Arc<Server> {
+        &self.worlds[&self.primary]
+    }
+
+    /// Bind the primary world's listeners and serve every world
+    pub async fn run(&self) -> Result<(), ServerError> {
+        let config = self.primary().config();
+        let listener = TcpListener::bind(&config.listen).await?;
+        let api_listener = TcpListener::bind(&config.api_listen).await?;
+        info!(
+            "Listening on {} (API {}) for {} world(s)",
+            config.listen,
+            config.api_listen,
+            self.worlds.len()
+        );
+        self.serve(listener, api_listener).await
+    }
+
+    /// Start every world and serve both listeners until the primary world
+    /// shuts down
+    pub async fn serve(
+        &self,
+        listener: TcpListener,
+        api_listener: TcpListener,
+    ) -> Result<(), ServerError> {
+        let mut handles = Vec::new();
+        for world in self.world_ids() {
+            handles.extend(self.worlds[&world].start()?);
+        }
+
+        let api = self
+            .worlds
+            .iter()
+            .filter(|(world, _)| **world != self.primary)
+            .fold(
+                ApiHost::new(self.primary, Arc::new(self.primary().api())),
+                |api, (world, server)| api.with_world(*world, Arc::new(server.api())),
+            );
+        handles.push(tokio::spawn(
+            Arc::new(api).serve(api_listener, self.primary().shutdown_signal()),
+        ));
+
+        let mut shutdown_rx = self.primary().shutdown_signal();
+        loop {
+            tokio::select! {
+                result = listener.accept() => match result {
+                    Ok((stream, addr)) => {
+                        let worlds = self.worlds.clone();
+                        tokio::spawn(async move {
+                            if let Err(e) = Self::route(&worlds, stream, addr).await {
+                                debug!("Dropping connection from {}: {}", addr, e);
+                            }
+                        });
+                    }
+                    Err(e) => error!("Accept error: {}", e),
+                },
+                _ = shutdown_rx.recv() => {
+                    info!("Shutting down...");
+                    break;
+                }
+            }
+        }
+
+        self.shutdown();
+        for handle in handles {
+            handle.abort();
+        }
+        for server in self.worlds.values() {
+            server.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Hand an inbound connection to the world named in its HELLO
+    async fn route(
+        worlds: &HashMap<WorldId, Arc<Server>>,
+        mut stream: TcpStream,
+        addr: SocketAddr,
+    ) -> Result<(), ServerError> {
+        let frame = read_hello(&mut stream).await?;
+        if frame.frame_type != FrameType::Hello {
+            return Err(ServerError::Handshake(format!(
+                "expected HELLO, got {:?}",
+                frame.frame_type
+            )));
+        }
+        let hello = messages::Hello::from_frame(&frame)?;
+        let Some(server) = worlds.get(&hello.world) else {
+            warn!(
+                "Peer at {} asked for unhosted world {:02x?}",
+                addr,
+                &hello.world.0[..8]
+            );
+            return Err(ServerError::Handshake("world mismatch".into()));
+        };
+        server.clone().accept_routed(stream, addr, frame);
+        Ok(())
+    }
+
+    /// Shut down every world
+    pub fn shutdown(&self) {
+        for server in self.worlds.values() {
+            server.shutdown();
+        }
+    }
+}
@@ -0,0 +1,230 @@
+//! Secondary index keys for the event store
+//!
+//! Every index entry is a composite sled key with an empty value:
+//!
+//! - `EpochType`:  epoch_be(8) || event_type(1) || event_id(32)
+//! - `Target`:     target_ref(32) || epoch_be(8) || event_id(32)
+//! - `Signer`:     signer_key(32) || epoch_be(8) || event_id(32)
+//! - `Challenge`:  challenge_id(32) || epoch_be(8) || event_id(32)
+//! - `Descriptor`: descriptor_id(32) || epoch_be(8) || event_id(32)
+//! - `Fah`:        fah(32) || epoch_be(8) || event_id(32)
+//!
+//! Big-endian epochs keep entries for one key ordered by epoch, so an epoch
+//! range is a single contiguous scan.
+
+use terrain_gossip_core::crypto::derive_fah;
+use terrain_gossip_core::types::*;
+
+/// Index format version; bump to force a rebuild on open
+pub const INDEX_VERSION: u8 = 2;
+
+/// Secondary index kinds
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum IndexKind {
+    /// By (epoch, event type)
+    EpochType,
+    /// By TargetRef (receipts, attestations, link hints)
+    Target,
+    /// By signer transport public key
+    Signer,
+    /// By challenge ID (receipts, attestations)
+    Challenge,
+    /// By descriptor ID (descriptor publications)
+    Descriptor,
+    /// By advertised FAH (descriptor publications)
+    Fah,
+}
+
+impl IndexKind {
+    /// All index kinds
+    pub const ALL: [IndexKind; 6] = [
+        IndexKind::EpochType,
+        IndexKind::Target,
+        IndexKind::Signer,
+        IndexKind::Challenge,
+        IndexKind::Descriptor,
+        IndexKind::Fah,
+    ];
+
+    /// sled tree name
+    pub fn tree_name(&self) -> &'static str {
+        match self {
+            IndexKind::EpochType => "idx_epoch_type",
+            IndexKind::Target => "idx_target",
+            IndexKind::Signer => "idx_signer",
+            IndexKind::Challenge => "idx_challenge",
+            IndexKind::Descriptor => "idx_descriptor",
+            IndexKind::Fah => "idx_fah",
+        }
+    }
+}
+
+/// Normalize a signer public key to 32 bytes
+///
+/// Ed25519 keys are used as-is; anything else is hashed so every key has a
+/// fixed-width prefix.
+pub fn signer_key(pubkey: &[u8]) -> [u8; 32] {
+    match <[u8; 32]>::try_from(pubkey) {
+        Ok(key) => key,
+        Err(_) => *blake3::hash(pubkey).as_bytes(),
+    }
+}
+
+/// FAH advertised by a descriptor
+pub fn descriptor_fah(descriptor: &ProviderDescriptor) -> Option<Fah> {
+    match &descriptor.unsigned.capability {
+        DescriptorCapability::Fah(fah) => Some(*fah),
+        DescriptorCapability::Manifest(manifest) => derive_fah(manifest).ok(),
+    }
+}
+
+/// `prefix || epoch_be || event_id`
+fn keyed(prefix: &[u8; 32], epoch_id: u64, event_id: &EventId) -> Vec<u8> {
+    let mut key = Vec::with_capacity(72);
+    key.extend_from_slice(prefix);
+    key.extend_from_slice(&epoch_id.to_be_bytes());
+    key.extend_from_slice(&event_id.0);
+    key
+}
+
+/// Lower and upper bound (inclusive) for `prefix` within an epoch range
+pub fn prefix_range(prefix: &[u8; 32], epoch_from: u64, epoch_to: u64) -> (Vec<u8>, Vec<u8>) {
+    let mut lower = prefix.to_vec();
+    lower.extend_from_slice(&epoch_from.to_be_bytes());
+    let mut upper = prefix.to_vec();
+    upper.extend_from_slice(&epoch_to.to_be_bytes());
+    upper.extend_from_slice(&[0xff; 32]);
+    (lower, upper)
+}
+
+/// Lower and upper bound (inclusive) for the epoch/type index
+pub fn epoch_range(epoch_from: u64, epoch_to: u64) -> (Vec<u8>, Vec<u8>) {
+    let lower = epoch_from.to_be_bytes().to_vec();
+    let mut upper = epoch_to.to_be_bytes().to_vec();
+    upper.extend_from_slice(&[0xff; 33]);
+    (lower, upper)
+}
+
+/// Event ID stored at the end of an index key
+pub fn event_id_from_key(key: &[u8]) -> Option<EventId> {
+    let start = key.len().checked_sub(32)?;
+    key[start..].try_into().ok().map(EventId)
+}
+
+/// All index entries for an event
+pub fn index_keys(event: &Event) -> Vec<(IndexKind, Vec<u8>)> {
+    let epoch = event.epoch_id;
+    let id = &event.event_id;
+
+    let mut epoch_type = Vec::with_capacity(41);
+    epoch_type.extend_from_slice(&epoch.to_be_bytes());
+    epoch_type.push(event.event_type as u8);
+    epoch_type.extend_from_slice(&id.0);
+    let mut keys = vec![(IndexKind::EpochType, epoch_type)];
+
+    match &event.body {
+        EventBody::Receipt(receipt) => {
+            keys.push((IndexKind::Target, keyed(&receipt.target_ref.0, epoch, id)));
+            keys.push((
+                IndexKind::Challenge,
+                keyed(&receipt.challenge_id.0, epoch, id),
+            ));
+            keys.push((
+                IndexKind::Signer,
+                keyed(&signer_key(&receipt.prober_transport_pubkey), epoch, id),
+            ));
+        }
+        EventBody::Attestation(attestation) => {
+            keys.push((
+                IndexKind::Target,
+                keyed(&attestation.target_ref.0, epoch, id),
+            ));
+            keys.push((
+                IndexKind::Challenge,
+                keyed(&attestation.challenge_id.0, epoch, id),
+            ));
+            keys.push((
+                IndexKind::Signer,
+                keyed(&signer_key(&attestation.prober_transport_pubkey), epoch, id),
+            ));
+        }
+        EventBody::Dispute(dispute) => {
+            keys.push((
+                IndexKind::Signer,
+                keyed(&signer_key(&dispute.disputer_transport_pubkey), epoch, id),
+            ));
+        }
+        EventBody::LinkHint(hint) => {
+            keys.push((IndexKind::Target, keyed(&hint.target_a.0, epoch, id)));
+            if hint.target_b != hint.target_a {
+                keys.push((IndexKind::Target, keyed(&hint.target_b.0, epoch, id)));
+            }
+            keys.push((
+                IndexKind::Signer,
+                keyed(&signer_key(&hint.signer_transport_pubkey), epoch, id),
+            ));
+        }
+        EventBody::RuleEndorsement(endorsement) => {
+            keys.push((
+                IndexKind::Signer,
+                keyed(&signer_key(&endorsement.signer_transport_pubkey), epoch, id),
+            ));
+        }
+        EventBody::DescriptorPublish(publish) => {
+            let descriptor = &publish.descriptor;
+            keys.push((
+                IndexKind::Descriptor,
+                keyed(&descriptor.descriptor_id.0, epoch, id),
+            ));
+            if let Some(fah) = descriptor_fah(descriptor) {
+                keys.push((IndexKind::Fah, keyed(&fah.0, epoch, id)));
+            }
+            keys.push((
+                IndexKind::Signer,
+                keyed(
+                    &signer_key(&descriptor.provider_transport_pubkey),
+                    epoch,
+                    id,
+                ),
+            ));
+        }
+        EventBody::DescriptorRevoke(revoke) => {
+            keys.push((
+                IndexKind::Descriptor,
+                keyed(&revoke.descriptor_id.0, epoch, id),
+            ));
+            keys.push((
+                IndexKind::Signer,
+                keyed(&signer_key(&revoke.provider_transport_pubkey), epoch, id),
+            ));
+        }
+    }
+
+    keys
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key_ordering_and_ranges() {
+        let target = [7; 32];
+        let a = keyed(&target, 5, &EventId([1; 32]));
+        let b = keyed(&target, 256, &EventId([0; 32]));
+        // Big-endian epochs sort numerically
+        assert!(a < b);
+
+        let (lower, upper) = prefix_range(&target, 5, 10);
+        assert!(a >= lower && a <= upper);
+        assert!(b > upper);
+
+        assert_eq!(event_id_from_key(&b), Some(EventId([0; 32])));
+    }
+
+    #[test]
+    fn test_signer_key_normalization() {
+        assert_eq!(signer_key(&[3; 32]), [3; 32]);
+        assert_ne!(signer_key(&[3; 33]), [3; 32]);
+    }
+}
@@ -6,17 +6,53 @@
 //! - Version vector management
 //! - Event validation and verification
 //! - Control-plane membership gating
+//! - Local API for routerd, prober and infernode
+//! - Signed checkpoints for fast bootstrap
+//! - Misbehavior scoring and sanctions
+//! - Semantic search over event summaries
+//! - Anomaly detection over attestations
+//! - Automatic disputes for conflicting attestations
+//! - Rule bundle endorsement tallies and world forks
+//! - Want-list fetch of specific events
+//! - Several worlds hosted by one process
 
+pub mod anomaly;
+pub mod api;
+pub mod backend;
+pub mod checkpoint;
 pub mod config;
+pub mod dispute;
+pub mod endorsement;
 pub mod event_log;
+pub mod fetch;
+pub mod gossip;
+pub mod host;
+pub mod index;
 pub mod membership;
+pub mod peer_book;
+pub mod policy;
+pub mod quarantine;
+pub mod query;
+pub mod retention;
+pub mod semantic;
 pub mod server;
 pub mod storage;
 pub mod sync;
 
+pub use anomaly::AnomalyDetector;
+pub use api::ApiClient;
+pub use backend::{MemoryBackend, SledBackend, StorageBackend};
+pub use checkpoint::Checkpoint;
 pub use config::Config;
+pub use dispute::DisputeDetector;
+pub use endorsement::EndorsementTally;
 pub use event_log::EventLog;
+pub use gossip::PushGossip;
 pub use membership::MembershipManager;
+pub use peer_book::PeerBook;
+pub use policy::MisbehaviorPolicy;
+pub use query::{EventQuery, QueryPage};
+pub use semantic::{Embedder, HashingEmbedder, SemanticIndex};
 pub use server::Server;
 pub use storage::Storage;
 pub use sync::SyncManager;
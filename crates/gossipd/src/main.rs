@@ -5,8 +5,9 @@
 
 use clap::Parser;
 use gossipd::config::Config;
-use gossipd::server::Server;
+use gossipd::host::Host;
 use std::process::ExitCode;
+use std::sync::Arc;
 use tracing::{error, info};
 use tracing_subscriber::{fmt, prelude::*, EnvFilter};
 
@@ -26,21 +27,20 @@ async fn main() -> ExitCode {
         env!("CARGO_PKG_VERSION")
     );
 
-    // Create and run server
-    match Server::new(config) {
-        Ok(server) => {
-            // Install signal handlers
-            let shutdown_server = {
-                let server_ref = &server;
-                move || server_ref.shutdown()
-            };
+    // Create and run the hosted worlds
+    match Host::new(config) {
+        Ok(host) => {
+            let host = Arc::new(host);
 
+            // Install signal handlers
+            let shutdown_host = host.clone();
             tokio::spawn(async move {
                 tokio::signal::ctrl_c().await.ok();
                 info!("Received shutdown signal");
+                shutdown_host.shutdown();
             });
 
-            if let Err(e) = server.run().await {
+            if let Err(e) = host.run().await {
                 error!("Server error: {}", e);
                 return ExitCode::FAILURE;
             }
@@ -1,9 +1,25 @@
 //! Membership and control-plane gating
-
+//!
+//! Member records, bans and an audit trail of suspensions and bans are
+//! persisted in `Storage`, so a restarted node keeps its bans and the
+//! reputation it has accumulated. Times are wall-clock Unix milliseconds.
+//! Ban lists can be exported and imported between an operator's nodes.
+//!
+//! Admission records the roles a peer announced. Providers are never
+//! control-plane participants, whether they say so or their transport key
+//! appears in a provider descriptor, and each RPC is checked against the
+//! sender's roles.
+
+use crate::peer_book::now_millis;
+use crate::storage::{Storage, StorageError};
 use blake3::Hasher;
 use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
 use std::time::{Duration, Instant};
+use terrain_gossip_net::framing::FrameType;
+use terrain_gossip_net::peer::PeerRoles;
 use thiserror::Error;
 
 /// Membership errors
@@ -15,38 +31,148 @@ pub enum MembershipError {
     NotAdmitted([u8; 32]),
     #[error("Peer banned: {0:?}")]
     Banned([u8; 32]),
+    #[error("Peer suspended: {0:?}")]
+    Suspended([u8; 32]),
     #[error("Rate limited")]
     RateLimited,
+    #[error("Providers are not control-plane participants: {0:?}")]
+    Provider([u8; 32]),
+    #[error("Peer roles do not permit {0:?}")]
+    Unauthorized(ControlRpc),
+    #[error("Storage error: {0}")]
+    Storage(#[from] StorageError),
 }
 
 /// Member status
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum MemberStatus {
     /// Pending admission
     Pending,
     /// Admitted member
     Admitted,
-    /// Temporarily suspended
-    Suspended { until: Instant },
+    /// Temporarily suspended until a Unix time in milliseconds
+    Suspended { until: u64 },
     /// Permanently banned
     Banned,
 }
 
+/// Control-plane RPCs subject to role checks
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ControlRpc {
+    /// Ask for a delta of the event log, or for specific events
+    SyncRequest,
+    /// Deliver a delta of the event log, or fetched events
+    SyncResponse,
+    /// Eager and lazy push gossip
+    EventPush,
+    /// Exchange control-plane peer addresses
+    PeerExchange,
+    /// Look up a single provider descriptor
+    DescriptorQuery,
+}
+
+impl ControlRpc {
+    /// RPC carried by a frame type; `None` for frames every admitted peer
+    /// may send (keepalives) or that are not control-plane RPCs
+    pub fn for_frame(frame_type: FrameType) -> Option<ControlRpc> {
+        match frame_type {
+            FrameType::DeltaSyncRequest | FrameType::EventFetch => Some(ControlRpc::SyncRequest),
+            FrameType::DeltaSyncResponse | FrameType::EventFetchResponse => {
+                Some(ControlRpc::SyncResponse)
+            }
+            FrameType::EventBroadcast
+            | FrameType::IHave
+            | FrameType::IWant
+            | FrameType::GossipPrune => Some(ControlRpc::EventPush),
+            FrameType::PeerExchange => Some(ControlRpc::PeerExchange),
+            FrameType::DescriptorQuery => Some(ControlRpc::DescriptorQuery),
+            _ => None,
+        }
+    }
+
+    /// Whether `roles` may use this RPC
+    ///
+    /// gossipd, router and prober nodes are full participants. Relays
+    /// (infernode) may only look up descriptors. Providers get nothing.
+    pub fn permitted(self, roles: &PeerRoles) -> bool {
+        if roles.provider {
+            return false;
+        }
+        if is_full_participant(roles) {
+            return true;
+        }
+        roles.relay && self == ControlRpc::DescriptorQuery
+    }
+}
+
+/// Whether `roles` take part in sync and push gossip
+pub fn is_full_participant(roles: &PeerRoles) -> bool {
+    !roles.provider && (roles.gossipd || roles.router || roles.prober)
+}
+
 /// Member information
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Member {
     /// Peer public key
     pub pubkey: [u8; 32],
     /// Current status
     pub status: MemberStatus,
-    /// When the member joined
-    pub joined_at: Instant,
-    /// Last activity timestamp
-    pub last_seen: Instant,
+    /// When the member first joined (unix millis)
+    pub joined_at: u64,
+    /// Last activity (unix millis)
+    pub last_seen: u64,
     /// Number of events contributed
     pub event_count: u64,
     /// Reputation score (0.0-1.0)
     pub reputation: f64,
+    /// Roles announced at the latest admission
+    pub roles: PeerRoles,
+}
+
+/// A banned peer
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BanRecord {
+    /// Peer public key
+    pub pubkey: [u8; 32],
+    /// Why the peer was banned
+    pub reason: String,
+    /// When the ban was issued (unix millis)
+    pub banned_at: u64,
+    /// Node the ban was imported from (`None` if issued locally)
+    pub imported_from: Option<[u8; 32]>,
+}
+
+/// Bans exported by one node for import on another
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BanList {
+    /// Transport public key of the exporting node
+    pub exported_by: [u8; 32],
+    /// Unix millis
+    pub exported_at: u64,
+    /// Bans, oldest first
+    pub bans: Vec<BanRecord>,
+}
+
+/// Membership change recorded in the audit trail
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum AuditAction {
+    Admitted,
+    Suspended { until: u64 },
+    Reinstated,
+    Banned,
+    Unbanned,
+    /// Admission withdrawn because the peer is a provider
+    Revoked,
+}
+
+/// Audit trail entry
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuditRecord {
+    /// Unix millis
+    pub at: u64,
+    pub pubkey: [u8; 32],
+    pub action: AuditAction,
+    pub reason: String,
 }
 
 /// Membership manager with control-plane gating
@@ -55,10 +181,14 @@ pub struct MembershipManager {
     world_id: [u8; 32],
     /// World admission phrase
     world_phrase: String,
+    /// Persistent membership state
+    storage: Arc<Storage>,
     /// Member registry
     members: RwLock<HashMap<[u8; 32], Member>>,
     /// Banned peers (permanent)
-    banned: RwLock<HashSet<[u8; 32]>>,
+    banned: RwLock<HashMap<[u8; 32], BanRecord>>,
+    /// Transport keys seen in provider descriptors
+    providers: RwLock<HashSet<[u8; 32]>>,
     /// Rate limiting state
     rate_limits: RwLock<HashMap<[u8; 32], RateLimitState>>,
     /// Maximum requests per minute
@@ -72,7 +202,7 @@ struct RateLimitState {
 }
 
 impl MembershipManager {
-    /// Create a new membership manager
+    /// Create a new membership manager with in-memory state
     pub fn new(world_phrase: impl Into<String>, rate_limit_rpm: u32) -> Self {
         let phrase = world_phrase.into();
         let world_id = Self::derive_world_id(&phrase);
@@ -80,15 +210,42 @@ impl MembershipManager {
         Self {
             world_id,
             world_phrase: phrase,
+            storage: Arc::new(Storage::memory()),
             members: RwLock::new(HashMap::new()),
-            banned: RwLock::new(HashSet::new()),
+            banned: RwLock::new(HashMap::new()),
+            providers: RwLock::new(HashSet::new()),
             rate_limits: RwLock::new(HashMap::new()),
             rate_limit_rpm,
         }
     }
 
+    /// Persist membership in `storage`, loading any existing state
+    pub fn with_storage(mut self, storage: Arc<Storage>) -> Result<Self, StorageError> {
+        let members = storage
+            .all_members()?
+            .into_iter()
+            .map(|m| (m.pubkey, m))
+            .collect();
+        let banned = storage
+            .all_bans()?
+            .into_iter()
+            .map(|b| (b.pubkey, b))
+            .collect();
+        self.members = RwLock::new(members);
+        self.banned = RwLock::new(banned);
+        self.storage = storage;
+        Ok(self)
+    }
+
+    /// Gate admission on `world_id` instead of the phrase-derived one, e.g.
+    /// after following a fork
+    pub fn with_world_id(mut self, world_id: [u8; 32]) -> Self {
+        self.world_id = world_id;
+        self
+    }
+
     /// Derive world ID from phrase using BLAKE3
-    fn derive_world_id(phrase: &str) -> [u8; 32] {
+    pub(crate) fn derive_world_id(phrase: &str) -> [u8; 32] {
         let mut hasher = Hasher::new();
         hasher.update(b"terrain-gossip-world-v1:");
         hasher.update(phrase.as_bytes());
@@ -100,30 +257,86 @@ impl MembershipManager {
         self.world_id
     }
 
-    /// Verify world phrase and admit peer
-    pub fn admit_peer(&self, pubkey: [u8; 32], offered_phrase: &str) -> Result<(), MembershipError> {
+    /// Record a membership change in the audit trail
+    fn audit(
+        &self,
+        pubkey: [u8; 32],
+        action: AuditAction,
+        reason: &str,
+    ) -> Result<(), StorageError> {
+        self.storage.append_audit(&AuditRecord {
+            at: now_millis(),
+            pubkey,
+            action,
+            reason: reason.to_string(),
+        })
+    }
+
+    /// Proof of knowing the world phrase, bound to a handshake `transcript`
+    ///
+    /// A keyed hash rather than the phrase itself, so it cannot be reused on
+    /// another connection.
+    pub fn phrase_proof(&self, transcript: &[u8; 32]) -> [u8; 32] {
+        let key = blake3::derive_key(
+            "terrain-gossip world phrase proof v1",
+            self.world_phrase.as_bytes(),
+        );
+        *blake3::keyed_hash(&key, transcript).as_bytes()
+    }
+
+    /// Verify a peer's world phrase proof over its handshake `transcript`
+    /// and admit it with the roles it announced
+    ///
+    /// A returning member keeps its reputation and history; an unexpired
+    /// suspension is not lifted by reconnecting.
+    pub fn admit_peer(
+        &self,
+        pubkey: [u8; 32],
+        transcript: &[u8; 32],
+        phrase_proof: &[u8; 32],
+        roles: PeerRoles,
+    ) -> Result<(), MembershipError> {
         // Check if banned
-        if self.banned.read().contains(&pubkey) {
+        if self.banned.read().contains_key(&pubkey) {
             return Err(MembershipError::Banned(pubkey));
         }
 
-        // Verify phrase
-        if offered_phrase != self.world_phrase {
+        // Providers stay out of the control plane
+        if roles.provider || self.is_provider(&pubkey) {
+            return Err(MembershipError::Provider(pubkey));
+        }
+
+        // Verify phrase (`Hash` equality is constant time)
+        if blake3::Hash::from(*phrase_proof) != blake3::Hash::from(self.phrase_proof(transcript)) {
             return Err(MembershipError::InvalidWorldPhrase);
         }
 
-        // Admit member
-        let now = Instant::now();
-        let member = Member {
+        let now = now_millis();
+        let mut members = self.members.write();
+        let member = members.entry(pubkey).or_insert_with(|| Member {
             pubkey,
-            status: MemberStatus::Admitted,
+            status: MemberStatus::Pending,
             joined_at: now,
             last_seen: now,
             event_count: 0,
             reputation: 1.0,
-        };
+            roles,
+        });
+        if let MemberStatus::Suspended { until } = member.status {
+            if now < until {
+                return Err(MembershipError::Suspended(pubkey));
+            }
+        }
+        let newly_admitted = member.status != MemberStatus::Admitted;
+        member.status = MemberStatus::Admitted;
+        member.last_seen = now;
+        member.roles = roles;
+        self.storage.put_member(member)?;
+        drop(members);
 
-        self.members.write().insert(pubkey, member);
+        if newly_admitted {
+            self.audit(pubkey, AuditAction::Admitted, "world phrase accepted")?;
+        }
         Ok(())
     }
 
@@ -139,7 +352,7 @@ impl MembershipManager {
     /// Check peer authorization (with rate limiting)
     pub fn check_authorized(&self, pubkey: &[u8; 32]) -> Result<(), MembershipError> {
         // Check banned
-        if self.banned.read().contains(pubkey) {
+        if self.banned.read().contains_key(pubkey) {
             return Err(MembershipError::Banned(*pubkey));
         }
 
@@ -149,9 +362,9 @@ impl MembershipManager {
             None => return Err(MembershipError::NotAdmitted(*pubkey)),
             Some(m) => match &m.status {
                 MemberStatus::Admitted => {}
-                MemberStatus::Suspended { until } if Instant::now() >= *until => {}
+                MemberStatus::Suspended { until } if now_millis() >= *until => {}
                 MemberStatus::Suspended { .. } => {
-                    return Err(MembershipError::NotAdmitted(*pubkey))
+                    return Err(MembershipError::Suspended(*pubkey))
                 }
                 MemberStatus::Banned => return Err(MembershipError::Banned(*pubkey)),
                 MemberStatus::Pending => return Err(MembershipError::NotAdmitted(*pubkey)),
@@ -163,13 +376,51 @@ impl MembershipManager {
         self.check_rate_limit(pubkey)?;
 
         // Update last seen
-        if let Some(member) = self.members.write().get_mut(pubkey) {
-            member.last_seen = Instant::now();
-        }
+        self.touch(pubkey);
 
         Ok(())
     }
 
+    /// Check that a peer's roles permit `rpc`
+    pub fn authorize(&self, pubkey: &[u8; 32], rpc: ControlRpc) -> Result<(), MembershipError> {
+        let members = self.members.read();
+        let Some(member) = members.get(pubkey) else {
+            return Err(MembershipError::NotAdmitted(*pubkey));
+        };
+        if !rpc.permitted(&member.roles) {
+            return Err(MembershipError::Unauthorized(rpc));
+        }
+        Ok(())
+    }
+
+    /// Record a transport key seen in a provider descriptor
+    ///
+    /// An admitted member with that key loses its admission. Returns true
+    /// if that happened.
+    pub fn mark_provider(&self, pubkey: [u8; 32]) -> Result<bool, MembershipError> {
+        if !self.providers.write().insert(pubkey) {
+            return Ok(false);
+        }
+        {
+            let mut members = self.members.write();
+            let Some(member) = members.get_mut(&pubkey) else {
+                return Ok(false);
+            };
+            if member.status != MemberStatus::Admitted {
+                return Ok(false);
+            }
+            member.status = MemberStatus::Pending;
+            self.storage.put_member(member)?;
+        }
+        self.audit(pubkey, AuditAction::Revoked, "published a provider descriptor")?;
+        Ok(true)
+    }
+
+    /// Whether a transport key appears in a provider descriptor
+    pub fn is_provider(&self, pubkey: &[u8; 32]) -> bool {
+        self.providers.read().contains(pubkey)
+    }
+
     /// Check rate limit for a peer
     fn check_rate_limit(&self, pubkey: &[u8; 32]) -> Result<(), MembershipError> {
         let now = Instant::now();
@@ -196,35 +447,218 @@ impl MembershipManager {
         Ok(())
     }
 
+    /// Record activity from a member
+    ///
+    /// Kept in memory only; `prune_stale` writes it back.
+    pub fn touch(&self, pubkey: &[u8; 32]) {
+        if let Some(member) = self.members.write().get_mut(pubkey) {
+            member.last_seen = now_millis();
+        }
+    }
+
+    /// Forget members idle for longer than `max_idle`, except `keep`.
+    ///
+    /// Expired suspensions are lifted, rate-limit windows for departed
+    /// peers are dropped, and banned peers are never forgotten. Surviving
+    /// members are written back with their latest activity.
+    pub fn prune_stale(
+        &self,
+        max_idle: Duration,
+        keep: &HashSet<[u8; 32]>,
+    ) -> Result<usize, MembershipError> {
+        let now = now_millis();
+        let max_idle = max_idle.as_millis() as u64;
+        let mut members = self.members.write();
+
+        let mut reinstated = Vec::new();
+        for member in members.values_mut() {
+            if matches!(member.status, MemberStatus::Suspended { until } if now >= until) {
+                member.status = MemberStatus::Admitted;
+                reinstated.push(member.pubkey);
+            }
+        }
+
+        let mut removed = Vec::new();
+        members.retain(|pubkey, member| {
+            let stay = keep.contains(pubkey)
+                || member.status == MemberStatus::Banned
+                || now.saturating_sub(member.last_seen) < max_idle;
+            if !stay {
+                removed.push(*pubkey);
+            }
+            stay
+        });
+
+        for pubkey in &removed {
+            self.storage.remove_member(pubkey)?;
+        }
+        for member in members.values() {
+            self.storage.put_member(member)?;
+        }
+
+        self.rate_limits
+            .write()
+            .retain(|pubkey, _| members.contains_key(pubkey));
+        drop(members);
+
+        for pubkey in reinstated {
+            self.audit(pubkey, AuditAction::Reinstated, "suspension expired")?;
+        }
+
+        Ok(removed.len())
+    }
+
     /// Update member's event count
-    pub fn record_event(&self, pubkey: &[u8; 32]) {
+    pub fn record_event(&self, pubkey: &[u8; 32]) -> Result<(), MembershipError> {
         if let Some(member) = self.members.write().get_mut(pubkey) {
             member.event_count += 1;
+            self.storage.put_member(member)?;
         }
+        Ok(())
     }
 
-    /// Suspend a peer temporarily
-    pub fn suspend_peer(&self, pubkey: &[u8; 32], duration: Duration) {
-        if let Some(member) = self.members.write().get_mut(pubkey) {
-            member.status = MemberStatus::Suspended {
-                until: Instant::now() + duration,
+    /// Suspend a peer until `duration` from now
+    pub fn suspend_peer(
+        &self,
+        pubkey: &[u8; 32],
+        duration: Duration,
+        reason: &str,
+    ) -> Result<(), MembershipError> {
+        let until = now_millis().saturating_add(duration.as_millis() as u64);
+        {
+            let mut members = self.members.write();
+            let Some(member) = members.get_mut(pubkey) else {
+                return Err(MembershipError::NotAdmitted(*pubkey));
             };
+            if member.status == MemberStatus::Banned {
+                return Err(MembershipError::Banned(*pubkey));
+            }
+            member.status = MemberStatus::Suspended { until };
+            self.storage.put_member(member)?;
         }
+        self.audit(*pubkey, AuditAction::Suspended { until }, reason)?;
+        Ok(())
     }
 
     /// Ban a peer permanently
-    pub fn ban_peer(&self, pubkey: &[u8; 32]) {
-        self.banned.write().insert(*pubkey);
-        if let Some(member) = self.members.write().get_mut(pubkey) {
+    pub fn ban_peer(&self, pubkey: &[u8; 32], reason: &str) -> Result<(), MembershipError> {
+        self.insert_ban(BanRecord {
+            pubkey: *pubkey,
+            reason: reason.to_string(),
+            banned_at: now_millis(),
+            imported_from: None,
+        })?;
+        self.audit(*pubkey, AuditAction::Banned, reason)?;
+        Ok(())
+    }
+
+    fn insert_ban(&self, record: BanRecord) -> Result<(), MembershipError> {
+        if let Some(member) = self.members.write().get_mut(&record.pubkey) {
             member.status = MemberStatus::Banned;
+            self.storage.put_member(member)?;
+        }
+        self.storage.put_ban(&record)?;
+        self.banned.write().insert(record.pubkey, record);
+        Ok(())
+    }
+
+    /// Lift a ban; the peer must be admitted again
+    pub fn unban_peer(&self, pubkey: &[u8; 32], reason: &str) -> Result<bool, MembershipError> {
+        if self.banned.write().remove(pubkey).is_none() {
+            return Ok(false);
+        }
+        self.storage.remove_ban(pubkey)?;
+        if self.members.write().remove(pubkey).is_some() {
+            self.storage.remove_member(pubkey)?;
+        }
+        self.audit(*pubkey, AuditAction::Unbanned, reason)?;
+        Ok(true)
+    }
+
+    /// Whether a peer is banned
+    pub fn is_banned(&self, pubkey: &[u8; 32]) -> bool {
+        self.banned.read().contains_key(pubkey)
+    }
+
+    /// All bans, oldest first, for import on another node
+    pub fn export_bans(&self, exported_by: [u8; 32]) -> BanList {
+        let mut bans: Vec<BanRecord> = self.banned.read().values().cloned().collect();
+        bans.sort_by_key(|b| (b.banned_at, b.pubkey));
+        BanList {
+            exported_by,
+            exported_at: now_millis(),
+            bans,
         }
     }
 
+    /// Apply bans exported by another node; returns how many were new
+    ///
+    /// Bans keep their original reason and time; `imported_from` records
+    /// the node that first issued them.
+    pub fn import_bans(&self, list: &BanList) -> Result<usize, MembershipError> {
+        let mut imported = 0;
+        for ban in &list.bans {
+            if self.is_banned(&ban.pubkey) {
+                continue;
+            }
+            self.insert_ban(BanRecord {
+                imported_from: Some(ban.imported_from.unwrap_or(list.exported_by)),
+                ..ban.clone()
+            })?;
+            self.audit(
+                ban.pubkey,
+                AuditAction::Banned,
+                &format!("imported: {}", ban.reason),
+            )?;
+            imported += 1;
+        }
+        Ok(imported)
+    }
+
+    /// Audit trail, oldest first, optionally for one peer
+    pub fn audit_log(
+        &self,
+        pubkey: Option<&[u8; 32]>,
+        limit: usize,
+    ) -> Result<Vec<AuditRecord>, MembershipError> {
+        let mut records: Vec<AuditRecord> = self
+            .storage
+            .audit_records()?
+            .into_iter()
+            .filter(|r| !matches!(pubkey, Some(p) if r.pubkey != *p))
+            .collect();
+        let excess = records.len().saturating_sub(limit);
+        records.drain(..excess);
+        Ok(records)
+    }
+
     /// Update reputation for a peer
-    pub fn update_reputation(&self, pubkey: &[u8; 32], delta: f64) {
+    pub fn update_reputation(&self, pubkey: &[u8; 32], delta: f64) -> Result<(), MembershipError> {
         if let Some(member) = self.members.write().get_mut(pubkey) {
             member.reputation = (member.reputation + delta).clamp(0.0, 1.0);
+            self.storage.put_member(member)?;
+        }
+        Ok(())
+    }
+
+    /// Raise the reputation of every member not banned by `amount`, up to
+    /// 1.0; returns how many members changed
+    pub fn recover_reputation(&self, amount: f64) -> Result<usize, MembershipError> {
+        let mut recovered = 0;
+        for member in self.members.write().values_mut() {
+            if member.status == MemberStatus::Banned || member.reputation >= 1.0 {
+                continue;
+            }
+            member.reputation = (member.reputation + amount).min(1.0);
+            self.storage.put_member(member)?;
+            recovered += 1;
         }
+        Ok(recovered)
+    }
+
+    /// Get a member record
+    pub fn get_member(&self, pubkey: &[u8; 32]) -> Option<Member> {
+        self.members.read().get(pubkey).cloned()
     }
 
     /// Get member count
@@ -277,6 +711,25 @@ pub struct MembershipStats {
 mod tests {
     use super::*;
 
+    fn gossipd() -> PeerRoles {
+        PeerRoles {
+            gossipd: true,
+            ..Default::default()
+        }
+    }
+
+    const TRANSCRIPT: [u8; 32] = [9; 32];
+
+    /// Admit with a valid phrase proof
+    fn admit(
+        manager: &MembershipManager,
+        pubkey: [u8; 32],
+        roles: PeerRoles,
+    ) -> Result<(), MembershipError> {
+        let proof = manager.phrase_proof(&TRANSCRIPT);
+        manager.admit_peer(pubkey, &TRANSCRIPT, &proof, roles)
+    }
+
     #[test]
     fn test_world_id_derivation() {
         let manager = MembershipManager::new("test-world", 100);
@@ -297,10 +750,20 @@ mod tests {
         let pubkey = [1; 32];
 
         // Wrong phrase should fail
-        assert!(manager.admit_peer(pubkey, "wrong").is_err());
+        let wrong = MembershipManager::new("wrong", 100).phrase_proof(&TRANSCRIPT);
+        assert!(matches!(
+            manager.admit_peer(pubkey, &TRANSCRIPT, &wrong, gossipd()),
+            Err(MembershipError::InvalidWorldPhrase)
+        ));
+
+        // A proof is only valid for its own transcript
+        let proof = manager.phrase_proof(&[1; 32]);
+        assert!(manager
+            .admit_peer(pubkey, &TRANSCRIPT, &proof, gossipd())
+            .is_err());
 
         // Correct phrase should succeed
-        assert!(manager.admit_peer(pubkey, "secret-phrase").is_ok());
+        assert!(admit(&manager, pubkey, gossipd()).is_ok());
         assert!(manager.is_admitted(&pubkey));
     }
 
@@ -309,14 +772,72 @@ mod tests {
         let manager = MembershipManager::new("phrase", 100);
         let pubkey = [2; 32];
 
-        manager.admit_peer(pubkey, "phrase").unwrap();
+        admit(&manager, pubkey, gossipd()).unwrap();
         assert!(manager.is_admitted(&pubkey));
 
-        manager.ban_peer(&pubkey);
+        manager.ban_peer(&pubkey, "test").unwrap();
         assert!(!manager.is_admitted(&pubkey));
 
         // Re-admission should fail
-        assert!(manager.admit_peer(pubkey, "phrase").is_err());
+        assert!(admit(&manager, pubkey, gossipd()).is_err());
+    }
+
+    #[test]
+    fn test_prune_stale_members() {
+        let manager = MembershipManager::new("phrase", 100);
+        admit(&manager, [1; 32], gossipd()).unwrap();
+        admit(&manager, [2; 32], gossipd()).unwrap();
+        admit(&manager, [3; 32], gossipd()).unwrap();
+        manager.ban_peer(&[3; 32], "test").unwrap();
+
+        let keep: HashSet<[u8; 32]> = [[2; 32]].into_iter().collect();
+        assert_eq!(manager.prune_stale(Duration::ZERO, &keep).unwrap(), 1);
+        assert!(!manager.is_admitted(&[1; 32]));
+        assert!(manager.is_admitted(&[2; 32]));
+        // Banned peers stay banned
+        assert!(admit(&manager, [3; 32], gossipd()).is_err());
+    }
+
+    #[test]
+    fn test_roles() {
+        let manager = MembershipManager::new("phrase", 100);
+        let relay = PeerRoles {
+            relay: true,
+            ..Default::default()
+        };
+        let provider = PeerRoles {
+            provider: true,
+            ..gossipd()
+        };
+
+        admit(&manager, [1; 32], gossipd()).unwrap();
+        admit(&manager, [2; 32], relay).unwrap();
+        assert!(matches!(
+            admit(&manager, [3; 32], provider),
+            Err(MembershipError::Provider(_))
+        ));
+
+        manager.authorize(&[1; 32], ControlRpc::SyncRequest).unwrap();
+        manager.authorize(&[2; 32], ControlRpc::DescriptorQuery).unwrap();
+        assert!(matches!(
+            manager.authorize(&[2; 32], ControlRpc::SyncRequest),
+            Err(MembershipError::Unauthorized(ControlRpc::SyncRequest))
+        ));
+        assert!(manager.authorize(&[3; 32], ControlRpc::DescriptorQuery).is_err());
+
+        // A key found in a provider descriptor loses admission and cannot
+        // rejoin, whatever roles it announces
+        assert!(manager.mark_provider([1; 32]).unwrap());
+        assert!(!manager.is_admitted(&[1; 32]));
+        assert!(matches!(
+            admit(&manager, [1; 32], gossipd()),
+            Err(MembershipError::Provider(_))
+        ));
+        assert!(!manager.mark_provider([1; 32]).unwrap());
+        assert_eq!(
+            manager.audit_log(Some(&[1; 32]), 1).unwrap()[0].action,
+            AuditAction::Revoked
+        );
     }
 
     #[test]
@@ -324,7 +845,7 @@ mod tests {
         let manager = MembershipManager::new("phrase", 3); // 3 requests per minute
         let pubkey = [3; 32];
 
-        manager.admit_peer(pubkey, "phrase").unwrap();
+        admit(&manager, pubkey, gossipd()).unwrap();
 
         // First 3 should succeed
         assert!(manager.check_authorized(&pubkey).is_ok());
@@ -337,4 +858,62 @@ mod tests {
             Err(MembershipError::RateLimited)
         ));
     }
+
+    #[test]
+    fn test_state_survives_restart() {
+        let storage = Arc::new(Storage::memory());
+        let open = || {
+            MembershipManager::new("phrase", 100)
+                .with_storage(storage.clone())
+                .unwrap()
+        };
+
+        let manager = open();
+        admit(&manager, [1; 32], gossipd()).unwrap();
+        admit(&manager, [2; 32], gossipd()).unwrap();
+        manager.update_reputation(&[1; 32], -0.25).unwrap();
+        manager
+            .suspend_peer(&[1; 32], Duration::from_secs(60), "flooding")
+            .unwrap();
+        manager.ban_peer(&[2; 32], "forged receipts").unwrap();
+        drop(manager);
+
+        let manager = open();
+        let member = manager.get_member(&[1; 32]).unwrap();
+        assert_eq!(member.reputation, 0.75);
+        assert!(matches!(member.status, MemberStatus::Suspended { .. }));
+        // Reconnecting does not lift a suspension
+        assert!(matches!(
+            admit(&manager, [1; 32], gossipd()),
+            Err(MembershipError::Suspended(_))
+        ));
+        assert!(manager.is_banned(&[2; 32]));
+
+        let trail = manager.audit_log(Some(&[2; 32]), 10).unwrap();
+        let actions: Vec<_> = trail.iter().map(|r| r.action.clone()).collect();
+        assert_eq!(actions, vec![AuditAction::Admitted, AuditAction::Banned]);
+        assert_eq!(trail[1].reason, "forged receipts");
+    }
+
+    #[test]
+    fn test_ban_list_export_import() {
+        let a = MembershipManager::new("phrase", 100);
+        a.ban_peer(&[7; 32], "spam").unwrap();
+        a.ban_peer(&[8; 32], "sybil").unwrap();
+        let list = a.export_bans([1; 32]);
+        assert_eq!(list.bans.len(), 2);
+
+        let b = MembershipManager::new("phrase", 100);
+        b.ban_peer(&[8; 32], "seen locally").unwrap();
+        assert_eq!(b.import_bans(&list).unwrap(), 1);
+        assert!(admit(&b, [7; 32], gossipd()).is_err());
+
+        let imported = b.export_bans([2; 32]);
+        let ban = imported.bans.iter().find(|ban| ban.pubkey == [7; 32]).unwrap();
+        assert_eq!(ban.imported_from, Some([1; 32]));
+        assert_eq!(ban.reason, "spam");
+
+        assert!(b.unban_peer(&[7; 32], "appeal").unwrap());
+        assert!(admit(&b, [7; 32], gossipd()).is_ok());
+    }
 }
@@ -0,0 +1,527 @@
+//! Persisted peer book and outbound dial policy
+//!
+//! Records every control-plane peer we have completed a handshake with,
+//! plus unverified addresses learned through peer exchange or claimed by
+//! inbound peers. An address is verified only once we have dialed it
+//! ourselves. The book is persisted in `Storage` so a restarted node can
+//! rejoin the overlay without a bootstrap list.
+
+use crate::storage::{Storage, StorageError};
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use terrain_gossip_net::peer::PeerRoles;
+use terrain_gossip_net::transport::messages::PeerAdvert;
+
+/// Maximum number of records kept in the book
+const MAX_PEER_BOOK_SIZE: usize = 1024;
+
+/// Records are evicted after this many consecutive dial failures
+const MAX_DIAL_FAILURES: u32 = 16;
+
+/// Initial redial delay after a failure
+const BACKOFF_BASE: Duration = Duration::from_secs(1);
+
+/// Upper bound on the redial delay
+const BACKOFF_MAX: Duration = Duration::from_secs(300);
+
+/// A known control-plane peer
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PeerRecord {
+    /// Peer transport public key
+    pub transport_pubkey: [u8; 32],
+    /// Dialable address
+    pub addr: SocketAddr,
+    /// Roles the peer announced
+    pub roles: PeerRoles,
+    /// Last successful contact (unix millis)
+    pub last_seen: u64,
+    /// Consecutive dial failures
+    pub failures: u32,
+    /// Whether we completed a handshake after dialing `addr` ourselves
+    pub verified: bool,
+}
+
+#[derive(Debug, Clone)]
+struct Backoff {
+    failures: u32,
+    next_attempt: Instant,
+}
+
+/// Peer book with dial backoff
+pub struct PeerBook {
+    storage: Arc<Storage>,
+    /// Our own transport key (never stored or dialed)
+    own_key: [u8; 32],
+    /// Configured bootstrap addresses
+    bootstrap: Vec<SocketAddr>,
+    /// Cached records, keyed by transport public key
+    records: RwLock<HashMap<[u8; 32], PeerRecord>>,
+    /// Per-address redial backoff
+    backoff: RwLock<HashMap<SocketAddr, Backoff>>,
+    /// Addresses with a dial or connection in progress
+    dialing: RwLock<HashSet<SocketAddr>>,
+    /// Addresses that turned out to be ourselves
+    own_addrs: RwLock<HashSet<SocketAddr>>,
+}
+
+impl PeerBook {
+    /// Load the peer book from storage
+    pub fn new(
+        storage: Arc<Storage>,
+        own_key: [u8; 32],
+        bootstrap: Vec<SocketAddr>,
+    ) -> Result<Self, StorageError> {
+        let records = storage
+            .all_peers()?
+            .into_iter()
+            .filter(|r| r.transport_pubkey != own_key)
+            .map(|r| (r.transport_pubkey, r))
+            .collect();
+
+        Ok(Self {
+            storage,
+            own_key,
+            bootstrap,
+            records: RwLock::new(records),
+            backoff: RwLock::new(HashMap::new()),
+            dialing: RwLock::new(HashSet::new()),
+            own_addrs: RwLock::new(HashSet::new()),
+        })
+    }
+
+    /// Number of known peers
+    pub fn len(&self) -> usize {
+        self.records.read().len()
+    }
+
+    /// Whether the book is empty
+    pub fn is_empty(&self) -> bool {
+        self.records.read().is_empty()
+    }
+
+    /// Get a peer record
+    pub fn get(&self, pubkey: &[u8; 32]) -> Option<PeerRecord> {
+        self.records.read().get(pubkey).cloned()
+    }
+
+    /// Record a completed handshake.
+    ///
+    /// `addr` is the dialable address for the peer, if known. It is
+    /// `verified` when we dialed it; an address an inbound peer claims is
+    /// kept unverified and never replaces a verified one.
+    pub fn record_connected(
+        &self,
+        pubkey: [u8; 32],
+        addr: Option<SocketAddr>,
+        roles: PeerRoles,
+        verified: bool,
+    ) -> Result<(), StorageError> {
+        if pubkey == self.own_key {
+            return Ok(());
+        }
+        if let (Some(addr), true) = (addr, verified) {
+            self.backoff.write().remove(&addr);
+        }
+
+        let mut records = self.records.write();
+        let record = match (records.get_mut(&pubkey), addr) {
+            (Some(record), addr) => {
+                if let Some(addr) = addr {
+                    if verified || !record.verified {
+                        record.addr = addr;
+                        record.verified = verified;
+                    }
+                }
+                record.roles = roles;
+                record.last_seen = now_millis();
+                if verified {
+                    record.failures = 0;
+                }
+                record.clone()
+            }
+            (None, Some(addr)) => {
+                let record = PeerRecord {
+                    transport_pubkey: pubkey,
+                    addr,
+                    roles,
+                    last_seen: now_millis(),
+                    failures: 0,
+                    verified,
+                };
+                records.insert(pubkey, record.clone());
+                record
+            }
+            // Inbound peer without a listen address: nothing to dial
+            (None, None) => return Ok(()),
+        };
+        self.storage.put_peer(&record)
+    }
+
+    /// Record a failed dial or handshake to an address
+    pub fn record_failure(&self, addr: SocketAddr, now: Instant) -> Result<(), StorageError> {
+        {
+            let mut backoff = self.backoff.write();
+            let entry = backoff.entry(addr).or_insert(Backoff {
+                failures: 0,
+                next_attempt: now,
+            });
+            entry.failures = entry.failures.saturating_add(1);
+            entry.next_attempt = now + backoff_delay(entry.failures);
+        }
+
+        let mut records = self.records.write();
+        let Some(key) = records
+            .values()
+            .find(|r| r.addr == addr)
+            .map(|r| r.transport_pubkey)
+        else {
+            return Ok(());
+        };
+
+        let record = records.get_mut(&key).expect("record exists");
+        record.failures = record.failures.saturating_add(1);
+        if record.failures >= MAX_DIAL_FAILURES {
+            records.remove(&key);
+            self.storage.remove_peer(&key)
+        } else {
+            self.storage.put_peer(record)
+        }
+    }
+
+    /// Remember that an address reaches ourselves so it is never dialed again
+    pub fn record_self(&self, addr: SocketAddr) -> Result<(), StorageError> {
+        self.own_addrs.write().insert(addr);
+
+        let mut records = self.records.write();
+        let stale: Vec<[u8; 32]> = records
+            .values()
+            .filter(|r| r.addr == addr)
+            .map(|r| r.transport_pubkey)
+            .collect();
+        for key in stale {
+            records.remove(&key);
+            self.storage.remove_peer(&key)?;
+        }
+        Ok(())
+    }
+
+    /// Merge peers learned through peer exchange.
+    ///
+    /// Only unknown or still-unverified entries are touched; our own view of
+    /// peers we have connected to always wins. When the book is full the
+    /// least recently seen unverified entry makes room. Returns the number
+    /// of new peers.
+    pub fn learn(&self, adverts: &[PeerAdvert]) -> Result<usize, StorageError> {
+        let own_addrs = self.own_addrs.read();
+        let mut records = self.records.write();
+        let mut learned = 0;
+
+        for advert in adverts {
+            if advert.transport_pubkey == self.own_key || own_addrs.contains(&advert.addr) {
+                continue;
+            }
+            match records.get_mut(&advert.transport_pubkey) {
+                Some(record) if !record.verified => {
+                    record.addr = advert.addr;
+                    record.roles = advert.roles;
+                    self.storage.put_peer(record)?;
+                }
+                Some(_) => {}
+                None => {
+                    if records.len() >= MAX_PEER_BOOK_SIZE {
+                        let Some(oldest) = records
+                            .values()
+                            .filter(|r| !r.verified)
+                            .min_by_key(|r| r.last_seen)
+                            .map(|r| r.transport_pubkey)
+                        else {
+                            continue;
+                        };
+                        records.remove(&oldest);
+                        self.storage.remove_peer(&oldest)?;
+                    }
+                    let record = PeerRecord {
+                        transport_pubkey: advert.transport_pubkey,
+                        addr: advert.addr,
+                        roles: advert.roles,
+                        last_seen: advert.last_seen.min(now_millis()),
+                        failures: 0,
+                        verified: false,
+                    };
+                    self.storage.put_peer(&record)?;
+                    records.insert(advert.transport_pubkey, record);
+                    learned += 1;
+                }
+            }
+        }
+
+        Ok(learned)
+    }
+
+    /// Verified peers to advertise in peer exchange, most recently seen first
+    pub fn adverts(&self, limit: usize) -> Vec<PeerAdvert> {
+        let mut verified: Vec<PeerRecord> = self
+            .records
+            .read()
+            .values()
+            .filter(|r| r.verified)
+            .cloned()
+            .collect();
+        verified.sort_by_key(|r| std::cmp::Reverse(r.last_seen));
+
+        verified
+            .into_iter()
+            .take(limit)
+            .map(|r| PeerAdvert {
+                transport_pubkey: r.transport_pubkey,
+                addr: r.addr,
+                roles: r.roles,
+                last_seen: r.last_seen,
+            })
+            .collect()
+    }
+
+    /// Pick up to `limit` addresses to dial.
+    ///
+    /// Skips connected peers, addresses in backoff or already being dialed,
+    /// and prefers verified, reliable, recently seen peers. Bootstrap
+    /// addresses fill any remaining slots.
+    pub fn dial_candidates(
+        &self,
+        connected: &HashSet<[u8; 32]>,
+        limit: usize,
+        now: Instant,
+    ) -> Vec<SocketAddr> {
+        if limit == 0 {
+            return Vec::new();
+        }
+
+        let backoff = self.backoff.read();
+        let dialing = self.dialing.read();
+        let own_addrs = self.own_addrs.read();
+        let available = |addr: &SocketAddr| {
+            !dialing.contains(addr)
+                && !own_addrs.contains(addr)
+                && !matches!(backoff.get(addr), Some(b) if b.next_attempt > now)
+        };
+
+        let records = self.records.read();
+        let mut candidates: Vec<&PeerRecord> = records
+            .values()
+            .filter(|r| !connected.contains(&r.transport_pubkey) && available(&r.addr))
+            .collect();
+        candidates.sort_by(|a, b| {
+            b.verified
+                .cmp(&a.verified)
+                .then(a.failures.cmp(&b.failures))
+                .then(b.last_seen.cmp(&a.last_seen))
+        });
+
+        let mut addrs: Vec<SocketAddr> = candidates.iter().map(|r| r.addr).collect();
+
+        // Bootstrap addresses already represented in the book are covered above
+        let known: HashSet<SocketAddr> = records.values().map(|r| r.addr).collect();
+        for addr in &self.bootstrap {
+            if !known.contains(addr) && available(addr) && !addrs.contains(addr) {
+                addrs.push(*addr);
+            }
+        }
+
+        addrs.truncate(limit);
+        addrs
+    }
+
+    /// Mark an address as being dialed; returns false if already in progress
+    pub fn begin_dial(&self, addr: SocketAddr) -> bool {
+        self.dialing.write().insert(addr)
+    }
+
+    /// Clear the in-progress mark for an address
+    pub fn end_dial(&self, addr: &SocketAddr) {
+        self.dialing.write().remove(addr);
+    }
+}
+
+/// Exponential redial delay for the given failure count
+fn backoff_delay(failures: u32) -> Duration {
+    let exp = failures.saturating_sub(1).min(16);
+    BACKOFF_BASE.saturating_mul(1u32 << exp).min(BACKOFF_MAX)
+}
+
+/// Current time in unix milliseconds
+pub fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn addr(port: u16) -> SocketAddr {
+        SocketAddr::from(([127, 0, 0, 1], port))
+    }
+
+    fn roles() -> PeerRoles {
+        PeerRoles {
+            gossipd: true,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_book_persists_across_reopen() {
+        let dir = tempdir().unwrap();
+        {
+            let storage = Arc::new(Storage::open(dir.path()).unwrap());
+            let book = PeerBook::new(storage, [0; 32], vec![]).unwrap();
+            book.record_connected([1; 32], Some(addr(9101)), roles(), true)
+                .unwrap();
+            // Inbound peer without listen address is not recorded
+            book.record_connected([2; 32], None, roles(), false)
+                .unwrap();
+            assert_eq!(book.len(), 1);
+        }
+
+        let storage = Arc::new(Storage::open(dir.path()).unwrap());
+        let book = PeerBook::new(storage, [0; 32], vec![]).unwrap();
+        let record = book.get(&[1; 32]).unwrap();
+        assert_eq!(record.addr, addr(9101));
+        assert!(record.verified);
+
+        let candidates = book.dial_candidates(&HashSet::new(), 8, Instant::now());
+        assert_eq!(candidates, vec![addr(9101)]);
+    }
+
+    #[test]
+    fn test_backoff_and_eviction() {
+        let dir = tempdir().unwrap();
+        let storage = Arc::new(Storage::open(dir.path()).unwrap());
+        let book = PeerBook::new(storage, [0; 32], vec![]).unwrap();
+        book.record_connected([1; 32], Some(addr(9101)), roles(), true)
+            .unwrap();
+
+        let now = Instant::now();
+        book.record_failure(addr(9101), now).unwrap();
+        assert!(book.dial_candidates(&HashSet::new(), 8, now).is_empty());
+        assert_eq!(
+            book.dial_candidates(&HashSet::new(), 8, now + Duration::from_secs(2)),
+            vec![addr(9101)]
+        );
+
+        for _ in 1..MAX_DIAL_FAILURES {
+            book.record_failure(addr(9101), now).unwrap();
+        }
+        assert!(book.get(&[1; 32]).is_none());
+    }
+
+    #[test]
+    fn test_learn_and_candidate_order() {
+        let dir = tempdir().unwrap();
+        let storage = Arc::new(Storage::open(dir.path()).unwrap());
+        let book = PeerBook::new(storage, [0; 32], vec![addr(9200)]).unwrap();
+        book.record_connected([1; 32], Some(addr(9101)), roles(), true)
+            .unwrap();
+
+        let adverts = vec![
+            PeerAdvert {
+                transport_pubkey: [0; 32], // ourselves
+                addr: addr(9100),
+                roles: roles(),
+                last_seen: 0,
+            },
+            PeerAdvert {
+                transport_pubkey: [3; 32],
+                addr: addr(9103),
+                roles: roles(),
+                last_seen: 0,
+            },
+            PeerAdvert {
+                transport_pubkey: [1; 32], // verified entry is not overwritten
+                addr: addr(9999),
+                roles: roles(),
+                last_seen: 0,
+            },
+        ];
+        assert_eq!(book.learn(&adverts).unwrap(), 1);
+        assert_eq!(book.get(&[1; 32]).unwrap().addr, addr(9101));
+
+        // Verified first, then learned, then bootstrap
+        let candidates = book.dial_candidates(&HashSet::new(), 8, Instant::now());
+        assert_eq!(candidates, vec![addr(9101), addr(9103), addr(9200)]);
+
+        // Connected peers and in-progress dials are skipped
+        let connected: HashSet<[u8; 32]> = [[1; 32]].into_iter().collect();
+        assert!(book.begin_dial(addr(9103)));
+        let candidates = book.dial_candidates(&connected, 8, Instant::now());
+        assert_eq!(candidates, vec![addr(9200)]);
+
+        // Only verified peers are advertised
+        let adverts = book.adverts(10);
+        assert_eq!(adverts.len(), 1);
+        assert_eq!(adverts[0].transport_pubkey, [1; 32]);
+    }
+
+    #[test]
+    fn test_inbound_claims_stay_unverified() {
+        let dir = tempdir().unwrap();
+        let storage = Arc::new(Storage::open(dir.path()).unwrap());
+        let book = PeerBook::new(storage, [0; 32], vec![]).unwrap();
+
+        // An inbound peer's listen address is a claim until we dial it
+        book.record_connected([1; 32], Some(addr(9101)), roles(), false)
+            .unwrap();
+        assert!(!book.get(&[1; 32]).unwrap().verified);
+        assert!(book.adverts(10).is_empty());
+        book.record_connected([1; 32], Some(addr(9101)), roles(), true)
+            .unwrap();
+        assert!(book.get(&[1; 32]).unwrap().verified);
+
+        // and never replaces an address we verified
+        book.record_connected([1; 32], Some(addr(9999)), roles(), false)
+            .unwrap();
+        let record = book.get(&[1; 32]).unwrap();
+        assert_eq!(record.addr, addr(9101));
+        assert!(record.verified);
+    }
+
+    #[test]
+    fn test_full_book_evicts_oldest_unverified() {
+        let dir = tempdir().unwrap();
+        let storage = Arc::new(Storage::open(dir.path()).unwrap());
+        let book = PeerBook::new(storage, [0; 32], vec![]).unwrap();
+        book.record_connected([0xff; 32], Some(addr(9000)), roles(), true)
+            .unwrap();
+
+        let advert = |i: usize, last_seen: u64| {
+            let mut key = [0; 32];
+            key[..8].copy_from_slice(&(i as u64 + 1).to_le_bytes());
+            PeerAdvert {
+                transport_pubkey: key,
+                addr: addr(10_000 + i as u16),
+                roles: roles(),
+                last_seen,
+            }
+        };
+        let adverts: Vec<PeerAdvert> = (0..MAX_PEER_BOOK_SIZE - 1)
+            .map(|i| advert(i, 1_000 + i as u64))
+            .collect();
+        assert_eq!(book.learn(&adverts).unwrap(), MAX_PEER_BOOK_SIZE - 1);
+        assert_eq!(book.len(), MAX_PEER_BOOK_SIZE);
+
+        // A new peer replaces the stalest unverified one, not the verified
+        let newcomer = advert(MAX_PEER_BOOK_SIZE, 5_000);
+        assert_eq!(book.learn(std::slice::from_ref(&newcomer)).unwrap(), 1);
+        assert_eq!(book.len(), MAX_PEER_BOOK_SIZE);
+        assert!(book.get(&adverts[0].transport_pubkey).is_none());
+        assert!(book.get(&newcomer.transport_pubkey).is_some());
+        assert!(book.get(&[0xff; 32]).is_some());
+    }
+}
@@ -0,0 +1,251 @@
+//! Misbehavior policy
+//!
+//! Maps concrete offenses by a peer to reputation penalties and escalates
+//! to suspension or a ban once reputation falls below configured
+//! thresholds. Reputation recovers slowly over time, so honest peers that
+//! trip over a stale event now and then are not pushed out.
+
+use crate::membership::{MemberStatus, MembershipError, MembershipManager};
+use crate::quarantine::QuarantineReason;
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{info, warn};
+
+/// Something a peer did wrong
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Offense {
+    /// Event signature or signer key did not verify
+    InvalidSignature,
+    /// Event ID or header does not match the body
+    HashMismatch,
+    /// Event or frame for another world
+    WorldMismatch,
+    /// Exceeded the per-peer request rate
+    RateLimited,
+    /// Frame larger than the codec allows
+    OversizedFrame,
+    /// Re-sent an event that was already pruned
+    ReplayedEvent,
+}
+
+impl Offense {
+    /// Offense for a quarantine reason; `None` for reasons an honest peer
+    /// can hit (unknown descriptor, early epoch, missing dependency)
+    pub fn from_quarantine(reason: &QuarantineReason) -> Option<Offense> {
+        match reason {
+            QuarantineReason::BadSignature(_) => Some(Offense::InvalidSignature),
+            QuarantineReason::Malformed(_) => Some(Offense::HashMismatch),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Offense {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Offense::InvalidSignature => "invalid signature",
+            Offense::HashMismatch => "hash mismatch",
+            Offense::WorldMismatch => "world mismatch",
+            Offense::RateLimited => "rate limit exceeded",
+            Offense::OversizedFrame => "oversized frame",
+            Offense::ReplayedEvent => "replayed event",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Penalties and escalation thresholds
+#[derive(Debug, Clone)]
+pub struct PolicyConfig {
+    pub invalid_signature: f64,
+    pub hash_mismatch: f64,
+    pub world_mismatch: f64,
+    pub rate_limited: f64,
+    pub oversized_frame: f64,
+    pub replayed_event: f64,
+    /// Suspend when reputation falls to or below this
+    pub suspend_below: f64,
+    /// Ban when reputation falls to or below this
+    pub ban_below: f64,
+    /// Length of a suspension
+    pub suspend_for: Duration,
+    /// Reputation regained per hour without offenses
+    pub recovery_per_hour: f64,
+}
+
+impl Default for PolicyConfig {
+    fn default() -> Self {
+        Self {
+            invalid_signature: 0.2,
+            hash_mismatch: 0.2,
+            world_mismatch: 0.1,
+            rate_limited: 0.02,
+            oversized_frame: 0.25,
+            replayed_event: 0.01,
+            suspend_below: 0.5,
+            ban_below: 0.1,
+            suspend_for: Duration::from_secs(600),
+            recovery_per_hour: 0.05,
+        }
+    }
+}
+
+impl PolicyConfig {
+    /// Reputation penalty for an offense
+    pub fn penalty(&self, offense: Offense) -> f64 {
+        match offense {
+            Offense::InvalidSignature => self.invalid_signature,
+            Offense::HashMismatch => self.hash_mismatch,
+            Offense::WorldMismatch => self.world_mismatch,
+            Offense::RateLimited => self.rate_limited,
+            Offense::OversizedFrame => self.oversized_frame,
+            Offense::ReplayedEvent => self.replayed_event,
+        }
+    }
+}
+
+/// What the policy did about an offense
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Sanction {
+    /// Reputation lowered only
+    Penalized,
+    /// Peer suspended
+    Suspended,
+    /// Peer banned
+    Banned,
+}
+
+/// Misbehavior policy engine
+pub struct MisbehaviorPolicy {
+    membership: Arc<MembershipManager>,
+    config: PolicyConfig,
+}
+
+impl MisbehaviorPolicy {
+    /// Create a policy acting on `membership`
+    pub fn new(membership: Arc<MembershipManager>, config: PolicyConfig) -> Self {
+        Self { membership, config }
+    }
+
+    /// Policy configuration
+    pub fn config(&self) -> &PolicyConfig {
+        &self.config
+    }
+
+    /// Penalize a peer for an offense and escalate if needed
+    pub fn report(&self, peer: &[u8; 32], offense: Offense) -> Result<Sanction, MembershipError> {
+        self.report_many(peer, offense, 1)
+    }
+
+    /// Penalize a peer for `count` instances of an offense
+    pub fn report_many(
+        &self,
+        peer: &[u8; 32],
+        offense: Offense,
+        count: usize,
+    ) -> Result<Sanction, MembershipError> {
+        if count == 0 || self.membership.is_banned(peer) {
+            return Ok(Sanction::Penalized);
+        }
+        let penalty = self.config.penalty(offense) * count as f64;
+        self.membership.update_reputation(peer, -penalty)?;
+        let Some(member) = self.membership.get_member(peer) else {
+            return Ok(Sanction::Penalized);
+        };
+
+        let reason = format!(
+            "{} x{} (reputation {:.2})",
+            offense, count, member.reputation
+        );
+        if member.reputation <= self.config.ban_below {
+            warn!("Banning peer {:02x?}: {}", &peer[..8], reason);
+            self.membership.ban_peer(peer, &reason)?;
+            return Ok(Sanction::Banned);
+        }
+        if member.reputation <= self.config.suspend_below
+            && !matches!(member.status, MemberStatus::Suspended { .. })
+        {
+            info!("Suspending peer {:02x?}: {}", &peer[..8], reason);
+            self.membership
+                .suspend_peer(peer, self.config.suspend_for, &reason)?;
+            return Ok(Sanction::Suspended);
+        }
+        Ok(Sanction::Penalized)
+    }
+
+    /// Restore reputation for `elapsed` time without offenses
+    pub fn recover(&self, elapsed: Duration) -> Result<usize, MembershipError> {
+        let amount = self.config.recovery_per_hour * elapsed.as_secs_f64() / 3600.0;
+        self.membership.recover_reputation(amount)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use terrain_gossip_net::peer::PeerRoles;
+
+    fn gossipd() -> PeerRoles {
+        PeerRoles {
+            gossipd: true,
+            ..Default::default()
+        }
+    }
+
+    fn policy() -> (MisbehaviorPolicy, Arc<MembershipManager>) {
+        let membership = Arc::new(MembershipManager::new("phrase", 100));
+        let proof = membership.phrase_proof(&[0; 32]);
+        membership
+            .admit_peer([1; 32], &[0; 32], &proof, gossipd())
+            .unwrap();
+        (
+            MisbehaviorPolicy::new(membership.clone(), PolicyConfig::default()),
+            membership,
+        )
+    }
+
+    #[test]
+    fn test_escalation() {
+        let (policy, membership) = policy();
+        let peer = [1; 32];
+
+        for _ in 0..2 {
+            assert_eq!(
+                policy.report(&peer, Offense::InvalidSignature).unwrap(),
+                Sanction::Penalized
+            );
+        }
+        assert_eq!(
+            policy.report(&peer, Offense::InvalidSignature).unwrap(),
+            Sanction::Suspended
+        );
+        assert!(!membership.is_admitted(&peer));
+        // Further offenses while suspended lower reputation without
+        // re-suspending, until the ban threshold
+        assert_eq!(
+            policy.report(&peer, Offense::HashMismatch).unwrap(),
+            Sanction::Penalized
+        );
+        assert_eq!(
+            policy.report_many(&peer, Offense::HashMismatch, 2).unwrap(),
+            Sanction::Banned
+        );
+        assert!(membership.is_banned(&peer));
+
+        let trail = membership.audit_log(Some(&peer), 10).unwrap();
+        assert!(trail.last().unwrap().reason.starts_with("hash mismatch"));
+    }
+
+    #[test]
+    fn test_recovery() {
+        let (policy, membership) = policy();
+        let peer = [1; 32];
+        policy.report(&peer, Offense::WorldMismatch).unwrap();
+        assert!(membership.get_member(&peer).unwrap().reputation < 1.0);
+
+        assert_eq!(policy.recover(Duration::from_secs(2 * 3600)).unwrap(), 1);
+        assert_eq!(membership.get_member(&peer).unwrap().reputation, 1.0);
+        assert_eq!(policy.recover(Duration::from_secs(3600)).unwrap(), 0);
+    }
+}
@@ -0,0 +1,243 @@
+//! Quarantine for events that fail validation
+//!
+//! Events that cannot be accepted yet (bad signature, unknown descriptor,
+//! future epoch, missing dependency) are held in a separate tree with the
+//! reason, the peer they came from and an expiry. Entries waiting on a
+//! dependency are released for re-validation when it arrives; the rest stay
+//! for forensics until they expire or are purged.
+
+use crate::peer_book::now_millis;
+use crate::storage::{Storage, StorageError};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+use terrain_gossip_core::types::*;
+use tracing::debug;
+
+/// Default time an entry is kept
+pub const DEFAULT_QUARANTINE_TTL: Duration = Duration::from_secs(3600);
+
+/// Default maximum number of entries
+pub const DEFAULT_MAX_QUARANTINED: usize = 10_000;
+
+/// Why an event was quarantined
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum QuarantineReason {
+    /// Signature or public key did not verify
+    BadSignature(String),
+    /// event_id does not match the body, or header and body disagree
+    Malformed(String),
+    /// Refers to a FAH no known descriptor advertises
+    UnknownDescriptor(Fah),
+    /// Epoch too far ahead of the local clock
+    EpochOutOfRange { epoch_id: u64, current_epoch: u64 },
+    /// Refers to an event not seen yet
+    MissingDependency(EventId),
+    /// Revokes a descriptor not seen yet
+    MissingDescriptor(DescriptorId),
+}
+
+impl fmt::Display for QuarantineReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::BadSignature(e) => write!(f, "bad signature: {}", e),
+            Self::Malformed(e) => write!(f, "malformed: {}", e),
+            Self::UnknownDescriptor(fah) => {
+                write!(f, "unknown descriptor for FAH {}", hex_prefix(&fah.0))
+            }
+            Self::EpochOutOfRange {
+                epoch_id,
+                current_epoch,
+            } => write!(
+                f,
+                "epoch {} out of range (current {})",
+                epoch_id, current_epoch
+            ),
+            Self::MissingDependency(id) => write!(f, "missing dependency {}", hex_prefix(&id.0)),
+            Self::MissingDescriptor(id) => {
+                write!(f, "missing descriptor {}", hex_prefix(&id.0))
+            }
+        }
+    }
+}
+
+fn hex_prefix(bytes: &[u8; 32]) -> String {
+    bytes[..8].iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Something a quarantined event is waiting for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Dependency {
+    /// An event with this ID
+    Event(EventId),
+    /// A descriptor advertising this FAH
+    Fah(Fah),
+    /// A descriptor with this ID
+    Descriptor(DescriptorId),
+}
+
+impl Dependency {
+    /// Key in the dependency tree: kind(1) || id(32)
+    pub fn key(&self) -> [u8; 33] {
+        let (kind, id) = match self {
+            Dependency::Event(id) => (0, id.0),
+            Dependency::Fah(fah) => (1, fah.0),
+            Dependency::Descriptor(id) => (2, id.0),
+        };
+        let mut key = [0u8; 33];
+        key[0] = kind;
+        key[1..].copy_from_slice(&id);
+        key
+    }
+}
+
+impl QuarantineReason {
+    /// Dependency whose arrival should trigger re-validation
+    pub fn dependency(&self) -> Option<Dependency> {
+        match self {
+            Self::MissingDependency(id) => Some(Dependency::Event(*id)),
+            Self::UnknownDescriptor(fah) => Some(Dependency::Fah(*fah)),
+            Self::MissingDescriptor(id) => Some(Dependency::Descriptor(*id)),
+            _ => None,
+        }
+    }
+}
+
+/// A quarantined event
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QuarantineEntry {
+    pub event: Event,
+    pub reason: QuarantineReason,
+    /// Replica the event was merged from
+    pub source: [u8; 32],
+    /// Unix time in milliseconds
+    pub quarantined_at: u64,
+    /// Unix time in milliseconds after which the entry is dropped
+    pub expires_at: u64,
+}
+
+/// Persistent quarantine backed by storage
+pub struct Quarantine {
+    storage: Arc<Storage>,
+    ttl: Duration,
+    max_entries: usize,
+}
+
+impl Quarantine {
+    /// Create a quarantine over storage
+    pub fn new(storage: Arc<Storage>, ttl: Duration, max_entries: usize) -> Self {
+        Self {
+            storage,
+            ttl,
+            max_entries,
+        }
+    }
+
+    /// Hold an event; returns false if the quarantine is full
+    pub fn insert(
+        &self,
+        event: Event,
+        reason: QuarantineReason,
+        source: [u8; 32],
+    ) -> Result<bool, StorageError> {
+        let existing = self.storage.get_quarantined(&event.event_id)?.is_some();
+        if !existing && self.storage.quarantine_count() >= self.max_entries {
+            debug!("Quarantine full; dropping {:02x?}", &event.event_id.0[..8]);
+            return Ok(false);
+        }
+
+        let now = now_millis();
+        let entry = QuarantineEntry {
+            event,
+            reason,
+            source,
+            quarantined_at: now,
+            expires_at: now.saturating_add(self.ttl.as_millis() as u64),
+        };
+        self.storage.put_quarantined(&entry)?;
+        Ok(true)
+    }
+
+    /// Remove and return entries waiting on a dependency
+    pub fn take_waiting(
+        &self,
+        dependency: &Dependency,
+    ) -> Result<Vec<QuarantineEntry>, StorageError> {
+        let mut entries = Vec::new();
+        for event_id in self.storage.quarantine_waiting(&dependency.key())? {
+            if let Some(entry) = self.storage.remove_quarantined(&event_id)? {
+                entries.push(entry);
+            }
+        }
+        Ok(entries)
+    }
+
+    /// Look up an entry
+    pub fn get(&self, event_id: &EventId) -> Result<Option<QuarantineEntry>, StorageError> {
+        self.storage.get_quarantined(event_id)
+    }
+
+    /// Up to `limit` entries, oldest first
+    pub fn list(&self, limit: usize) -> Result<Vec<QuarantineEntry>, StorageError> {
+        let mut entries = self.storage.all_quarantined()?;
+        entries.sort_by_key(|e| e.quarantined_at);
+        entries.truncate(limit);
+        Ok(entries)
+    }
+
+    /// Remove an entry
+    pub fn remove(&self, event_id: &EventId) -> Result<Option<QuarantineEntry>, StorageError> {
+        self.storage.remove_quarantined(event_id)
+    }
+
+    /// Remove every entry
+    pub fn purge(&self) -> Result<usize, StorageError> {
+        let entries = self.storage.all_quarantined()?;
+        for entry in &entries {
+            self.storage.remove_quarantined(&entry.event.event_id)?;
+        }
+        Ok(entries.len())
+    }
+
+    /// Remove and return entries expired at `now` (Unix milliseconds)
+    pub fn take_expired(&self, now: u64) -> Result<Vec<QuarantineEntry>, StorageError> {
+        let mut expired = Vec::new();
+        for entry in self.storage.all_quarantined()? {
+            if entry.expires_at <= now {
+                self.storage.remove_quarantined(&entry.event.event_id)?;
+                expired.push(entry);
+            }
+        }
+        Ok(expired)
+    }
+
+    /// Remove and return entries rejected only for being early, once
+    /// `current_epoch` has caught up with them
+    pub fn take_due(
+        &self,
+        current_epoch: u64,
+        max_skew: u64,
+    ) -> Result<Vec<QuarantineEntry>, StorageError> {
+        let mut due = Vec::new();
+        for entry in self.storage.all_quarantined()? {
+            if let QuarantineReason::EpochOutOfRange { epoch_id, .. } = entry.reason {
+                if epoch_id <= current_epoch.saturating_add(max_skew) {
+                    self.storage.remove_quarantined(&entry.event.event_id)?;
+                    due.push(entry);
+                }
+            }
+        }
+        Ok(due)
+    }
+
+    /// Number of entries
+    pub fn len(&self) -> usize {
+        self.storage.quarantine_count()
+    }
+
+    /// Whether the quarantine is empty
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
@@ -0,0 +1,294 @@
+//! Typed queries over the event store
+//!
+//! Queries are answered from the secondary indexes in `index`: the most
+//! selective filter picks the index to scan, and the remaining filters are
+//! checked against each event. Results come back in index order with an
+//! opaque cursor for the next page.
+
+use crate::index::{self, IndexKind};
+use crate::storage::{Storage, StorageError};
+use serde::{Deserialize, Serialize};
+use terrain_gossip_core::types::*;
+
+/// Default page size
+pub const DEFAULT_QUERY_LIMIT: usize = 100;
+
+/// Maximum page size
+pub const MAX_QUERY_LIMIT: usize = 1000;
+
+/// Position after the last index entry of a page
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QueryCursor(pub Vec<u8>);
+
+/// Event query
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EventQuery {
+    /// Only events of this type
+    pub event_type: Option<EventType>,
+    /// First epoch (inclusive)
+    pub epoch_from: u64,
+    /// Last epoch (inclusive)
+    pub epoch_to: u64,
+    /// Events about this target
+    pub target_ref: Option<TargetRef>,
+    /// Events signed by this transport public key
+    pub signer: Option<Vec<u8>>,
+    /// Receipts and attestations for this challenge
+    pub challenge_id: Option<ChallengeId>,
+    /// Publications and revocations of this descriptor
+    pub descriptor_id: Option<DescriptorId>,
+    /// Page size (capped at `MAX_QUERY_LIMIT`)
+    pub limit: usize,
+    /// Resume after a previous page
+    pub cursor: Option<QueryCursor>,
+}
+
+impl Default for EventQuery {
+    fn default() -> Self {
+        Self {
+            event_type: None,
+            epoch_from: 0,
+            epoch_to: u64::MAX,
+            target_ref: None,
+            signer: None,
+            challenge_id: None,
+            descriptor_id: None,
+            limit: DEFAULT_QUERY_LIMIT,
+            cursor: None,
+        }
+    }
+}
+
+/// One page of query results
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct QueryPage {
+    pub events: Vec<Event>,
+    /// Cursor for the next page (`None` when exhausted)
+    pub next_cursor: Option<QueryCursor>,
+}
+
+impl EventQuery {
+    /// Query matching every event
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Restrict to an event type
+    pub fn event_type(mut self, event_type: EventType) -> Self {
+        self.event_type = Some(event_type);
+        self
+    }
+
+    /// Restrict to an inclusive epoch range
+    pub fn epochs(mut self, from: u64, to: u64) -> Self {
+        self.epoch_from = from;
+        self.epoch_to = to;
+        self
+    }
+
+    /// Restrict to a target
+    pub fn target_ref(mut self, target_ref: TargetRef) -> Self {
+        self.target_ref = Some(target_ref);
+        self
+    }
+
+    /// Restrict to a signer
+    pub fn signer(mut self, pubkey: impl Into<Vec<u8>>) -> Self {
+        self.signer = Some(pubkey.into());
+        self
+    }
+
+    /// Restrict to a challenge
+    pub fn challenge_id(mut self, challenge_id: ChallengeId) -> Self {
+        self.challenge_id = Some(challenge_id);
+        self
+    }
+
+    /// Restrict to a descriptor
+    pub fn descriptor_id(mut self, descriptor_id: DescriptorId) -> Self {
+        self.descriptor_id = Some(descriptor_id);
+        self
+    }
+
+    /// Set the page size
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = limit;
+        self
+    }
+
+    /// Resume after a previous page
+    pub fn after(mut self, cursor: QueryCursor) -> Self {
+        self.cursor = Some(cursor);
+        self
+    }
+
+    /// Keyed filters as (index, 32-byte prefix), most selective first
+    fn keyed_filters(&self) -> Vec<(IndexKind, [u8; 32])> {
+        let mut filters = Vec::new();
+        if let Some(descriptor_id) = &self.descriptor_id {
+            filters.push((IndexKind::Descriptor, descriptor_id.0));
+        }
+        if let Some(challenge_id) = &self.challenge_id {
+            filters.push((IndexKind::Challenge, challenge_id.0));
+        }
+        if let Some(target_ref) = &self.target_ref {
+            filters.push((IndexKind::Target, target_ref.0));
+        }
+        if let Some(signer) = &self.signer {
+            filters.push((IndexKind::Signer, index::signer_key(signer)));
+        }
+        filters
+    }
+
+    /// Whether an event satisfies every filter
+    pub fn matches(&self, event: &Event) -> bool {
+        if self.event_type.is_some_and(|t| t != event.event_type) {
+            return false;
+        }
+        if event.epoch_id < self.epoch_from || event.epoch_id > self.epoch_to {
+            return false;
+        }
+        let keys = index::index_keys(event);
+        self.keyed_filters().iter().all(|(kind, prefix)| {
+            keys.iter()
+                .any(|(k, key)| k == kind && key.starts_with(prefix))
+        })
+    }
+
+    /// Run the query against storage
+    pub fn execute(&self, storage: &Storage) -> Result<QueryPage, StorageError> {
+        let limit = self.limit.clamp(1, MAX_QUERY_LIMIT);
+        if self.epoch_from > self.epoch_to {
+            return Ok(QueryPage::default());
+        }
+
+        let (kind, (lower, upper)) = match self.keyed_filters().first() {
+            Some((kind, prefix)) => (
+                *kind,
+                index::prefix_range(prefix, self.epoch_from, self.epoch_to),
+            ),
+            None => (
+                IndexKind::EpochType,
+                index::epoch_range(self.epoch_from, self.epoch_to),
+            ),
+        };
+
+        let after = self.cursor.as_ref().map(|c| c.0.clone());
+        let mut events = Vec::new();
+        let mut last_key = None;
+
+        for result in storage.scan_index(kind, lower, upper, after) {
+            let key = result?;
+            let Some(event_id) = index::event_id_from_key(&key) else {
+                continue;
+            };
+            last_key = Some(key);
+
+            if let Some(event) = storage.get_event(&event_id)? {
+                if self.matches(&event) {
+                    events.push(event);
+                    if events.len() == limit {
+                        break;
+                    }
+                }
+            }
+        }
+
+        let next_cursor = if events.len() == limit {
+            last_key.map(QueryCursor)
+        } else {
+            None
+        };
+        Ok(QueryPage {
+            events,
+            next_cursor,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn receipt(id: u8, epoch_id: u64, target: u8, prober: u8) -> Event {
+        Event {
+            event_id: EventId([id; 32]),
+            world: WorldId([0; 32]),
+            epoch_id,
+            event_type: EventType::Receipt,
+            body: EventBody::Receipt(ProbeReceipt {
+                receipt_id: ReceiptId([id; 32]),
+                world: WorldId([0; 32]),
+                epoch_id,
+                challenge_id: ChallengeId([id; 32]),
+                target_ref: TargetRef([target; 32]),
+                target_fah: None,
+                outcome_commitment: [0; 32],
+                ticket: None,
+                prober_transport_pubkey: vec![prober; 32],
+                signature: vec![],
+            }),
+        }
+    }
+
+    fn storage_with(events: &[Event]) -> (Storage, tempfile::TempDir) {
+        let dir = tempdir().unwrap();
+        let storage = Storage::open(dir.path()).unwrap();
+        for event in events {
+            storage.put_event(event).unwrap();
+        }
+        (storage, dir)
+    }
+
+    #[test]
+    fn test_query_by_target_and_epochs() {
+        let (storage, _dir) = storage_with(&[
+            receipt(1, 5, 9, 1),
+            receipt(2, 7, 9, 2),
+            receipt(3, 7, 8, 1),
+            receipt(4, 12, 9, 1),
+        ]);
+
+        let page = EventQuery::new()
+            .event_type(EventType::Receipt)
+            .target_ref(TargetRef([9; 32]))
+            .epochs(5, 10)
+            .execute(&storage)
+            .unwrap();
+        let ids: Vec<_> = page.events.iter().map(|e| e.event_id.0[0]).collect();
+        assert_eq!(ids, vec![1, 2]);
+        assert!(page.next_cursor.is_none());
+
+        // Secondary filters narrow the primary index scan
+        let page = EventQuery::new()
+            .target_ref(TargetRef([9; 32]))
+            .signer(vec![1; 32])
+            .execute(&storage)
+            .unwrap();
+        let ids: Vec<_> = page.events.iter().map(|e| e.event_id.0[0]).collect();
+        assert_eq!(ids, vec![1, 4]);
+    }
+
+    #[test]
+    fn test_query_pagination_and_removal() {
+        let events: Vec<_> = (1..=5).map(|i| receipt(i, i as u64, 9, 1)).collect();
+        let (storage, _dir) = storage_with(&events);
+
+        let mut query = EventQuery::new().signer(vec![1; 32]).limit(2);
+        let mut seen = Vec::new();
+        loop {
+            let page = query.execute(&storage).unwrap();
+            seen.extend(page.events.iter().map(|e| e.epoch_id));
+            match page.next_cursor {
+                Some(cursor) => query = query.after(cursor),
+                None => break,
+            }
+        }
+        assert_eq!(seen, vec![1, 2, 3, 4, 5]);
+
+        storage.remove_event(&EventId([3; 32])).unwrap();
+        let page = EventQuery::new().epochs(3, 3).execute(&storage).unwrap();
+        assert!(page.events.is_empty());
+    }
+}
@@ -0,0 +1,143 @@
+//! Event retention policies
+//!
+//! Each event type is kept for a number of epochs (or forever). Events
+//! older than their type's horizon are pruned from the log and refused by
+//! merge, so anti-entropy does not pull them back in. The latest descriptor
+//! per provider is always kept regardless of age.
+
+use std::collections::HashMap;
+use std::str::FromStr;
+use terrain_gossip_core::types::*;
+
+/// Default epoch length when no rule bundle is configured (5 minutes)
+pub const DEFAULT_EPOCH_LEN_MS: u64 = 300_000;
+
+/// Retention for a single event type
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetentionRule {
+    pub event_type: EventType,
+    /// Epochs to keep (`None` = forever)
+    pub epochs: Option<u64>,
+}
+
+impl FromStr for RetentionRule {
+    type Err = String;
+
+    /// Parse `type=epochs` or `type=forever`, e.g. `receipt=288`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, value) = s
+            .split_once('=')
+            .ok_or_else(|| format!("expected TYPE=EPOCHS, got '{}'", s))?;
+        let event_type = parse_event_type(name.trim())?;
+        let epochs = match value.trim() {
+            "forever" => None,
+            n => Some(
+                n.parse::<u64>()
+                    .map_err(|_| format!("invalid epoch count '{}'", n))?,
+            ),
+        };
+        Ok(Self { event_type, epochs })
+    }
+}
+
+fn parse_event_type(name: &str) -> Result<EventType, String> {
+    Ok(match name {
+        "receipt" => EventType::Receipt,
+        "attestation" => EventType::Attestation,
+        "dispute" => EventType::Dispute,
+        "link_hint" => EventType::LinkHint,
+        "rule_endorsement" => EventType::RuleEndorsement,
+        "shard" => EventType::Shard,
+        "verdict" => EventType::Verdict,
+        "training_manifest" => EventType::TrainingManifest,
+        "descriptor_publish" => EventType::DescriptorPublish,
+        "descriptor_revoke" => EventType::DescriptorRevoke,
+        _ => return Err(format!("unknown event type '{}'", name)),
+    })
+}
+
+/// Per-type retention policy
+#[derive(Debug, Clone)]
+pub struct RetentionPolicy {
+    /// Epochs to keep per type; types without an entry are kept forever
+    epochs: HashMap<EventType, u64>,
+}
+
+impl Default for RetentionPolicy {
+    /// Defaults assume 5-minute epochs
+    fn default() -> Self {
+        let epochs = HashMap::from([
+            (EventType::Receipt, 288),            // 1 day
+            (EventType::LinkHint, 288),           // 1 day
+            (EventType::Attestation, 2016),       // 1 week
+            (EventType::DescriptorPublish, 2016), // 1 week (superseded only)
+            (EventType::Dispute, 8640),           // 30 days
+        ]);
+        Self { epochs }
+    }
+}
+
+impl RetentionPolicy {
+    /// Policy that keeps everything
+    pub fn keep_all() -> Self {
+        Self {
+            epochs: HashMap::new(),
+        }
+    }
+
+    /// Apply a rule, overriding any existing retention for its type
+    pub fn with_rule(mut self, rule: RetentionRule) -> Self {
+        match rule.epochs {
+            Some(epochs) => self.epochs.insert(rule.event_type, epochs),
+            None => self.epochs.remove(&rule.event_type),
+        };
+        self
+    }
+
+    /// Epochs kept for a type (`None` = forever)
+    pub fn epochs(&self, event_type: EventType) -> Option<u64> {
+        self.epochs.get(&event_type).copied()
+    }
+
+    /// Oldest epoch still retained for a type at `current_epoch`
+    pub fn horizon(&self, event_type: EventType, current_epoch: u64) -> Option<u64> {
+        self.epochs(event_type)
+            .map(|keep| current_epoch.saturating_sub(keep))
+    }
+
+    /// Whether an event falls outside its retention horizon
+    pub fn is_expired(&self, event: &Event, current_epoch: u64) -> bool {
+        self.horizon(event.event_type, current_epoch)
+            .is_some_and(|horizon| event.epoch_id < horizon)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_rule() {
+        let rule: RetentionRule = "receipt=10".parse().unwrap();
+        assert_eq!(rule.event_type, EventType::Receipt);
+        assert_eq!(rule.epochs, Some(10));
+
+        let rule: RetentionRule = "dispute=forever".parse().unwrap();
+        assert_eq!(rule.epochs, None);
+
+        assert!("bogus=1".parse::<RetentionRule>().is_err());
+        assert!("receipt".parse::<RetentionRule>().is_err());
+    }
+
+    #[test]
+    fn test_horizon() {
+        let policy = RetentionPolicy::keep_all().with_rule(RetentionRule {
+            event_type: EventType::Receipt,
+            epochs: Some(10),
+        });
+
+        assert_eq!(policy.horizon(EventType::Receipt, 100), Some(90));
+        assert_eq!(policy.horizon(EventType::Receipt, 5), Some(0));
+        assert_eq!(policy.horizon(EventType::Dispute, 100), None);
+    }
+}
@@ -0,0 +1,579 @@
+//! Semantic vector memory over event summaries
+//!
+//! Every stored event is rendered as a short text summary, embedded and
+//! added to an approximate nearest-neighbour index, so an operator can ask
+//! "why did we downrank this provider?" and get back the attestations and
+//! disputes that explain it. The default embedder is a deterministic
+//! feature-hashing model that needs no GPU or external service; other
+//! models plug in through `Embedder`.
+//!
+//! The index uses random-hyperplane LSH: each table hashes a vector to a
+//! bucket by the signs of its projections. Search ranks the candidates in
+//! the query's buckets, and in buckets one bit away, by cosine similarity,
+//! and falls back to a full scan when they are too few. The index lives in
+//! memory and is rebuilt from storage at startup.
+
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use terrain_gossip_core::types::*;
+use thiserror::Error;
+
+/// Default embedding dimension
+pub const DEFAULT_DIM: usize = 256;
+
+/// Largest `top_k` served
+pub const MAX_TOP_K: usize = 100;
+
+/// Number of LSH tables
+const LSH_TABLES: usize = 8;
+
+/// Hyperplanes (signature bits) per table
+const LSH_BITS: usize = 12;
+
+/// Domain separator for feature hashing
+const DOMAIN_FEATURE: &[u8] = b"semantic-feature";
+
+/// Domain separator for LSH hyperplanes
+const DOMAIN_PLANE: &[u8] = b"semantic-lsh-plane";
+
+/// Semantic index errors
+#[derive(Debug, Error)]
+pub enum SemanticError {
+    #[error("Embedding has {actual} dimensions, index uses {expected}")]
+    Dimension { expected: usize, actual: usize },
+    #[error("Embedding bytes are not a whole number of f32s ({0} bytes)")]
+    Encoding(usize),
+}
+
+/// Turns text into a unit vector
+pub trait Embedder: Send + Sync {
+    /// Output dimension
+    fn dim(&self) -> usize;
+
+    /// Embed `text` as a unit vector of `dim()` components (all zeros for
+    /// text without features)
+    fn embed(&self, text: &str) -> Vec<f32>;
+}
+
+/// Deterministic feature-hashing embedder
+///
+/// Lower-cased alphanumeric tokens and token bigrams are hashed to a signed
+/// component each. Texts sharing words land close together; no model
+/// weights are needed and every node computes identical vectors.
+#[derive(Debug, Clone)]
+pub struct HashingEmbedder {
+    dim: usize,
+}
+
+impl HashingEmbedder {
+    /// Create an embedder with `dim` components
+    pub fn new(dim: usize) -> Self {
+        Self { dim: dim.max(1) }
+    }
+
+    fn add_feature(&self, vector: &mut [f32], feature: &str, weight: f32) {
+        let mut hasher = blake3::Hasher::new();
+        hasher.update(DOMAIN_FEATURE);
+        hasher.update(feature.as_bytes());
+        let hash = hasher.finalize();
+        let bytes = hash.as_bytes();
+        let slot = u64::from_le_bytes(bytes[..8].try_into().expect("8 bytes")) as usize % self.dim;
+        let sign = if bytes[8] & 1 == 0 { 1.0 } else { -1.0 };
+        vector[slot] += sign * weight;
+    }
+}
+
+impl Default for HashingEmbedder {
+    fn default() -> Self {
+        Self::new(DEFAULT_DIM)
+    }
+}
+
+impl Embedder for HashingEmbedder {
+    fn dim(&self) -> usize {
+        self.dim
+    }
+
+    fn embed(&self, text: &str) -> Vec<f32> {
+        let lower = text.to_lowercase();
+        let tokens: Vec<&str> = lower
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|t| !t.is_empty())
+            .collect();
+
+        let mut vector = vec![0.0; self.dim];
+        for token in &tokens {
+            self.add_feature(&mut vector, token, 1.0);
+        }
+        for pair in tokens.windows(2) {
+            self.add_feature(&mut vector, &format!("{} {}", pair[0], pair[1]), 0.5);
+        }
+        normalize(&mut vector);
+        vector
+    }
+}
+
+fn normalize(vector: &mut [f32]) {
+    let norm = vector.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        vector.iter_mut().for_each(|x| *x /= norm);
+    }
+}
+
+fn dot(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
+
+/// Encode an embedding as little-endian f32s (`SemanticQuery::embedding`)
+pub fn encode_embedding(vector: &[f32]) -> Vec<u8> {
+    vector.iter().flat_map(|x| x.to_le_bytes()).collect()
+}
+
+/// Decode little-endian f32s
+pub fn decode_embedding(bytes: &[u8]) -> Result<Vec<f32>, SemanticError> {
+    let chunks = bytes.chunks_exact(4);
+    if !chunks.remainder().is_empty() {
+        return Err(SemanticError::Encoding(bytes.len()));
+    }
+    Ok(chunks
+        .map(|c| f32::from_le_bytes(c.try_into().expect("4 bytes")))
+        .collect())
+}
+
+fn short(bytes: &[u8]) -> String {
+    hex::encode(&bytes[..bytes.len().min(4)])
+}
+
+/// Describe metrics in words so questions about behaviour match them
+fn describe_metrics(metrics: &MetricsVector) -> String {
+    let mut words = vec![format!(
+        "success rate {:.2} latency p50 {} ms p95 {} ms",
+        metrics.success_rate, metrics.latency_p50_ms, metrics.latency_p95_ms
+    )];
+    if metrics.success_rate < 0.5 {
+        words.push("low success rate failing requests downrank".into());
+    } else if metrics.success_rate >= 0.9 {
+        words.push("high success rate reliable".into());
+    }
+    if metrics.latency_p95_ms > 5_000 {
+        words.push("slow high latency timeouts".into());
+    }
+    if metrics.refusal_consistency < 0.5 {
+        words.push("inconsistent refusals".into());
+    }
+    if metrics.tool_fidelity < 0.5 {
+        words.push("poor tool fidelity broken tool calls".into());
+    }
+    if metrics.robustness_score < 0.5 {
+        words.push("fragile low robustness".into());
+    }
+    if metrics.drift_indicator > 0.5 {
+        words.push("behaviour drift model changed".into());
+    }
+    words.join(" ")
+}
+
+/// Short text summary of an event for embedding
+pub fn summarize(event: &Event) -> String {
+    match &event.body {
+        EventBody::Receipt(receipt) => format!(
+            "probe receipt target {} challenge {} prober {}",
+            short(&receipt.target_ref.0),
+            short(&receipt.challenge_id.0),
+            short(&receipt.prober_transport_pubkey)
+        ),
+        EventBody::Attestation(attestation) => format!(
+            "behavior attestation target {} prober {} {}",
+            short(&attestation.target_ref.0),
+            short(&attestation.prober_transport_pubkey),
+            describe_metrics(&attestation.metrics)
+        ),
+        EventBody::Dispute(dispute) => format!(
+            "dispute conflicting events {} {} reason {} disputer {}",
+            short(&dispute.event_a.0),
+            short(&dispute.event_b.0),
+            dispute.reason,
+            short(&dispute.disputer_transport_pubkey)
+        ),
+        EventBody::LinkHint(hint) => format!(
+            "link hint same provider targets {} {} compatibility {:.2}",
+            short(&hint.target_a.0),
+            short(&hint.target_b.0),
+            hint.compatibility_score
+        ),
+        EventBody::RuleEndorsement(endorsement) => format!(
+            "rule bundle endorsement {} weight {:.2} signer {}",
+            short(&endorsement.rule_bundle_hash),
+            endorsement.weight,
+            short(&endorsement.signer_transport_pubkey)
+        ),
+        EventBody::DescriptorPublish(publish) => {
+            let descriptor = &publish.descriptor;
+            let capability = match &descriptor.unsigned.capability {
+                DescriptorCapability::Fah(fah) => format!("fah {}", short(&fah.0)),
+                DescriptorCapability::Manifest(manifest) => format!(
+                    "model {} runtime {} context {} safety {}",
+                    manifest.base_model_id,
+                    manifest.runtime_id,
+                    manifest.context_limit,
+                    manifest.safety_mode
+                ),
+            };
+            format!(
+                "provider descriptor {} published epoch {} {}",
+                short(&descriptor.descriptor_id.0),
+                descriptor.unsigned.descriptor_epoch,
+                capability
+            )
+        }
+        EventBody::DescriptorRevoke(revoke) => format!(
+            "provider descriptor {} revoked withdrawn by provider {}",
+            short(&revoke.descriptor_id.0),
+            short(&revoke.provider_transport_pubkey)
+        ),
+    }
+}
+
+/// One search result
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SemanticHit {
+    pub event_id: EventId,
+    pub event_type: EventType,
+    /// Cosine similarity to the query
+    pub score: f32,
+}
+
+struct Entry {
+    event_id: EventId,
+    event_type: EventType,
+    vector: Vec<f32>,
+}
+
+/// Random-hyperplane LSH over unit vectors
+struct LshIndex {
+    /// `[table][bit]` hyperplanes
+    planes: Vec<Vec<Vec<f32>>>,
+    /// Per table: signature -> entry slots
+    tables: Vec<HashMap<u64, Vec<usize>>>,
+    entries: Vec<Option<Entry>>,
+    slots: HashMap<EventId, usize>,
+    free: Vec<usize>,
+}
+
+impl LshIndex {
+    fn new(dim: usize) -> Self {
+        let planes = (0..LSH_TABLES)
+            .map(|table| (0..LSH_BITS).map(|bit| plane(dim, table, bit)).collect())
+            .collect();
+        Self {
+            planes,
+            tables: vec![HashMap::new(); LSH_TABLES],
+            entries: Vec::new(),
+            slots: HashMap::new(),
+            free: Vec::new(),
+        }
+    }
+
+    fn signature(&self, table: usize, vector: &[f32]) -> u64 {
+        self.planes[table]
+            .iter()
+            .enumerate()
+            .filter(|(_, plane)| dot(plane, vector) >= 0.0)
+            .fold(0, |sig, (bit, _)| sig | (1 << bit))
+    }
+
+    fn insert(&mut self, entry: Entry) {
+        if self.slots.contains_key(&entry.event_id) {
+            return;
+        }
+        let slot = self.free.pop().unwrap_or(self.entries.len());
+        for table in 0..LSH_TABLES {
+            let sig = self.signature(table, &entry.vector);
+            self.tables[table].entry(sig).or_default().push(slot);
+        }
+        self.slots.insert(entry.event_id, slot);
+        if slot == self.entries.len() {
+            self.entries.push(Some(entry));
+        } else {
+            self.entries[slot] = Some(entry);
+        }
+    }
+
+    fn remove(&mut self, event_id: &EventId) -> bool {
+        let Some(slot) = self.slots.remove(event_id) else {
+            return false;
+        };
+        let Some(entry) = self.entries[slot].take() else {
+            return false;
+        };
+        for table in 0..LSH_TABLES {
+            let sig = self.signature(table, &entry.vector);
+            if let Some(bucket) = self.tables[table].get_mut(&sig) {
+                bucket.retain(|s| *s != slot);
+                if bucket.is_empty() {
+                    self.tables[table].remove(&sig);
+                }
+            }
+        }
+        self.free.push(slot);
+        true
+    }
+
+    fn search(&self, query: &[f32], top_k: usize, filter: Option<EventType>) -> Vec<SemanticHit> {
+        let matches = |slot: &usize| {
+            self.entries[*slot]
+                .as_ref()
+                .is_some_and(|e| !matches!(filter, Some(t) if e.event_type != t))
+        };
+
+        let mut candidates = HashSet::new();
+        for table in 0..LSH_TABLES {
+            let sig = self.signature(table, query);
+            let probes = std::iter::once(sig).chain((0..LSH_BITS).map(|bit| sig ^ (1 << bit)));
+            for probe in probes {
+                if let Some(bucket) = self.tables[table].get(&probe) {
+                    candidates.extend(bucket.iter().copied().filter(matches));
+                }
+            }
+        }
+        if candidates.len() < top_k {
+            candidates = (0..self.entries.len()).filter(matches).collect();
+        }
+
+        let mut hits: Vec<SemanticHit> = candidates
+            .into_iter()
+            .filter_map(|slot| self.entries[slot].as_ref())
+            .map(|e| SemanticHit {
+                event_id: e.event_id,
+                event_type: e.event_type,
+                score: dot(query, &e.vector),
+            })
+            .collect();
+        hits.sort_by(|a, b| {
+            b.score
+                .total_cmp(&a.score)
+                .then_with(|| a.event_id.0.cmp(&b.event_id.0))
+        });
+        hits.truncate(top_k);
+        hits
+    }
+}
+
+/// Deterministic hyperplane with components in [-1, 1)
+fn plane(dim: usize, table: usize, bit: usize) -> Vec<f32> {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(DOMAIN_PLANE);
+    hasher.update(&(table as u32).to_le_bytes());
+    hasher.update(&(bit as u32).to_le_bytes());
+    let mut reader = hasher.finalize_xof();
+    let mut bytes = vec![0u8; dim * 4];
+    reader.fill(&mut bytes);
+    bytes
+        .chunks_exact(4)
+        .map(|c| {
+            let x = u32::from_le_bytes(c.try_into().expect("4 bytes"));
+            (x as f64 / u32::MAX as f64 * 2.0 - 1.0) as f32
+        })
+        .collect()
+}
+
+/// Embedding index over event summaries
+pub struct SemanticIndex {
+    embedder: Arc<dyn Embedder>,
+    index: RwLock<LshIndex>,
+}
+
+impl SemanticIndex {
+    /// Create an empty index using `embedder`
+    pub fn new(embedder: Arc<dyn Embedder>) -> Self {
+        let index = LshIndex::new(embedder.dim());
+        Self {
+            embedder,
+            index: RwLock::new(index),
+        }
+    }
+
+    /// The embedder used for summaries and text queries
+    pub fn embedder(&self) -> &dyn Embedder {
+        self.embedder.as_ref()
+    }
+
+    /// Embed and index an event (no-op if already indexed)
+    pub fn insert(&self, event: &Event) {
+        if self.index.read().slots.contains_key(&event.event_id) {
+            return;
+        }
+        let vector = self.embedder.embed(&summarize(event));
+        self.index.write().insert(Entry {
+            event_id: event.event_id,
+            event_type: event.event_type,
+            vector,
+        });
+    }
+
+    /// Drop an event; returns false if it was not indexed
+    pub fn remove(&self, event_id: &EventId) -> bool {
+        self.index.write().remove(event_id)
+    }
+
+    /// Number of indexed events
+    pub fn len(&self) -> usize {
+        self.index.read().slots.len()
+    }
+
+    /// Whether no events are indexed
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Top `top_k` events nearest to `embedding`, optionally of one type
+    pub fn search(
+        &self,
+        embedding: &[f32],
+        top_k: usize,
+        filter: Option<EventType>,
+    ) -> Result<Vec<SemanticHit>, SemanticError> {
+        let expected = self.embedder.dim();
+        if embedding.len() != expected {
+            return Err(SemanticError::Dimension {
+                expected,
+                actual: embedding.len(),
+            });
+        }
+        let mut query = embedding.to_vec();
+        normalize(&mut query);
+        Ok(self
+            .index
+            .read()
+            .search(&query, top_k.min(MAX_TOP_K), filter))
+    }
+
+    /// Embed `text` with the index's embedder and search
+    pub fn search_text(
+        &self,
+        text: &str,
+        top_k: usize,
+        filter: Option<EventType>,
+    ) -> Vec<SemanticHit> {
+        let query = self.embedder.embed(text);
+        self.index
+            .read()
+            .search(&query, top_k.min(MAX_TOP_K), filter)
+    }
+
+    /// Answer a wire-format `SemanticQuery`
+    pub fn query(&self, query: &SemanticQuery) -> Result<SemanticQueryResult, SemanticError> {
+        let embedding = decode_embedding(&query.embedding)?;
+        let hits = self.search(&embedding, query.top_k as usize, query.filter_type)?;
+        Ok(SemanticQueryResult {
+            event_ids: hits.into_iter().map(|h| h.event_id).collect(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dispute(id: u8, reason: &str) -> Event {
+        Event {
+            event_id: EventId([id; 32]),
+            world: WorldId([0; 32]),
+            epoch_id: 1,
+            event_type: EventType::Dispute,
+            body: EventBody::Dispute(DisputeEvent {
+                world: WorldId([0; 32]),
+                epoch_id: 1,
+                event_a: EventId([100; 32]),
+                event_b: EventId([101; 32]),
+                reason: reason.to_string(),
+                disputer_transport_pubkey: vec![],
+                signature: vec![],
+            }),
+        }
+    }
+
+    fn endorsement(id: u8) -> Event {
+        Event {
+            event_id: EventId([id; 32]),
+            world: WorldId([0; 32]),
+            epoch_id: 1,
+            event_type: EventType::RuleEndorsement,
+            body: EventBody::RuleEndorsement(RuleEndorsementEvent {
+                world: WorldId([0; 32]),
+                epoch_id: 1,
+                rule_bundle_hash: [id; 32],
+                weight: 1.0,
+                signer_transport_pubkey: vec![],
+                signature: vec![],
+            }),
+        }
+    }
+
+    #[test]
+    fn test_hashing_embedder() {
+        let embedder = HashingEmbedder::new(64);
+        let a = embedder.embed("provider returned wrong tool output");
+        assert_eq!(a, embedder.embed("Provider returned WRONG tool output!"));
+        assert!((dot(&a, &a) - 1.0).abs() < 1e-5);
+
+        let near = embedder.embed("wrong tool output from provider");
+        let far = embedder.embed("rule bundle endorsement weight");
+        assert!(dot(&a, &near) > dot(&a, &far));
+        assert!(embedder.embed("  ").iter().all(|x| *x == 0.0));
+    }
+
+    #[test]
+    fn test_search_filter_and_remove() {
+        let index = SemanticIndex::new(Arc::new(HashingEmbedder::default()));
+        index.insert(&dispute(
+            1,
+            "attestation claims success but receipts show timeouts",
+        ));
+        index.insert(&dispute(2, "duplicate receipt for the same challenge"));
+        for id in 10..60 {
+            index.insert(&endorsement(id));
+        }
+        index.insert(&dispute(1, "ignored: already indexed"));
+        assert_eq!(index.len(), 52);
+
+        let hits = index.search_text("why timeouts for this provider", 3, None);
+        assert_eq!(hits[0].event_id, EventId([1; 32]));
+
+        let disputes = index.search_text("rule bundle endorsement", 5, Some(EventType::Dispute));
+        assert_eq!(disputes.len(), 2);
+        assert!(disputes.iter().all(|h| h.event_type == EventType::Dispute));
+
+        // Wire-format query with a caller-computed embedding
+        let embedding = index.embedder().embed("duplicate receipt");
+        let result = index
+            .query(&SemanticQuery {
+                world: WorldId([0; 32]),
+                embedding: encode_embedding(&embedding),
+                top_k: 1,
+                filter_type: Some(EventType::Dispute),
+            })
+            .unwrap();
+        assert_eq!(result.event_ids, vec![EventId([2; 32])]);
+        assert!(matches!(
+            index.search(&[1.0; 3], 1, None),
+            Err(SemanticError::Dimension {
+                expected: 256,
+                actual: 3
+            })
+        ));
+
+        assert!(index.remove(&EventId([1; 32])));
+        assert!(!index.remove(&EventId([1; 32])));
+        let hits = index.search_text("timeouts", 60, Some(EventType::Dispute));
+        assert_eq!(hits.len(), 1);
+        // Freed slots are reused
+        index.insert(&dispute(3, "timeouts again"));
+        assert_eq!(
+            index.search_text("timeouts again", 1, None)[0].event_id,
+            EventId([3; 32])
+        );
+    }
+}
@@ -1,23 +1,70 @@
 //! gossipd server - main service loop
 
-use crate::config::Config;
-use crate::event_log::EventLog;
-use crate::membership::MembershipManager;
+use crate::anomaly::{AnomalyAlert, AnomalyConfig, AnomalyDetector};
+use crate::api::ApiServer;
+use crate::checkpoint::{Checkpoint, CheckpointError};
+use crate::config::{Config, NodeState, SealMode};
+use crate::dispute::{DisputeConfig, DisputeDetector};
+use crate::endorsement::{
+    ActivationPolicy, EndorsementError, EndorsementTally, ForkChoice, TrustFn,
+};
+use crate::event_log::{CheckpointImport, EventLog, EventLogError, MergeOutcome, PruneStats};
+use crate::fetch::{FetchConfig, FetchManager};
+use crate::gossip::{GossipAction, GossipConfig, PushGossip};
+use crate::host::Host;
+use crate::membership::{
+    is_full_participant, BanList, ControlRpc, MembershipError, MembershipManager,
+};
+use crate::peer_book::{now_millis, PeerBook};
+use crate::policy::{MisbehaviorPolicy, Offense, PolicyConfig};
+use crate::quarantine::QuarantineReason;
+use crate::retention::{RetentionPolicy, DEFAULT_EPOCH_LEN_MS};
+use crate::semantic::{HashingEmbedder, SemanticIndex};
 use crate::storage::Storage;
-use crate::sync::SyncManager;
+use crate::sync::{SyncError, SyncManager};
 use parking_lot::RwLock;
-use std::collections::HashMap;
+use rand::RngCore;
+use std::collections::{HashMap, HashSet};
 use std::net::SocketAddr;
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
+use terrain_gossip_core::crypto::{derive_control_plane_key, EventSigner};
 use terrain_gossip_core::types::*;
-use terrain_gossip_net::crypto::KeyPair;
+use terrain_gossip_net::crypto::{EphemeralKeyExchange, KeyPair};
+use terrain_gossip_net::framing::{read_frame, write_frame, Frame, FrameError, FrameType};
 use terrain_gossip_net::peer::{PeerId, PeerRoles};
+use terrain_gossip_net::sealed::{self, Opener, SealError, SealKey, SealOffer, Sealer};
+use terrain_gossip_net::transport::messages;
 use thiserror::Error;
-use tokio::net::{TcpListener, TcpStream};
-use tokio::sync::broadcast;
+use tokio::io::AsyncRead;
+use tokio::net::TcpStream;
+use tokio::sync::{broadcast, mpsc};
 use tokio::time::interval;
-use tracing::{debug, error, info, warn};
+use tracing::{debug, info, warn};
+
+/// Outbound frame queue depth per peer
+const PEER_QUEUE_DEPTH: usize = 256;
+
+/// Time allowed for the peer's HELLO after connecting
+const HELLO_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Time allowed for an outbound TCP connect
+const DIAL_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Maximum peers sent or accepted in one peer exchange
+const MAX_PEX_PEERS: usize = 64;
+
+/// Dial rounds between periodic peer exchanges
+const PEX_EVERY_ROUNDS: u64 = 6;
+
+/// Checkpoint files kept on disk
+const CHECKPOINTS_KEPT: usize = 3;
+
+/// Metadata key for the persisted node state
+const NODE_STATE_KEY: &str = "node_state";
+
+/// Connected peers, keyed by transport public key
+type PeerMap = RwLock<HashMap<[u8; 32], ConnectedPeer>>;
 
 /// Server errors
 #[derive(Debug, Error)]
@@ -28,20 +75,75 @@ pub enum ServerError {
     Storage(#[from] crate::storage::StorageError),
     #[error("Bind failed: {0}")]
     BindFailed(SocketAddr),
+    #[error("Frame error: {0}")]
+    Frame(#[from] FrameError),
+    #[error("Codec error: {0}")]
+    Codec(#[from] postcard::Error),
+    #[error("Membership error: {0}")]
+    Membership(#[from] MembershipError),
+    #[error("Sync error: {0}")]
+    Sync(#[from] SyncError),
+    #[error("Handshake failed: {0}")]
+    Handshake(String),
+    #[error("Connected to self")]
+    SelfConnection,
+    #[error("Invalid rule bundle: {0}")]
+    RuleBundle(String),
+    #[error("Invalid ban list: {0}")]
+    BanList(String),
+    #[error("Invalid world list: {0}")]
+    Worlds(String),
+    #[error("Event log error: {0}")]
+    EventLog(#[from] crate::event_log::EventLogError),
+    #[error("Checkpoint error: {0}")]
+    Checkpoint(#[from] CheckpointError),
+    #[error("Seal error: {0}")]
+    Seal(#[from] SealError),
+    #[error("Endorsement error: {0}")]
+    Endorsement(#[from] EndorsementError),
     #[error("Server shutdown")]
     Shutdown,
 }
 
+/// Read the first frame of a connection, which must arrive within
+/// `HELLO_TIMEOUT`
+pub(crate) async fn read_hello<R: AsyncRead + Unpin>(reader: &mut R) -> Result<Frame, ServerError> {
+    tokio::time::timeout(HELLO_TIMEOUT, read_frame(reader))
+        .await
+        .map_err(|_| ServerError::Handshake("timed out waiting for HELLO".into()))??
+        .ok_or_else(|| ServerError::Handshake("closed before HELLO".into()))
+}
+
 /// Server state
 pub struct Server {
     config: Config,
     keypair: KeyPair,
+    /// Signs events this node originates, under the node key
+    signer: Arc<EventSigner>,
+    /// Node state: key, membership, peer book and fork choice
     storage: Arc<Storage>,
+    /// Events of the current world; apart from `storage` after following a
+    /// fork, so the old world's events are not served as the new world's
+    world_storage: Arc<Storage>,
     event_log: Arc<EventLog>,
     membership: Arc<MembershipManager>,
     sync_manager: Arc<SyncManager>,
+    /// Eager/lazy push gossip overlay
+    gossip: Arc<PushGossip>,
+    /// Want-list of specific events to fetch from peers
+    fetch: Arc<FetchManager>,
     /// Connected peers
-    peers: RwLock<HashMap<[u8; 32], ConnectedPeer>>,
+    peers: Arc<PeerMap>,
+    /// Persisted peer book and dial policy
+    peer_book: Arc<PeerBook>,
+    /// Penalties and sanctions for misbehaving peers
+    policy: Arc<MisbehaviorPolicy>,
+    /// Liar-sensor and collusion detection over attestations
+    anomaly: Arc<AnomalyDetector>,
+    /// Conflict and equivocation detection over attestations
+    disputes: Arc<DisputeDetector>,
+    /// Rule bundle endorsements and activations
+    endorsements: Arc<EndorsementTally>,
     /// Shutdown signal
     shutdown_tx: broadcast::Sender<()>,
 }
@@ -53,29 +155,99 @@ pub struct ConnectedPeer {
     pub addr: SocketAddr,
     pub roles: PeerRoles,
     pub connected_at: std::time::Instant,
+    /// Whether we dialed this peer
+    pub outbound: bool,
+    /// Outbound frame queue drained by the connection's writer task
+    pub tx: mpsc::Sender<Frame>,
 }
 
 impl Server {
     /// Create a new server instance
     pub fn new(config: Config) -> Result<Self, ServerError> {
-        // Generate or load keypair
-        let keypair = KeyPair::generate();
-        
-        // Open storage
+        // Open storage, with a followed fork's events kept apart
         let storage = Arc::new(Storage::open(&config.data_dir)?);
-        
-        // Create membership manager
-        let membership = Arc::new(MembershipManager::new(
+        let world_storage = match ForkChoice::load(&storage)? {
+            ForkChoice::Follow { world, .. } => Arc::new(Storage::open(config.fork_dir(&world))?),
+            ForkChoice::Stay => storage.clone(),
+        };
+        Self::with_storages(config, storage, world_storage)
+    }
+
+    /// Create a server over existing storage (e.g. in-memory for simulations)
+    ///
+    /// A followed fork's world starts from an empty in-memory event log.
+    pub fn with_storage(config: Config, storage: Arc<Storage>) -> Result<Self, ServerError> {
+        let world_storage = match ForkChoice::load(&storage)? {
+            ForkChoice::Follow { .. } => Arc::new(Storage::memory()),
+            ForkChoice::Stay => storage.clone(),
+        };
+        Self::with_storages(config, storage, world_storage)
+    }
+
+    /// Create a server keeping node state in `storage` and the current
+    /// world's events in `world_storage`
+    pub fn with_storages(
+        config: Config,
+        storage: Arc<Storage>,
+        world_storage: Arc<Storage>,
+    ) -> Result<Self, ServerError> {
+        // Create membership manager, on a followed fork's world if chosen
+        let mut membership = MembershipManager::new(
             &config.world_phrase,
-            1000, // Default rate limit RPM
-        ));
+            config.peer_rate_limit_rpm,
+        )
+        .with_storage(storage.clone())?;
+        if let ForkChoice::Follow { rule_bundle_hash, world } = ForkChoice::load(&storage)? {
+            info!(
+                "Following fork of rule bundle {} to world {:02x?}",
+                hex::encode(rule_bundle_hash),
+                &world.0[..8]
+            );
+            membership = membership.with_world_id(world.0);
+        }
+        let membership = Arc::new(membership);
+
+        // Load or generate the node key
+        let seed = Self::load_seed(&storage, membership.world_id())?;
+        let keypair = KeyPair::from_seed(&seed);
+        let signer = Arc::new(EventSigner::from_seed(&seed));
         
-        // Create event log
-        let event_log = Arc::new(EventLog::new(
+        // Load peer book
+        let peer_book = Arc::new(PeerBook::new(
             storage.clone(),
+            keypair.public_key(),
+            config.bootstrap.clone(),
+        )?);
+        
+        // Create event log with retention
+        let rule_bundle = match &config.rule_bundle {
+            Some(path) => Some(Self::load_rule_bundle(path)?),
+            None => None,
+        };
+        let epoch_len_ms = rule_bundle
+            .as_ref()
+            .map_or(DEFAULT_EPOCH_LEN_MS, |bundle| bundle.epoch_len_ms);
+        let retention = config
+            .retention
+            .iter()
+            .fold(RetentionPolicy::default(), |policy, rule| policy.with_rule(*rule));
+        let mut event_log = EventLog::new(
+            world_storage.clone(),
             WorldId(membership.world_id()),
             keypair.public_key(),
-        ));
+        )
+        .with_retention(retention, epoch_len_ms)
+        .with_quarantine(
+            Duration::from_secs(config.quarantine_ttl_secs),
+            config.max_quarantined,
+        )
+        .with_verification(!config.insecure_skip_verify)
+        .with_descriptor_ttl(rule_bundle.as_ref().and_then(|b| b.descriptor_ttl_epochs));
+        if config.semantic_dim > 0 {
+            let embedder = Arc::new(HashingEmbedder::new(config.semantic_dim));
+            event_log = event_log.with_semantic_index(Arc::new(SemanticIndex::new(embedder)))?;
+        }
+        let event_log = Arc::new(event_log);
         
         // Create sync manager
         let sync_manager = Arc::new(SyncManager::new(
@@ -84,20 +256,109 @@ impl Server {
             config.max_sync_events as usize,
         ));
         
+        // Create push gossip overlay
+        let gossip = Arc::new(PushGossip::new(GossipConfig {
+            eager_fanout: config.push_fanout,
+            lazy_fanout: config.lazy_fanout,
+            seen_cache_size: config.seen_cache_size,
+            ihave_timeout: Duration::from_millis(config.ihave_timeout_ms),
+        }));
+        let fetch = Arc::new(FetchManager::new(FetchConfig {
+            max_ids_per_request: config.max_sync_events as usize,
+            timeout: Duration::from_millis(config.fetch_timeout_ms),
+            ..FetchConfig::default()
+        }));
+
+        let policy = Arc::new(MisbehaviorPolicy::new(
+            membership.clone(),
+            PolicyConfig {
+                suspend_below: config.suspend_below_reputation,
+                ban_below: config.ban_below_reputation,
+                suspend_for: Duration::from_secs(config.suspension_secs),
+                recovery_per_hour: config.reputation_recovery_per_hour,
+                ..PolicyConfig::default()
+            },
+        ));
+
+        let anomaly = Arc::new(AnomalyDetector::new(AnomalyConfig {
+            correlation_threshold: config.anomaly_correlation_threshold,
+            ..rule_bundle
+                .as_ref()
+                .map_or_else(AnomalyConfig::default, AnomalyConfig::from_rule_bundle)
+        }));
+        let disputes = Arc::new(DisputeDetector::new(
+            rule_bundle
+                .as_ref()
+                .map_or_else(DisputeConfig::default, DisputeConfig::from_rule_bundle),
+        ));
+
+        let members = membership.clone();
+        let trust: TrustFn = Arc::new(move |signer: &[u8]| {
+            let key = <[u8; 32]>::try_from(signer).ok()?;
+            if members.is_banned(&key) {
+                return Some(0.0);
+            }
+            members.get_member(&key).map(|member| member.reputation)
+        });
+        let policy_config = ActivationPolicy {
+            threshold: config.activation_threshold,
+            epochs: config.activation_epochs,
+            unknown_trust: config.unknown_signer_trust,
+        };
+        let endorsements = Arc::new(
+            EndorsementTally::new(&config.world_phrase, policy_config)
+                .with_trust(trust)
+                .with_storage(storage.clone())?,
+        );
+
         let (shutdown_tx, _) = broadcast::channel(1);
         
         Ok(Self {
             config,
             keypair,
+            signer,
             storage,
+            world_storage,
             event_log,
             membership,
             sync_manager,
-            peers: RwLock::new(HashMap::new()),
+            gossip,
+            fetch,
+            peers: Arc::new(RwLock::new(HashMap::new())),
+            peer_book,
+            policy,
+            anomaly,
+            disputes,
+            endorsements,
             shutdown_tx,
         })
     }
 
+    /// Load a rule bundle from a JSON file
+    fn load_rule_bundle(path: &std::path::Path) -> Result<RuleBundle, ServerError> {
+        let bytes = std::fs::read(path)?;
+        serde_json::from_slice(&bytes)
+            .map_err(|e| ServerError::RuleBundle(format!("{}: {}", path.display(), e)))
+    }
+
+    /// Load the persisted node key seed, or create one on first start
+    fn load_seed(storage: &Storage, world_id: [u8; 32]) -> Result<[u8; 32], ServerError> {
+        if let Some(bytes) = storage.get_metadata(NODE_STATE_KEY)? {
+            let state: NodeState = postcard::from_bytes(&bytes)?;
+            return Ok(state.keypair_seed);
+        }
+
+        let mut seed = [0u8; 32];
+        rand::RngCore::fill_bytes(&mut rand::rngs::OsRng, &mut seed);
+        let state = NodeState {
+            keypair_seed: seed,
+            world_id,
+            control_plane_key: None,
+        };
+        storage.put_metadata(NODE_STATE_KEY, &postcard::to_allocvec(&state)?)?;
+        Ok(seed)
+    }
+
     /// Get the server's public key
     pub fn public_key(&self) -> [u8; 32] {
         self.keypair.public_key()
@@ -110,103 +371,890 @@ impl Server {
 
     /// Run the server
     pub async fn run(&self) -> Result<(), ServerError> {
+        Host::from_servers(vec![self.clone_arc()])?.run().await
+    }
+
+    /// Import bans and checkpoints, prime detectors and spawn background
+    /// tasks; the caller owns the listeners
+    pub(crate) fn start(&self) -> Result<Vec<tokio::task::JoinHandle<()>>, ServerError> {
         info!(
-            "Starting gossipd on {} (world: {:02x?})",
-            self.config.listen,
-            &self.world_id()[..8]
+            "Starting world {:02x?} on {}",
+            &self.world_id()[..8],
+            self.config.listen
         );
 
         // Bootstrap peers
         for addr in &self.config.bootstrap {
             info!("Bootstrap peer: {}", addr);
-            // TODO: Connect to bootstrap peers
         }
+        info!("Peer book has {} known peers", self.peer_book.len());
 
-        // Spawn background tasks
-        let sync_handle = self.spawn_sync_task();
-        let prune_handle = self.spawn_prune_task();
+        if let Some(path) = &self.config.import_bans {
+            let bytes = std::fs::read(path)?;
+            let list: BanList = serde_json::from_slice(&bytes)
+                .map_err(|e| ServerError::BanList(format!("{}: {}", path.display(), e)))?;
+            let imported = self.membership.import_bans(&list)?;
+            info!(
+                "Imported {} of {} bans from {}",
+                imported,
+                list.bans.len(),
+                path.display()
+            );
+        }
 
-        // Start TCP listener
-        let listener = TcpListener::bind(&self.config.listen).await?;
-        info!("Listening on {}", self.config.listen);
+        if let Some(path) = &self.config.cosign_checkpoint {
+            let signatures = self.cosign_checkpoint(path)?;
+            info!(
+                "Co-signed checkpoint {} ({} signatures)",
+                path.display(),
+                signatures
+            );
+        }
 
-        let mut shutdown_rx = self.shutdown_tx.subscribe();
+        // Import a checkpoint before syncing so only later events are pulled
+        if let Some(path) = &self.config.import_checkpoint {
+            let stats = self.import_checkpoint(path)?;
+            info!(
+                "Imported checkpoint {}: {} events, {} skipped",
+                path.display(),
+                stats.imported,
+                stats.skipped
+            );
+        }
 
-        loop {
-            tokio::select! {
-                result = listener.accept() => {
-                    match result {
-                        Ok((stream, addr)) => {
-                            debug!("Accepted connection from {}", addr);
-                            let server = self.clone_arc();
-                            tokio::spawn(async move {
-                                if let Err(e) = server.handle_connection(stream, addr).await {
-                                    warn!("Connection error from {}: {}", addr, e);
-                                }
-                            });
-                        }
-                        Err(e) => {
-                            error!("Accept error: {}", e);
-                        }
-                    }
-                }
-                _ = shutdown_rx.recv() => {
-                    info!("Shutting down...");
-                    break;
-                }
-            }
+        // Subscribe before scanning so no descriptor is missed
+        let provider_handle = self.spawn_provider_task();
+        let providers = self.load_providers()?;
+        if providers > 0 {
+            info!("{} provider keys excluded from the control plane", providers);
         }
+        let detection_handle = self.spawn_detection_task();
+        let replayed = self.load_recent_events()?;
+        debug!("Detectors primed with {} recent events", replayed);
+        self.check_activations(self.event_log.current_epoch());
 
-        // Wait for background tasks
-        sync_handle.abort();
-        prune_handle.abort();
+        Ok(vec![
+            provider_handle,
+            detection_handle,
+            self.spawn_dial_task(),
+            self.spawn_sync_task(),
+            self.spawn_gossip_task(),
+            self.spawn_fetch_task(),
+            self.spawn_prune_task(),
+            self.spawn_checkpoint_task(),
+        ])
+    }
 
-        // Flush storage
-        self.storage.flush()?;
+    /// Local API over this world
+    pub(crate) fn api(&self) -> ApiServer {
+        ApiServer::new(self.event_log.clone())
+            .with_membership(self.membership.clone(), self.public_key())
+            .with_endorsements(self.endorsements.clone())
+    }
 
-        Ok(())
+    /// Take an inbound connection whose HELLO was already read
+    pub(crate) fn accept_routed(
+        self: Arc<Self>,
+        stream: TcpStream,
+        addr: SocketAddr,
+        hello: Frame,
+    ) {
+        if self.inbound_count() >= self.config.max_inbound {
+            debug!("Rejecting {}: inbound limit reached", addr);
+            return;
+        }
+        debug!("Accepted connection from {}", addr);
+        tokio::spawn(async move {
+            if let Err(e) = self.connection(stream, addr, false, Some(hello)).await {
+                warn!("Connection error from {}: {}", addr, e);
+            }
+        });
+    }
+
+    /// Receiver signalled when this server shuts down
+    pub(crate) fn shutdown_signal(&self) -> broadcast::Receiver<()> {
+        self.shutdown_tx.subscribe()
+    }
+
+    /// Flush storage to disk
+    pub(crate) fn flush(&self) -> Result<(), ServerError> {
+        self.world_storage.flush()?;
+        Ok(self.storage.flush()?)
+    }
+
+    /// Configuration this server runs with
+    pub fn config(&self) -> &Config {
+        &self.config
     }
 
     /// Clone as Arc for spawning tasks
     fn clone_arc(&self) -> Arc<Self> {
-        // This is a simplified version - in production we'd use Arc<Self>
-        // For now, we'll create a new instance with shared state
+        // All mutable state lives behind shared Arcs, so the clone observes
+        // the same peers, log and gossip overlay as `self`
         Arc::new(Self {
             config: self.config.clone(),
             keypair: self.keypair.clone(),
+            signer: self.signer.clone(),
             storage: self.storage.clone(),
+            world_storage: self.world_storage.clone(),
             event_log: self.event_log.clone(),
             membership: self.membership.clone(),
             sync_manager: self.sync_manager.clone(),
-            peers: RwLock::new(HashMap::new()),
+            gossip: self.gossip.clone(),
+            fetch: self.fetch.clone(),
+            peers: self.peers.clone(),
+            peer_book: self.peer_book.clone(),
+            policy: self.policy.clone(),
+            anomaly: self.anomaly.clone(),
+            disputes: self.disputes.clone(),
+            endorsements: self.endorsements.clone(),
             shutdown_tx: self.shutdown_tx.clone(),
         })
     }
 
-    /// Handle an incoming connection
+    /// Number of inbound connections
+    fn inbound_count(&self) -> usize {
+        self.peers.read().values().filter(|p| !p.outbound).count()
+    }
+
+    /// Number of outbound connections
+    fn outbound_count(&self) -> usize {
+        self.peers.read().values().filter(|p| p.outbound).count()
+    }
+
+    /// Dial a peer and serve the connection in the background
+    pub async fn connect(&self, addr: SocketAddr) -> Result<(), ServerError> {
+        if !self.peer_book.begin_dial(addr) {
+            return Ok(());
+        }
+
+        let stream = match tokio::time::timeout(DIAL_TIMEOUT, TcpStream::connect(addr)).await {
+            Ok(Ok(stream)) => stream,
+            result => {
+                self.peer_book.end_dial(&addr);
+                self.peer_book.record_failure(addr, Instant::now())?;
+                return Err(match result {
+                    Ok(Err(e)) => e.into(),
+                    _ => std::io::Error::from(std::io::ErrorKind::TimedOut).into(),
+                });
+            }
+        };
+
+        let server = self.clone_arc();
+        tokio::spawn(async move {
+            let result = server.clone().handle_connection(stream, addr, true).await;
+            server.peer_book.end_dial(&addr);
+            let recorded = match &result {
+                Ok(()) => Ok(()),
+                Err(ServerError::SelfConnection) => server.peer_book.record_self(addr),
+                Err(_) => server.peer_book.record_failure(addr, Instant::now()),
+            };
+            if let Err(e) = result {
+                warn!("Connection error to {}: {}", addr, e);
+            }
+            if let Err(e) = recorded {
+                warn!("Failed to update peer book: {}", e);
+            }
+        });
+        Ok(())
+    }
+
+    /// Handle a connection (inbound or outbound)
+    ///
+    /// `addr` is the remote address; for outbound connections it is the
+    /// address we dialed.
     async fn handle_connection(
         self: Arc<Self>,
         stream: TcpStream,
         addr: SocketAddr,
+        outbound: bool,
     ) -> Result<(), ServerError> {
-        // TODO: Implement full protocol handshake
-        // 1. Receive HELLO with peer's pubkey and world phrase
-        // 2. Verify world phrase matches
-        // 3. Admit peer to membership
-        // 4. Exchange version vectors
-        // 5. Start delta sync
-        
-        info!("Connection handler for {} (placeholder)", addr);
-        
-        // Placeholder: just keep connection alive briefly
-        tokio::time::sleep(Duration::from_secs(1)).await;
-        
+        self.connection(stream, addr, outbound, None).await
+    }
+
+    /// Run a connection, using the peer's HELLO if it was already read
+    async fn connection(
+        self: Arc<Self>,
+        stream: TcpStream,
+        addr: SocketAddr,
+        outbound: bool,
+        peer_hello: Option<Frame>,
+    ) -> Result<(), ServerError> {
+        let (mut reader, mut writer) = stream.into_split();
+        let world = WorldId(self.world_id());
+
+        // Both sides send HELLO first
+        let exchange = (self.config.seal == SealMode::Session).then(EphemeralKeyExchange::new);
+        let seal = match (&exchange, self.current_seal_key()) {
+            (Some(exchange), _) => Some(SealOffer::session(&self.keypair, &world, exchange)),
+            (None, Some(key)) => Some(SealOffer::Epoch { key_id: key.id() }),
+            (None, None) => None,
+        };
+        let mut nonce = [0u8; 32];
+        rand::rngs::OsRng.fill_bytes(&mut nonce);
+        let our_hello = messages::Hello {
+            world: WorldId(self.world_id()),
+            transport_pubkey: self.public_key(),
+            nonce,
+            listen_addr: Some(self.config.advertise_addr.unwrap_or(self.config.listen)),
+            roles: PeerRoles {
+                gossipd: true,
+                ..Default::default()
+            },
+            seal,
+        };
+        write_frame(&mut writer, our_hello.to_frame()?).await?;
+
+        let frame = match peer_hello {
+            Some(frame) => frame,
+            None => read_hello(&mut reader).await?,
+        };
+        if frame.frame_type != FrameType::Hello {
+            return Err(ServerError::Handshake(format!(
+                "expected HELLO, got {:?}",
+                frame.frame_type
+            )));
+        }
+
+        let hello = messages::Hello::from_frame(&frame)?;
+        if hello.world.0 != self.world_id() {
+            return Err(ServerError::Handshake("world mismatch".into()));
+        }
+        let peer_key = hello.transport_pubkey;
+        if peer_key == self.public_key() {
+            return Err(ServerError::SelfConnection);
+        }
+
+        // Prove the world phrase and the transport key only once sealing is
+        // agreed, over transcripts that bind the proofs and the seal to this
+        // connection
+        let ours = our_hello.transcript(&hello)?;
+        let theirs = hello.transcript(&our_hello)?;
+        let (mut sealer, mut opener) =
+            self.negotiate_seal(exchange, &hello, &peer_key, (ours, theirs))?;
+        let auth =
+            messages::HelloAuth::new(&self.keypair, &ours, self.membership.phrase_proof(&ours));
+        let frame = match sealer.as_mut() {
+            Some(sealer) => self.seal_frame(sealer, &auth.to_frame()?)?,
+            None => auth.to_frame()?,
+        };
+        write_frame(&mut writer, frame).await?;
+
+        let frame = read_hello(&mut reader).await?;
+        let frame = match opener.as_mut() {
+            Some(opener) => self.open_frame(opener, &frame)?,
+            None => frame,
+        };
+        if frame.frame_type != FrameType::HelloAuth {
+            return Err(ServerError::Handshake(format!(
+                "expected HELLO auth, got {:?}",
+                frame.frame_type
+            )));
+        }
+        let auth = messages::HelloAuth::from_frame(&frame)?;
+        if !auth.verify(&peer_key, &theirs) {
+            return Err(ServerError::Handshake("transport key not proven".into()));
+        }
+        self.membership
+            .admit_peer(peer_key, &theirs, &auth.phrase_proof, hello.roles)?;
+        // Read-only peers (relays) never join sync or push gossip
+        let participant = is_full_participant(&hello.roles);
+
+        let (tx, mut rx) = mpsc::channel::<Frame>(PEER_QUEUE_DEPTH);
+        {
+            let mut peers = self.peers.write();
+            if peers.contains_key(&peer_key) {
+                return Err(ServerError::Handshake("already connected".into()));
+            }
+            peers.insert(
+                peer_key,
+                ConnectedPeer {
+                    peer_id: PeerId::from_public_key(&peer_key),
+                    addr,
+                    roles: hello.roles,
+                    connected_at: Instant::now(),
+                    outbound,
+                    tx,
+                },
+            );
+        }
+        if participant {
+            self.sync_manager.register_peer(peer_key);
+            self.gossip.add_peer(peer_key);
+        }
+
+        // Outbound: the address we dialed is known to work. Inbound: use the
+        // advertised listen port at the address we observed, unverified
+        // until we dial it.
+        let dial_addr = if outbound {
+            Some(addr)
+        } else {
+            hello.listen_addr.filter(|listen| listen.port() != 0).map(|listen| {
+                if listen.ip().is_unspecified() {
+                    SocketAddr::new(addr.ip(), listen.port())
+                } else {
+                    listen
+                }
+            })
+        };
+        if let Err(e) = self
+            .peer_book
+            .record_connected(peer_key, dial_addr, hello.roles, outbound)
+        {
+            warn!("Failed to update peer book: {}", e);
+        }
+        info!("Peer {} connected from {}", PeerId::from_public_key(&peer_key), addr);
+
+        let sealing = self.clone_arc();
+        let writer_handle = tokio::spawn(async move {
+            while let Some(frame) = rx.recv().await {
+                let frame = match sealer.as_mut() {
+                    Some(sealer) => match sealing.seal_frame(sealer, &frame) {
+                        Ok(sealed) => sealed,
+                        Err(e) => {
+                            warn!("Sealing for {} failed: {}", addr, e);
+                            break;
+                        }
+                    },
+                    None => frame,
+                };
+                if let Err(e) = write_frame(&mut writer, frame).await {
+                    debug!("Write to {} failed: {}", addr, e);
+                    break;
+                }
+            }
+        });
+
+        // Catch up immediately rather than waiting for the sync interval
+        if participant {
+            Self::request_sync(&self.sync_manager, &self.peers, world, &peer_key);
+            Self::send_peer_exchange(&self.peer_book, &self.peers, world, &peer_key);
+        }
+
+        let result = self.read_loop(&mut reader, peer_key, opener).await;
+
+        self.peers.write().remove(&peer_key);
+        self.sync_manager.unregister_peer(&peer_key);
+        self.gossip.remove_peer(&peer_key);
+        self.fetch.remove_peer(&peer_key);
+        writer_handle.abort();
+        info!("Peer {} disconnected", PeerId::from_public_key(&peer_key));
+
+        result
+    }
+
+    /// Read and dispatch frames until the peer disconnects or we shut down
+    async fn read_loop<R: AsyncRead + Unpin>(
+        &self,
+        reader: &mut R,
+        peer_key: [u8; 32],
+        mut opener: Option<Opener>,
+    ) -> Result<(), ServerError> {
+        let mut shutdown_rx = self.shutdown_tx.subscribe();
+
+        loop {
+            let frame = tokio::select! {
+                result = read_frame(reader) => match result {
+                    Ok(Some(frame)) => frame,
+                    Ok(None) => return Ok(()),
+                    Err(e) => {
+                        if matches!(e, FrameError::TooLarge(_)) {
+                            self.penalize(&peer_key, Offense::OversizedFrame, 1);
+                        }
+                        return Err(e.into());
+                    }
+                },
+                _ = shutdown_rx.recv() => return Ok(()),
+            };
+
+            // With sealing on, every frame after HELLO must be sealed
+            let frame = match opener.as_mut() {
+                Some(opener) => match self.open_frame(opener, &frame) {
+                    Ok(frame) => frame,
+                    Err(SealError::Replay(counter)) => {
+                        debug!("Dropping replayed sealed frame {}", counter);
+                        self.penalize(&peer_key, Offense::ReplayedEvent, 1);
+                        continue;
+                    }
+                    Err(SealError::Stale(skew)) => {
+                        debug!("Dropping sealed frame {} ms out of date", skew);
+                        continue;
+                    }
+                    Err(e) => return Err(e.into()),
+                },
+                None => frame,
+            };
+
+            // Suspended or banned peers are dropped; frames over the rate
+            // limit are discarded
+            match self.membership.check_authorized(&peer_key) {
+                Ok(()) => {}
+                Err(MembershipError::RateLimited) => {
+                    self.penalize(&peer_key, Offense::RateLimited, 1);
+                    continue;
+                }
+                Err(e) => return Err(e.into()),
+            }
+
+            self.handle_frame(peer_key, frame)?;
+        }
+    }
+
+    /// Handle a single frame from an admitted peer
+    fn handle_frame(&self, peer_key: [u8; 32], frame: Frame) -> Result<(), ServerError> {
+        let world = WorldId(self.world_id());
+
+        if let Some(rpc) = ControlRpc::for_frame(frame.frame_type) {
+            if let Err(e) = self.membership.authorize(&peer_key, rpc) {
+                debug!("Dropping frame from {}: {}", PeerId::from_public_key(&peer_key), e);
+                return Ok(());
+            }
+        }
+
+        match frame.frame_type {
+            FrameType::Ping => {
+                Self::send_frame(&self.peers, &peer_key, Frame::pong());
+            }
+            FrameType::Pong => {}
+            FrameType::DeltaSyncRequest => {
+                let request = messages::DeltaSyncRequest::from_frame(&frame)?;
+                if request.world != world {
+                    self.penalize(&peer_key, Offense::WorldMismatch, 1);
+                    return Ok(());
+                }
+                let response = self.sync_manager.handle_request(crate::sync::DeltaSyncRequest {
+                    version_vector: request.since,
+                    max_events: request.max_events.min(self.config.max_sync_events),
+                })?;
+                let reply = messages::DeltaSyncResponse {
+                    world,
+                    events: response.events,
+                    now: response.version_vector,
+                    has_more: response.has_more,
+                };
+                Self::send_frame(&self.peers, &peer_key, reply.to_frame()?);
+            }
+            FrameType::DeltaSyncResponse => {
+                let response = messages::DeltaSyncResponse::from_frame(&frame)?;
+                if response.world != world {
+                    self.penalize(&peer_key, Offense::WorldMismatch, 1);
+                    return Ok(());
+                }
+                let outcome = self.sync_manager.handle_response(
+                    peer_key,
+                    crate::sync::DeltaSyncResponse {
+                        events: response.events,
+                        version_vector: response.now,
+                        has_more: response.has_more,
+                    },
+                )?;
+                if outcome.merged > 0 {
+                    debug!("Merged {} events from anti-entropy sync", outcome.merged);
+                }
+                // Fetch the next page without waiting for the sync interval
+                if response.has_more {
+                    Self::request_sync(&self.sync_manager, &self.peers, world, &peer_key);
+                }
+                self.penalize(&peer_key, Offense::WorldMismatch, outcome.world_mismatches);
+                for reason in &outcome.quarantined {
+                    self.want_missing(reason);
+                    if let Some(offense) = Offense::from_quarantine(reason) {
+                        self.penalize(&peer_key, offense, 1);
+                    }
+                }
+            }
+            FrameType::EventBroadcast => {
+                let broadcast = messages::EventBroadcast::from_frame(&frame)?;
+                if broadcast.world != world {
+                    self.penalize(&peer_key, Offense::WorldMismatch, 1);
+                    return Ok(());
+                }
+                for event in broadcast.events {
+                    let (fresh, actions) = self.gossip.on_push(peer_key, &event);
+                    if !fresh {
+                        Self::dispatch_actions(&self.peers, world, actions);
+                        continue;
+                    }
+                    // Only a merged event is marked seen and forwarded, so a
+                    // rejected copy does not shadow a valid one, and an event
+                    // released from quarantine is pushed on its notification
+                    match self.event_log.merge_checked(event.clone(), peer_key) {
+                        Ok(MergeOutcome::Merged) => {
+                            let actions = self.gossip.on_merged(peer_key, &event);
+                            Self::dispatch_actions(&self.peers, world, actions)
+                        }
+                        // Peers prune on their own schedule, so an expired
+                        // event is not evidence of a replay
+                        Ok(MergeOutcome::Duplicate | MergeOutcome::Expired) => {}
+                        Ok(MergeOutcome::Quarantined(reason)) => {
+                            self.want_missing(&reason);
+                            if let Some(offense) = Offense::from_quarantine(&reason) {
+                                self.penalize(&peer_key, offense, 1);
+                            }
+                        }
+                        Err(EventLogError::WorldMismatch) => {
+                            self.penalize(&peer_key, Offense::WorldMismatch, 1)
+                        }
+                        Err(e) => warn!("Rejected pushed event: {}", e),
+                    }
+                }
+            }
+            FrameType::EventFetch => {
+                let request = messages::EventFetch::from_frame(&frame)?;
+                if request.world != world {
+                    self.penalize(&peer_key, Offense::WorldMismatch, 1);
+                    return Ok(());
+                }
+                let events: Vec<Event> = request
+                    .event_ids
+                    .iter()
+                    .take(self.config.max_sync_events as usize)
+                    .filter_map(|id| self.event_log.get_event(id).ok().flatten())
+                    .collect();
+                let reply = messages::EventFetchResponse { world, events };
+                Self::send_frame(&self.peers, &peer_key, reply.to_frame()?);
+            }
+            FrameType::EventFetchResponse => {
+                let response = messages::EventFetchResponse::from_frame(&frame)?;
+                if response.world != world {
+                    self.penalize(&peer_key, Offense::WorldMismatch, 1);
+                    return Ok(());
+                }
+                // Only events we asked this peer for are taken
+                for event in self.fetch.on_response(peer_key, response.events) {
+                    match self.event_log.merge_checked(event, peer_key) {
+                        Ok(MergeOutcome::Quarantined(reason)) => {
+                            self.want_missing(&reason);
+                            if let Some(offense) = Offense::from_quarantine(&reason) {
+                                self.penalize(&peer_key, offense, 1);
+                            }
+                        }
+                        Ok(_) => {}
+                        Err(EventLogError::WorldMismatch) => {
+                            self.penalize(&peer_key, Offense::WorldMismatch, 1)
+                        }
+                        Err(e) => warn!("Rejected fetched event: {}", e),
+                    }
+                }
+            }
+            FrameType::IHave => {
+                let ihave = messages::IHave::from_frame(&frame)?;
+                if ihave.world != world {
+                    self.penalize(&peer_key, Offense::WorldMismatch, 1);
+                    return Ok(());
+                }
+                self.gossip.on_ihave(peer_key, &ihave.event_ids, Instant::now());
+            }
+            FrameType::IWant => {
+                let iwant = messages::IWant::from_frame(&frame)?;
+                if iwant.world != world {
+                    self.penalize(&peer_key, Offense::WorldMismatch, 1);
+                    return Ok(());
+                }
+                self.gossip.on_iwant(peer_key);
+                let events: Vec<Event> = iwant
+                    .event_ids
+                    .iter()
+                    .take(self.config.max_sync_events as usize)
+                    .filter_map(|id| self.event_log.get_event(id).ok().flatten())
+                    .collect();
+                if !events.is_empty() {
+                    let reply = messages::EventBroadcast { world, events };
+                    Self::send_frame(&self.peers, &peer_key, reply.to_frame()?);
+                }
+            }
+            FrameType::GossipPrune => {
+                self.gossip.on_prune(peer_key);
+            }
+            FrameType::PeerExchange => {
+                let exchange = messages::PeerExchange::from_frame(&frame)?;
+                if exchange.world != world {
+                    self.penalize(&peer_key, Offense::WorldMismatch, 1);
+                    return Ok(());
+                }
+                let peers = &exchange.peers[..exchange.peers.len().min(MAX_PEX_PEERS)];
+                let learned = self.peer_book.learn(peers)?;
+                if learned > 0 {
+                    debug!("Learned {} peers via peer exchange", learned);
+                }
+            }
+            FrameType::DescriptorQuery => {
+                let query = messages::DescriptorQuery::from_frame(&frame)?;
+                if query.world != world {
+                    self.penalize(&peer_key, Offense::WorldMismatch, 1);
+                    return Ok(());
+                }
+                let reply = messages::DescriptorResponse {
+                    descriptor: self.event_log.get_descriptor(&query.descriptor_id)?,
+                };
+                Self::send_frame(&self.peers, &peer_key, reply.to_frame()?);
+            }
+            other => {
+                debug!("Ignoring unexpected {:?} frame", other);
+            }
+        }
+
         Ok(())
     }
 
+    /// Seal key for an epoch in `--seal epoch` mode
+    fn epoch_seal_key(&self, epoch_id: u64) -> Option<SealKey> {
+        if self.config.seal != SealMode::Epoch {
+            return None;
+        }
+        let master = self.config.control_plane_master_key.as_ref()?;
+        let key = derive_control_plane_key(master, &WorldId(self.world_id()), epoch_id);
+        Some(SealKey::from_control_plane_key(&key))
+    }
+
+    /// Epoch seal key for the current epoch
+    fn current_seal_key(&self) -> Option<SealKey> {
+        self.epoch_seal_key(self.event_log.current_epoch())
+    }
+
+    /// Epoch seal keys accepted now: previous, current and next epoch, so
+    /// peers on either side of a boundary interoperate
+    fn accepted_seal_keys(&self) -> Vec<SealKey> {
+        let epoch = self.event_log.current_epoch();
+        [epoch.checked_sub(1), Some(epoch), epoch.checked_add(1)]
+            .into_iter()
+            .flatten()
+            .filter_map(|e| self.epoch_seal_key(e))
+            .collect()
+    }
+
+    /// Agree on sealing with a peer from the HELLOs; both sides must use
+    /// the same mode
+    ///
+    /// Frames are bound to the (ours, theirs) handshake transcripts, so
+    /// sealed frames cannot be replayed into another connection even under
+    /// a shared epoch key.
+    fn negotiate_seal(
+        &self,
+        exchange: Option<EphemeralKeyExchange>,
+        hello: &messages::Hello,
+        peer_key: &[u8; 32],
+        (ours, theirs): ([u8; 32], [u8; 32]),
+    ) -> Result<(Option<Sealer>, Option<Opener>), ServerError> {
+        let world = WorldId(self.world_id());
+        match (self.config.seal, &hello.seal) {
+            (SealMode::Off, None) => Ok((None, None)),
+            (SealMode::Epoch, Some(SealOffer::Epoch { key_id })) => {
+                let keys = self.accepted_seal_keys();
+                if !keys.iter().any(|k| k.id() == *key_id) {
+                    return Err(ServerError::Handshake("control-plane key mismatch".into()));
+                }
+                let current = self
+                    .current_seal_key()
+                    .ok_or_else(|| ServerError::Handshake("no control-plane key".into()))?;
+                Ok((
+                    Some(Sealer::new(current).with_channel(ours)),
+                    Some(Opener::new(keys).with_channel(theirs)),
+                ))
+            }
+            (SealMode::Session, Some(offer @ SealOffer::Session { .. })) => {
+                let exchange = exchange
+                    .ok_or_else(|| ServerError::Handshake("no session key exchange".into()))?;
+                let (seal, open) = sealed::session_keys(exchange, offer, peer_key, &world)?;
+                Ok((
+                    Some(Sealer::new(seal).with_channel(ours)),
+                    Some(Opener::new(vec![open]).with_channel(theirs)),
+                ))
+            }
+            (mode, _) => Err(ServerError::Handshake(format!(
+                "seal mode mismatch (ours: {:?})",
+                mode
+            ))),
+        }
+    }
+
+    /// Seal an outgoing frame, moving to the current epoch key if needed
+    fn seal_frame(&self, sealer: &mut Sealer, frame: &Frame) -> Result<Frame, SealError> {
+        if let Some(key) = self.current_seal_key() {
+            if key.id() != sealer.key_id() {
+                sealer.rekey(key);
+            }
+        }
+        sealer.seal(frame, now_millis())
+    }
+
+    /// Open an incoming sealed frame, refreshing accepted epoch keys
+    fn open_frame(&self, opener: &mut Opener, frame: &Frame) -> Result<Frame, SealError> {
+        if self.config.seal == SealMode::Epoch {
+            let keys = self.accepted_seal_keys();
+            if keys.iter().map(|k| k.id()).ne(opener.key_ids()) {
+                opener.set_keys(keys);
+            }
+        }
+        opener.open(frame, now_millis())
+    }
+
+    /// Exclude transport keys of known providers from the control plane;
+    /// returns how many keys were found
+    fn load_providers(&self) -> Result<usize, ServerError> {
+        let mut found = 0;
+        for descriptor in self.event_log.get_descriptors()? {
+            if let Ok(key) = <[u8; 32]>::try_from(descriptor.provider_transport_pubkey.as_slice()) {
+                self.membership.mark_provider(key)?;
+                found += 1;
+            }
+        }
+        Ok(found)
+    }
+
+    /// Put a quarantined event's missing dependency on the want-list
+    fn want_missing(&self, reason: &QuarantineReason) {
+        if let QuarantineReason::MissingDependency(id) = reason {
+            self.fetch.want([*id]);
+        }
+    }
+
+    /// Report `count` offenses by a peer to the misbehavior policy
+    fn penalize(&self, peer_key: &[u8; 32], offense: Offense, count: usize) {
+        if count == 0 {
+            return;
+        }
+        debug!(
+            "Peer {} offense: {} x{}",
+            PeerId::from_public_key(peer_key),
+            offense,
+            count
+        );
+        if let Err(e) = self.policy.report_many(peer_key, offense, count) {
+            warn!("Failed to record offense: {}", e);
+        }
+    }
+
+    /// Queue a frame for a connected peer; returns false if it could not be queued
+    fn send_frame(peers: &PeerMap, peer_key: &[u8; 32], frame: Frame) -> bool {
+        let peers = peers.read();
+        let Some(peer) = peers.get(peer_key) else {
+            return false;
+        };
+        match peer.tx.try_send(frame) {
+            Ok(()) => true,
+            Err(e) => {
+                // Dropped pushes are recovered by anti-entropy
+                debug!("Dropping frame for {}: {}", peer.peer_id, e);
+                false
+            }
+        }
+    }
+
+    /// Send gossip actions to their peers
+    fn dispatch_actions(peers: &PeerMap, world: WorldId, actions: Vec<GossipAction>) {
+        for action in actions {
+            let (peer, frame) = match action {
+                GossipAction::Push { peer, events } => {
+                    (peer, messages::EventBroadcast { world, events }.to_frame())
+                }
+                GossipAction::IHave { peer, event_ids } => {
+                    (peer, messages::IHave { world, event_ids }.to_frame())
+                }
+                GossipAction::IWant { peer, event_ids } => {
+                    (peer, messages::IWant { world, event_ids }.to_frame())
+                }
+                GossipAction::Prune { peer } => (peer, messages::GossipPrune { world }.to_frame()),
+            };
+            match frame {
+                Ok(frame) => {
+                    Self::send_frame(peers, &peer, frame);
+                }
+                Err(e) => warn!("Failed to encode gossip frame: {}", e),
+            }
+        }
+    }
+
+    /// Send our verified peers to a peer
+    fn send_peer_exchange(book: &PeerBook, peers: &PeerMap, world: WorldId, peer_key: &[u8; 32]) {
+        let message = messages::PeerExchange {
+            world,
+            peers: book
+                .adverts(MAX_PEX_PEERS + 1)
+                .into_iter()
+                .filter(|advert| advert.transport_pubkey != *peer_key)
+                .take(MAX_PEX_PEERS)
+                .collect(),
+        };
+        match message.to_frame() {
+            Ok(frame) => {
+                Self::send_frame(peers, peer_key, frame);
+            }
+            Err(e) => warn!("Failed to encode peer exchange: {}", e),
+        }
+    }
+
+    /// Send a delta sync request to a peer
+    fn request_sync(sync_manager: &SyncManager, peers: &PeerMap, world: WorldId, peer_key: &[u8; 32]) {
+        let request = sync_manager.create_request(peer_key);
+        let message = messages::DeltaSyncRequest {
+            world,
+            since: request.version_vector,
+            max_events: request.max_events,
+        };
+        let sent = match message.to_frame() {
+            Ok(frame) => Self::send_frame(peers, peer_key, frame),
+            Err(e) => {
+                warn!("Failed to encode sync request: {}", e);
+                false
+            }
+        };
+        if !sent {
+            sync_manager.mark_failure(peer_key);
+        }
+    }
+
+    /// Spawn background dial task
+    ///
+    /// Keeps the outbound degree at `target_outbound` by dialing peer book
+    /// entries (falling back to bootstrap addresses), and periodically
+    /// exchanges peer lists with connected peers.
+    fn spawn_dial_task(&self) -> tokio::task::JoinHandle<()> {
+        let server = self.clone_arc();
+        let mut shutdown_rx = self.shutdown_tx.subscribe();
+
+        tokio::spawn(async move {
+            let mut ticker = interval(Duration::from_secs(server.config.dial_interval_secs.max(1)));
+            let mut round: u64 = 0;
+
+            loop {
+                tokio::select! {
+                    _ = ticker.tick() => {
+                        let deficit = server
+                            .config
+                            .target_outbound
+                            .saturating_sub(server.outbound_count());
+                        let connected: HashSet<[u8; 32]> =
+                            server.peers.read().keys().copied().collect();
+                        for addr in server.peer_book.dial_candidates(&connected, deficit, Instant::now()) {
+                            debug!("Dialing {}", addr);
+                            if let Err(e) = server.connect(addr).await {
+                                debug!("Dial to {} failed: {}", addr, e);
+                            }
+                        }
+
+                        round += 1;
+                        if round >= PEX_EVERY_ROUNDS {
+                            round = 0;
+                            let world = WorldId(server.world_id());
+                            for peer_key in connected {
+                                Self::send_peer_exchange(&server.peer_book, &server.peers, world, &peer_key);
+                            }
+                        }
+                    }
+                    _ = shutdown_rx.recv() => {
+                        break;
+                    }
+                }
+            }
+        })
+    }
+
     /// Spawn background sync task
     fn spawn_sync_task(&self) -> tokio::task::JoinHandle<()> {
         let sync_manager = self.sync_manager.clone();
+        let peers = self.peers.clone();
+        let world = WorldId(self.world_id());
         let interval_secs = self.config.sync_interval_secs;
         let mut shutdown_rx = self.shutdown_tx.subscribe();
 
@@ -216,11 +1264,51 @@ impl Server {
             loop {
                 tokio::select! {
                     _ = ticker.tick() => {
-                        let peers = sync_manager.peers_needing_sync();
-                        for peer_id in peers {
+                        for peer_id in sync_manager.peers_needing_sync() {
                             debug!("Syncing with peer {:02x?}", &peer_id[..8]);
-                            // TODO: Perform actual sync
+                            Self::request_sync(&sync_manager, &peers, world, &peer_id);
+                        }
+                    }
+                    _ = shutdown_rx.recv() => {
+                        break;
+                    }
+                }
+            }
+        })
+    }
+
+    /// Spawn background push gossip task
+    ///
+    /// Pushes every event that enters the log (local append, anti-entropy
+    /// merge) and periodically requests lazily announced events that never
+    /// arrived. Events received by push are forwarded once merged, so the
+    /// seen-cache turns their notifications into no-ops.
+    fn spawn_gossip_task(&self) -> tokio::task::JoinHandle<()> {
+        let gossip = self.gossip.clone();
+        let peers = self.peers.clone();
+        let world = WorldId(self.world_id());
+        let mut notify_rx = self.event_log.subscribe();
+        let tick = Duration::from_millis(self.config.ihave_timeout_ms.max(10) / 2 + 1);
+        let mut shutdown_rx = self.shutdown_tx.subscribe();
+
+        tokio::spawn(async move {
+            let mut ticker = interval(tick);
+
+            loop {
+                tokio::select! {
+                    result = notify_rx.recv() => match result {
+                        Ok(new_event) => {
+                            let actions = gossip.broadcast(&new_event.event, new_event.source);
+                            Self::dispatch_actions(&peers, world, actions);
+                        }
+                        Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                            debug!("Push gossip lagged by {} events; anti-entropy will catch up", skipped);
                         }
+                        Err(broadcast::error::RecvError::Closed) => break,
+                    },
+                    _ = ticker.tick() => {
+                        let actions = gossip.tick(Instant::now());
+                        Self::dispatch_actions(&peers, world, actions);
                     }
                     _ = shutdown_rx.recv() => {
                         break;
@@ -230,18 +1318,319 @@ impl Server {
         })
     }
 
+    /// Spawn background want-list fetch task
+    ///
+    /// Picks up missing dependencies of quarantined events, including ones
+    /// quarantined before a restart, and asks peers for them in turn.
+    fn spawn_fetch_task(&self) -> tokio::task::JoinHandle<()> {
+        let server = self.clone_arc();
+        let world = WorldId(self.world_id());
+        let mut notify_rx = self.event_log.subscribe();
+        let tick = Duration::from_millis(self.config.fetch_timeout_ms.max(10) / 2 + 1);
+        let mut shutdown_rx = self.shutdown_tx.subscribe();
+
+        tokio::spawn(async move {
+            let mut ticker = interval(tick);
+
+            loop {
+                tokio::select! {
+                    result = notify_rx.recv() => match result {
+                        Ok(new_event) => server.fetch.fulfilled(&new_event.event.event_id),
+                        Err(broadcast::error::RecvError::Lagged(_)) => {}
+                        Err(broadcast::error::RecvError::Closed) => break,
+                    },
+                    _ = ticker.tick() => {
+                        if let Err(e) = server.want_quarantined() {
+                            warn!("Failed to scan quarantine: {}", e);
+                        }
+                        let peers: Vec<[u8; 32]> = server.peers.read().keys().copied().collect();
+                        for (peer, event_ids) in server.fetch.tick(&peers, Instant::now()) {
+                            match (messages::EventFetch { world, event_ids }).to_frame() {
+                                Ok(frame) => {
+                                    Self::send_frame(&server.peers, &peer, frame);
+                                }
+                                Err(e) => warn!("Failed to encode event fetch: {}", e),
+                            }
+                        }
+                    }
+                    _ = shutdown_rx.recv() => {
+                        break;
+                    }
+                }
+            }
+        })
+    }
+
+    /// Want every event the quarantine is waiting on that is not stored
+    fn want_quarantined(&self) -> Result<usize, ServerError> {
+        let missing: Vec<EventId> = self
+            .event_log
+            .quarantine()
+            .list(usize::MAX)?
+            .iter()
+            .filter_map(|entry| match entry.reason {
+                QuarantineReason::MissingDependency(id) => Some(id),
+                _ => None,
+            })
+            .filter(|id| !self.fetch.is_wanted(id))
+            .filter(|id| !self.event_log.has_event(id).unwrap_or(true))
+            .collect();
+        Ok(self.fetch.want(missing))
+    }
+
     /// Spawn background prune task
     fn spawn_prune_task(&self) -> tokio::task::JoinHandle<()> {
+        let server = self.clone_arc();
         let mut shutdown_rx = self.shutdown_tx.subscribe();
 
         tokio::spawn(async move {
-            let mut ticker = interval(Duration::from_secs(3600)); // Hourly
+            let mut ticker = interval(Duration::from_secs(server.config.prune_interval_secs.max(1)));
             
             loop {
                 tokio::select! {
                     _ = ticker.tick() => {
                         debug!("Running prune cycle");
-                        // TODO: Prune old events, stale peers
+                        if let Err(e) = server.prune() {
+                            warn!("Prune cycle failed: {}", e);
+                        }
+                    }
+                    _ = shutdown_rx.recv() => {
+                        break;
+                    }
+                }
+            }
+        })
+    }
+
+    /// Run one prune cycle: expire events by retention policy, forget stale
+    /// disconnected peers, then compact storage
+    pub fn prune(&self) -> Result<PruneStats, ServerError> {
+        let epoch = self.event_log.current_epoch();
+        let stats = self.event_log.prune(epoch)?;
+
+        let connected: HashSet<[u8; 32]> = self.peers.read().keys().copied().collect();
+        let max_idle = Duration::from_secs(self.config.stale_peer_secs);
+        let stale_sync = self.sync_manager.prune_stale(max_idle, &connected).len();
+        let stale_members = self.membership.prune_stale(max_idle, &connected)?;
+        self.policy
+            .recover(Duration::from_secs(self.config.prune_interval_secs))?;
+        self.anomaly.prune(epoch);
+        self.disputes.prune(epoch);
+        self.check_activations(epoch);
+        self.endorsements.prune(epoch);
+        let sweep = self.event_log.sweep_quarantine()?;
+
+        let mut size = self.storage.compact()?;
+        if !Arc::ptr_eq(&self.storage, &self.world_storage) {
+            size += self.world_storage.compact()?;
+        }
+        info!(
+            "Pruned {} of {} events at epoch {} ({} stale sync peers, {} stale members, {} bytes on disk)",
+            stats.pruned, stats.scanned, epoch, stale_sync, stale_members, size
+        );
+        if sweep.expired > 0 || sweep.released > 0 {
+            info!(
+                "Quarantine: {} expired, {} released, {} held",
+                sweep.expired,
+                sweep.released,
+                self.event_log.quarantine().len()
+            );
+        }
+
+        Ok(stats)
+    }
+
+    /// Spawn task excluding providers as their descriptors arrive
+    ///
+    /// A connected peer whose key turns up in a descriptor is disconnected.
+    fn spawn_provider_task(&self) -> tokio::task::JoinHandle<()> {
+        let server = self.clone_arc();
+        let mut notify_rx = self.event_log.subscribe();
+        let mut shutdown_rx = self.shutdown_tx.subscribe();
+
+        tokio::spawn(async move {
+            loop {
+                tokio::select! {
+                    result = notify_rx.recv() => match result {
+                        Ok(new_event) => {
+                            let EventBody::DescriptorPublish(publish) = &new_event.event.body else {
+                                continue;
+                            };
+                            let key = publish.descriptor.provider_transport_pubkey.as_slice();
+                            let Ok(key) = <[u8; 32]>::try_from(key) else {
+                                continue;
+                            };
+                            match server.membership.mark_provider(key) {
+                                Ok(true) => {
+                                    info!(
+                                        "Peer {} is a provider; revoking admission",
+                                        PeerId::from_public_key(&key)
+                                    );
+                                    // Dropping the sender ends the writer; the
+                                    // read loop then fails authorization
+                                    server.peers.write().remove(&key);
+                                }
+                                Ok(false) => {}
+                                Err(e) => warn!("Failed to record provider: {}", e),
+                            }
+                        }
+                        Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                            debug!("Provider watch lagged by {} events; rescanning", skipped);
+                            if let Err(e) = server.load_providers() {
+                                warn!("Failed to rescan providers: {}", e);
+                            }
+                        }
+                        Err(broadcast::error::RecvError::Closed) => break,
+                    },
+                    _ = shutdown_rx.recv() => {
+                        break;
+                    }
+                }
+            }
+        })
+    }
+
+    /// Spawn task feeding attestations to the anomaly and dispute detectors
+    /// and endorsements to the tally
+    fn spawn_detection_task(&self) -> tokio::task::JoinHandle<()> {
+        let server = self.clone_arc();
+        let mut notify_rx = self.event_log.subscribe();
+        let mut shutdown_rx = self.shutdown_tx.subscribe();
+
+        tokio::spawn(async move {
+            loop {
+                tokio::select! {
+                    result = notify_rx.recv() => match result {
+                        Ok(new_event) => {
+                            for alert in server.anomaly.observe(&new_event.event) {
+                                server.raise_alert(&alert);
+                            }
+                            if server.endorsements.record(&new_event.event) {
+                                server.check_activations(server.event_log.current_epoch());
+                            }
+                            for finding in server.disputes.observe(&new_event.event) {
+                                info!("Disputing {:?}: {}", finding.kind, finding.reason());
+                                if !server.config.no_auto_disputes {
+                                    server.publish_dispute(
+                                        finding.to_dispute(server.event_log.world_id()),
+                                    );
+                                }
+                            }
+                        }
+                        Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                            warn!("Attestation detectors skipped {} events", skipped);
+                        }
+                        Err(broadcast::error::RecvError::Closed) => break,
+                    },
+                    _ = shutdown_rx.recv() => {
+                        break;
+                    }
+                }
+            }
+        })
+    }
+
+    /// Feed stored attestations, disputes and endorsements within the
+    /// detection and activation windows to the detectors and the tally;
+    /// findings while replaying are not published
+    fn load_recent_events(&self) -> Result<usize, ServerError> {
+        let window = self
+            .anomaly
+            .config()
+            .window_epochs
+            .max(self.endorsements.policy().epochs);
+        let oldest = self.event_log.current_epoch().saturating_sub(window);
+        let mut replayed = 0;
+        for event in self.world_storage.all_events() {
+            let event = event?;
+            let relevant = matches!(
+                event.event_type,
+                EventType::Attestation | EventType::Dispute | EventType::RuleEndorsement
+            );
+            if relevant && event.epoch_id >= oldest {
+                self.anomaly.observe(&event);
+                self.disputes.observe(&event);
+                self.endorsements.record(&event);
+                replayed += 1;
+            }
+        }
+        Ok(replayed)
+    }
+
+    /// Log bundles that activated by `epoch`
+    fn check_activations(&self, epoch: u64) {
+        match self.endorsements.evaluate(epoch) {
+            Ok(activated) => {
+                for activation in activated {
+                    info!(
+                        "Rule bundle {} activated at epoch {}; successor world {}",
+                        hex::encode(activation.rule_bundle_hash),
+                        activation.epoch_id,
+                        hex::encode(activation.successor.0)
+                    );
+                }
+            }
+            Err(e) => warn!("Failed to evaluate endorsements: {}", e),
+        }
+    }
+
+    /// Log an anomaly alert and, if enabled, publish a signed dispute
+    fn raise_alert(&self, alert: &AnomalyAlert) {
+        let probers: Vec<String> = alert
+            .probers
+            .iter()
+            .map(|key| hex::encode(&key[..key.len().min(8)]))
+            .collect();
+        warn!("Anomaly: {} (probers {})", alert.reason(), probers.join(", "));
+        let fresh = self
+            .disputes
+            .claim(alert.event_a, alert.event_b, alert.epoch_id);
+        if self.config.anomaly_disputes && fresh {
+            self.publish_dispute(alert.to_dispute(self.event_log.world_id()));
+        }
+    }
+
+    /// Sign and append a dispute body
+    fn publish_dispute(&self, body: EventBody) {
+        let event = match self.signer.sign(body) {
+            Ok(event) => event,
+            Err(e) => {
+                warn!("Failed to sign dispute: {}", e);
+                return;
+            }
+        };
+        match self.event_log.append(event) {
+            Ok(()) | Err(EventLogError::DuplicateEvent(_)) => {}
+            Err(e) => warn!("Failed to publish dispute: {}", e),
+        }
+    }
+
+    /// Local anomaly alerts, oldest first
+    pub fn anomaly_alerts(&self) -> Vec<AnomalyAlert> {
+        self.anomaly.recent_alerts()
+    }
+
+    /// Spawn background checkpoint task
+    fn spawn_checkpoint_task(&self) -> tokio::task::JoinHandle<()> {
+        let server = self.clone_arc();
+        let mut shutdown_rx = self.shutdown_tx.subscribe();
+
+        tokio::spawn(async move {
+            if server.config.checkpoint_interval_secs == 0 {
+                return;
+            }
+            let period = Duration::from_secs(server.config.checkpoint_interval_secs);
+            let mut ticker = tokio::time::interval_at(tokio::time::Instant::now() + period, period);
+
+            loop {
+                tokio::select! {
+                    _ = ticker.tick() => {
+                        let writer = server.clone();
+                        match tokio::task::spawn_blocking(move || writer.write_checkpoint()).await {
+                            Ok(Ok(path)) => info!("Wrote checkpoint {}", path.display()),
+                            Ok(Err(e)) => warn!("Checkpoint failed: {}", e),
+                            Err(e) => warn!("Checkpoint task failed: {}", e),
+                        }
                     }
                     _ = shutdown_rx.recv() => {
                         break;
@@ -251,6 +1640,57 @@ impl Server {
         })
     }
 
+    /// Snapshot the log into a checkpoint signed by this node
+    pub fn checkpoint(&self) -> Result<Checkpoint, ServerError> {
+        let mut checkpoint = self.event_log.export_checkpoint()?;
+        checkpoint.sign(&self.keypair)?;
+        Ok(checkpoint)
+    }
+
+    /// Write a signed checkpoint to the checkpoint directory, keeping the
+    /// most recent `CHECKPOINTS_KEPT`
+    pub fn write_checkpoint(&self) -> Result<std::path::PathBuf, ServerError> {
+        let checkpoint = self.checkpoint()?;
+        let dir = self.config.checkpoint_dir();
+        std::fs::create_dir_all(&dir)?;
+        let path = dir.join(format!("checkpoint-{:020}.ckpt", checkpoint.header.head_seq));
+        checkpoint.write_to(&path)?;
+
+        let mut files: Vec<_> = std::fs::read_dir(&dir)?
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|p| p.extension().is_some_and(|ext| ext == "ckpt"))
+            .collect();
+        files.sort();
+        let excess = files.len().saturating_sub(CHECKPOINTS_KEPT);
+        for old in &files[..excess] {
+            std::fs::remove_file(old)?;
+        }
+
+        Ok(path)
+    }
+
+    /// Import a checkpoint file signed by the configured trusted members
+    pub fn import_checkpoint(
+        &self,
+        path: &std::path::Path,
+    ) -> Result<CheckpointImport, ServerError> {
+        let checkpoint = Checkpoint::read_from(path)?;
+        Ok(self.event_log.import_checkpoint(
+            &checkpoint,
+            &self.config.trusted_checkpoint_signers,
+            self.config.checkpoint_threshold,
+        )?)
+    }
+
+    /// Check a checkpoint file another member produced and add this node's
+    /// signature to it, returning how many signatures it now has
+    pub fn cosign_checkpoint(&self, path: &std::path::Path) -> Result<usize, ServerError> {
+        let mut checkpoint = Checkpoint::read_from(path)?;
+        checkpoint.cosign(WorldId(self.world_id()), &self.keypair)?;
+        checkpoint.write_to(path)?;
+        Ok(checkpoint.signatures.len())
+    }
+
     /// Shutdown the server
     pub fn shutdown(&self) {
         let _ = self.shutdown_tx.send(());
@@ -260,6 +1700,8 @@ impl Server {
     pub fn stats(&self) -> ServerStats {
         ServerStats {
             peer_count: self.peers.read().len(),
+            outbound_count: self.outbound_count(),
+            known_peers: self.peer_book.len(),
             event_count: self.event_log.event_count(),
             member_count: self.membership.member_count(),
             sync_stats: self.sync_manager.stats(),
@@ -271,6 +1713,8 @@ impl Server {
 #[derive(Debug, Clone)]
 pub struct ServerStats {
     pub peer_count: usize,
+    pub outbound_count: usize,
+    pub known_peers: usize,
     pub event_count: usize,
     pub member_count: usize,
     pub sync_stats: crate::sync::SyncStats,
@@ -280,17 +1724,58 @@ pub struct ServerStats {
 mod tests {
     use super::*;
     use tempfile::tempdir;
+    use tokio::net::TcpListener;
+    use crate::query::EventQuery;
+    use terrain_gossip_core::crypto::EventSigner;
 
     fn test_config() -> Config {
         let dir = tempdir().unwrap();
         Config {
             listen: "127.0.0.1:0".parse().unwrap(),
-            data_dir: dir.into_path(),
+            api_listen: "127.0.0.1:0".parse().unwrap(),
+            data_dir: dir.keep(),
             world_phrase: "test-world phrase".to_string(),
             rule_bundle: None,
+            worlds: None,
             bootstrap: vec![],
+            advertise_addr: None,
+            target_outbound: 8,
+            max_inbound: 32,
+            dial_interval_secs: 10,
             max_sync_events: 100,
             sync_interval_secs: 30,
+            push_fanout: 4,
+            lazy_fanout: 8,
+            seen_cache_size: 1024,
+            ihave_timeout_ms: 500,
+            fetch_timeout_ms: 200,
+            retention: vec![],
+            prune_interval_secs: 3600,
+            stale_peer_secs: 86400,
+            quarantine_ttl_secs: 3600,
+            max_quarantined: 1000,
+            checkpoint_interval_secs: 0,
+            checkpoint_dir: None,
+            import_checkpoint: None,
+            trusted_checkpoint_signers: vec![],
+            checkpoint_threshold: 1,
+            cosign_checkpoint: None,
+            import_bans: None,
+            peer_rate_limit_rpm: 6000,
+            suspend_below_reputation: 0.5,
+            ban_below_reputation: 0.1,
+            suspension_secs: 600,
+            reputation_recovery_per_hour: 0.05,
+            semantic_dim: 64,
+            anomaly_correlation_threshold: 0.9,
+            anomaly_disputes: false,
+            no_auto_disputes: false,
+            activation_threshold: 3.0,
+            activation_epochs: 3,
+            unknown_signer_trust: 0.0,
+            seal: SealMode::Off,
+            control_plane_master_key: None,
+            insecure_skip_verify: false,
             verbose: false,
             log_format: "pretty".to_string(),
         }
@@ -313,4 +1798,465 @@ mod tests {
         let world_id = server.world_id();
         assert_ne!(world_id, [0; 32]);
     }
+
+    #[test]
+    fn test_foreign_world_sync_and_ihave_penalized() {
+        let server = Server::new(test_config()).unwrap();
+        let peer = [5; 32];
+        let proof = server.membership.phrase_proof(&[0; 32]);
+        let roles = PeerRoles {
+            gossipd: true,
+            ..Default::default()
+        };
+        server
+            .membership
+            .admit_peer(peer, &[0; 32], &proof, roles)
+            .unwrap();
+        let reputation = || server.membership.get_member(&peer).unwrap().reputation;
+
+        let foreign = WorldId([9; 32]);
+        let request = messages::DeltaSyncRequest {
+            world: foreign,
+            since: vec![],
+            max_events: 10,
+        };
+        server
+            .handle_frame(peer, request.to_frame().unwrap())
+            .unwrap();
+        let after_sync = reputation();
+        assert!(after_sync < 1.0);
+
+        let ihave = messages::IHave {
+            world: foreign,
+            event_ids: vec![EventId([1; 32])],
+        };
+        server
+            .handle_frame(peer, ihave.to_frame().unwrap())
+            .unwrap();
+        assert!(reputation() < after_sync);
+        // The announcement is not followed up
+        let later = Instant::now() + Duration::from_secs(60);
+        assert!(server.gossip.tick(later).is_empty());
+    }
+
+    #[test]
+    fn test_identity_persists() {
+        let config = test_config();
+        let first = Server::new(config.clone()).unwrap().public_key();
+        let second = Server::new(config).unwrap().public_key();
+        assert_eq!(first, second);
+    }
+
+    #[tokio::test]
+    async fn test_push_between_servers() {
+        push_between(test_config(), test_config()).await;
+    }
+
+    #[tokio::test]
+    async fn test_sealed_push_between_servers() {
+        let sealed = |seal| Config {
+            seal,
+            control_plane_master_key: Some([9; 32]),
+            ..test_config()
+        };
+        push_between(sealed(SealMode::Session), sealed(SealMode::Session)).await;
+        push_between(sealed(SealMode::Epoch), sealed(SealMode::Epoch)).await;
+
+        // Peers must agree on sealing
+        let a = Arc::new(Server::new(sealed(SealMode::Epoch)).unwrap());
+        let b = Arc::new(Server::new(test_config()).unwrap());
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (stream, from) = listener.accept().await.unwrap();
+            let _ = b.handle_connection(stream, from, false).await;
+        });
+        let stream = TcpStream::connect(addr).await.unwrap();
+        assert!(matches!(
+            a.handle_connection(stream, addr, true).await,
+            Err(ServerError::Handshake(_))
+        ));
+    }
+
+    /// Connect two servers and check an event pushes from `a` to `b`
+    async fn push_between(config_a: Config, config_b: Config) {
+        let a = Arc::new(Server::new(config_a).unwrap());
+        let b = Arc::new(Server::new(config_b).unwrap());
+        let _gossip_a = a.spawn_gossip_task();
+        let _gossip_b = b.spawn_gossip_task();
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let acceptor = b.clone();
+        tokio::spawn(async move {
+            let (stream, from) = listener.accept().await.unwrap();
+            let _ = acceptor.handle_connection(stream, from, false).await;
+        });
+        a.connect(addr).await.unwrap();
+
+        for _ in 0..100 {
+            if a.stats().peer_count == 1 && b.stats().peer_count == 1 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(a.stats().peer_count, 1);
+        assert_eq!(a.stats().outbound_count, 1);
+        // The dialed address is remembered for reconnects
+        assert_eq!(a.stats().known_peers, 1);
+
+        let world = WorldId(a.world_id());
+        let signer = EventSigner::from_seed(&[42; 32]);
+        let event = signer
+            .sign(EventBody::RuleEndorsement(RuleEndorsementEvent {
+                world,
+                epoch_id: 1,
+                rule_bundle_hash: [0; 32],
+                weight: 1.0,
+                signer_transport_pubkey: vec![],
+                signature: vec![],
+            }))
+            .unwrap();
+        a.event_log.append(event.clone()).unwrap();
+
+        // Delivered by push well before the 30s sync interval
+        for _ in 0..200 {
+            if b.event_log.has_event(&event.event_id).unwrap() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert!(b.event_log.has_event(&event.event_id).unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_sync_pages_through_log() {
+        let config = || Config {
+            max_sync_events: 2,
+            ..test_config()
+        };
+        let a = Arc::new(Server::new(config()).unwrap());
+        let b = Arc::new(Server::new(config()).unwrap());
+        let world = WorldId(a.world_id());
+        for seed in 1..=5u8 {
+            let event = EventSigner::from_seed(&[seed; 32])
+                .sign(EventBody::RuleEndorsement(RuleEndorsementEvent {
+                    world,
+                    epoch_id: 1,
+                    rule_bundle_hash: [seed; 32],
+                    weight: 1.0,
+                    signer_transport_pubkey: vec![],
+                    signature: vec![],
+                }))
+                .unwrap();
+            a.event_log.append(event).unwrap();
+        }
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let acceptor = a.clone();
+        tokio::spawn(async move {
+            let (stream, from) = listener.accept().await.unwrap();
+            let _ = acceptor.handle_connection(stream, from, false).await;
+        });
+        b.connect(addr).await.unwrap();
+
+        // The sync on connect continues page by page, well before the 30s
+        // sync interval
+        for _ in 0..200 {
+            if b.stats().event_count == 5 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(b.stats().event_count, 5);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_missing_dependencies() {
+        let a = Arc::new(Server::new(test_config()).unwrap());
+        let b = Arc::new(Server::new(test_config()).unwrap());
+        let _fetch_b = b.spawn_fetch_task();
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let acceptor = a.clone();
+        tokio::spawn(async move {
+            let (stream, from) = listener.accept().await.unwrap();
+            let _ = acceptor.handle_connection(stream, from, false).await;
+        });
+        b.connect(addr).await.unwrap();
+        // Let the initial sync finish before `a` gains events
+        tokio::time::sleep(Duration::from_millis(200)).await;
+
+        // Only `a` holds the disputed pair; no push or periodic sync runs
+        let world = WorldId(a.world_id());
+        let epoch_id = a.event_log.current_epoch();
+        let endorse = |seed: u8| {
+            EventSigner::from_seed(&[seed; 32])
+                .sign(EventBody::RuleEndorsement(RuleEndorsementEvent {
+                    world,
+                    epoch_id,
+                    rule_bundle_hash: [seed; 32],
+                    weight: 1.0,
+                    signer_transport_pubkey: vec![],
+                    signature: vec![],
+                }))
+                .unwrap()
+        };
+        let (event_a, event_b) = (endorse(1), endorse(2));
+        a.event_log.append(event_a.clone()).unwrap();
+        a.event_log.append(event_b.clone()).unwrap();
+
+        let dispute = EventSigner::from_seed(&[3; 32])
+            .sign(EventBody::Dispute(DisputeEvent {
+                world,
+                epoch_id,
+                event_a: event_a.event_id,
+                event_b: event_b.event_id,
+                reason: "test".to_string(),
+                disputer_transport_pubkey: vec![],
+                signature: vec![],
+            }))
+            .unwrap();
+        let outcome = b.event_log.merge_checked(dispute.clone(), [3; 32]).unwrap();
+        assert!(matches!(outcome, MergeOutcome::Quarantined(_)));
+
+        // The quarantine scan wants the pair, `a` returns it, and the
+        // dispute is released
+        for _ in 0..300 {
+            if b.event_log.has_event(&dispute.event_id).unwrap() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert!(b.event_log.has_event(&event_a.event_id).unwrap());
+        assert!(b.event_log.has_event(&event_b.event_id).unwrap());
+        assert!(b.event_log.has_event(&dispute.event_id).unwrap());
+        assert_eq!(b.fetch.wanted_count(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_host_routes_worlds() {
+        use crate::api::ApiClient;
+        use crate::host::Host;
+
+        let config = test_config();
+        let data_dir = config.data_dir.clone();
+        let worlds = config.data_dir.join("worlds.json");
+        std::fs::write(&worlds, r#"[{"world_phrase": "second world phrase"}]"#).unwrap();
+        let host = Arc::new(
+            Host::new(Config {
+                worlds: Some(worlds),
+                ..config
+            })
+            .unwrap(),
+        );
+        let ids = host.world_ids();
+        assert_eq!(ids.len(), 2);
+        // Hosted worlds are stored by world ID, not phrase
+        let stored: Vec<_> = std::fs::read_dir(data_dir.join("worlds"))
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect();
+        assert_eq!(stored.len(), 1);
+        assert!(ids
+            .iter()
+            .any(|id| stored[0] == std::ffi::OsStr::new(&hex::encode(id.0))));
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let api_listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let api_addr = api_listener.local_addr().unwrap();
+        let serving = host.clone();
+        let run = tokio::spawn(async move { serving.serve(listener, api_listener).await });
+
+        // One peer per world dials the shared listener
+        let first = Server::new(test_config()).unwrap();
+        let second = Server::new(Config {
+            world_phrase: "second world phrase".to_string(),
+            ..test_config()
+        })
+        .unwrap();
+        first.connect(addr).await.unwrap();
+        second.connect(addr).await.unwrap();
+        for (world, peer) in ids.iter().zip([&first, &second]) {
+            assert_eq!(WorldId(peer.world_id()), *world);
+            let hosted = host.world(world).unwrap();
+            for _ in 0..100 {
+                if hosted.stats().peer_count == 1 {
+                    break;
+                }
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+            assert_eq!(hosted.stats().peer_count, 1);
+            assert_eq!(peer.stats().peer_count, 1);
+        }
+
+        // API connections start in the primary world and can switch
+        let mut api = ApiClient::connect(api_addr).await.unwrap();
+        assert_eq!(api.worlds().await.unwrap(), ids);
+        assert_eq!(api.status().await.unwrap().world, ids[0]);
+        assert_eq!(api.select_world(ids[1]).await.unwrap().world, ids[1]);
+        assert_eq!(api.status().await.unwrap().world, ids[1]);
+        assert!(api.select_world(WorldId([0; 32])).await.is_err());
+
+        host.shutdown();
+        run.await.unwrap().unwrap();
+    }
+
+    #[test]
+    fn test_conflicting_attestations_disputed() {
+        let server = Server::new(test_config()).unwrap();
+        let world = WorldId(server.world_id());
+        let mut findings = Vec::new();
+        for (seed, success_rate) in [(1u8, 0.95), (2, 0.1)] {
+            let event = EventSigner::from_seed(&[seed; 32])
+                .sign(EventBody::Attestation(BehaviorAttestation {
+                    attestation_id: AttestationId([seed; 32]),
+                    world,
+                    epoch_id: 1,
+                    challenge_id: ChallengeId([1; 32]),
+                    target_ref: TargetRef([1; 32]),
+                    target_fah: None,
+                    metrics: MetricsVector {
+                        success_rate,
+                        refusal_consistency: 1.0,
+                        tool_fidelity: 1.0,
+                        latency_p50_ms: 100,
+                        latency_p95_ms: 200,
+                        robustness_score: 1.0,
+                        drift_indicator: 0.0,
+                        freshness: FreshnessStrength::None,
+                    },
+                    evidence_commitment: [0; 32],
+                    freshness_anchor: None,
+                    prober_transport_pubkey: vec![],
+                    signature: vec![],
+                }))
+                .unwrap();
+            server.event_log.append(event.clone()).unwrap();
+            findings.extend(server.disputes.observe(&event));
+        }
+        assert_eq!(findings.len(), 1);
+
+        let before = server.stats().event_count;
+        server.publish_dispute(findings[0].to_dispute(world));
+        assert_eq!(server.stats().event_count, before + 1);
+        let disputes = server
+            .event_log
+            .query(&EventQuery {
+                event_type: Some(EventType::Dispute),
+                ..EventQuery::default()
+            })
+            .unwrap();
+        let EventBody::Dispute(dispute) = &disputes.events[0].body else {
+            panic!("expected a dispute");
+        };
+        assert_eq!(dispute.disputer_transport_pubkey, server.public_key().to_vec());
+    }
+
+    #[test]
+    fn test_follow_fork_on_restart() {
+        let config = test_config();
+        let server = Server::new(config.clone()).unwrap();
+        let world = WorldId(server.world_id());
+        let current = server.event_log.current_epoch();
+        // Only members' endorsements count
+        let proof = server.membership.phrase_proof(&[0; 32]);
+        for seed in 1..=7u8 {
+            let signer = EventSigner::from_seed(&[seed; 32]);
+            let roles = PeerRoles {
+                gossipd: true,
+                ..Default::default()
+            };
+            server
+                .membership
+                .admit_peer(signer.public_key(), &[0; 32], &proof, roles)
+                .unwrap();
+            for epoch_id in current - 2..=current {
+                let event = signer
+                    .sign(EventBody::RuleEndorsement(RuleEndorsementEvent {
+                        world,
+                        epoch_id,
+                        rule_bundle_hash: [9; 32],
+                        weight: 1.0,
+                        signer_transport_pubkey: vec![],
+                        signature: vec![],
+                    }))
+                    .unwrap();
+                server.event_log.append(event.clone()).unwrap();
+                server.endorsements.record(&event);
+            }
+        }
+        let activated = server.endorsements.evaluate(current).unwrap();
+        assert_eq!(activated.len(), 1);
+        assert_ne!(activated[0].successor, world);
+        server.endorsements.choose(Some([9; 32])).unwrap();
+        drop(server);
+
+        let server = Server::new(config.clone()).unwrap();
+        assert_eq!(server.world_id(), activated[0].successor.0);
+        assert_eq!(server.endorsements.activations(), activated);
+
+        // The old world's events stay behind and are not synced as the new
+        // world's
+        assert!(config.fork_dir(&activated[0].successor).exists());
+        assert_eq!(server.stats().event_count, 0);
+        let delta = server.event_log.compute_delta(&[], 100).unwrap();
+        assert!(delta.events.is_empty());
+    }
+
+    #[test]
+    fn test_checkpoint_bootstrap() {
+        let a = Server::new(test_config()).unwrap();
+        let world = WorldId(a.world_id());
+        let signer = EventSigner::from_seed(&[42; 32]);
+        for epoch_id in 1..=3 {
+            let event = signer
+                .sign(EventBody::RuleEndorsement(RuleEndorsementEvent {
+                    world,
+                    epoch_id,
+                    rule_bundle_hash: [0; 32],
+                    weight: 1.0,
+                    signer_transport_pubkey: vec![],
+                    signature: vec![],
+                }))
+                .unwrap();
+            a.event_log.append(event).unwrap();
+        }
+        let path = a.write_checkpoint().unwrap();
+
+        // Not signed by a trusted member
+        let untrusted = Server::new(test_config()).unwrap();
+        assert!(untrusted.import_checkpoint(&path).is_err());
+        assert_eq!(untrusted.stats().event_count, 0);
+
+        let mut config = test_config();
+        config.trusted_checkpoint_signers = vec![a.public_key()];
+        let b = Server::new(config).unwrap();
+        let stats = b.import_checkpoint(&path).unwrap();
+        assert_eq!(stats.imported, 3);
+        assert_eq!(b.stats().event_count, 3);
+        // Sync with A resumes after the checkpoint
+        let cursor = b.event_log.get_version_vector();
+        assert!(cursor.contains(&VersionVectorEntry {
+            replica_id: a.public_key(),
+            counter: 3,
+        }));
+        let delta = a.event_log.compute_delta(&cursor, 100).unwrap();
+        assert!(delta.events.is_empty());
+
+        // Re-importing is a no-op
+        assert_eq!(b.import_checkpoint(&path).unwrap().skipped, 3);
+
+        // A threshold of two needs a co-signer
+        let mut config = test_config();
+        config.trusted_checkpoint_signers = vec![a.public_key(), b.public_key()];
+        config.checkpoint_threshold = 2;
+        let c = Server::new(config).unwrap();
+        assert!(c.import_checkpoint(&path).is_err());
+        assert_eq!(b.cosign_checkpoint(&path).unwrap(), 2);
+        assert_eq!(c.import_checkpoint(&path).unwrap().imported, 3);
+    }
 }
@@ -1,6 +1,11 @@
-//! Persistent storage using sled
+//! Typed storage for gossipd over a pluggable key-value backend
 
-use sled::Db;
+use crate::backend::{MemoryBackend, SledBackend, StorageBackend, Tree};
+use crate::index::{self, IndexKind, INDEX_VERSION};
+use crate::membership::{AuditRecord, BanRecord, Member};
+use crate::peer_book::PeerRecord;
+use crate::quarantine::QuarantineEntry;
+use std::ops::Bound;
 use std::path::Path;
 use terrain_gossip_core::types::*;
 use thiserror::Error;
@@ -18,48 +23,75 @@ pub enum StorageError {
     DescriptorNotFound(String),
 }
 
-/// Storage backend for gossipd
+/// Storage for gossipd
+///
+/// Encodes events, descriptors, version vectors, metadata, peers, the
+/// quarantine and secondary indexes into the trees of a `StorageBackend`.
 pub struct Storage {
-    db: Db,
-    /// Event tree: event_id -> Event
-    events: sled::Tree,
-    /// Descriptor tree: descriptor_id -> ProviderDescriptor
-    descriptors: sled::Tree,
-    /// Version vector tree: replica_id -> counter
-    version_vectors: sled::Tree,
-    /// Metadata tree: key -> value
-    metadata: sled::Tree,
+    backend: Box<dyn StorageBackend>,
 }
 
+/// Audit records kept before the oldest are dropped
+const MAX_AUDIT_RECORDS: usize = 10_000;
+
+/// Metadata key holding the index format version
+const INDEX_VERSION_KEY: &str = "index_version";
+
 impl Storage {
-    /// Open storage at the given path
+    /// Open sled-backed storage at the given path
     pub fn open(path: impl AsRef<Path>) -> Result<Self, StorageError> {
-        let db = sled::open(path)?;
-        let events = db.open_tree("events")?;
-        let descriptors = db.open_tree("descriptors")?;
-        let version_vectors = db.open_tree("version_vectors")?;
-        let metadata = db.open_tree("metadata")?;
-
-        Ok(Self {
-            db,
-            events,
-            descriptors,
-            version_vectors,
-            metadata,
-        })
-    }
-
-    /// Store an event
+        Self::with_backend(Box::new(SledBackend::open(path)?))
+    }
+
+    /// Volatile in-memory storage
+    pub fn memory() -> Self {
+        Self::with_backend(Box::new(MemoryBackend::new()))
+            .expect("in-memory storage cannot fail")
+    }
+
+    /// Storage over any backend
+    pub fn with_backend(backend: Box<dyn StorageBackend>) -> Result<Self, StorageError> {
+        let storage = Self { backend };
+        storage.ensure_indexes()?;
+        Ok(storage)
+    }
+
+    /// Rebuild secondary indexes if they predate the current format
+    fn ensure_indexes(&self) -> Result<(), StorageError> {
+        let version = self
+            .backend
+            .get(Tree::Metadata, INDEX_VERSION_KEY.as_bytes())?;
+        if version.as_deref() == Some(&[INDEX_VERSION][..]) {
+            return Ok(());
+        }
+        for kind in IndexKind::ALL {
+            self.backend.clear(Tree::Index(kind))?;
+        }
+        for result in self.all_events() {
+            self.index_event(&result?)?;
+        }
+        self.backend
+            .insert(Tree::Metadata, INDEX_VERSION_KEY.as_bytes(), &[INDEX_VERSION])?;
+        Ok(())
+    }
+
+    fn index_event(&self, event: &Event) -> Result<(), StorageError> {
+        for (kind, key) in index::index_keys(event) {
+            self.backend.insert(Tree::Index(kind), &key, &[])?;
+        }
+        Ok(())
+    }
+
+    /// Store an event and its index entries
     pub fn put_event(&self, event: &Event) -> Result<(), StorageError> {
-        let key = event.event_id.0;
         let value = postcard::to_allocvec(event)?;
-        self.events.insert(key, value)?;
-        Ok(())
+        self.backend.insert(Tree::Events, &event.event_id.0, &value)?;
+        self.index_event(event)
     }
 
     /// Get an event by ID
     pub fn get_event(&self, event_id: &EventId) -> Result<Option<Event>, StorageError> {
-        match self.events.get(event_id.0)? {
+        match self.backend.get(Tree::Events, &event_id.0)? {
             Some(bytes) => {
                 let event: Event = postcard::from_bytes(&bytes)?;
                 Ok(Some(event))
@@ -70,29 +102,190 @@ impl Storage {
 
     /// Check if event exists
     pub fn has_event(&self, event_id: &EventId) -> Result<bool, StorageError> {
-        Ok(self.events.contains_key(event_id.0)?)
+        self.backend.contains(Tree::Events, &event_id.0)
     }
 
     /// Get all events (for iteration)
     pub fn all_events(&self) -> impl Iterator<Item = Result<Event, StorageError>> + '_ {
-        self.events.iter().map(|result| {
-            let (_, bytes) = result?;
-            let event: Event = postcard::from_bytes(&bytes)?;
-            Ok(event)
-        })
+        self.backend
+            .range(Tree::Events, Bound::Unbounded, Bound::Unbounded)
+            .map(|result| {
+                let (_, bytes) = result?;
+                let event: Event = postcard::from_bytes(&bytes)?;
+                Ok(event)
+            })
     }
 
     /// Count events
     pub fn event_count(&self) -> usize {
-        self.events.len()
+        self.backend.len(Tree::Events)
+    }
+
+    /// Delete an event and its index entries
+    pub fn remove_event(&self, event_id: &EventId) -> Result<(), StorageError> {
+        if let Some(bytes) = self.backend.remove(Tree::Events, &event_id.0)? {
+            let event: Event = postcard::from_bytes(&bytes)?;
+            for (kind, key) in index::index_keys(&event) {
+                self.backend.remove(Tree::Index(kind), &key)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Iterate index keys in `lower..=upper`, starting after `after` if given
+    pub fn scan_index(
+        &self,
+        kind: IndexKind,
+        lower: Vec<u8>,
+        upper: Vec<u8>,
+        after: Option<Vec<u8>>,
+    ) -> impl Iterator<Item = Result<Vec<u8>, StorageError>> + '_ {
+        let start = match after {
+            Some(after) if after >= lower => Bound::Excluded(after.min(upper.clone())),
+            _ => Bound::Included(lower),
+        };
+        self.backend
+            .range(Tree::Index(kind), start, Bound::Included(upper))
+            .map(|result| Ok(result?.0))
+    }
+
+    /// Whether any index entry starts with `prefix`
+    pub fn has_index_prefix(&self, kind: IndexKind, prefix: &[u8]) -> Result<bool, StorageError> {
+        Ok(self
+            .backend
+            .scan_prefix(Tree::Index(kind), prefix)
+            .next()
+            .transpose()?
+            .is_some())
+    }
+
+    /// Store a quarantine entry and register the dependency it waits on
+    pub fn put_quarantined(&self, entry: &QuarantineEntry) -> Result<(), StorageError> {
+        let event_id = entry.event.event_id;
+        self.backend
+            .insert(Tree::Quarantine, &event_id.0, &postcard::to_allocvec(entry)?)?;
+        if let Some(dependency) = entry.reason.dependency() {
+            let mut key = dependency.key().to_vec();
+            key.extend_from_slice(&event_id.0);
+            self.backend.insert(Tree::QuarantineDeps, &key, &[])?;
+        }
+        Ok(())
+    }
+
+    /// Get a quarantine entry
+    pub fn get_quarantined(&self, event_id: &EventId) -> Result<Option<QuarantineEntry>, StorageError> {
+        match self.backend.get(Tree::Quarantine, &event_id.0)? {
+            Some(bytes) => Ok(Some(postcard::from_bytes(&bytes)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Remove a quarantine entry and its dependency registration
+    pub fn remove_quarantined(
+        &self,
+        event_id: &EventId,
+    ) -> Result<Option<QuarantineEntry>, StorageError> {
+        let Some(bytes) = self.backend.remove(Tree::Quarantine, &event_id.0)? else {
+            return Ok(None);
+        };
+        let entry: QuarantineEntry = postcard::from_bytes(&bytes)?;
+        if let Some(dependency) = entry.reason.dependency() {
+            let mut key = dependency.key().to_vec();
+            key.extend_from_slice(&event_id.0);
+            self.backend.remove(Tree::QuarantineDeps, &key)?;
+        }
+        Ok(Some(entry))
+    }
+
+    /// Quarantined event IDs waiting on a dependency key
+    pub fn quarantine_waiting(&self, dependency_key: &[u8]) -> Result<Vec<EventId>, StorageError> {
+        let mut waiting = Vec::new();
+        for result in self.backend.scan_prefix(Tree::QuarantineDeps, dependency_key) {
+            let (key, _) = result?;
+            if let Some(event_id) = index::event_id_from_key(&key) {
+                waiting.push(event_id);
+            }
+        }
+        Ok(waiting)
+    }
+
+    /// Get all quarantine entries
+    pub fn all_quarantined(&self) -> Result<Vec<QuarantineEntry>, StorageError> {
+        let mut entries = Vec::new();
+        for result in self
+            .backend
+            .range(Tree::Quarantine, Bound::Unbounded, Bound::Unbounded)
+        {
+            let (_, bytes) = result?;
+            entries.push(postcard::from_bytes(&bytes)?);
+        }
+        Ok(entries)
+    }
+
+    /// Count quarantine entries
+    pub fn quarantine_count(&self) -> usize {
+        self.backend.len(Tree::Quarantine)
+    }
+
+    /// Record the local sequence number an event entered the log at
+    pub fn put_seq(&self, seq: u64, event_id: &EventId) -> Result<(), StorageError> {
+        self.backend
+            .insert(Tree::EventSeq, &seq.to_be_bytes(), &event_id.0)
+    }
+
+    /// Highest sequence number assigned so far (0 if none)
+    pub fn last_seq(&self) -> Result<u64, StorageError> {
+        Ok(self
+            .backend
+            .last(Tree::EventSeq)?
+            .and_then(|(key, _)| key.as_slice().try_into().ok())
+            .map(u64::from_be_bytes)
+            .unwrap_or(0))
+    }
+
+    /// Sequence entries after `seq`, in order
+    ///
+    /// Entries of pruned events are kept; callers skip IDs that no longer
+    /// resolve to an event.
+    pub fn seq_after(
+        &self,
+        seq: u64,
+    ) -> impl Iterator<Item = Result<(u64, EventId), StorageError>> + '_ {
+        let lower = match seq.checked_add(1) {
+            Some(next) => Bound::Included(next.to_be_bytes().to_vec()),
+            None => Bound::Excluded(u64::MAX.to_be_bytes().to_vec()),
+        };
+        self.backend
+            .range(Tree::EventSeq, lower, Bound::Unbounded)
+            .map(|result| {
+                let (key, value) = result?;
+                let seq = u64::from_be_bytes(key.as_slice().try_into().unwrap_or([0; 8]));
+                let event_id = EventId(value.as_slice().try_into().unwrap_or([0; 32]));
+                Ok((seq, event_id))
+            })
+    }
+
+    /// Record that an event was pruned so it is not merged again
+    pub fn put_tombstone(&self, event_id: &EventId, epoch_id: u64) -> Result<(), StorageError> {
+        self.backend
+            .insert(Tree::Tombstones, &event_id.0, &epoch_id.to_le_bytes())
+    }
+
+    /// Check whether an event was pruned
+    pub fn has_tombstone(&self, event_id: &EventId) -> Result<bool, StorageError> {
+        self.backend.contains(Tree::Tombstones, &event_id.0)
+    }
+
+    /// Count tombstones
+    pub fn tombstone_count(&self) -> usize {
+        self.backend.len(Tree::Tombstones)
     }
 
     /// Store a descriptor
     pub fn put_descriptor(&self, descriptor: &ProviderDescriptor) -> Result<(), StorageError> {
-        let key = descriptor.descriptor_id.0;
         let value = postcard::to_allocvec(descriptor)?;
-        self.descriptors.insert(key, value)?;
-        Ok(())
+        self.backend
+            .insert(Tree::Descriptors, &descriptor.descriptor_id.0, &value)
     }
 
     /// Get a descriptor by ID
@@ -100,7 +293,7 @@ impl Storage {
         &self,
         descriptor_id: &DescriptorId,
     ) -> Result<Option<ProviderDescriptor>, StorageError> {
-        match self.descriptors.get(descriptor_id.0)? {
+        match self.backend.get(Tree::Descriptors, &descriptor_id.0)? {
             Some(bytes) => {
                 let desc: ProviderDescriptor = postcard::from_bytes(&bytes)?;
                 Ok(Some(desc))
@@ -111,9 +304,9 @@ impl Storage {
 
     /// Get version vector entry
     pub fn get_version(&self, replica_id: &[u8; 32]) -> Result<u64, StorageError> {
-        match self.version_vectors.get(replica_id)? {
+        match self.backend.get(Tree::VersionVectors, replica_id)? {
             Some(bytes) => {
-                let counter = u64::from_le_bytes(bytes.as_ref().try_into().unwrap_or([0; 8]));
+                let counter = u64::from_le_bytes(bytes.as_slice().try_into().unwrap_or([0; 8]));
                 Ok(counter)
             }
             None => Ok(0),
@@ -122,18 +315,20 @@ impl Storage {
 
     /// Update version vector entry
     pub fn put_version(&self, replica_id: &[u8; 32], counter: u64) -> Result<(), StorageError> {
-        self.version_vectors
-            .insert(replica_id, &counter.to_le_bytes())?;
-        Ok(())
+        self.backend
+            .insert(Tree::VersionVectors, replica_id, &counter.to_le_bytes())
     }
 
     /// Get all version vector entries
     pub fn get_all_versions(&self) -> Result<Vec<VersionVectorEntry>, StorageError> {
         let mut entries = Vec::new();
-        for result in self.version_vectors.iter() {
+        for result in self
+            .backend
+            .range(Tree::VersionVectors, Bound::Unbounded, Bound::Unbounded)
+        {
             let (key, value) = result?;
-            let replica_id: [u8; 32] = key.as_ref().try_into().unwrap_or([0; 32]);
-            let counter = u64::from_le_bytes(value.as_ref().try_into().unwrap_or([0; 8]));
+            let replica_id: [u8; 32] = key.as_slice().try_into().unwrap_or([0; 32]);
+            let counter = u64::from_le_bytes(value.as_slice().try_into().unwrap_or([0; 8]));
             entries.push(VersionVectorEntry { replica_id, counter });
         }
         Ok(entries)
@@ -141,19 +336,147 @@ impl Storage {
 
     /// Store metadata
     pub fn put_metadata(&self, key: &str, value: &[u8]) -> Result<(), StorageError> {
-        self.metadata.insert(key, value)?;
-        Ok(())
+        self.backend.insert(Tree::Metadata, key.as_bytes(), value)
     }
 
     /// Get metadata
     pub fn get_metadata(&self, key: &str) -> Result<Option<Vec<u8>>, StorageError> {
-        Ok(self.metadata.get(key)?.map(|v| v.to_vec()))
+        self.backend.get(Tree::Metadata, key.as_bytes())
+    }
+
+    /// Store a peer book record
+    pub fn put_peer(&self, record: &PeerRecord) -> Result<(), StorageError> {
+        let value = postcard::to_allocvec(record)?;
+        self.backend
+            .insert(Tree::PeerBook, &record.transport_pubkey, &value)
+    }
+
+    /// Remove a peer book record
+    pub fn remove_peer(&self, pubkey: &[u8; 32]) -> Result<(), StorageError> {
+        self.backend.remove(Tree::PeerBook, pubkey)?;
+        Ok(())
+    }
+
+    /// Get all peer book records
+    pub fn all_peers(&self) -> Result<Vec<PeerRecord>, StorageError> {
+        let mut records = Vec::new();
+        for result in self
+            .backend
+            .range(Tree::PeerBook, Bound::Unbounded, Bound::Unbounded)
+        {
+            let (_, bytes) = result?;
+            records.push(postcard::from_bytes(&bytes)?);
+        }
+        Ok(records)
+    }
+
+    /// Store a member record
+    pub fn put_member(&self, member: &Member) -> Result<(), StorageError> {
+        let value = postcard::to_allocvec(member)?;
+        self.backend.insert(Tree::Members, &member.pubkey, &value)
+    }
+
+    /// Remove a member record
+    pub fn remove_member(&self, pubkey: &[u8; 32]) -> Result<(), StorageError> {
+        self.backend.remove(Tree::Members, pubkey)?;
+        Ok(())
+    }
+
+    /// Get all member records
+    pub fn all_members(&self) -> Result<Vec<Member>, StorageError> {
+        let mut members = Vec::new();
+        for result in self
+            .backend
+            .range(Tree::Members, Bound::Unbounded, Bound::Unbounded)
+        {
+            let (_, bytes) = result?;
+            members.push(postcard::from_bytes(&bytes)?);
+        }
+        Ok(members)
+    }
+
+    /// Store a ban
+    pub fn put_ban(&self, ban: &BanRecord) -> Result<(), StorageError> {
+        let value = postcard::to_allocvec(ban)?;
+        self.backend.insert(Tree::Bans, &ban.pubkey, &value)
+    }
+
+    /// Remove a ban
+    pub fn remove_ban(&self, pubkey: &[u8; 32]) -> Result<(), StorageError> {
+        self.backend.remove(Tree::Bans, pubkey)?;
+        Ok(())
+    }
+
+    /// Get all bans
+    pub fn all_bans(&self) -> Result<Vec<BanRecord>, StorageError> {
+        let mut bans = Vec::new();
+        for result in self
+            .backend
+            .range(Tree::Bans, Bound::Unbounded, Bound::Unbounded)
+        {
+            let (_, bytes) = result?;
+            bans.push(postcard::from_bytes(&bytes)?);
+        }
+        Ok(bans)
+    }
+
+    /// Append to the membership audit trail, dropping the oldest records
+    /// beyond `MAX_AUDIT_RECORDS`
+    pub fn append_audit(&self, record: &AuditRecord) -> Result<(), StorageError> {
+        let next = self
+            .backend
+            .last(Tree::MembershipAudit)?
+            .and_then(|(key, _)| key.as_slice().try_into().ok())
+            .map(|seq| u64::from_be_bytes(seq) + 1)
+            .unwrap_or(0);
+        self.backend.insert(
+            Tree::MembershipAudit,
+            &next.to_be_bytes(),
+            &postcard::to_allocvec(record)?,
+        )?;
+
+        let excess = self
+            .backend
+            .len(Tree::MembershipAudit)
+            .saturating_sub(MAX_AUDIT_RECORDS);
+        let oldest: Vec<Vec<u8>> = self
+            .backend
+            .range(Tree::MembershipAudit, Bound::Unbounded, Bound::Unbounded)
+            .take(excess)
+            .map(|result| result.map(|(key, _)| key))
+            .collect::<Result<_, _>>()?;
+        for key in oldest {
+            self.backend.remove(Tree::MembershipAudit, &key)?;
+        }
+        Ok(())
+    }
+
+    /// Membership audit trail, oldest first
+    pub fn audit_records(&self) -> Result<Vec<AuditRecord>, StorageError> {
+        let mut records = Vec::new();
+        for result in self
+            .backend
+            .range(Tree::MembershipAudit, Bound::Unbounded, Bound::Unbounded)
+        {
+            let (_, bytes) = result?;
+            records.push(postcard::from_bytes(&bytes)?);
+        }
+        Ok(records)
     }
 
     /// Flush all pending writes
     pub fn flush(&self) -> Result<(), StorageError> {
-        self.db.flush()?;
-        Ok(())
+        self.backend.flush()
+    }
+
+    /// Compact after bulk deletes.
+    ///
+    /// sled reclaims segments freed by removals in the background once they
+    /// are flushed; this forces the flush and returns the resulting on-disk
+    /// size in bytes.
+    pub fn compact(&self) -> Result<u64, StorageError> {
+        self.backend.flush()?;
+        self.backend.size_on_disk()
     }
 }
 
@@ -200,4 +523,64 @@ mod tests {
         storage.put_version(&replica_id, 42).unwrap();
         assert_eq!(storage.get_version(&replica_id).unwrap(), 42);
     }
+
+    /// Typed behavior every backend must preserve
+    fn check_conformance(storage: &Storage) {
+        let event = |id: u8, epoch_id: u64| Event {
+            event_id: EventId([id; 32]),
+            world: WorldId([0; 32]),
+            epoch_id,
+            event_type: EventType::RuleEndorsement,
+            body: EventBody::RuleEndorsement(RuleEndorsementEvent {
+                world: WorldId([0; 32]),
+                epoch_id,
+                rule_bundle_hash: [0; 32],
+                weight: 1.0,
+                signer_transport_pubkey: vec![id; 32],
+                signature: vec![],
+            }),
+        };
+
+        for id in 1..=3 {
+            storage.put_event(&event(id, id as u64)).unwrap();
+            storage.put_seq(id as u64, &EventId([id; 32])).unwrap();
+        }
+        assert_eq!(storage.event_count(), 3);
+        assert_eq!(storage.all_events().count(), 3);
+        assert!(storage.has_index_prefix(IndexKind::Signer, &[2; 32]).unwrap());
+
+        let seqs: Vec<u64> = storage.seq_after(1).map(|r| r.unwrap().0).collect();
+        assert_eq!(seqs, vec![2, 3]);
+        assert_eq!(storage.last_seq().unwrap(), 3);
+        assert_eq!(storage.seq_after(u64::MAX).count(), 0);
+
+        storage.remove_event(&EventId([2; 32])).unwrap();
+        assert!(!storage.has_event(&EventId([2; 32])).unwrap());
+        assert!(!storage.has_index_prefix(IndexKind::Signer, &[2; 32]).unwrap());
+        let (lower, upper) = index::epoch_range(0, u64::MAX);
+        assert_eq!(storage.scan_index(IndexKind::EpochType, lower, upper, None).count(), 2);
+
+        storage.put_tombstone(&EventId([2; 32]), 2).unwrap();
+        assert!(storage.has_tombstone(&EventId([2; 32])).unwrap());
+
+        storage.put_version(&[7; 32], 5).unwrap();
+        storage.put_version(&[7; 32], 6).unwrap();
+        assert_eq!(storage.get_all_versions().unwrap().len(), 1);
+        assert_eq!(storage.get_version(&[7; 32]).unwrap(), 6);
+
+        storage.put_metadata("key", b"value").unwrap();
+        assert_eq!(storage.get_metadata("key").unwrap(), Some(b"value".to_vec()));
+        assert_eq!(storage.get_metadata("missing").unwrap(), None);
+        storage.flush().unwrap();
+    }
+
+    #[test]
+    fn test_backend_conformance() {
+        let dir = tempdir().unwrap();
+        check_conformance(&Storage::open(dir.path()).unwrap());
+        check_conformance(&Storage::memory());
+        check_conformance(
+            &Storage::with_backend(Box::new(SledBackend::temporary().unwrap())).unwrap(),
+        );
+    }
 }
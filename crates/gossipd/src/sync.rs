@@ -1,7 +1,16 @@
 //! Delta-state CRDT synchronization protocol
+//!
+//! Each node numbers the events it stores in a local sequence. A node's
+//! version vector holds, per peer replica, how far into that replica's
+//! sequence it has synced, and its own head. A sync request carries the
+//! requester's vector; the responder sends the events after the
+//! requester's entry for it, and its own entry in the response's vector is
+//! where that page ends.
 
-use crate::event_log::{EventLog, EventLogError};
+use crate::event_log::{EventLog, EventLogError, MergeOutcome};
+use crate::quarantine::QuarantineReason;
 use parking_lot::RwLock;
+use std::collections::HashSet;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 use terrain_gossip_core::types::*;
@@ -34,12 +43,25 @@ pub struct DeltaSyncRequest {
 pub struct DeltaSyncResponse {
     /// Events the sender has that receiver lacks
     pub events: Vec<Event>,
-    /// Sender's current version vector
+    /// Sender's version vector, its own entry at the last event sent
     pub version_vector: Vec<VersionVectorEntry>,
     /// Whether there are more events available
     pub has_more: bool,
 }
 
+/// Result of applying a delta sync response
+#[derive(Debug, Clone, Default)]
+pub struct SyncOutcome {
+    /// Events stored
+    pub merged: usize,
+    /// Events already pruned or outside retention
+    pub expired: usize,
+    /// Events for another world
+    pub world_mismatches: usize,
+    /// Why events were quarantined
+    pub quarantined: Vec<QuarantineReason>,
+}
+
 /// Anti-entropy sync state for a peer
 #[derive(Debug)]
 pub struct PeerSyncState {
@@ -88,7 +110,7 @@ impl SyncManager {
     /// Register a peer for synchronization
     pub fn register_peer(&self, peer_id: [u8; 32]) {
         let mut peers = self.peers.write();
-        peers.entry(peer_id).or_insert_with(PeerSyncState::default);
+        peers.entry(peer_id).or_default();
     }
 
     /// Remove a peer from synchronization
@@ -99,19 +121,14 @@ impl SyncManager {
     /// Handle incoming sync request
     pub fn handle_request(&self, request: DeltaSyncRequest) -> Result<DeltaSyncResponse, SyncError> {
         // Compute delta based on peer's version vector
-        let events = self.event_log.compute_delta(&request.version_vector)?;
-        
-        let limited_events: Vec<Event> = events
-            .into_iter()
-            .take(request.max_events as usize)
-            .collect();
-
-        let has_more = limited_events.len() >= request.max_events as usize;
+        let delta = self
+            .event_log
+            .compute_delta(&request.version_vector, request.max_events as usize)?;
 
         Ok(DeltaSyncResponse {
-            events: limited_events,
-            version_vector: self.event_log.get_version_vector(),
-            has_more,
+            events: delta.events,
+            version_vector: self.event_log.version_vector_at(delta.cursor),
+            has_more: delta.has_more,
         })
     }
 
@@ -120,16 +137,30 @@ impl SyncManager {
         &self,
         peer_id: [u8; 32],
         response: DeltaSyncResponse,
-    ) -> Result<usize, SyncError> {
-        let mut merged_count = 0;
+    ) -> Result<SyncOutcome, SyncError> {
+        let mut outcome = SyncOutcome::default();
 
         // Merge each event
         for event in response.events {
-            if self.event_log.merge(event, peer_id)? {
-                merged_count += 1;
+            match self.event_log.merge_checked(event, peer_id) {
+                Ok(MergeOutcome::Merged) => outcome.merged += 1,
+                Ok(MergeOutcome::Duplicate) => {}
+                Ok(MergeOutcome::Expired) => outcome.expired += 1,
+                Ok(MergeOutcome::Quarantined(reason)) => outcome.quarantined.push(reason),
+                Err(EventLogError::WorldMismatch) => outcome.world_mismatches += 1,
+                Err(e) => return Err(e.into()),
             }
         }
 
+        // Resume after this page next time
+        if let Some(entry) = response
+            .version_vector
+            .iter()
+            .find(|entry| entry.replica_id == peer_id)
+        {
+            self.event_log.advance_version(peer_id, entry.counter)?;
+        }
+
         // Update peer state
         {
             let mut peers = self.peers.write();
@@ -141,7 +172,7 @@ impl SyncManager {
             }
         }
 
-        Ok(merged_count)
+        Ok(outcome)
     }
 
     /// Create a sync request for a peer
@@ -163,6 +194,20 @@ impl SyncManager {
             .collect()
     }
 
+    /// Drop sync state for peers not synced within `max_idle`, except `keep`
+    pub fn prune_stale(&self, max_idle: Duration, keep: &HashSet<[u8; 32]>) -> Vec<[u8; 32]> {
+        let now = Instant::now();
+        let mut removed = Vec::new();
+        self.peers.write().retain(|id, state| {
+            let stale = !keep.contains(id) && now.duration_since(state.last_sync) >= max_idle;
+            if stale {
+                removed.push(*id);
+            }
+            !stale
+        });
+        removed
+    }
+
     /// Mark peer sync as failed
     pub fn mark_failure(&self, peer_id: &[u8; 32]) {
         let mut peers = self.peers.write();
@@ -222,6 +267,18 @@ mod tests {
         assert!(!manager.peers.read().contains_key(&peer_id));
     }
 
+    #[test]
+    fn test_prune_stale_peers() {
+        let (manager, _dir) = create_test_manager();
+        manager.register_peer([2; 32]);
+        manager.register_peer([3; 32]);
+
+        let keep: HashSet<[u8; 32]> = [[3; 32]].into_iter().collect();
+        let removed = manager.prune_stale(Duration::ZERO, &keep);
+        assert_eq!(removed, vec![[2; 32]]);
+        assert!(manager.peers.read().contains_key(&[3; 32]));
+    }
+
     #[test]
     fn test_sync_request_response() {
         let (manager, _dir) = create_test_manager();
@@ -233,4 +290,58 @@ mod tests {
         assert!(response.events.is_empty());
         assert!(!response.has_more);
     }
+
+    fn endorsement(id: u8) -> Event {
+        Event {
+            event_id: EventId([id; 32]),
+            world: WorldId([0; 32]),
+            epoch_id: 1,
+            event_type: EventType::RuleEndorsement,
+            body: EventBody::RuleEndorsement(RuleEndorsementEvent {
+                world: WorldId([0; 32]),
+                epoch_id: 1,
+                rule_bundle_hash: [id; 32],
+                weight: 1.0,
+                signer_transport_pubkey: vec![],
+                signature: vec![],
+            }),
+        }
+    }
+
+    #[test]
+    fn test_sync_resumes_from_version_vector() {
+        let (a, _dir_a) = create_test_manager();
+        let dir = tempdir().unwrap();
+        let log = Arc::new(EventLog::new(
+            Arc::new(Storage::open(dir.path()).unwrap()),
+            WorldId([0; 32]),
+            [2; 32],
+        ));
+        let b = SyncManager::new(log.clone(), Duration::from_secs(30), 2);
+        for id in 1..=3 {
+            a.event_log.append(endorsement(id)).unwrap();
+        }
+
+        // B pages through A's log, then asks for nothing it already has
+        let response = a.handle_request(b.create_request(&[1; 32])).unwrap();
+        assert_eq!(response.events.len(), 2);
+        assert!(response.has_more);
+        assert_eq!(b.handle_response([1; 32], response).unwrap().merged, 2);
+        let response = a.handle_request(b.create_request(&[1; 32])).unwrap();
+        assert_eq!(response.events.len(), 1);
+        assert!(!response.has_more);
+        b.handle_response([1; 32], response).unwrap();
+        assert!(a
+            .handle_request(b.create_request(&[1; 32]))
+            .unwrap()
+            .events
+            .is_empty());
+
+        // Only what A stored since is sent
+        a.event_log.append(endorsement(4)).unwrap();
+        let response = a.handle_request(b.create_request(&[1; 32])).unwrap();
+        assert_eq!(response.events.len(), 1);
+        assert_eq!(response.events[0].event_id, EventId([4; 32]));
+        assert_eq!(log.event_count(), 3);
+    }
 }
@@ -0,0 +1,316 @@
+//! Local inference API for co-located daemons
+//!
+//! routerd hands inference requests to infernode over a loopback TCP
+//! connection using the regular frame codec. Each `InferenceRequest` frame
+//! names the provider descriptor routerd chose and carries the client's
+//! request body unchanged; infernode answers with `InferenceResponse` frames:
+//! `Started` with the provider's HTTP status, the response body in chunks as
+//! the provider produces it, then `Done` or `Failed`. `NoRoute` instead
+//! means this node cannot reach the provider at all, which says nothing
+//! about the provider. A `RouteQuery` frame asks which providers the node
+//! can reach. A connection carries one request at a time.
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use terrain_gossip_core::types::ProviderDescriptor;
+use terrain_gossip_net::framing::{read_frame, write_frame, Frame, FrameError, FrameType};
+use thiserror::Error;
+use tokio::net::{TcpListener, TcpStream, ToSocketAddrs};
+use tokio::sync::mpsc;
+use tracing::{debug, error, warn};
+
+/// Response frames buffered per request
+const RESPONSE_QUEUE_DEPTH: usize = 64;
+
+/// Inference API errors
+#[derive(Debug, Error)]
+pub enum InferenceError {
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Frame error: {0}")]
+    Frame(#[from] FrameError),
+    #[error("Codec error: {0}")]
+    Codec(#[from] postcard::Error),
+    #[error("Inference failed: {0}")]
+    Remote(String),
+    #[error("No route to provider: {0}")]
+    NoRoute(String),
+    #[error("Unexpected frame: {0:?}")]
+    UnexpectedFrame(FrameType),
+    #[error("Unexpected response")]
+    UnexpectedResponse,
+    #[error("Connection closed")]
+    Closed,
+}
+
+/// OpenAI-compatible endpoint a request is for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum InferenceEndpoint {
+    ChatCompletions,
+    Completions,
+}
+
+impl InferenceEndpoint {
+    /// Path of the endpoint on a provider's backend
+    pub fn path(&self) -> &'static str {
+        match self {
+            Self::ChatCompletions => "/v1/chat/completions",
+            Self::Completions => "/v1/completions",
+        }
+    }
+}
+
+/// Request from a local client
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InferenceRequest {
+    /// Provider to send the request to
+    pub descriptor: ProviderDescriptor,
+    pub endpoint: InferenceEndpoint,
+    /// JSON request body, forwarded as-is
+    pub body: Vec<u8>,
+}
+
+/// Response frame for a request
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum InferenceResponse {
+    /// The provider accepted the request with this HTTP status
+    Started { status: u16 },
+    /// Next part of the response body
+    Chunk(Vec<u8>),
+    /// The response is complete
+    Done,
+    /// The request could not be completed
+    Failed(String),
+    /// This node has no route to the provider
+    NoRoute(String),
+}
+
+fn encode<T: Serialize>(frame_type: FrameType, message: &T) -> Result<Frame, InferenceError> {
+    Ok(Frame::new(frame_type, postcard::to_allocvec(message)?))
+}
+
+fn decode<T: DeserializeOwned>(frame: Frame, expected: FrameType) -> Result<T, InferenceError> {
+    if frame.frame_type != expected {
+        return Err(InferenceError::UnexpectedFrame(frame.frame_type));
+    }
+    Ok(postcard::from_bytes(&frame.payload)?)
+}
+
+/// Forwards a request, sending its response frames on the channel
+///
+/// Called once per request; it should spawn any work rather than block.
+pub type Forward = dyn Fn(InferenceRequest, mpsc::Sender<InferenceResponse>) + Send + Sync;
+
+/// Transport keys of the providers a node can reach
+pub type Reachable = dyn Fn() -> Vec<Vec<u8>> + Send + Sync;
+
+/// Serves the local inference API
+pub struct InferenceServer {
+    forward: Arc<Forward>,
+    reachable: Option<Arc<Reachable>>,
+}
+
+impl InferenceServer {
+    /// Create a server handing requests to `forward`
+    pub fn new(forward: Arc<Forward>) -> Self {
+        Self {
+            forward,
+            reachable: None,
+        }
+    }
+
+    /// Answer route queries with `reachable`; without it, every provider
+    /// may be reachable
+    pub fn with_reachable(mut self, reachable: Arc<Reachable>) -> Self {
+        self.reachable = Some(reachable);
+        self
+    }
+
+    /// Accept API connections
+    pub async fn serve(self: Arc<Self>, listener: TcpListener) {
+        loop {
+            match listener.accept().await {
+                Ok((stream, addr)) => {
+                    debug!("Inference client connected from {}", addr);
+                    let server = self.clone();
+                    tokio::spawn(async move {
+                        if let Err(e) = server.handle_connection(stream).await {
+                            warn!("Inference connection error from {}: {}", addr, e);
+                        }
+                    });
+                }
+                Err(e) => error!("Inference API accept error: {}", e),
+            }
+        }
+    }
+
+    async fn handle_connection(&self, stream: TcpStream) -> Result<(), InferenceError> {
+        let (mut reader, mut writer) = stream.into_split();
+
+        while let Some(frame) = read_frame(&mut reader).await? {
+            if frame.frame_type == FrameType::RouteQuery {
+                let reachable = self.reachable.as_ref().map(|reachable| reachable());
+                write_frame(&mut writer, encode(FrameType::RouteResponse, &reachable)?).await?;
+                continue;
+            }
+            let request: InferenceRequest = decode(frame, FrameType::InferenceRequest)?;
+            let (response_tx, mut response_rx) = mpsc::channel(RESPONSE_QUEUE_DEPTH);
+            (self.forward)(request, response_tx);
+
+            loop {
+                // A forwarder that stops without finishing has failed
+                let response = response_rx
+                    .recv()
+                    .await
+                    .unwrap_or_else(|| InferenceResponse::Failed("request abandoned".into()));
+                let last = matches!(
+                    response,
+                    InferenceResponse::Done
+                        | InferenceResponse::Failed(_)
+                        | InferenceResponse::NoRoute(_)
+                );
+                write_frame(
+                    &mut writer,
+                    encode(FrameType::InferenceResponse, &response)?,
+                )
+                .await?;
+                if last {
+                    break;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Client for the local inference API
+pub struct InferenceClient {
+    stream: TcpStream,
+}
+
+impl InferenceClient {
+    /// Connect to infernode's inference API address
+    pub async fn connect(addr: impl ToSocketAddrs) -> Result<Self, InferenceError> {
+        Ok(Self {
+            stream: TcpStream::connect(addr).await?,
+        })
+    }
+
+    /// Send a request, returning the provider's HTTP status
+    pub async fn infer(&mut self, request: &InferenceRequest) -> Result<u16, InferenceError> {
+        write_frame(
+            &mut self.stream,
+            encode(FrameType::InferenceRequest, request)?,
+        )
+        .await?;
+        match self.next().await? {
+            InferenceResponse::Started { status } => Ok(status),
+            InferenceResponse::Failed(reason) => Err(InferenceError::Remote(reason)),
+            InferenceResponse::NoRoute(reason) => Err(InferenceError::NoRoute(reason)),
+            _ => Err(InferenceError::UnexpectedResponse),
+        }
+    }
+
+    /// Transport keys of the providers the node can reach, or `None` if it
+    /// does not restrict them
+    pub async fn reachable(&mut self) -> Result<Option<Vec<Vec<u8>>>, InferenceError> {
+        write_frame(&mut self.stream, Frame::new(FrameType::RouteQuery, vec![])).await?;
+        let frame = read_frame(&mut self.stream)
+            .await?
+            .ok_or(InferenceError::Closed)?;
+        decode(frame, FrameType::RouteResponse)
+    }
+
+    /// Next part of the response body, or `None` once it is complete
+    pub async fn next_chunk(&mut self) -> Result<Option<Vec<u8>>, InferenceError> {
+        match self.next().await? {
+            InferenceResponse::Chunk(chunk) => Ok(Some(chunk)),
+            InferenceResponse::Done => Ok(None),
+            InferenceResponse::Failed(reason) => Err(InferenceError::Remote(reason)),
+            InferenceResponse::NoRoute(reason) => Err(InferenceError::NoRoute(reason)),
+            InferenceResponse::Started { .. } => Err(InferenceError::UnexpectedResponse),
+        }
+    }
+
+    async fn next(&mut self) -> Result<InferenceResponse, InferenceError> {
+        let frame = read_frame(&mut self.stream)
+            .await?
+            .ok_or(InferenceError::Closed)?;
+        decode(frame, FrameType::InferenceResponse)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use terrain_gossip_core::types::*;
+
+    fn descriptor() -> ProviderDescriptor {
+        ProviderDescriptor {
+            descriptor_id: DescriptorId([1; 32]),
+            unsigned: ProviderDescriptorUnsigned {
+                world: WorldId([0; 32]),
+                descriptor_epoch: 1,
+                contact_points: vec![],
+                capability: DescriptorCapability::Fah(Fah([2; 32])),
+            },
+            provider_transport_pubkey: vec![1; 32],
+            signature: vec![],
+        }
+    }
+
+    #[tokio::test]
+    async fn test_request_streams_back() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let forward: Arc<Forward> = Arc::new(|request: InferenceRequest, tx| {
+            tokio::spawn(async move {
+                if request.body.is_empty() {
+                    let _ = tx
+                        .send(InferenceResponse::Failed("empty body".into()))
+                        .await;
+                    return;
+                }
+                if request.descriptor.provider_transport_pubkey != [1; 32] {
+                    let _ = tx.send(InferenceResponse::NoRoute("remote".into())).await;
+                    return;
+                }
+                let _ = tx.send(InferenceResponse::Started { status: 200 }).await;
+                for part in request.body.chunks(2) {
+                    let _ = tx.send(InferenceResponse::Chunk(part.to_vec())).await;
+                }
+                let _ = tx.send(InferenceResponse::Done).await;
+            });
+        });
+        let reachable: Arc<Reachable> = Arc::new(|| vec![vec![1; 32]]);
+        let server = InferenceServer::new(forward).with_reachable(reachable);
+        tokio::spawn(Arc::new(server).serve(listener));
+
+        let mut client = InferenceClient::connect(addr).await.unwrap();
+        let mut request = InferenceRequest {
+            descriptor: descriptor(),
+            endpoint: InferenceEndpoint::Completions,
+            body: b"hello".to_vec(),
+        };
+        assert_eq!(client.infer(&request).await.unwrap(), 200);
+        let mut body = Vec::new();
+        while let Some(chunk) = client.next_chunk().await.unwrap() {
+            body.extend(chunk);
+        }
+        assert_eq!(body, b"hello");
+
+        // The connection carries further requests, including failed ones
+        assert_eq!(client.reachable().await.unwrap(), Some(vec![vec![1; 32]]));
+        request.descriptor.provider_transport_pubkey = vec![2; 32];
+        assert!(matches!(
+            client.infer(&request).await,
+            Err(InferenceError::NoRoute(_))
+        ));
+        request.body.clear();
+        assert!(matches!(
+            client.infer(&request).await,
+            Err(InferenceError::Remote(_))
+        ));
+    }
+}
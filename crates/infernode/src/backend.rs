@@ -0,0 +1,77 @@
+//! Exit to a provider's local inference backend
+//!
+//! A provider node ends circuits at its own OpenAI-compatible backend: the
+//! request body is posted to the endpoint's path under the backend URL and
+//! the response body is streamed back as it arrives.
+
+use crate::api::{InferenceRequest, InferenceResponse};
+use reqwest::header::CONTENT_TYPE;
+use tokio::sync::mpsc;
+use tracing::debug;
+
+/// HTTP client for the local backend
+#[derive(Clone)]
+pub struct Backend {
+    client: reqwest::Client,
+    base_url: String,
+}
+
+impl Backend {
+    /// Backend at `base_url`, e.g. `http://127.0.0.1:8000`
+    pub fn new(base_url: &str) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+        }
+    }
+
+    /// Forward a request, sending its response frames on `tx`
+    pub async fn forward(&self, request: InferenceRequest, tx: mpsc::Sender<InferenceResponse>) {
+        let url = format!("{}{}", self.base_url, request.endpoint.path());
+        let mut response = match self
+            .client
+            .post(&url)
+            .header(CONTENT_TYPE, "application/json")
+            .body(request.body)
+            .send()
+            .await
+        {
+            Ok(response) => response,
+            Err(e) => {
+                let _ = tx
+                    .send(InferenceResponse::Failed(format!(
+                        "backend unavailable: {}",
+                        e
+                    )))
+                    .await;
+                return;
+            }
+        };
+
+        let status = response.status().as_u16();
+        if tx
+            .send(InferenceResponse::Started { status })
+            .await
+            .is_err()
+        {
+            return;
+        }
+        let last = loop {
+            match response.chunk().await {
+                Ok(Some(chunk)) => {
+                    if tx
+                        .send(InferenceResponse::Chunk(chunk.to_vec()))
+                        .await
+                        .is_err()
+                    {
+                        debug!("Client left before the response from {} completed", url);
+                        return;
+                    }
+                }
+                Ok(None) => break InferenceResponse::Done,
+                Err(e) => break InferenceResponse::Failed(format!("backend response: {}", e)),
+            }
+        };
+        let _ = tx.send(last).await;
+    }
+}
@@ -12,6 +12,10 @@ pub struct Config {
     #[arg(short, long, default_value = "0.0.0.0:9004")]
     pub listen: String,
 
+    /// Listen address for the local inference API used by routerd
+    #[arg(long, default_value = "127.0.0.1:9005")]
+    pub api_listen: String,
+
     /// Address of gossipd for event subscription
     #[arg(long, default_value = "127.0.0.1:9001")]
     pub gossipd: String,
@@ -44,7 +48,7 @@ pub struct Config {
     #[arg(long, default_value = "true")]
     pub enable_relay: bool,
 
-    /// Local inference backend URL (if provider)
+    /// Local OpenAI-compatible inference backend URL (if provider)
     #[arg(long)]
     pub inference_backend: Option<String>,
 
@@ -1,5 +1,7 @@
 //! infernode - TerrainGossip inference node daemon
 
+pub mod api;
+pub mod backend;
 pub mod circuit;
 pub mod config;
 pub mod onion;
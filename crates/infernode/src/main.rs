@@ -4,15 +4,21 @@
 //! either a relay node or a final destination (provider).
 
 use clap::Parser;
+use infernode::api::{Forward, InferenceResponse, InferenceServer, Reachable};
+use infernode::backend::Backend;
 use infernode::circuit::CircuitManager;
 use infernode::config::Config;
 use infernode::relay::Relay;
+use parking_lot::RwLock;
+use std::collections::HashMap;
 use std::process::ExitCode;
 use std::sync::Arc;
 use std::time::Duration;
+use terrain_gossip_core::types::ProviderDescriptor;
 use terrain_gossip_net::crypto::KeyPair;
+use tokio::net::TcpListener;
 use tokio::time::interval;
-use tracing::info;
+use tracing::{error, info};
 use tracing_subscriber::{fmt, prelude::*, EnvFilter};
 
 #[tokio::main]
@@ -44,8 +50,14 @@ async fn main() -> ExitCode {
         info!("Relay mode enabled");
     }
 
-    // Generate node keypair
-    let keypair = KeyPair::generate();
+    // Load or generate the node key; a provider's descriptors name it
+    let keypair = match KeyPair::load_or_generate(&config.data_dir.join("node.key")) {
+        Ok(keypair) => keypair,
+        Err(e) => {
+            error!("Failed to load node key: {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
     let node_id = keypair.public_key();
     info!("Node ID: {:02x?}", &node_id[..16]);
 
@@ -63,6 +75,10 @@ async fn main() -> ExitCode {
         config.enable_relay,
     ));
 
+    // Provider descriptors known from gossipd, by descriptor ID
+    let known_providers: Arc<RwLock<HashMap<[u8; 32], ProviderDescriptor>>> =
+        Arc::new(RwLock::new(HashMap::new()));
+
     // Spawn maintenance task
     let maint_circuits = circuit_manager.clone();
     let maint_relay = relay.clone();
@@ -90,6 +106,7 @@ async fn main() -> ExitCode {
     // Spawn stats logging task
     let stats_circuits = circuit_manager.clone();
     let stats_relay = relay.clone();
+    let stats_providers = known_providers.clone();
     
     tokio::spawn(async move {
         let mut ticker = interval(Duration::from_secs(300));
@@ -100,20 +117,62 @@ async fn main() -> ExitCode {
             let relay_stats = stats_relay.stats();
 
             info!(
-                "Stats: {} client circuits ({} ready), {} relay circuits, {} cells processed",
+                "Stats: {} client circuits ({} ready), {} relay circuits, {} cells processed, {} known providers",
                 circuit_stats.total,
                 circuit_stats.ready,
                 stats_relay.circuit_count(),
-                relay_stats.cells_processed
+                relay_stats.cells_processed,
+                stats_providers.read().len()
             );
         }
     });
 
+    // Track provider descriptors published through gossipd
+    let descriptor_providers = known_providers.clone();
+    tokio::spawn(gossipd::api::follow_descriptors(
+        config.gossipd.clone(),
+        move |descriptors| {
+            *descriptor_providers.write() = descriptors
+                .into_iter()
+                .map(|descriptor| (descriptor.descriptor_id.0, descriptor))
+                .collect();
+        },
+    ));
+
+    // Serve inference requests from routerd. Until cells are carried
+    // between nodes, only this node's own descriptors are reachable, served
+    // by its backend; requests for others get no route, so routerd tries
+    // another provider without blaming this one.
+    let backend = config.inference_backend.as_deref().map(Backend::new);
+    let reachable: Arc<Reachable> = match &backend {
+        Some(_) => Arc::new(move || vec![node_id.to_vec()]),
+        None => Arc::new(Vec::new),
+    };
+    let forward: Arc<Forward> = Arc::new(move |request, response_tx| {
+        match &backend {
+            Some(backend) if request.descriptor.provider_transport_pubkey == node_id => {
+                let backend = backend.clone();
+                tokio::spawn(async move { backend.forward(request, response_tx).await });
+            }
+            _ => {
+                let _ = response_tx.try_send(InferenceResponse::NoRoute(
+                    "no circuit to provider".into(),
+                ));
+            }
+        }
+    });
+    match TcpListener::bind(&config.api_listen).await {
+        Ok(listener) => {
+            info!("Inference API listening on {}", config.api_listen);
+            let server = InferenceServer::new(forward).with_reachable(reachable);
+            tokio::spawn(Arc::new(server).serve(listener));
+        }
+        Err(e) => error!("Failed to bind inference API on {}: {}", config.api_listen, e),
+    }
+
     // TODO: Start network listener
-    // TODO: Connect to gossipd for descriptor updates
     // TODO: Register as provider (if configured)
     // TODO: Handle incoming circuit requests
-    // TODO: Forward inference requests
 
     info!("Inference node started (placeholder - press Ctrl+C to exit)");
 
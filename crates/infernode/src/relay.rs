@@ -84,6 +84,11 @@ impl Relay {
         }
     }
 
+    /// Our node ID
+    pub fn node_id(&self) -> [u8; 32] {
+        self.node_id
+    }
+
     /// Process an incoming cell
     pub fn process_cell(
         &self,
@@ -113,7 +118,7 @@ impl Relay {
             // We are the destination - process locally
             debug!(
                 "Final destination for circuit {:?}",
-                hex::encode(&cell.circuit_id)
+                hex::encode(cell.circuit_id)
             );
             
             {
@@ -129,7 +134,7 @@ impl Relay {
             // Forward to next hop
             debug!(
                 "Forwarding circuit {:?} to {:?}",
-                hex::encode(&cell.circuit_id),
+                hex::encode(cell.circuit_id),
                 hex::encode(&header.next_hop[..8])
             );
 
@@ -276,4 +281,82 @@ mod tests {
         // 4th should fail
         assert!(relay.check_rate_limit(&peer).is_err());
     }
+
+    #[tokio::test]
+    async fn test_sealed_frames_cross_relays() {
+        use crate::circuit::CircuitManager;
+        use std::time::{SystemTime, UNIX_EPOCH};
+        use terrain_gossip_net::framing::{read_frame, write_frame, Frame, FrameType};
+        use terrain_gossip_net::sealed::{Opener, SealError, SealKey, Sealer};
+
+        // A control-plane frame sealed end to end
+        let secret = b"target refs".to_vec();
+        let now_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_millis() as u64;
+        let key = SealKey::from_control_plane_key(&[7; 32]);
+        let mut sealer = Sealer::new(key.clone());
+        let mut opener = Opener::new(vec![key]);
+        let inner = Frame::new(FrameType::EventBroadcast, secret.clone());
+        let sealed = sealer.seal(&inner, now_ms).unwrap();
+        let mut bytes = Vec::new();
+        write_frame(&mut bytes, sealed).await.unwrap();
+
+        // Carried over a three-hop circuit
+        let relays: Vec<Relay> = (1..=3u8)
+            .map(|i| Relay::new([i; 32], 100, 1000, true))
+            .collect();
+        let hop_keys: Vec<OnionHopKey> = (1..=3u8)
+            .map(|i| OnionHopKey::derive([i; 32], &[i; 32], i - 1))
+            .collect();
+        let circuits = CircuitManager::new(10, 600);
+        let path = relays.iter().map(|relay| relay.node_id()).collect();
+        let circuit_id = circuits.create_circuit(path, hop_keys.clone()).unwrap();
+        circuits.mark_ready(&circuit_id).unwrap();
+        let mut cell = circuits.encrypt_request(&circuit_id, &bytes).unwrap();
+
+        let contains_secret = |bytes: &[u8]| bytes.windows(secret.len()).any(|w| w == secret);
+        let mut from_peer = [0; 32];
+        let mut delivered = None;
+        for (i, (relay, hop_key)) in relays.iter().zip(&hop_keys).enumerate() {
+            assert!(!contains_secret(&cell.payload));
+            let incoming = IncomingCell {
+                from_peer,
+                cell,
+                received_at: Instant::now(),
+            };
+            match relay.process_cell(incoming, hop_key).unwrap() {
+                RelayAction::Forward {
+                    to_peer,
+                    cell: next,
+                } => {
+                    assert_eq!(to_peer, relays[i + 1].node_id());
+                    from_peer = relay.node_id();
+                    cell = next;
+                }
+                RelayAction::Deliver { payload, .. } => {
+                    delivered = Some(payload);
+                    break;
+                }
+                RelayAction::Drop => panic!("cell dropped"),
+            }
+        }
+
+        // The exit relay gets the sealed frame, still opaque, and only the
+        // key holder opens it, once
+        let delivered = delivered.unwrap();
+        assert!(!contains_secret(&delivered));
+        let frame = read_frame(&mut delivered.as_slice())
+            .await
+            .unwrap()
+            .unwrap();
+        let opened = opener.open(&frame, now_ms).unwrap();
+        assert_eq!(opened.frame_type, FrameType::EventBroadcast);
+        assert_eq!(opened.payload, secret);
+        assert!(matches!(
+            opener.open(&frame, now_ms),
+            Err(SealError::Replay(_))
+        ));
+    }
 }
@@ -3,8 +3,11 @@
 use blake3::Hasher;
 use rand::Rng;
 use std::time::{SystemTime, UNIX_EPOCH};
+use terrain_gossip_belief::BehaviorSample;
 use thiserror::Error;
 
+const DOMAIN_CANARY: &[u8] = b"prober-canary";
+
 /// Challenge errors
 #[derive(Debug, Error)]
 pub enum ChallengeError {
@@ -33,6 +36,8 @@ pub struct Challenge {
     pub timestamp: u64,
     /// Expiry timestamp
     pub expires_at: u64,
+    /// Hash of the prompt when it is a canary shared by every provider
+    pub canary_prompt: Option<[u8; 32]>,
 }
 
 impl Challenge {
@@ -56,7 +61,7 @@ impl Challenge {
 
         // Generate prompt tokens (simple token generation)
         let prompt_tokens: Vec<String> = (0..token_count)
-            .map(|i| format!("probe_token_{:04x}", rng.gen::<u16>()))
+            .map(|_| format!("probe_token_{:04x}", rng.gen::<u16>()))
             .collect();
 
         let now = SystemTime::now()
@@ -72,9 +77,40 @@ impl Challenge {
             expected_token_count: token_count * 2, // Expect roughly 2x tokens back
             timestamp: now,
             expires_at: now + ttl_secs,
+            canary_prompt: None,
         }
     }
 
+    /// Generate a canary challenge: every provider probed in the same
+    /// `round` gets the same prompt, derived from the prober's secret `seed`
+    pub fn canary(
+        target_provider: [u8; 32],
+        seed: &[u8; 32],
+        round: u64,
+        token_count: usize,
+        ttl_secs: u64,
+    ) -> Self {
+        let mut challenge = Self::generate(target_provider, token_count, ttl_secs);
+
+        let mut prompt = Hasher::new_keyed(seed);
+        prompt.update(DOMAIN_CANARY);
+        prompt.update(&round.to_le_bytes());
+        let mut tokens = prompt.finalize_xof();
+        challenge.prompt_tokens = (0..token_count)
+            .map(|_| {
+                let mut token = [0u8; 2];
+                tokens.fill(&mut token);
+                format!("probe_token_{:04x}", u16::from_le_bytes(token))
+            })
+            .collect();
+
+        let mut hasher = Hasher::new();
+        hasher.update(DOMAIN_CANARY);
+        hasher.update(challenge.as_prompt().as_bytes());
+        challenge.canary_prompt = Some(*hasher.finalize().as_bytes());
+        challenge
+    }
+
     /// Check if challenge is expired
     pub fn is_expired(&self) -> bool {
         let now = SystemTime::now()
@@ -148,6 +184,28 @@ impl ChallengeResponse {
         hasher.update(&self.timestamp.to_le_bytes());
         *hasher.finalize().as_bytes()
     }
+
+    /// Hash of the response tokens alone, comparable across providers
+    pub fn output_hash(&self) -> [u8; 32] {
+        let mut hasher = Hasher::new();
+        for token in &self.response_tokens {
+            hasher.update(&(token.len() as u64).to_le_bytes());
+            hasher.update(token.as_bytes());
+        }
+        *hasher.finalize().as_bytes()
+    }
+
+    /// What this response reveals about the provider, for link hints
+    pub fn behavior_sample(&self, challenge: &Challenge, latency_ms: f64) -> BehaviorSample {
+        BehaviorSample {
+            latency_ms,
+            response_tokens: Some(self.response_tokens.len().min(u32::MAX as usize) as u32),
+            refused: self.response_tokens.is_empty(),
+            canary: challenge
+                .canary_prompt
+                .map(|prompt| (prompt, self.output_hash())),
+        }
+    }
 }
 
 /// Challenge verifier
@@ -247,6 +305,29 @@ mod tests {
         assert!(prompt.starts_with("Complete this sequence:"));
     }
 
+    #[test]
+    fn test_canary_is_shared_per_round() {
+        let seed = [7u8; 32];
+        let a = Challenge::canary([1u8; 32], &seed, 3, 5, 300);
+        let b = Challenge::canary([2u8; 32], &seed, 3, 5, 300);
+        assert_eq!(a.prompt_tokens, b.prompt_tokens);
+        assert_eq!(a.canary_prompt, b.canary_prompt);
+        assert_ne!(a.id, b.id);
+
+        let next = Challenge::canary([1u8; 32], &seed, 4, 5, 300);
+        assert_ne!(a.canary_prompt, next.canary_prompt);
+        let plain = Challenge::generate([1u8; 32], 5, 300);
+        assert!(plain.canary_prompt.is_none());
+
+        let response = ChallengeResponse::new(a.id, vec!["x".to_string(); 4], vec![]);
+        let sample = response.behavior_sample(&a, 120.0);
+        assert_eq!(sample.response_tokens, Some(4));
+        assert_eq!(
+            sample.canary,
+            Some((a.canary_prompt.unwrap(), response.output_hash()))
+        );
+    }
+
     #[test]
     fn test_verification() {
         let provider = [1u8; 32];
@@ -9,10 +9,75 @@ use prober::scheduler::Scheduler;
 use std::process::ExitCode;
 use std::sync::Arc;
 use std::time::Duration;
-use tokio::time::interval;
-use tracing::info;
+use gossipd::api::{ApiClient, ApiError};
+use prober::challenge::ChallengeResponse;
+use prober::receipt::ProbeReceipt;
+use terrain_gossip_belief::{SimilarityConfig, SimilarityDetector};
+use terrain_gossip_core::crypto::EventSigner;
+use terrain_gossip_core::types::TargetRef;
+use terrain_gossip_net::crypto::KeyPair;
+use tokio::sync::mpsc;
+use tokio::time::{interval, Instant};
+use tracing::{debug, error, info, warn};
 use tracing_subscriber::{fmt, prelude::*, EnvFilter};
 
+/// Receipts buffered while gossipd is unreachable
+const RECEIPT_QUEUE_DEPTH: usize = 1024;
+
+/// Seconds during which all providers get the same canary prompt
+const CANARY_ROUND_SECS: u64 = 3600;
+
+/// Publish probe receipts through gossipd's local API, reconnecting as needed
+async fn publish_receipts(
+    addr: String,
+    signer: EventSigner,
+    mut receipt_rx: mpsc::Receiver<ProbeReceipt>,
+) {
+    let mut client: Option<ApiClient> = None;
+
+    while let Some(receipt) = receipt_rx.recv().await {
+        if client.is_none() {
+            match ApiClient::connect(addr.as_str()).await {
+                Ok(connected) => client = Some(connected),
+                Err(e) => {
+                    warn!("Dropping receipt: gossipd at {} unavailable: {}", addr, e);
+                    continue;
+                }
+            }
+        }
+        let Some(api) = client.as_mut() else {
+            continue;
+        };
+
+        let status = match api.status().await {
+            Ok(status) => status,
+            Err(e) => {
+                warn!("Failed to publish receipt: {}", e);
+                client = None;
+                continue;
+            }
+        };
+        let body = receipt.to_event(status.world, status.current_epoch).body;
+        let event = match signer.sign(body) {
+            Ok(event) => event,
+            Err(e) => {
+                warn!("Failed to sign receipt: {}", e);
+                continue;
+            }
+        };
+
+        let result = api.publish(event).await;
+        match result {
+            Ok(()) => {}
+            Err(ApiError::Remote(reason)) => warn!("gossipd rejected receipt: {}", reason),
+            Err(e) => {
+                warn!("Failed to publish receipt: {}", e);
+                client = None;
+            }
+        }
+    }
+}
+
 #[tokio::main]
 async fn main() -> ExitCode {
     // Initialize logging
@@ -31,13 +96,65 @@ async fn main() -> ExitCode {
     info!("Probe interval: {}s", config.probe_interval_secs);
     info!("Concurrent probes: {}", config.concurrent_probes);
 
-    // Create scheduler
-    let scheduler = Arc::new(Scheduler::new(
-        config.challenge_token_count,
-        config.probe_timeout_secs,
-        1000,
+    // Sign with a key kept across restarts, so the trust this prober earns
+    // stays with it
+    let key_path = config.data_dir.join("prober.key");
+    let keypair = match KeyPair::load_or_generate(&key_path) {
+        Ok(keypair) => keypair,
+        Err(e) => {
+            error!("Failed to load prober key {}: {}", key_path.display(), e);
+            return ExitCode::FAILURE;
+        }
+    };
+    let prober_pubkey = keypair.public_key();
+
+    // Create scheduler, with canary prompts providers cannot predict
+    let canary_seed = blake3::derive_key("terrain prober canary seed", &keypair.seed());
+    let scheduler = Arc::new(
+        Scheduler::new(
+            config.challenge_token_count,
+            config.probe_timeout_secs,
+            1000,
+        )
+        .with_canaries(canary_seed, CANARY_ROUND_SECS),
+    );
+
+    // Fingerprint providers from probe responses and hint the ones that
+    // behave alike. Hints weigh what this key earns for its attestations, so
+    // they count for nothing until probes publish scored attestations.
+    let similarity = Arc::new(SimilarityDetector::new(SimilarityConfig::default()));
+    let hint_similarity = similarity.clone();
+    tokio::spawn(gossipd::api::publish_every(
+        config.gossipd.clone(),
+        EventSigner::from_seed(&keypair.seed()),
+        Duration::from_secs(config.probe_interval_secs),
+        move |status| {
+            hint_similarity
+                .detect(status.current_epoch)
+                .iter()
+                .map(|finding| finding.to_link_hint(status.world))
+                .collect()
+        },
     ));
 
+    // Discover providers from descriptor publications
+    let discovery_scheduler = scheduler.clone();
+    let discovery_similarity = similarity.clone();
+    tokio::spawn(gossipd::api::follow_descriptors(
+        config.gossipd.clone(),
+        move |descriptors| {
+            let current = descriptors.iter().map(|d| d.descriptor_id.0).collect();
+            for removed in discovery_scheduler.set_providers(current) {
+                discovery_similarity.remove(&TargetRef(removed));
+            }
+        },
+    ));
+
+    // Publish probe receipts to the gossip mesh
+    let (receipt_tx, receipt_rx) = mpsc::channel(RECEIPT_QUEUE_DEPTH);
+    let signer = EventSigner::from_seed(&keypair.seed());
+    tokio::spawn(publish_receipts(config.gossipd.clone(), signer, receipt_rx));
+
     // Spawn probe scheduling task
     let schedule_scheduler = scheduler.clone();
     let min_providers = config.min_providers_per_round;
@@ -50,6 +167,12 @@ async fn main() -> ExitCode {
             ticker.tick().await;
             let scheduled = schedule_scheduler.schedule_due_probes(max_providers);
             info!("Scheduled {} probes", scheduled);
+            if scheduled < min_providers {
+                debug!(
+                    "Only {} providers due this round (minimum {})",
+                    scheduled, min_providers
+                );
+            }
         }
     });
 
@@ -66,16 +189,35 @@ async fn main() -> ExitCode {
             for _ in 0..available_slots {
                 if let Some(probe) = exec_scheduler.next_probe() {
                     let exec_scheduler = exec_scheduler.clone();
+                    let receipt_tx = receipt_tx.clone();
+                    let similarity = similarity.clone();
                     tokio::spawn(async move {
                         // TODO: Execute probe against provider
-                        // For now, simulate with random result
+                        // For now, simulate with random result and no response
+                        let started = Instant::now();
                         tokio::time::sleep(Duration::from_millis(100)).await;
+                        let response: Option<ChallengeResponse> = None;
                         let passed = rand::random::<bool>();
-                        exec_scheduler.report_result(
+
+                        // Only real responses feed the provider's fingerprint
+                        if let Some(response) = &response {
+                            let latency_ms = started.elapsed().as_secs_f64() * 1000.0;
+                            similarity.record(
+                                TargetRef(probe.provider_id),
+                                response.behavior_sample(&probe.challenge, latency_ms),
+                            );
+                        }
+
+                        let receipt = exec_scheduler.report_result(
                             &probe.provider_id,
                             passed,
-                            [0u8; 32],
+                            prober_pubkey,
                         );
+                        if let Some(receipt) = receipt {
+                            if receipt_tx.try_send(receipt).is_err() {
+                                warn!("Receipt queue full; dropping receipt");
+                            }
+                        }
                     });
                 }
             }
@@ -84,9 +226,6 @@ async fn main() -> ExitCode {
         }
     });
 
-    // TODO: Connect to gossipd for provider discovery
-    // TODO: Publish probe receipts to gossip mesh
-
     info!("Prober started (placeholder - press Ctrl+C to exit)");
 
     // Wait for shutdown
@@ -4,7 +4,7 @@ use crate::challenge::{Challenge, ChallengeVerifier};
 use crate::receipt::ProbeReceipt;
 use parking_lot::RwLock;
 use std::collections::{HashMap, HashSet, VecDeque};
-use std::time::{Duration, Instant};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use thiserror::Error;
 use tracing::debug;
 
@@ -73,10 +73,8 @@ impl ProbeHistory {
 
     /// Determine priority based on history
     pub fn priority(&self) -> ProbePriority {
-        if self.total_probes < 5 {
-            ProbePriority::High // New provider
-        } else if self.consecutive_failures > 2 {
-            ProbePriority::High // Unreliable
+        if self.total_probes < 5 || self.consecutive_failures > 2 {
+            ProbePriority::High // New or unreliable provider
         } else if self.success_rate() > 0.95 {
             ProbePriority::Low // Very reliable
         } else {
@@ -125,6 +123,8 @@ pub struct Scheduler {
     challenge_token_count: usize,
     probe_timeout_secs: u64,
     max_queue_size: usize,
+    /// Secret seed and round length for canary challenges, if enabled
+    canaries: Option<([u8; 32], u64)>,
 }
 
 impl Scheduler {
@@ -144,9 +144,22 @@ impl Scheduler {
             challenge_token_count,
             probe_timeout_secs,
             max_queue_size,
+            canaries: None,
         }
     }
 
+    /// Probe with canary challenges, shared by all providers probed within
+    /// the same `round_secs`, so their outputs can be compared
+    pub fn with_canaries(mut self, seed: [u8; 32], round_secs: u64) -> Self {
+        self.canaries = Some((seed, round_secs.max(1)));
+        self
+    }
+
+    /// Challenge verifier used for probe responses
+    pub fn verifier(&self) -> &ChallengeVerifier {
+        &self.verifier
+    }
+
     /// Register a provider for probing
     pub fn register_provider(&self, provider_id: [u8; 32]) {
         self.providers.write().insert(provider_id);
@@ -158,6 +171,18 @@ impl Scheduler {
         self.providers.write().remove(provider_id);
     }
 
+    /// Probe exactly `provider_ids`; returns the providers dropped
+    pub fn set_providers(&self, provider_ids: HashSet<[u8; 32]>) -> Vec<[u8; 32]> {
+        let mut providers = self.providers.write();
+        let removed = providers.difference(&provider_ids).copied().collect();
+        let mut history = self.history.write();
+        for id in &provider_ids {
+            history.entry(*id).or_default();
+        }
+        *providers = provider_ids;
+        removed
+    }
+
     /// Schedule a probe for a provider
     pub fn schedule_probe(&self, provider_id: [u8; 32]) -> Result<(), SchedulerError> {
         // Check if already in flight
@@ -174,11 +199,26 @@ impl Scheduler {
             .unwrap_or(ProbePriority::High);
 
         // Generate challenge
-        let challenge = Challenge::generate(
-            provider_id,
-            self.challenge_token_count,
-            self.probe_timeout_secs,
-        );
+        let challenge = match &self.canaries {
+            Some((seed, round_secs)) => {
+                let now = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap()
+                    .as_secs();
+                Challenge::canary(
+                    provider_id,
+                    seed,
+                    now / round_secs,
+                    self.challenge_token_count,
+                    self.probe_timeout_secs,
+                )
+            }
+            None => Challenge::generate(
+                provider_id,
+                self.challenge_token_count,
+                self.probe_timeout_secs,
+            ),
+        };
 
         let probe = ScheduledProbe {
             provider_id,
@@ -244,7 +284,7 @@ impl Scheduler {
         &self,
         provider_id: &[u8; 32],
         passed: bool,
-        prober_pubkey: [u8; 32],
+        _prober_pubkey: [u8; 32],
     ) -> Option<ProbeReceipt> {
         // Remove from in-flight
         self.in_flight.write().remove(provider_id);
@@ -371,6 +411,17 @@ mod tests {
         assert_eq!(probe.unwrap().provider_id, provider);
     }
 
+    #[test]
+    fn test_set_providers() {
+        let scheduler = Scheduler::new(5, 30, 100);
+        scheduler.register_provider([1; 32]);
+        scheduler.register_provider([2; 32]);
+
+        let removed = scheduler.set_providers([[2; 32], [3; 32]].into_iter().collect());
+        assert_eq!(removed, vec![[1; 32]]);
+        assert_eq!(scheduler.stats().providers, 2);
+    }
+
     #[test]
     fn test_due_probes() {
         let scheduler = Scheduler::new(5, 30, 100);
@@ -8,10 +8,14 @@ use std::path::PathBuf;
 #[command(name = "routerd")]
 #[command(author, version, about, long_about = None)]
 pub struct Config {
-    /// Listen address for routing requests
+    /// Listen address for the OpenAI-compatible HTTP API
     #[arg(short, long, default_value = "127.0.0.1:9002")]
     pub listen: String,
 
+    /// Address of infernode's inference API for forwarding requests
+    #[arg(long, default_value = "127.0.0.1:9005")]
+    pub infernode: String,
+
     /// Address of gossipd for event subscription
     #[arg(long, default_value = "127.0.0.1:9001")]
     pub gossipd: String,
@@ -24,6 +28,11 @@ pub struct Config {
     #[arg(long, env = "TERRAIN_WORLD_PHRASE")]
     pub world_phrase: String,
 
+    /// Path to the world's rule bundle JSON file, whose weights score
+    /// provider metrics
+    #[arg(long)]
+    pub rule_bundle: Option<PathBuf>,
+
     /// Minimum provider reputation score (0.0-1.0)
     #[arg(long, default_value = "0.5")]
     pub min_reputation: f64,
@@ -0,0 +1,578 @@
+//! OpenAI-compatible HTTP inference API
+//!
+//! `POST /v1/chat/completions` and `POST /v1/completions` take `model` as the
+//! route's model family, pick a provider with [`Router::route`] and forward
+//! the request body unchanged through infernode. The provider's response is
+//! streamed back as it arrives. Every attempt feeds the router: latency on
+//! success (to the first chunk when streaming, to the whole response
+//! otherwise), a failure otherwise, after which the provider is excluded and
+//! the next one tried. A provider infernode has no route to is excluded
+//! without counting as a failure. Once a response has started streaming it
+//! is not retried. `GET /v1/models` lists the model families that have
+//! providers.
+//!
+//! Successful responses also feed the provider's behavioral fingerprint,
+//! timed to the whole response whether streamed or not.
+//! Requests at temperature 0 are deterministic, so their output is recorded
+//! as a canary: providers that answer the same such request identically are
+//! likely the same backend.
+
+use crate::router::{RouteRequest, Router, RouterError};
+use axum::body::{Body, Bytes};
+use axum::extract::State;
+use axum::http::{header, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::Json;
+use infernode::api::{InferenceClient, InferenceEndpoint, InferenceError, InferenceRequest};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use terrain_gossip_belief::BehaviorSample;
+use terrain_gossip_core::types::Bytes32;
+use tokio::sync::mpsc;
+use tokio::time::timeout;
+use tokio_stream::wrappers::ReceiverStream;
+use tracing::{debug, warn};
+
+/// Providers tried per request
+const MAX_ATTEMPTS: usize = 3;
+
+/// Time a provider has to start responding
+const START_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Streamed chunks buffered towards the HTTP client
+const STREAM_QUEUE_DEPTH: usize = 32;
+
+const DOMAIN_CANARY: &[u8] = b"routerd-canary";
+
+/// Request fields that do not change a deterministic response
+const TRANSPORT_FIELDS: [&str; 3] = ["stream", "stream_options", "user"];
+
+/// Fields of a completion request the router needs; the rest is forwarded
+#[derive(Debug, Deserialize)]
+struct CompletionRequest {
+    model: String,
+    #[serde(default)]
+    stream: bool,
+    #[serde(default)]
+    temperature: Option<f64>,
+}
+
+/// HTTP API state
+pub struct HttpApi {
+    router: Arc<Router>,
+    /// Address of infernode's inference API
+    infernode: String,
+}
+
+impl HttpApi {
+    /// Serve requests with `router`, forwarding through infernode at `infernode`
+    pub fn new(router: Arc<Router>, infernode: String) -> Self {
+        Self { router, infernode }
+    }
+
+    /// HTTP routes of the API
+    pub fn routes(self: Arc<Self>) -> axum::Router {
+        axum::Router::new()
+            .route("/v1/chat/completions", post(chat_completions))
+            .route("/v1/completions", post(completions))
+            .route("/v1/models", get(models))
+            .with_state(self)
+    }
+
+    /// Route and forward a request, trying other providers on failure
+    async fn complete(&self, endpoint: InferenceEndpoint, body: Bytes) -> Response {
+        let request: CompletionRequest = match serde_json::from_slice(&body) {
+            Ok(request) => request,
+            Err(e) => {
+                return error(
+                    StatusCode::BAD_REQUEST,
+                    "invalid_request_error",
+                    &e.to_string(),
+                )
+            }
+        };
+
+        let canary = (request.temperature == Some(0.0))
+            .then(|| canary_prompt(endpoint, &body))
+            .flatten();
+
+        let mut exclude = Vec::new();
+        for _ in 0..MAX_ATTEMPTS {
+            let route = match self.router.route(RouteRequest {
+                model_family: request.model.clone(),
+                capabilities: 0,
+                max_latency_ms: None,
+                preferred_hops: Some(self.router.config().max_hops),
+                exclude: exclude.clone(),
+            }) {
+                Ok(route) => route,
+                Err(RouterError::NoProviders(model)) if exclude.is_empty() => {
+                    return error(
+                        StatusCode::NOT_FOUND,
+                        "model_not_found",
+                        &format!("no providers for model {}", model),
+                    )
+                }
+                Err(_) => break,
+            };
+            let provider_id = route.provider.id;
+            let attempt = Attempt {
+                router: self.router.clone(),
+                provider_id,
+                model_family: request.model.clone(),
+                started: Instant::now(),
+                stream: request.stream,
+                canary,
+            };
+
+            match self
+                .open(InferenceRequest {
+                    descriptor: route.state.descriptor,
+                    endpoint,
+                    body: body.to_vec(),
+                })
+                .await
+            {
+                Ok((status, client)) if status.is_client_error() => {
+                    // The request itself was refused: relay it, nobody failed
+                    return attempt.collect(status, client).await.unwrap_or_else(|e| {
+                        error(StatusCode::BAD_GATEWAY, "provider_error", &e.to_string())
+                    });
+                }
+                Ok((status, client)) if status.is_success() => {
+                    if request.stream {
+                        return attempt.stream(status, client);
+                    }
+                    match attempt.collect(status, client).await {
+                        Ok(response) => return response,
+                        Err(e) => warn!("Provider response failed: {}", e),
+                    }
+                }
+                Ok((status, _)) => warn!("Provider answered {}", status),
+                Err(InferenceError::NoRoute(reason)) => {
+                    // Not the provider's fault: try another without blame
+                    warn!("No route to provider: {}", reason);
+                    exclude.push(provider_id);
+                    continue;
+                }
+                Err(e) => warn!("Failed to reach provider: {}", e),
+            }
+            self.router.report_failure(&provider_id, &request.model);
+            exclude.push(provider_id);
+        }
+        error(
+            StatusCode::BAD_GATEWAY,
+            "provider_error",
+            "no provider completed the request",
+        )
+    }
+
+    /// Send a request through infernode and wait for the provider to start
+    async fn open(
+        &self,
+        request: InferenceRequest,
+    ) -> Result<(StatusCode, InferenceClient), InferenceError> {
+        timeout(START_TIMEOUT, async {
+            let mut client = InferenceClient::connect(self.infernode.as_str()).await?;
+            let status = client.infer(&request).await?;
+            let status =
+                StatusCode::from_u16(status).map_err(|_| InferenceError::UnexpectedResponse)?;
+            Ok((status, client))
+        })
+        .await
+        .map_err(|_| InferenceError::Remote("provider did not respond".into()))?
+    }
+}
+
+/// One provider's handling of a request
+struct Attempt {
+    router: Arc<Router>,
+    provider_id: [u8; 32],
+    model_family: String,
+    started: Instant,
+    stream: bool,
+    /// Prompt hash when the request is a deterministic canary
+    canary: Option<Bytes32>,
+}
+
+impl Attempt {
+    /// Read the whole response and return it
+    async fn collect(
+        self,
+        status: StatusCode,
+        mut client: InferenceClient,
+    ) -> Result<Response, InferenceError> {
+        let mut body = Vec::new();
+        while let Some(chunk) = client.next_chunk().await? {
+            body.extend(chunk);
+        }
+        if status.is_success() {
+            let mut observed = Observed::default();
+            observed.body(&body);
+            let latency_ms = self.elapsed_ms();
+            self.succeeded(latency_ms, latency_ms, observed);
+        }
+        Ok((status, [(header::CONTENT_TYPE, "application/json")], body).into_response())
+    }
+
+    /// Stream the response to the client as it arrives
+    fn stream(self, status: StatusCode, mut client: InferenceClient) -> Response {
+        let (chunk_tx, chunk_rx) =
+            mpsc::channel::<Result<Bytes, InferenceError>>(STREAM_QUEUE_DEPTH);
+        tokio::spawn(async move {
+            let mut observed = Observed::default();
+            let mut first_chunk_ms = None;
+            loop {
+                match client.next_chunk().await {
+                    Ok(Some(chunk)) => {
+                        first_chunk_ms.get_or_insert_with(|| self.elapsed_ms());
+                        observed.stream(&chunk);
+                        if chunk_tx.send(Ok(Bytes::from(chunk))).await.is_err() {
+                            debug!("HTTP client left before the response completed");
+                            return;
+                        }
+                    }
+                    Ok(None) => {
+                        let response_ms = self.elapsed_ms();
+                        let latency_ms = first_chunk_ms.unwrap_or(response_ms);
+                        self.succeeded(latency_ms, response_ms, observed);
+                        return;
+                    }
+                    Err(e) => {
+                        warn!("Provider stream failed: {}", e);
+                        self.router
+                            .report_failure(&self.provider_id, &self.model_family);
+                        let _ = chunk_tx.send(Err(e)).await;
+                        return;
+                    }
+                }
+            }
+        });
+        (
+            status,
+            [(header::CONTENT_TYPE, "text/event-stream")],
+            Body::from_stream(ReceiverStream::new(chunk_rx)),
+        )
+            .into_response()
+    }
+
+    fn elapsed_ms(&self) -> f64 {
+        self.started.elapsed().as_secs_f64() * 1000.0
+    }
+
+    /// Report a completed response: `latency_ms` for routing, and the time
+    /// to the whole response, `response_ms`, for the fingerprint, so
+    /// streamed and whole responses are fingerprinted alike
+    fn succeeded(self, latency_ms: f64, response_ms: f64, observed: Observed) {
+        self.router
+            .report_success(&self.provider_id, &self.model_family, latency_ms);
+        self.router.record_behavior(
+            &self.provider_id,
+            BehaviorSample {
+                latency_ms: response_ms,
+                response_tokens: observed.tokens(self.stream),
+                refused: observed.refused,
+                canary: self.canary.map(|prompt| (prompt, observed.output())),
+            },
+        );
+    }
+}
+
+/// What a response revealed about the provider
+#[derive(Default)]
+struct Observed {
+    /// Partial SSE line carried between chunks
+    line: Vec<u8>,
+    /// Streamed chunks with choices
+    deltas: u32,
+    /// Completion tokens reported in `usage`
+    usage_tokens: Option<u32>,
+    refused: bool,
+    /// Hash of the generated text, in order
+    output: blake3::Hasher,
+}
+
+impl Observed {
+    /// Inspect a complete JSON response body
+    fn body(&mut self, body: &[u8]) {
+        if let Ok(value) = serde_json::from_slice::<Value>(body) {
+            self.value(&value);
+        }
+    }
+
+    /// Inspect a chunk of a server-sent event stream
+    fn stream(&mut self, chunk: &[u8]) {
+        self.line.extend_from_slice(chunk);
+        while let Some(end) = self.line.iter().position(|b| *b == b'\n') {
+            let line: Vec<u8> = self.line.drain(..=end).collect();
+            let Some(data) = line.strip_prefix(b"data:") else {
+                continue;
+            };
+            let Ok(data) = std::str::from_utf8(data) else {
+                continue;
+            };
+            if let Ok(value) = serde_json::from_str::<Value>(data.trim()) {
+                if value["choices"].as_array().is_some_and(|c| !c.is_empty()) {
+                    self.deltas += 1;
+                }
+                self.value(&value);
+            }
+        }
+    }
+
+    fn value(&mut self, value: &Value) {
+        if let Some(tokens) = value["usage"]["completion_tokens"].as_u64() {
+            self.usage_tokens = Some(tokens.min(u64::from(u32::MAX)) as u32);
+        }
+        let refused = value["choices"].as_array().is_some_and(|choices| {
+            choices
+                .iter()
+                .any(|choice| choice["finish_reason"] == "content_filter")
+        });
+        self.refused |= refused;
+
+        for choice in value["choices"].as_array().into_iter().flatten() {
+            let text = choice["message"]["content"]
+                .as_str()
+                .or_else(|| choice["delta"]["content"].as_str())
+                .or_else(|| choice["text"].as_str());
+            if let Some(text) = text {
+                self.output.update(text.as_bytes());
+            }
+        }
+    }
+
+    /// Hash of the generated text
+    fn output(&self) -> Bytes32 {
+        *self.output.finalize().as_bytes()
+    }
+
+    /// Response length, from `usage` or else one token per streamed chunk
+    fn tokens(&self, stream: bool) -> Option<u32> {
+        self.usage_tokens
+            .or_else(|| (stream && self.deltas > 0).then_some(self.deltas))
+    }
+}
+
+/// Hash identifying a request's prompt and sampling parameters, or `None`
+/// if the body is not a JSON object
+fn canary_prompt(endpoint: InferenceEndpoint, body: &[u8]) -> Option<Bytes32> {
+    let Ok(Value::Object(mut fields)) = serde_json::from_slice::<Value>(body) else {
+        return None;
+    };
+    for field in TRANSPORT_FIELDS {
+        fields.remove(field);
+    }
+    // Object keys serialize sorted, so equal requests hash equally
+    let canonical = serde_json::to_vec(&fields).ok()?;
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(DOMAIN_CANARY);
+    hasher.update(endpoint.path().as_bytes());
+    hasher.update(&canonical);
+    Some(*hasher.finalize().as_bytes())
+}
+
+/// OpenAI-style error response
+fn error(status: StatusCode, kind: &str, message: &str) -> Response {
+    (
+        status,
+        Json(json!({ "error": { "message": message, "type": kind, "code": Value::Null } })),
+    )
+        .into_response()
+}
+
+async fn chat_completions(State(api): State<Arc<HttpApi>>, body: Bytes) -> Response {
+    api.complete(InferenceEndpoint::ChatCompletions, body).await
+}
+
+async fn completions(State(api): State<Arc<HttpApi>>, body: Bytes) -> Response {
+    api.complete(InferenceEndpoint::Completions, body).await
+}
+
+async fn models(State(api): State<Arc<HttpApi>>) -> Response {
+    let data: Vec<Value> = api
+        .router
+        .model_families()
+        .into_iter()
+        .map(|family| json!({ "id": family, "object": "model", "owned_by": "terrain" }))
+        .collect();
+    Json(json!({ "object": "list", "data": data })).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use axum::http::Request;
+    use infernode::api::{Forward, InferenceResponse, InferenceServer};
+    use parking_lot::Mutex;
+    use terrain_gossip_core::types::*;
+    use tokio::net::TcpListener;
+    use tower::ServiceExt;
+
+    fn test_config() -> Config {
+        Config {
+            listen: "127.0.0.1:0".to_string(),
+            infernode: "127.0.0.1:0".to_string(),
+            gossipd: "127.0.0.1:9001".to_string(),
+            cache_dir: std::path::PathBuf::from("/tmp/routerd-test"),
+            world_phrase: "test".to_string(),
+            rule_bundle: None,
+            min_reputation: 0.0,
+            max_hops: 3,
+            update_interval_secs: 60,
+            fah_alpha: 0.8,
+            enable_belief_fields: false,
+        }
+    }
+
+    fn test_descriptor(id: u8, model: &str) -> ProviderDescriptor {
+        ProviderDescriptor {
+            descriptor_id: DescriptorId([id; 32]),
+            unsigned: ProviderDescriptorUnsigned {
+                world: WorldId([0u8; 32]),
+                descriptor_epoch: 1,
+                contact_points: vec![],
+                capability: DescriptorCapability::Manifest(CapabilityManifest {
+                    base_model_id: model.to_string(),
+                    weights_digest: [0u8; 32],
+                    runtime_id: "default".to_string(),
+                    context_limit: 8192,
+                    tool_schemas_digest: [0u8; 32],
+                    safety_mode: "standard".to_string(),
+                    adapters: vec![],
+                }),
+            },
+            provider_transport_pubkey: vec![id; 32],
+            signature: vec![],
+        }
+    }
+
+    /// An infernode where provider 1 is unreachable and provider 2 answers
+    /// in two chunks, recording which providers were tried
+    async fn infernode(tried: Arc<Mutex<Vec<u8>>>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let forward: Arc<Forward> = Arc::new(move |request: InferenceRequest, tx| {
+            let id = request.descriptor.descriptor_id.0[0];
+            tried.lock().push(id);
+            tokio::spawn(async move {
+                if id == 1 {
+                    let _ = tx
+                        .send(InferenceResponse::Failed("unreachable".into()))
+                        .await;
+                    return;
+                }
+                let _ = tx.send(InferenceResponse::Started { status: 200 }).await;
+                for part in [&b"data: {\"choices\":[{}]}\n"[..], b"data: [DONE]\n"] {
+                    let _ = tx.send(InferenceResponse::Chunk(part.to_vec())).await;
+                }
+                let _ = tx.send(InferenceResponse::Done).await;
+            });
+        });
+        tokio::spawn(Arc::new(InferenceServer::new(forward)).serve(listener));
+        addr
+    }
+
+    async fn post(routes: axum::Router, path: &str, body: &str) -> (StatusCode, Bytes) {
+        let response = routes
+            .oneshot(
+                Request::post(path)
+                    .header(header::CONTENT_TYPE, "application/json")
+                    .body(Body::from(body.to_string()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let status = response.status();
+        (
+            status,
+            axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap(),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_requests_fail_over_and_stream() {
+        let tried = Arc::new(Mutex::new(Vec::new()));
+        let router = Arc::new(Router::new(test_config()));
+        router.register_provider(test_descriptor(1, "llama-3"));
+        router.register_provider(test_descriptor(2, "llama-3"));
+        let api = Arc::new(HttpApi::new(router.clone(), infernode(tried.clone()).await));
+
+        let (status, body) = post(
+            api.clone().routes(),
+            "/v1/chat/completions",
+            r#"{"model":"llama-3","stream":true,"messages":[]}"#,
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(&body[..], b"data: {\"choices\":[{}]}\ndata: [DONE]\n");
+        assert_eq!(tried.lock().last(), Some(&2));
+
+        // Unknown models and malformed requests are the client's error
+        let (status, body) = post(
+            api.clone().routes(),
+            "/v1/completions",
+            r#"{"model":"gpt-9"}"#,
+        )
+        .await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        let error: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(error["error"]["type"], "model_not_found");
+        let (status, _) = post(api.clone().routes(), "/v1/completions", "{}").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        // With only the unreachable provider left, the request fails
+        router.remove_provider(&[2; 32]);
+        let (status, _) = post(api.routes(), "/v1/completions", r#"{"model":"llama-3"}"#).await;
+        assert_eq!(status, StatusCode::BAD_GATEWAY);
+    }
+
+    #[test]
+    fn test_observed_stream() {
+        let mut observed = Observed::default();
+        observed.stream(b"data: {\"choices\":[{\"delta\":{}}]}\n\ndata: {\"cho");
+        observed.stream(b"ices\":[{\"finish_reason\":\"content_filter\"}]}\n\ndata: [DONE]\n\n");
+        assert_eq!(observed.tokens(true), Some(2));
+        assert!(observed.refused);
+
+        let mut observed = Observed::default();
+        observed
+            .body(br#"{"choices":[{"finish_reason":"stop"}],"usage":{"completion_tokens":17}}"#);
+        assert_eq!(observed.tokens(false), Some(17));
+        assert!(!observed.refused);
+    }
+
+    #[test]
+    fn test_canary_matches_streamed_and_whole_responses() {
+        let chat = InferenceEndpoint::ChatCompletions;
+        let prompt = canary_prompt(chat, br#"{"model":"m","temperature":0,"messages":[]}"#);
+        assert!(prompt.is_some());
+        assert_eq!(
+            prompt,
+            canary_prompt(
+                chat,
+                br#"{"messages":[],"stream":true,"model":"m","temperature":0}"#
+            )
+        );
+        assert_ne!(
+            prompt,
+            canary_prompt(
+                InferenceEndpoint::Completions,
+                br#"{"model":"m","temperature":0,"messages":[]}"#
+            )
+        );
+
+        let mut whole = Observed::default();
+        whole.body(br#"{"choices":[{"message":{"content":"hello"}}]}"#);
+        let mut streamed = Observed::default();
+        streamed.stream(b"data: {\"choices\":[{\"delta\":{\"content\":\"hel\"}}]}\n");
+        streamed.stream(b"data: {\"choices\":[{\"delta\":{\"content\":\"lo\"}}]}\n");
+        assert_eq!(whole.output(), streamed.output());
+    }
+}
@@ -1,6 +1,7 @@
 //! routerd - TerrainGossip terrain router daemon
 
 pub mod config;
+pub mod http;
 pub mod provider;
 pub mod router;
 pub mod scoring;
@@ -4,13 +4,20 @@
 //! provides provider selection based on pheromone trails.
 
 use clap::Parser;
+use infernode::api::InferenceClient;
 use routerd::config::Config;
+use routerd::http::HttpApi;
 use routerd::router::Router;
 use std::process::ExitCode;
 use std::sync::Arc;
 use std::time::Duration;
+use terrain_gossip_belief::{
+    BeliefConfig, BeliefField, LinkConfig, LinkGraph, ObservationWeights, TrustConfig, TrustEngine,
+};
+use terrain_gossip_core::types::{EventType, RuleBundle};
+use tokio::net::TcpListener;
 use tokio::time::interval;
-use tracing::info;
+use tracing::{error, info, warn};
 use tracing_subscriber::{fmt, prelude::*, EnvFilter};
 
 #[tokio::main]
@@ -28,11 +35,84 @@ async fn main() -> ExitCode {
         "routerd v{} - TerrainGossip Terrain Router",
         env!("CARGO_PKG_VERSION")
     );
-    info!("Listening on {}", config.listen);
     info!("Connecting to gossipd at {}", config.gossipd);
 
-    // Create router
-    let router = Arc::new(Router::new(config.clone()));
+    // Create router, scoring by beliefs built from probe reports and treating
+    // linked descriptors as one provider if enabled. Link hints are left to
+    // probers: a hint only counts with trust its signer earned for its
+    // attestations, and routerd publishes none.
+    let mut router = Router::new(config.clone());
+    if config.enable_belief_fields {
+        // Weigh provider metrics as the world's rule bundle says
+        let weights = match &config.rule_bundle {
+            Some(path) => {
+                let bundle = std::fs::read(path)
+                    .map_err(|e| e.to_string())
+                    .and_then(|bytes| {
+                        serde_json::from_slice::<RuleBundle>(&bytes).map_err(|e| e.to_string())
+                    });
+                match bundle {
+                    Ok(bundle) => ObservationWeights::from_rule_bundle(&bundle),
+                    Err(e) => {
+                        error!("Failed to load rule bundle {}: {}", path.display(), e);
+                        return ExitCode::FAILURE;
+                    }
+                }
+            }
+            None => ObservationWeights::default(),
+        };
+        let (belief_config, link_config) = (BeliefConfig::default(), LinkConfig::default());
+        // Only events still inside a window are fetched on startup
+        let window_epochs = belief_config.window_epochs.max(link_config.window_epochs);
+        let beliefs = Arc::new(BeliefField::new(weights, belief_config));
+        let links = Arc::new(LinkGraph::new(link_config));
+        router = router
+            .with_beliefs(beliefs.clone())
+            .with_links(links.clone());
+        let (follow_beliefs, follow_links) = (beliefs.clone(), links.clone());
+        tokio::spawn(gossipd::api::follow_recent(
+            config.gossipd.clone(),
+            vec![
+                EventType::Receipt,
+                EventType::Attestation,
+                EventType::LinkHint,
+            ],
+            window_epochs,
+            move |event| {
+                follow_beliefs.apply(event);
+                follow_links.apply(event);
+            },
+        ));
+
+        // Score probers against later consensus and keep trust across restarts
+        let trust_path = config.cache_dir.join("trust.json");
+        match TrustEngine::load(TrustConfig::default(), &trust_path) {
+            Ok(engine) => {
+                let update_interval = config.update_interval_secs;
+                tokio::spawn(async move {
+                    let mut ticker = interval(Duration::from_secs(update_interval));
+                    loop {
+                        ticker.tick().await;
+                        let update = engine.update(&beliefs);
+                        if update.scored > 0 {
+                            info!(
+                                "Scored {} prober reports ({} skipped, {} correlated clusters)",
+                                update.scored, update.skipped, update.clusters
+                            );
+                        }
+                        if let Err(e) = engine.save(&trust_path) {
+                            warn!("Failed to save prober trust: {}", e);
+                        }
+                        // Unknown and unscored signers count for nothing
+                        let earned = engine.earned_trust();
+                        links.recluster(|signer| earned.get(signer).copied().unwrap_or(0.0));
+                    }
+                });
+            }
+            Err(e) => warn!("Prober trust disabled: {}", e),
+        }
+    }
+    let router = Arc::new(router);
 
     // Spawn maintenance task
     let maintenance_router = router.clone();
@@ -45,10 +125,49 @@ async fn main() -> ExitCode {
         }
     });
 
-    // TODO: Connect to gossipd for descriptor events
-    // TODO: Start HTTP/gRPC server for routing requests
+    // Route to each provider's current descriptor from gossipd
+    let descriptor_router = router.clone();
+    tokio::spawn(gossipd::api::follow_descriptors(
+        config.gossipd.clone(),
+        move |descriptors| descriptor_router.sync_providers(descriptors),
+    ));
+
+    // Route only to providers infernode can reach, keeping the last answer
+    // while it is unavailable
+    let reachable_router = router.clone();
+    let infernode = config.infernode.clone();
+    tokio::spawn(async move {
+        let mut ticker = interval(Duration::from_secs(update_interval));
+        loop {
+            ticker.tick().await;
+            let reachable = match InferenceClient::connect(infernode.as_str()).await {
+                Ok(mut client) => client.reachable().await,
+                Err(e) => Err(e),
+            };
+            match reachable {
+                Ok(keys) => reachable_router.set_reachable(keys),
+                Err(e) => warn!("Failed to query reachable providers: {}", e),
+            }
+        }
+    });
+
+    // Serve the OpenAI-compatible inference API
+    let listener = match TcpListener::bind(&config.listen).await {
+        Ok(listener) => listener,
+        Err(e) => {
+            error!("Failed to bind {}: {}", config.listen, e);
+            return ExitCode::FAILURE;
+        }
+    };
+    info!("Listening on {}", config.listen);
+    let routes = Arc::new(HttpApi::new(router.clone(), config.infernode.clone())).routes();
+    tokio::spawn(async move {
+        if let Err(e) = axum::serve(listener, routes).await {
+            error!("HTTP server error: {}", e);
+        }
+    });
 
-    info!("Router started (placeholder - press Ctrl+C to exit)");
+    info!("Router started - press Ctrl+C to exit");
 
     // Wait for shutdown
     tokio::signal::ctrl_c().await.ok();
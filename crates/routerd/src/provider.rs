@@ -135,7 +135,7 @@ impl ProviderRegistry {
             // Index by model (only if it's an LLM manifest, not FAH)
             if let Some(model_family) = model {
                 let mut by_model = self.by_model.write();
-                by_model.entry(model_family).or_insert_with(Vec::new).push(id);
+                by_model.entry(model_family).or_default().push(id);
             }
         }
     }
@@ -152,6 +152,11 @@ impl ProviderRegistry {
         }
     }
 
+    /// IDs of all registered providers
+    pub fn ids(&self) -> Vec<[u8; 32]> {
+        self.providers.read().keys().copied().collect()
+    }
+
     /// Get provider by ID
     pub fn get(&self, id: &[u8; 32]) -> Option<ProviderState> {
         self.providers.read().get(id).cloned()
@@ -5,8 +5,10 @@ use crate::provider::{get_model_family, ProviderRegistry, ProviderState};
 use crate::scoring::{ScoredProvider, Scorer, ScoringWeights};
 use crate::terrain::{TerrainCoord, TerrainMap};
 use parking_lot::RwLock;
+use std::collections::HashSet;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
+use terrain_gossip_belief::{BehaviorSample, BeliefField, LinkGraph, SimilarityDetector};
 use terrain_gossip_core::types::*;
 use thiserror::Error;
 use tracing::{debug, info, warn};
@@ -54,6 +56,11 @@ pub struct Router {
     terrain: Arc<TerrainMap>,
     registry: Arc<ProviderRegistry>,
     scorer: Scorer,
+    /// Behavioral fingerprints for link hints
+    similarity: Option<Arc<SimilarityDetector>>,
+    /// Transport keys of the providers the inference node can reach, if it
+    /// has said
+    reachable: RwLock<Option<HashSet<Vec<u8>>>>,
     /// Last terrain update
     last_update: RwLock<Instant>,
 }
@@ -69,10 +76,35 @@ impl Router {
             terrain,
             registry,
             scorer,
+            similarity: None,
+            reachable: RwLock::new(None),
             last_update: RwLock::new(Instant::now()),
         }
     }
 
+    /// Score providers by local beliefs as well
+    pub fn with_beliefs(mut self, beliefs: Arc<BeliefField>) -> Self {
+        self.scorer = self.scorer.with_beliefs(beliefs);
+        self
+    }
+
+    /// Treat descriptors linked into one cluster as one provider
+    pub fn with_links(mut self, links: Arc<LinkGraph>) -> Self {
+        self.scorer = self.scorer.with_links(links);
+        self
+    }
+
+    /// Fingerprint providers from observed behavior for link hints
+    pub fn with_similarity(mut self, similarity: Arc<SimilarityDetector>) -> Self {
+        self.similarity = Some(similarity);
+        self
+    }
+
+    /// Router configuration
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
     /// Register a provider descriptor
     pub fn register_provider(&self, descriptor: ProviderDescriptor) {
         let id = descriptor.descriptor_id.0;
@@ -90,6 +122,32 @@ impl Router {
     pub fn remove_provider(&self, id: &[u8; 32]) {
         self.registry.remove(id);
         self.terrain.remove_provider(id);
+        if let Some(similarity) = &self.similarity {
+            similarity.remove(&TargetRef(*id));
+        }
+    }
+
+    /// Reconcile with the current descriptor set: register new providers
+    /// and remove those whose descriptors were superseded, expired or
+    /// revoked
+    pub fn sync_providers(&self, descriptors: Vec<ProviderDescriptor>) {
+        let current: HashSet<[u8; 32]> = descriptors.iter().map(|d| d.descriptor_id.0).collect();
+        for id in self.registry.ids() {
+            if !current.contains(&id) {
+                self.remove_provider(&id);
+            }
+        }
+        for descriptor in descriptors {
+            if self.registry.get(&descriptor.descriptor_id.0).is_none() {
+                self.register_provider(descriptor);
+            }
+        }
+    }
+
+    /// Route only to providers with these transport keys, or to any
+    /// provider with `None`
+    pub fn set_reachable(&self, keys: Option<Vec<Vec<u8>>>) {
+        *self.reachable.write() = keys.map(|keys| keys.into_iter().collect());
     }
 
     /// Route a request to a provider
@@ -106,6 +164,11 @@ impl Router {
             providers.retain(|p| !request.exclude.contains(&p.descriptor.descriptor_id.0));
         }
 
+        // Keep providers the inference node can reach
+        if let Some(reachable) = &*self.reachable.read() {
+            providers.retain(|p| reachable.contains(&p.descriptor.provider_transport_pubkey));
+        }
+
         // Filter by latency if specified
         if let Some(max_latency) = request.max_latency_ms {
             providers.retain(|p| {
@@ -131,13 +194,41 @@ impl Router {
             .find(|p| p.descriptor.descriptor_id.0 == selected.id)
             .ok_or(RouterError::ProvidersExhausted)?;
 
+        // Alternatives come from other clusters than the selection and each
+        // other, so a provider split into many descriptors is one choice
+        let mut clusters = vec![self.scorer.cluster_of(&selected.id)];
+        let alternatives = ranked
+            .into_iter()
+            .skip(1)
+            .filter(|scored| {
+                let cluster = self.scorer.cluster_of(&scored.id);
+                let new = !clusters.contains(&cluster);
+                clusters.push(cluster);
+                new
+            })
+            .take(3)
+            .collect();
+
         Ok(RouteResponse {
             provider: selected,
             state,
-            alternatives: ranked.into_iter().skip(1).take(3).collect(),
+            alternatives,
         })
     }
 
+    /// Model families with an available provider, sorted
+    pub fn model_families(&self) -> Vec<String> {
+        let mut families: Vec<String> = self
+            .registry
+            .all_available()
+            .into_iter()
+            .filter_map(|provider| provider.model_family)
+            .collect();
+        families.sort();
+        families.dedup();
+        families
+    }
+
     /// Report successful inference
     pub fn report_success(&self, provider_id: &[u8; 32], model_family: &str, latency_ms: f64) {
         let coord = TerrainCoord::new(model_family, 0);
@@ -157,6 +248,13 @@ impl Router {
         );
     }
 
+    /// Record how a provider behaved on a request, for link hints
+    pub fn record_behavior(&self, provider_id: &[u8; 32], sample: BehaviorSample) {
+        if let Some(similarity) = &self.similarity {
+            similarity.record(TargetRef(*provider_id), sample);
+        }
+    }
+
     /// Report failed inference
     pub fn report_failure(&self, provider_id: &[u8; 32], model_family: &str) {
         let coord = TerrainCoord::new(model_family, 0);
@@ -219,9 +317,11 @@ mod tests {
     fn test_config() -> Config {
         Config {
             listen: "127.0.0.1:9002".to_string(),
+            infernode: "127.0.0.1:9005".to_string(),
             gossipd: "127.0.0.1:9001".to_string(),
             cache_dir: std::path::PathBuf::from("/tmp/routerd-test"),
             world_phrase: "test".to_string(),
+            rule_bundle: None,
             min_reputation: 0.5,
             max_hops: 3,
             update_interval_secs: 60,
@@ -270,7 +370,96 @@ mod tests {
         };
 
         let response = router.route(request).unwrap();
-        assert!(!response.alternatives.is_empty() || true);
+        assert_eq!(response.alternatives.len(), 1);
+    }
+
+    #[test]
+    fn test_alternatives_span_clusters() {
+        let links = Arc::new(LinkGraph::new(Default::default()));
+        for signer in 10..12u8 {
+            links.apply(&Event {
+                event_id: EventId([signer; 32]),
+                world: WorldId([0u8; 32]),
+                epoch_id: 1,
+                event_type: EventType::LinkHint,
+                body: EventBody::LinkHint(LinkHintEvent {
+                    world: WorldId([0u8; 32]),
+                    epoch_id: 1,
+                    target_a: TargetRef([1; 32]),
+                    target_b: TargetRef([2; 32]),
+                    evidence_commitment: [0u8; 32],
+                    compatibility_score: 1.0,
+                    signer_transport_pubkey: vec![signer; 32],
+                    signature: vec![],
+                }),
+            });
+        }
+        links.recluster(|_| 1.0);
+        let router = Router::new(test_config()).with_links(links);
+        for id in 1..=3 {
+            router.register_provider(test_descriptor(id, "llama-3"));
+        }
+
+        let response = router
+            .route(RouteRequest {
+                model_family: "llama-3".to_string(),
+                capabilities: 1,
+                max_latency_ms: None,
+                preferred_hops: None,
+                exclude: vec![],
+            })
+            .unwrap();
+        // Descriptors 1 and 2 are one provider: only one other choice
+        assert_eq!(response.alternatives.len(), 1);
+    }
+
+    #[test]
+    fn test_routes_only_to_reachable_providers() {
+        let router = Router::new(test_config());
+        router.register_provider(test_descriptor(1, "llama-3"));
+        router.register_provider(test_descriptor(2, "llama-3"));
+        let request = RouteRequest {
+            model_family: "llama-3".to_string(),
+            capabilities: 1,
+            max_latency_ms: None,
+            preferred_hops: None,
+            exclude: vec![],
+        };
+
+        router.set_reachable(Some(vec![vec![2; 32]]));
+        let response = router.route(request.clone()).unwrap();
+        assert_eq!(response.provider.id, [2; 32]);
+        assert!(response.alternatives.is_empty());
+
+        router.set_reachable(Some(vec![]));
+        assert!(matches!(
+            router.route(request.clone()),
+            Err(RouterError::NoProviders(_))
+        ));
+
+        router.set_reachable(None);
+        assert_eq!(router.route(request).unwrap().alternatives.len(), 1);
+    }
+
+    #[test]
+    fn test_sync_providers() {
+        let router = Router::new(test_config());
+        router.sync_providers(vec![
+            test_descriptor(1, "llama-3"),
+            test_descriptor(2, "llama-3"),
+        ]);
+        router.report_success(&[1; 32], "llama-3", 50.0);
+
+        // A superseded descriptor is dropped; kept providers keep their stats
+        router.sync_providers(vec![
+            test_descriptor(1, "llama-3"),
+            test_descriptor(3, "llama-3"),
+        ]);
+        let mut ids = router.registry.ids();
+        ids.sort();
+        assert_eq!(ids, vec![[1; 32], [3; 32]]);
+        assert_eq!(router.registry.get(&[1; 32]).unwrap().successes, 1);
+        assert_eq!(router.registry.by_model("llama-3").len(), 2);
     }
 
     #[test]
@@ -3,6 +3,10 @@
 use crate::provider::ProviderState;
 use crate::terrain::{TerrainCoord, TerrainMap};
 use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::sync::Arc;
+use terrain_gossip_belief::{BeliefField, LinkGraph};
+use terrain_gossip_core::types::TargetRef;
 
 /// Scoring weights
 #[derive(Debug, Clone)]
@@ -17,6 +21,8 @@ pub struct ScoringWeights {
     pub latency: f64,
     /// Exploration bonus for low-traffic providers
     pub exploration: f64,
+    /// Belief field weight (only with a belief field attached)
+    pub belief: f64,
 }
 
 impl Default for ScoringWeights {
@@ -27,6 +33,7 @@ impl Default for ScoringWeights {
             success_rate: 0.2,
             latency: 0.15,
             exploration: 0.1,
+            belief: 0.25,
         }
     }
 }
@@ -47,6 +54,7 @@ pub struct ScoreComponents {
     pub success_rate: f64,
     pub latency: f64,
     pub exploration: f64,
+    pub belief: f64,
 }
 
 /// Provider scorer
@@ -54,11 +62,53 @@ pub struct Scorer {
     weights: ScoringWeights,
     /// Alpha for exploration vs exploitation
     alpha: f64,
+    /// Local beliefs about providers (probers report on `TargetRef(descriptor_id)`)
+    beliefs: Option<Arc<BeliefField>>,
+    /// Clusters of descriptors that link hints show to be one provider
+    links: Option<Arc<LinkGraph>>,
 }
 
 impl Scorer {
     pub fn new(weights: ScoringWeights, alpha: f64) -> Self {
-        Self { weights, alpha }
+        Self {
+            weights,
+            alpha,
+            beliefs: None,
+            links: None,
+        }
+    }
+
+    /// Score providers by belief as well
+    pub fn with_beliefs(mut self, beliefs: Arc<BeliefField>) -> Self {
+        self.beliefs = Some(beliefs);
+        self
+    }
+
+    /// Treat linked descriptors as one provider for beliefs and exploration
+    pub fn with_links(mut self, links: Arc<LinkGraph>) -> Self {
+        self.links = Some(links);
+        self
+    }
+
+    /// Cluster a provider belongs to (itself without link clustering)
+    pub fn cluster_of(&self, id: &[u8; 32]) -> [u8; 32] {
+        match &self.links {
+            Some(links) => links.cluster_of(&TargetRef(*id)).0,
+            None => *id,
+        }
+    }
+
+    /// Belief component in [0, 1]: quality discounted by uncertainty and
+    /// disagreement, 0.5 when nothing is known
+    fn belief(&self, id: &[u8; 32]) -> f64 {
+        let belief = self.beliefs.as_ref().and_then(|beliefs| match &self.links {
+            Some(links) => beliefs.cluster_belief(&links.members(&TargetRef(*id))),
+            None => beliefs.belief(&TargetRef(*id)),
+        });
+        match belief {
+            Some(b) => ((b.mu - 0.5 * b.sigma - b.disagreement).clamp(-1.0, 1.0) + 1.0) / 2.0,
+            None => 0.5,
+        }
     }
 
     /// Score a single provider for a coordinate
@@ -67,6 +117,17 @@ impl Scorer {
         provider: &ProviderState,
         coord: &TerrainCoord,
         terrain: &TerrainMap,
+    ) -> ScoredProvider {
+        self.score_with_usage(provider, coord, terrain, provider.successes + provider.failures)
+    }
+
+    /// Score a provider whose exploration bonus is based on `total_usage`
+    fn score_with_usage(
+        &self,
+        provider: &ProviderState,
+        coord: &TerrainCoord,
+        terrain: &TerrainMap,
+        total_usage: u64,
     ) -> ScoredProvider {
         let id = provider.descriptor.descriptor_id.0;
 
@@ -88,7 +149,6 @@ impl Scorer {
         };
 
         // Exploration bonus (inverse of usage)
-        let total_usage = provider.successes + provider.failures;
         let exploration = if total_usage < 10 {
             1.0 // High bonus for rarely-used providers
         } else if total_usage < 100 {
@@ -97,12 +157,18 @@ impl Scorer {
             0.1 // Low bonus for heavily-used providers
         };
 
+        // Belief component (neutral without a belief field)
+        let belief = self.belief(&id);
+
         // Compute weighted score
-        let score = self.weights.pheromone * pheromone
+        let mut score = self.weights.pheromone * pheromone
             + self.weights.reputation * reputation
             + self.weights.success_rate * success_rate
             + self.weights.latency * latency
             + self.weights.exploration * exploration * (1.0 - self.alpha);
+        if self.beliefs.is_some() {
+            score += self.weights.belief * belief;
+        }
 
         ScoredProvider {
             id,
@@ -113,6 +179,7 @@ impl Scorer {
                 success_rate,
                 latency,
                 exploration,
+                belief,
             },
         }
     }
@@ -124,9 +191,19 @@ impl Scorer {
         coord: &TerrainCoord,
         terrain: &TerrainMap,
     ) -> Vec<ScoredProvider> {
+        // Usage is counted per cluster, so splitting into many descriptors
+        // does not earn more exploration traffic
+        let mut usage: HashMap<[u8; 32], u64> = HashMap::new();
+        for p in providers {
+            let cluster = self.cluster_of(&p.descriptor.descriptor_id.0);
+            *usage.entry(cluster).or_default() += p.successes + p.failures;
+        }
         let mut scored: Vec<ScoredProvider> = providers
             .iter()
-            .map(|p| self.score(p, coord, terrain))
+            .map(|p| {
+                let cluster = self.cluster_of(&p.descriptor.descriptor_id.0);
+                self.score_with_usage(p, coord, terrain, usage[&cluster])
+            })
             .collect();
 
         // Sort by score descending
@@ -252,4 +329,93 @@ mod tests {
         // Higher reputation should rank higher
         assert_eq!(ranked[0].id, [2; 32]);
     }
+
+    #[test]
+    fn test_belief_ranking() {
+        let beliefs = Arc::new(BeliefField::new(Default::default(), Default::default()));
+        for (id, quality) in [(1u8, 0.95), (2, 0.2)] {
+            for prober in 10..13u8 {
+                beliefs.apply(&Event {
+                    event_id: EventId([id ^ prober; 32]),
+                    world: WorldId([0; 32]),
+                    epoch_id: 1,
+                    event_type: EventType::Attestation,
+                    body: EventBody::Attestation(BehaviorAttestation {
+                        attestation_id: AttestationId([prober; 32]),
+                        world: WorldId([0; 32]),
+                        epoch_id: 1,
+                        challenge_id: ChallengeId([prober; 32]),
+                        target_ref: TargetRef([id; 32]),
+                        target_fah: None,
+                        metrics: MetricsVector {
+                            success_rate: quality,
+                            refusal_consistency: quality,
+                            tool_fidelity: quality,
+                            latency_p50_ms: 200,
+                            latency_p95_ms: 300,
+                            robustness_score: quality,
+                            drift_indicator: 0.0,
+                            freshness: FreshnessStrength::None,
+                        },
+                        evidence_commitment: [0; 32],
+                        freshness_anchor: None,
+                        prober_transport_pubkey: vec![prober; 32],
+                        signature: vec![],
+                    }),
+                });
+            }
+        }
+
+        let scorer = Scorer::new(ScoringWeights::default(), 0.8).with_beliefs(beliefs);
+        let terrain = TerrainMap::new();
+        let coord = TerrainCoord::new("llama-3", 1);
+        let providers = vec![
+            test_provider(1, 0.5),
+            test_provider(2, 0.6),
+            test_provider(3, 0.55),
+        ];
+
+        // Probers agree provider 1 is good and 2 is poor; 3 is unknown
+        let ranked = scorer.rank(&providers, &coord, &terrain);
+        assert_eq!(ranked[0].id, [1; 32]);
+        assert_eq!(ranked[2].id, [2; 32]);
+        assert_eq!(ranked[1].components.belief, 0.5);
+    }
+
+    #[test]
+    fn test_linked_providers_share_usage() {
+        let links = Arc::new(LinkGraph::new(Default::default()));
+        for signer in 10..12u8 {
+            links.apply(&Event {
+                event_id: EventId([signer; 32]),
+                world: WorldId([0; 32]),
+                epoch_id: 1,
+                event_type: EventType::LinkHint,
+                body: EventBody::LinkHint(LinkHintEvent {
+                    world: WorldId([0; 32]),
+                    epoch_id: 1,
+                    target_a: TargetRef([1; 32]),
+                    target_b: TargetRef([2; 32]),
+                    evidence_commitment: [0; 32],
+                    compatibility_score: 1.0,
+                    signer_transport_pubkey: vec![signer; 32],
+                    signature: vec![],
+                }),
+            });
+        }
+        links.recluster(|_| 1.0);
+
+        let scorer = Scorer::new(ScoringWeights::default(), 0.8).with_links(links);
+        let terrain = TerrainMap::new();
+        let coord = TerrainCoord::new("llama-3", 1);
+        let mut providers = vec![test_provider(1, 0.5), test_provider(2, 0.5)];
+        providers[0].successes = 8;
+        providers[1].successes = 8;
+        assert_eq!(scorer.cluster_of(&[2; 32]), [1; 32]);
+
+        // Each descriptor alone is rarely used; together they are not
+        let ranked = scorer.rank(&providers, &coord, &terrain);
+        assert!(ranked.iter().all(|p| p.components.exploration == 0.5));
+        assert_eq!(scorer.score(&providers[0], &coord, &terrain).components.exploration, 1.0);
+    }
 }
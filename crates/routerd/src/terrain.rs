@@ -123,12 +123,12 @@ impl TerrainMap {
         let mut providers = self.providers_at.write();
         providers
             .entry(coord.clone())
-            .or_insert_with(Vec::new)
+            .or_default()
             .push(provider_id);
 
         // Initialize trail
         let edge = TerrainEdge { coord, provider_id };
-        self.trails.write().entry(edge).or_insert_with(PheromoneTrail::default);
+        self.trails.write().entry(edge).or_default();
     }
 
     /// Remove a provider
@@ -179,7 +179,7 @@ impl TerrainMap {
         let mut trails = self.trails.write();
         trails
             .entry(edge)
-            .or_insert_with(PheromoneTrail::default)
+            .or_default()
             .deposit(amount);
     }
 
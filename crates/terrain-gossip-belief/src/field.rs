@@ -0,0 +1,638 @@
+//! Per-target belief field (RFC-0001 §12.1, §12.3)
+//!
+//! Attestations become observations (see [`ObservationWeights`]), kept per
+//! target for a window of epochs. Each prober contributes one report per
+//! target and epoch: the median of its observations there. Beliefs are
+//! aggregated from reports on demand:
+//!
+//! - `disagreement` is the scaled MAD of all reports
+//! - reports further than `mad_cutoff` scaled MADs from the median are
+//!   rejected as outliers
+//! - `mu` is the trust-weighted median of the rest, `sigma` shrinks with the
+//!   effective number of reports and grows with their spread
+//! - `trend` is an EWMA of the change in per-epoch medians
+//!
+//! Reports are weighted by prober trust, and by `unreceipted_weight` unless
+//! the prober also published a receipt for the challenge. Aggregation does
+//! not depend on the order events were applied in.
+
+use crate::observation::ObservationWeights;
+use crate::residual::{self, COMPONENTS};
+use parking_lot::RwLock;
+use std::collections::{BTreeMap, HashMap};
+use terrain_gossip_core::types::*;
+
+/// Scale from MAD to standard deviation for normal data
+const MAD_SCALE: f64 = 1.4826;
+
+/// Smallest spread used for outlier rejection
+const MIN_SPREAD: f64 = 0.05;
+
+/// Uncertainty with no reports (observations span [-1, +1])
+const PRIOR_SIGMA: f64 = 1.0;
+
+type Prober = [u8; 32];
+
+/// Belief field configuration
+#[derive(Debug, Clone)]
+pub struct BeliefConfig {
+    /// Epochs of observations kept
+    pub window_epochs: u64,
+    /// Most observations kept per target (oldest epochs dropped first)
+    pub max_observations: usize,
+    /// Smoothing of the trend EWMA
+    pub trend_alpha: f64,
+    /// Scaled MADs from the median beyond which reports are rejected
+    pub mad_cutoff: f64,
+    /// Weight of reports without a matching probe receipt
+    pub unreceipted_weight: f64,
+    /// Trust of probers with no trust score
+    pub default_trust: f64,
+}
+
+impl Default for BeliefConfig {
+    fn default() -> Self {
+        Self {
+            window_epochs: 24,
+            max_observations: 1024,
+            trend_alpha: 0.3,
+            mad_cutoff: 3.0,
+            unreceipted_weight: 0.5,
+            default_trust: 0.5,
+        }
+    }
+}
+
+/// Local belief about one target
+#[derive(Debug, Clone, PartialEq)]
+pub struct Belief {
+    /// Expected quality in [-1, +1]
+    pub mu: f64,
+    /// Uncertainty of `mu`
+    pub sigma: f64,
+    /// EWMA of per-epoch change in quality
+    pub trend: f64,
+    /// Robust dispersion of reports
+    pub disagreement: f64,
+    /// Reports aggregated (one per prober and epoch)
+    pub reports: usize,
+    /// Reports rejected as outliers
+    pub outliers: usize,
+    /// Distinct probers reporting
+    pub probers: usize,
+}
+
+/// One prober's report on a target for an epoch
+#[derive(Debug, Clone, PartialEq)]
+pub struct Report {
+    pub prober: Prober,
+    pub epoch_id: u64,
+    /// Median of the prober's observations in the epoch
+    pub value: f64,
+    /// Component-wise median of the observed metrics (see
+    /// [`residual::components`])
+    pub components: [f64; COMPONENTS],
+    /// Whether a receipt backs one of the observations
+    pub receipted: bool,
+}
+
+#[derive(Debug, Clone)]
+struct Observation {
+    event_id: EventId,
+    prober: Prober,
+    epoch_id: u64,
+    challenge_id: ChallengeId,
+    value: f64,
+    components: [f64; COMPONENTS],
+}
+
+#[derive(Default)]
+struct FieldState {
+    /// Newest epoch seen
+    epoch_id: u64,
+    observations: HashMap<TargetRef, Vec<Observation>>,
+    /// Receipts by (prober, challenge), with their epoch
+    receipts: HashMap<(Prober, ChallengeId), u64>,
+    trust: HashMap<Prober, f64>,
+}
+
+/// Beliefs about targets, built incrementally from events
+pub struct BeliefField {
+    weights: ObservationWeights,
+    config: BeliefConfig,
+    state: RwLock<FieldState>,
+}
+
+impl BeliefField {
+    /// Create an empty field
+    pub fn new(weights: ObservationWeights, config: BeliefConfig) -> Self {
+        Self {
+            weights,
+            config,
+            state: RwLock::new(FieldState::default()),
+        }
+    }
+
+    /// Build a field from a log of events
+    pub fn rebuild<'a>(
+        weights: ObservationWeights,
+        config: BeliefConfig,
+        events: impl IntoIterator<Item = &'a Event>,
+    ) -> Self {
+        let field = Self::new(weights, config);
+        for event in events {
+            field.apply(event);
+        }
+        field
+    }
+
+    /// Apply one event, returning whether it was used
+    ///
+    /// Receipts and attestations are used; other events and repeats are
+    /// ignored, as are events older than the window.
+    pub fn apply(&self, event: &Event) -> bool {
+        let mut state = self.state.write();
+        if event.epoch_id + self.config.window_epochs < state.epoch_id {
+            return false;
+        }
+        let used = match &event.body {
+            EventBody::Receipt(receipt) => {
+                let Some(prober) = prober_key(&receipt.prober_transport_pubkey) else {
+                    return false;
+                };
+                state
+                    .receipts
+                    .insert((prober, receipt.challenge_id), event.epoch_id)
+                    .is_none()
+            }
+            EventBody::Attestation(attestation) => {
+                let Some(prober) = prober_key(&attestation.prober_transport_pubkey) else {
+                    return false;
+                };
+                let observations = state
+                    .observations
+                    .entry(attestation.target_ref)
+                    .or_default();
+                if observations.iter().any(|o| o.event_id == event.event_id) {
+                    return false;
+                }
+                observations.push(Observation {
+                    event_id: event.event_id,
+                    prober,
+                    epoch_id: event.epoch_id,
+                    challenge_id: attestation.challenge_id,
+                    value: self.weights.observe(&attestation.metrics),
+                    components: residual::components(&attestation.metrics),
+                });
+                if observations.len() > self.config.max_observations {
+                    // Drop the oldest; ties broken by event ID so the
+                    // survivors do not depend on arrival order
+                    observations.sort_by_key(|o| (std::cmp::Reverse(o.epoch_id), o.event_id.0));
+                    observations.truncate(self.config.max_observations);
+                }
+                true
+            }
+            _ => false,
+        };
+        if used && event.epoch_id > state.epoch_id {
+            state.epoch_id = event.epoch_id;
+            self.prune(&mut state);
+        }
+        used
+    }
+
+    /// Drop observations and receipts that left the window
+    fn prune(&self, state: &mut FieldState) {
+        let oldest = state.epoch_id.saturating_sub(self.config.window_epochs);
+        state.observations.retain(|_, observations| {
+            observations.retain(|o| o.epoch_id >= oldest);
+            !observations.is_empty()
+        });
+        state.receipts.retain(|_, epoch_id| *epoch_id >= oldest);
+    }
+
+    /// Newest epoch seen
+    pub fn epoch_id(&self) -> u64 {
+        self.state.read().epoch_id
+    }
+
+    /// Set a prober's trust in [0, 1]
+    pub fn set_trust(&self, prober: Prober, trust: f64) {
+        let trust = if trust.is_finite() {
+            trust.clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+        self.state.write().trust.insert(prober, trust);
+    }
+
+    /// A prober's trust
+    pub fn trust(&self, prober: &Prober) -> f64 {
+        self.state
+            .read()
+            .trust
+            .get(prober)
+            .copied()
+            .unwrap_or(self.config.default_trust)
+    }
+
+    /// Targets with observations, in byte order
+    pub fn targets(&self) -> Vec<TargetRef> {
+        let mut targets: Vec<TargetRef> = self.state.read().observations.keys().copied().collect();
+        targets.sort_by_key(|target| target.0);
+        targets
+    }
+
+    /// Reports on a target, ordered by epoch and prober
+    pub fn reports(&self, target: &TargetRef) -> Vec<Report> {
+        self.cluster_reports(std::slice::from_ref(target))
+    }
+
+    /// Reports on a cluster of targets taken as one provider, ordered by
+    /// epoch and prober
+    ///
+    /// A prober's observations across the cluster's targets in one epoch
+    /// form a single report.
+    pub fn cluster_reports(&self, targets: &[TargetRef]) -> Vec<Report> {
+        let state = self.state.read();
+        let mut grouped: BTreeMap<(u64, Prober), (Vec<&Observation>, bool)> = BTreeMap::new();
+        let observations = targets
+            .iter()
+            .filter_map(|target| state.observations.get(target))
+            .flatten();
+        for o in observations {
+            let group = grouped.entry((o.epoch_id, o.prober)).or_default();
+            group.0.push(o);
+            group.1 |= state.receipts.contains_key(&(o.prober, o.challenge_id));
+        }
+        grouped
+            .into_iter()
+            .map(|((epoch_id, prober), (observations, receipted))| {
+                let mut values: Vec<f64> = observations.iter().map(|o| o.value).collect();
+                let components: Vec<_> = observations.iter().map(|o| o.components).collect();
+                Report {
+                    prober,
+                    epoch_id,
+                    value: median(&mut values),
+                    components: residual::consensus(&components),
+                    receipted,
+                }
+            })
+            .collect()
+    }
+
+    /// Weight of a report in aggregation
+    fn weight(&self, report: &Report) -> f64 {
+        let receipt = if report.receipted {
+            1.0
+        } else {
+            self.config.unreceipted_weight
+        };
+        self.trust(&report.prober) * receipt
+    }
+
+    /// Belief about a target, if anything was observed
+    pub fn belief(&self, target: &TargetRef) -> Option<Belief> {
+        self.aggregate(self.reports(target))
+    }
+
+    /// Belief about a cluster of targets taken as one provider
+    pub fn cluster_belief(&self, targets: &[TargetRef]) -> Option<Belief> {
+        self.aggregate(self.cluster_reports(targets))
+    }
+
+    fn aggregate(&self, reports: Vec<Report>) -> Option<Belief> {
+        if reports.is_empty() {
+            return None;
+        }
+
+        let mut values: Vec<f64> = reports.iter().map(|r| r.value).collect();
+        let center = median(&mut values);
+        let disagreement = MAD_SCALE * mad(&values, center);
+        let cutoff = self.config.mad_cutoff * disagreement.max(MIN_SPREAD);
+        let kept: Vec<(&Report, f64)> = reports
+            .iter()
+            .filter(|r| (r.value - center).abs() <= cutoff)
+            .map(|r| (r, self.weight(r)))
+            .collect();
+
+        let weighted: Vec<(f64, f64)> = kept.iter().map(|(r, w)| (r.value, *w)).collect();
+        let mu = weighted_median(&weighted);
+        let total: f64 = weighted.iter().map(|(_, w)| w).sum();
+        let squares: f64 = weighted.iter().map(|(_, w)| w * w).sum();
+        let n_eff = if squares > 0.0 {
+            total * total / squares
+        } else {
+            0.0
+        };
+        let mut kept_values: Vec<f64> = weighted.iter().map(|(v, _)| *v).collect();
+        let kept_center = median(&mut kept_values);
+        let spread = MAD_SCALE * mad(&kept_values, kept_center);
+        let sigma = if n_eff > 0.0 {
+            (PRIOR_SIGMA.powi(2) / (1.0 + n_eff) + spread.powi(2) / n_eff)
+                .sqrt()
+                .min(PRIOR_SIGMA)
+        } else {
+            PRIOR_SIGMA
+        };
+
+        let mut probers: Vec<Prober> = reports.iter().map(|r| r.prober).collect();
+        probers.sort_unstable();
+        probers.dedup();
+
+        Some(Belief {
+            mu,
+            sigma,
+            trend: self.trend(&kept),
+            disagreement,
+            reports: reports.len(),
+            outliers: reports.len() - kept.len(),
+            probers: probers.len(),
+        })
+    }
+
+    /// EWMA of changes between consecutive per-epoch medians
+    fn trend(&self, kept: &[(&Report, f64)]) -> f64 {
+        let mut by_epoch: BTreeMap<u64, Vec<(f64, f64)>> = BTreeMap::new();
+        for (report, weight) in kept {
+            by_epoch
+                .entry(report.epoch_id)
+                .or_default()
+                .push((report.value, *weight));
+        }
+        let medians: Vec<f64> = by_epoch.values().map(|v| weighted_median(v)).collect();
+        medians.windows(2).fold(0.0, |trend, pair| {
+            let alpha = self.config.trend_alpha;
+            alpha * (pair[1] - pair[0]) + (1.0 - alpha) * trend
+        })
+    }
+
+    /// Beliefs about every observed target, in byte order
+    pub fn beliefs(&self) -> Vec<(TargetRef, Belief)> {
+        self.targets()
+            .into_iter()
+            .filter_map(|target| Some((target, self.belief(&target)?)))
+            .collect()
+    }
+}
+
+/// Prober identity from a transport public key
+fn prober_key(pubkey: &[u8]) -> Option<Prober> {
+    pubkey.try_into().ok()
+}
+
+pub(crate) fn median(values: &mut [f64]) -> f64 {
+    values.sort_by(f64::total_cmp);
+    let mid = values.len() / 2;
+    match values.len() {
+        0 => 0.0,
+        n if n % 2 == 1 => values[mid],
+        _ => (values[mid - 1] + values[mid]) / 2.0,
+    }
+}
+
+/// Median absolute deviation from `center`
+fn mad(values: &[f64], center: f64) -> f64 {
+    let mut deviations: Vec<f64> = values.iter().map(|v| (v - center).abs()).collect();
+    median(&mut deviations)
+}
+
+/// Weighted median of (value, weight) pairs; unweighted if no weight is
+/// positive
+pub(crate) fn weighted_median(values: &[(f64, f64)]) -> f64 {
+    let mut sorted: Vec<(f64, f64)> = values.iter().map(|(v, w)| (*v, w.max(0.0))).collect();
+    sorted.sort_by(|a, b| a.0.total_cmp(&b.0));
+    let total: f64 = sorted.iter().map(|(_, w)| w).sum();
+    if total <= 0.0 {
+        let mut plain: Vec<f64> = sorted.iter().map(|(v, _)| *v).collect();
+        return median(&mut plain);
+    }
+    let mut cumulative = 0.0;
+    for (i, (value, weight)) in sorted.iter().enumerate() {
+        cumulative += weight;
+        if cumulative * 2.0 > total {
+            return *value;
+        }
+        if cumulative * 2.0 == total {
+            // Exactly half below: average with the next weighted value
+            let next = sorted[i + 1..].iter().find(|(_, w)| *w > 0.0);
+            return next.map_or(*value, |(next, _)| (value + next) / 2.0);
+        }
+    }
+    sorted.last().map_or(0.0, |(v, _)| *v)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metrics(quality: f64) -> MetricsVector {
+        MetricsVector {
+            success_rate: quality,
+            refusal_consistency: quality,
+            tool_fidelity: quality,
+            latency_p50_ms: 0,
+            latency_p95_ms: 0,
+            robustness_score: quality,
+            drift_indicator: 0.0,
+            freshness: FreshnessStrength::None,
+        }
+    }
+
+    /// Observation for an attestation of `quality`
+    fn value(quality: f64) -> f64 {
+        ObservationWeights::default().observe(&metrics(quality))
+    }
+
+    fn attestation(prober: u8, target: u8, epoch_id: u64, quality: f64) -> Event {
+        let challenge_id = ChallengeId([prober ^ (epoch_id as u8); 32]);
+        Event {
+            event_id: event_id(prober, target, epoch_id, quality),
+            world: WorldId([0; 32]),
+            epoch_id,
+            event_type: EventType::Attestation,
+            body: EventBody::Attestation(BehaviorAttestation {
+                attestation_id: AttestationId([prober; 32]),
+                world: WorldId([0; 32]),
+                epoch_id,
+                challenge_id,
+                target_ref: TargetRef([target; 32]),
+                target_fah: None,
+                metrics: metrics(quality),
+                evidence_commitment: [0; 32],
+                freshness_anchor: None,
+                prober_transport_pubkey: vec![prober; 32],
+                signature: vec![],
+            }),
+        }
+    }
+
+    fn receipt(prober: u8, target: u8, epoch_id: u64) -> Event {
+        Event {
+            event_id: EventId([200 ^ prober ^ (epoch_id as u8); 32]),
+            world: WorldId([0; 32]),
+            epoch_id,
+            event_type: EventType::Receipt,
+            body: EventBody::Receipt(ProbeReceipt {
+                receipt_id: ReceiptId([prober; 32]),
+                world: WorldId([0; 32]),
+                epoch_id,
+                challenge_id: ChallengeId([prober ^ (epoch_id as u8); 32]),
+                target_ref: TargetRef([target; 32]),
+                target_fah: None,
+                outcome_commitment: [0; 32],
+                ticket: None,
+                prober_transport_pubkey: vec![prober; 32],
+                signature: vec![],
+            }),
+        }
+    }
+
+    fn event_id(prober: u8, target: u8, epoch_id: u64, quality: f64) -> EventId {
+        let mut id = [0u8; 32];
+        id[0] = prober;
+        id[1] = target;
+        id[2..10].copy_from_slice(&epoch_id.to_le_bytes());
+        id[10..18].copy_from_slice(&quality.to_le_bytes());
+        EventId(id)
+    }
+
+    fn field() -> BeliefField {
+        BeliefField::new(ObservationWeights::default(), BeliefConfig::default())
+    }
+
+    #[test]
+    fn test_liar_rejected_and_disagreement() {
+        let field = field();
+        assert!(field.belief(&TargetRef([1; 32])).is_none());
+
+        for prober in 1..=4 {
+            assert!(field.apply(&attestation(prober, 1, 1, 0.9)));
+        }
+        // Repeats are ignored
+        assert!(!field.apply(&attestation(1, 1, 1, 0.9)));
+        let agreed = field.belief(&TargetRef([1; 32])).unwrap();
+        assert_eq!(agreed.mu, value(0.9));
+        assert_eq!(agreed.disagreement, 0.0);
+
+        // A lying prober is rejected as an outlier
+        field.apply(&attestation(5, 1, 1, 0.0));
+        let belief = field.belief(&TargetRef([1; 32])).unwrap();
+        assert_eq!(belief.outliers, 1);
+        assert_eq!(belief.probers, 5);
+        assert_eq!(belief.mu, value(0.9));
+
+        // Genuinely split reports show up as disagreement
+        for prober in 6..=9 {
+            field.apply(&attestation(
+                prober,
+                2,
+                1,
+                if prober % 2 == 0 { 0.2 } else { 0.9 },
+            ));
+        }
+        let split = field.belief(&TargetRef([2; 32])).unwrap();
+        assert!(split.disagreement > 0.5);
+        assert_eq!(split.outliers, 0);
+        assert!(split.sigma > agreed.sigma);
+    }
+
+    #[test]
+    fn test_trust_and_receipts_weight_reports() {
+        let field = field();
+        field.apply(&attestation(1, 1, 1, 1.0));
+        field.apply(&attestation(2, 1, 1, 0.75));
+        field.apply(&attestation(3, 1, 1, 0.5));
+        let even = field.belief(&TargetRef([1; 32])).unwrap().mu;
+        assert_eq!(even, value(0.75));
+
+        // A receipt doubles prober 3's weight
+        assert!(field.apply(&receipt(3, 1, 1)));
+        assert!(field.reports(&TargetRef([1; 32]))[2].receipted);
+        assert!(field.belief(&TargetRef([1; 32])).unwrap().mu < even);
+
+        // An untrusted prober no longer counts
+        field.set_trust([3; 32], 0.0);
+        assert_eq!(field.trust(&[3; 32]), 0.0);
+        let mu = field.belief(&TargetRef([1; 32])).unwrap().mu;
+        assert!(mu > even);
+    }
+
+    #[test]
+    fn test_cluster_belief_pools_targets() {
+        let field = field();
+        // A provider split across two targets looks agreed on each alone
+        for prober in 1..=3 {
+            field.apply(&attestation(prober, 1, 1, 0.9));
+            field.apply(&attestation(prober + 3, 2, 1, 0.3));
+        }
+        // Prober 1 also probed the second target in the same epoch
+        field.apply(&attestation(1, 2, 1, 0.3));
+        assert_eq!(field.belief(&TargetRef([1; 32])).unwrap().disagreement, 0.0);
+        assert_eq!(field.belief(&TargetRef([2; 32])).unwrap().disagreement, 0.0);
+
+        let targets = [TargetRef([1; 32]), TargetRef([2; 32])];
+        let reports = field.cluster_reports(&targets);
+        assert_eq!(reports.len(), 6);
+        assert_eq!(reports[0].value, (value(0.9) + value(0.3)) / 2.0);
+        let pooled = field.cluster_belief(&targets).unwrap();
+        assert!(pooled.disagreement > 0.3);
+        assert_eq!(pooled.probers, 6);
+        assert!(field.cluster_belief(&[TargetRef([9; 32])]).is_none());
+    }
+
+    #[test]
+    fn test_trend_and_window() {
+        let field = BeliefField::new(
+            ObservationWeights::default(),
+            BeliefConfig {
+                window_epochs: 3,
+                ..BeliefConfig::default()
+            },
+        );
+        for epoch_id in 1..=4 {
+            let quality = 0.5 + 0.1 * epoch_id as f64;
+            field.apply(&attestation(1, 1, epoch_id, quality));
+            field.apply(&attestation(2, 1, epoch_id, quality));
+        }
+        let belief = field.belief(&TargetRef([1; 32])).unwrap();
+        assert!(belief.trend > 0.0);
+        assert_eq!(belief.reports, 8);
+
+        // Old epochs leave the window and late arrivals from them are ignored
+        field.apply(&attestation(1, 1, 7, 0.9));
+        assert_eq!(field.epoch_id(), 7);
+        assert_eq!(field.belief(&TargetRef([1; 32])).unwrap().reports, 3);
+        assert!(!field.apply(&attestation(3, 1, 2, 0.9)));
+    }
+
+    #[test]
+    fn test_rebuild_is_order_independent() {
+        let mut events: Vec<Event> = (1..=6u8)
+            .flat_map(|prober| {
+                (1..=3).map(move |epoch_id| {
+                    attestation(
+                        prober,
+                        prober % 2,
+                        epoch_id,
+                        0.1 * (prober as f64 + epoch_id as f64) / 2.0,
+                    )
+                })
+            })
+            .collect();
+        events.push(receipt(2, 0, 2));
+        let forward = BeliefField::rebuild(
+            ObservationWeights::default(),
+            BeliefConfig::default(),
+            &events,
+        );
+        events.reverse();
+        let backward = BeliefField::rebuild(
+            ObservationWeights::default(),
+            BeliefConfig::default(),
+            &events,
+        );
+        assert_eq!(forward.beliefs().len(), 2);
+        assert_eq!(forward.beliefs(), backward.beliefs());
+    }
+}
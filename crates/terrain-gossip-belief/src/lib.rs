@@ -0,0 +1,29 @@
+//! Local belief field for TerrainGossip routing (RFC-0001 §4.4, §12)
+//!
+//! This crate provides:
+//! - Observation mapping of attestation metrics using rule bundle weights
+//! - Per-target beliefs (mu, sigma, trend, disagreement) from robust
+//!   median/MAD aggregation, weighted by per-prober trust
+//! - Prober trust from how well reports predict later consensus, capped for
+//!   correlated probers and persisted across restarts
+//! - Residuals from consensus and correlated-prober clustering, shared
+//!   with gossipd's anomaly detection
+//! - Clustering of targets that link hints show to be one provider
+//! - Link hints from observer-local behavioral fingerprints of targets
+//!
+//! Beliefs are local: they are computed from the event log, never gossiped,
+//! and rebuilding from the same events gives the same field.
+
+pub mod field;
+pub mod link;
+pub mod observation;
+pub mod residual;
+pub mod similarity;
+pub mod trust;
+
+pub use field::{Belief, BeliefConfig, BeliefField, Report};
+pub use link::{LinkConfig, LinkGraph};
+pub use observation::ObservationWeights;
+pub use residual::CorrelationConfig;
+pub use similarity::{BehaviorSample, LinkFinding, SimilarityConfig, SimilarityDetector};
+pub use trust::{TrustConfig, TrustEngine, TrustError};
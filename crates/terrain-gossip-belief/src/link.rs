@@ -0,0 +1,277 @@
+//! Clustering targets from link hints
+//!
+//! A `LinkHintEvent` is one signer's evidence that two TargetRefs are the
+//! same provider. Each signer's latest hint per pair counts once, weighted
+//! by the signer's earned trust. Two targets are linked when the weighted
+//! compatibility reaches `threshold` from at least `min_signers` signers,
+//! and clusters are the connected components of the links. Clusters are
+//! recomputed from the hints on [`LinkGraph::recluster`], so they split
+//! again if hints expire or their signers lose trust.
+
+use parking_lot::RwLock;
+use std::collections::{BTreeMap, HashMap};
+use terrain_gossip_core::types::*;
+
+type Signer = [u8; 32];
+
+/// Link clustering thresholds
+#[derive(Debug, Clone)]
+pub struct LinkConfig {
+    /// Trust-weighted compatibility that links two targets
+    pub threshold: f64,
+    /// Distinct signers needed to link two targets
+    pub min_signers: usize,
+    /// Epochs of hints kept
+    pub window_epochs: u64,
+}
+
+impl Default for LinkConfig {
+    fn default() -> Self {
+        Self {
+            threshold: 1.0,
+            min_signers: 2,
+            window_epochs: 168,
+        }
+    }
+}
+
+#[derive(Default)]
+struct LinkState {
+    /// Newest epoch seen
+    epoch_id: u64,
+    /// Latest (epoch, score) per signer, by ordered target pair
+    hints: HashMap<(TargetRef, TargetRef), HashMap<Signer, (u64, f64)>>,
+    /// Cluster ID (smallest member) of each clustered target
+    clusters: HashMap<TargetRef, TargetRef>,
+}
+
+/// Link hints and the target clusters they support
+pub struct LinkGraph {
+    config: LinkConfig,
+    state: RwLock<LinkState>,
+}
+
+impl LinkGraph {
+    /// Create a graph with no hints
+    pub fn new(config: LinkConfig) -> Self {
+        Self {
+            config,
+            state: RwLock::new(LinkState::default()),
+        }
+    }
+
+    /// Record a link hint, returning whether it was used
+    ///
+    /// Hints between a target and itself, from malformed signers or older
+    /// than the window or than the signer's current hint are ignored.
+    pub fn apply(&self, event: &Event) -> bool {
+        let EventBody::LinkHint(hint) = &event.body else {
+            return false;
+        };
+        let Ok(signer) = Signer::try_from(hint.signer_transport_pubkey.as_slice()) else {
+            return false;
+        };
+        if hint.target_a == hint.target_b || !hint.compatibility_score.is_finite() {
+            return false;
+        }
+        let mut state = self.state.write();
+        if event.epoch_id + self.config.window_epochs < state.epoch_id {
+            return false;
+        }
+        let pair = if hint.target_a.0 < hint.target_b.0 {
+            (hint.target_a, hint.target_b)
+        } else {
+            (hint.target_b, hint.target_a)
+        };
+        let score = hint.compatibility_score.clamp(0.0, 1.0);
+        let signers = state.hints.entry(pair).or_default();
+        match signers.get(&signer) {
+            Some((epoch_id, _)) if *epoch_id >= event.epoch_id => return false,
+            _ => signers.insert(signer, (event.epoch_id, score)),
+        };
+        if event.epoch_id > state.epoch_id {
+            state.epoch_id = event.epoch_id;
+            let oldest = state.epoch_id.saturating_sub(self.config.window_epochs);
+            state.hints.retain(|_, signers| {
+                signers.retain(|_, (epoch_id, _)| *epoch_id >= oldest);
+                !signers.is_empty()
+            });
+        }
+        true
+    }
+
+    /// Recompute clusters with the given signer trust, returning the number
+    /// of clusters of two or more targets
+    ///
+    /// Signers without earned trust (see [`TrustEngine::earned_trust`])
+    /// must be given 0, or throwaway keys could merge any two targets.
+    ///
+    /// [`TrustEngine::earned_trust`]: crate::TrustEngine::earned_trust
+    pub fn recluster(&self, trust: impl Fn(&Signer) -> f64) -> usize {
+        let mut state = self.state.write();
+        let mut links: Vec<(TargetRef, TargetRef)> = state
+            .hints
+            .iter()
+            .filter(|(_, signers)| {
+                let weight: f64 = signers
+                    .iter()
+                    .map(|(signer, (_, score))| trust(signer).clamp(0.0, 1.0) * score)
+                    .sum();
+                signers.len() >= self.config.min_signers && weight >= self.config.threshold
+            })
+            .map(|(pair, _)| *pair)
+            .collect();
+        links.sort_by_key(|(a, b)| (a.0, b.0));
+
+        let mut parent: HashMap<TargetRef, TargetRef> = HashMap::new();
+        for (a, b) in links {
+            let (root_a, root_b) = (find(&mut parent, a), find(&mut parent, b));
+            if root_a != root_b {
+                // Smallest member is the cluster ID
+                let (root, child) = if root_a.0 < root_b.0 {
+                    (root_a, root_b)
+                } else {
+                    (root_b, root_a)
+                };
+                parent.insert(child, root);
+            }
+        }
+        let targets: Vec<TargetRef> = parent.keys().copied().collect();
+        state.clusters = targets
+            .into_iter()
+            .map(|target| (target, find(&mut parent, target)))
+            .collect();
+        let mut roots: Vec<TargetRef> = state.clusters.values().copied().collect();
+        roots.sort_by_key(|root| root.0);
+        roots.dedup();
+        roots.len()
+    }
+
+    /// Cluster ID of a target (the target itself when unlinked)
+    pub fn cluster_of(&self, target: &TargetRef) -> TargetRef {
+        self.state
+            .read()
+            .clusters
+            .get(target)
+            .copied()
+            .unwrap_or(*target)
+    }
+
+    /// Targets in the same cluster as `target`, including it, in byte order
+    pub fn members(&self, target: &TargetRef) -> Vec<TargetRef> {
+        let state = self.state.read();
+        let Some(cluster) = state.clusters.get(target) else {
+            return vec![*target];
+        };
+        let mut members: Vec<TargetRef> = state
+            .clusters
+            .iter()
+            .filter(|(_, c)| *c == cluster)
+            .map(|(target, _)| *target)
+            .collect();
+        members.sort_by_key(|target| target.0);
+        members
+    }
+
+    /// Clusters of two or more targets, each in byte order
+    pub fn clusters(&self) -> Vec<Vec<TargetRef>> {
+        let mut clusters: BTreeMap<Bytes32, Vec<TargetRef>> = BTreeMap::new();
+        for (target, cluster) in &self.state.read().clusters {
+            clusters.entry(cluster.0).or_default().push(*target);
+        }
+        clusters
+            .into_values()
+            .map(|mut members| {
+                members.sort_by_key(|target| target.0);
+                members
+            })
+            .collect()
+    }
+}
+
+fn find(parent: &mut HashMap<TargetRef, TargetRef>, target: TargetRef) -> TargetRef {
+    let mut root = target;
+    while let Some(next) = parent.get(&root).filter(|next| **next != root) {
+        root = *next;
+    }
+    parent.insert(target, root);
+    root
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hint(signer: u8, a: u8, b: u8, epoch_id: u64, score: f64) -> Event {
+        Event {
+            event_id: EventId([signer ^ a ^ b; 32]),
+            world: WorldId([0; 32]),
+            epoch_id,
+            event_type: EventType::LinkHint,
+            body: EventBody::LinkHint(LinkHintEvent {
+                world: WorldId([0; 32]),
+                epoch_id,
+                target_a: TargetRef([a; 32]),
+                target_b: TargetRef([b; 32]),
+                evidence_commitment: [0; 32],
+                compatibility_score: score,
+                signer_transport_pubkey: vec![signer; 32],
+                signature: vec![],
+            }),
+        }
+    }
+
+    #[test]
+    fn test_hints_merge_targets() {
+        let links = LinkGraph::new(LinkConfig::default());
+        let trusted = |_: &Signer| 0.6;
+
+        // One signer is not enough, however confident
+        assert!(links.apply(&hint(1, 1, 2, 1, 1.0)));
+        assert!(!links.apply(&hint(1, 2, 1, 1, 1.0)));
+        assert_eq!(links.recluster(trusted), 0);
+        assert_eq!(links.cluster_of(&TargetRef([2; 32])), TargetRef([2; 32]));
+
+        // A second signer reaches the threshold; a chain joins a third target
+        links.apply(&hint(2, 2, 1, 1, 0.8));
+        links.apply(&hint(1, 2, 3, 1, 0.9));
+        links.apply(&hint(3, 3, 2, 1, 0.9));
+        assert!(!links.apply(&hint(4, 5, 5, 1, 1.0)));
+        assert_eq!(links.recluster(trusted), 1);
+        assert_eq!(links.cluster_of(&TargetRef([3; 32])), TargetRef([1; 32]));
+        assert_eq!(
+            links.members(&TargetRef([2; 32])),
+            vec![TargetRef([1; 32]), TargetRef([2; 32]), TargetRef([3; 32])]
+        );
+        assert_eq!(links.members(&TargetRef([4; 32])), vec![TargetRef([4; 32])]);
+
+        // Distrusted signers no longer hold the cluster together
+        assert_eq!(
+            links.recluster(|signer: &Signer| if signer[0] == 1 { 0.0 } else { 0.6 }),
+            0
+        );
+        assert!(links.clusters().is_empty());
+    }
+
+    #[test]
+    fn test_signers_revise_and_hints_expire() {
+        let links = LinkGraph::new(LinkConfig {
+            window_epochs: 10,
+            ..LinkConfig::default()
+        });
+        links.apply(&hint(1, 1, 2, 1, 0.9));
+        links.apply(&hint(2, 1, 2, 1, 0.9));
+        assert_eq!(links.recluster(|_| 1.0), 1);
+
+        // A signer's later hint replaces its earlier one
+        links.apply(&hint(2, 1, 2, 3, 0.05));
+        assert_eq!(links.recluster(|_| 1.0), 0);
+        assert!(!links.apply(&hint(2, 1, 2, 2, 0.9)));
+
+        // Hints outside the window are dropped
+        links.apply(&hint(3, 1, 2, 4, 0.9));
+        assert_eq!(links.recluster(|_| 1.0), 1);
+        links.apply(&hint(4, 7, 8, 14, 0.1));
+        assert_eq!(links.recluster(|_| 1.0), 0);
+    }
+}
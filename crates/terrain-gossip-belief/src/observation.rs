@@ -0,0 +1,157 @@
+//! Mapping attestation metrics to observations (RFC-0001 §12.2)
+//!
+//! An observation is a single quality value in [-1, +1]: the weighted mean
+//! of normalized metrics, rescaled, minus a penalty for inconsistency
+//! (latency jitter and drift).
+
+use crate::residual::{
+    self, DRIFT, LATENCY, REFUSAL_CONSISTENCY, ROBUSTNESS, SUCCESS, TOOL_FIDELITY,
+};
+use terrain_gossip_core::types::{MetricsVector, RuleBundle};
+
+/// p50 latency at which the latency component reaches 0.5
+pub(crate) const LATENCY_SCALE_MS: f64 = 1_000.0;
+
+/// Largest penalty for fully inconsistent behavior
+const INCONSISTENCY_PENALTY: f64 = 0.5;
+
+/// Metric weights for observation mapping
+#[derive(Debug, Clone, PartialEq)]
+pub struct ObservationWeights {
+    pub success: f64,
+    pub tool_fidelity: f64,
+    pub latency: f64,
+    pub refusal_consistency: f64,
+    pub robustness: f64,
+}
+
+impl Default for ObservationWeights {
+    fn default() -> Self {
+        Self {
+            success: 0.2,
+            tool_fidelity: 0.2,
+            latency: 0.2,
+            refusal_consistency: 0.2,
+            robustness: 0.2,
+        }
+    }
+}
+
+impl ObservationWeights {
+    /// Weights taken from a world's rule bundle
+    pub fn from_rule_bundle(bundle: &RuleBundle) -> Self {
+        Self {
+            success: bundle.w_success,
+            tool_fidelity: bundle.w_tool_fidelity,
+            latency: bundle.w_latency,
+            refusal_consistency: bundle.w_refusal_consistency,
+            robustness: bundle.w_robustness,
+        }
+    }
+
+    /// Observation in [-1, +1] for one metrics report
+    ///
+    /// Negative or non-finite weights count as zero; if none remain, all
+    /// metrics are weighted equally.
+    pub fn observe(&self, metrics: &MetricsVector) -> f64 {
+        let p50 = metrics.latency_p50_ms as f64;
+        let p95 = (metrics.latency_p95_ms as f64).max(p50);
+        let values = residual::components(metrics);
+        let components = [
+            (self.success, values[SUCCESS]),
+            (self.tool_fidelity, values[TOOL_FIDELITY]),
+            (self.latency, values[LATENCY]),
+            (self.refusal_consistency, values[REFUSAL_CONSISTENCY]),
+            (self.robustness, values[ROBUSTNESS]),
+        ]
+        .map(|(weight, value)| (unit(weight.max(0.0), f64::INFINITY), value));
+
+        let total: f64 = components.iter().map(|(weight, _)| weight).sum();
+        let quality = if total > 0.0 {
+            components.iter().map(|(w, v)| w * v).sum::<f64>() / total
+        } else {
+            components.iter().map(|(_, v)| v).sum::<f64>() / components.len() as f64
+        };
+
+        let jitter = (p95 - p50) / (p95 + LATENCY_SCALE_MS);
+        let inconsistency = (jitter + values[DRIFT]) / 2.0;
+        (2.0 * quality - 1.0 - INCONSISTENCY_PENALTY * inconsistency).clamp(-1.0, 1.0)
+    }
+}
+
+/// Clamp to [0, max], treating non-finite values as zero
+fn unit(value: f64, max: f64) -> f64 {
+    if value.is_finite() {
+        value.clamp(0.0, max)
+    } else {
+        0.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use terrain_gossip_core::types::FreshnessStrength;
+
+    fn metrics(quality: f64, p50: u32, p95: u32, drift: f64) -> MetricsVector {
+        MetricsVector {
+            success_rate: quality,
+            refusal_consistency: quality,
+            tool_fidelity: quality,
+            latency_p50_ms: p50,
+            latency_p95_ms: p95,
+            robustness_score: quality,
+            drift_indicator: drift,
+            freshness: FreshnessStrength::None,
+        }
+    }
+
+    #[test]
+    fn test_observation_range_and_penalties() {
+        let weights = ObservationWeights::default();
+        let good = weights.observe(&metrics(1.0, 0, 0, 0.0));
+        assert!((good - 1.0).abs() < 1e-9);
+        assert_eq!(
+            weights.observe(&metrics(0.0, u32::MAX, u32::MAX, 1.0)),
+            -1.0
+        );
+
+        // Slow, jittery or drifting providers score lower
+        let steady = weights.observe(&metrics(0.9, 100, 150, 0.0));
+        assert!(weights.observe(&metrics(0.9, 2000, 2500, 0.0)) < steady);
+        assert!(weights.observe(&metrics(0.9, 100, 5000, 0.0)) < steady);
+        assert!(weights.observe(&metrics(0.9, 100, 150, 0.8)) < steady);
+
+        // Garbage metrics stay in range
+        let nan = weights.observe(&metrics(f64::NAN, 0, 0, f64::INFINITY));
+        assert!((-1.0..=1.0).contains(&nan));
+    }
+
+    #[test]
+    fn test_rule_bundle_weights() {
+        let mut report = metrics(1.0, 0, 0, 0.0);
+        report.success_rate = 0.0;
+        let success_only = ObservationWeights {
+            success: 1.0,
+            tool_fidelity: 0.0,
+            latency: 0.0,
+            refusal_consistency: 0.0,
+            robustness: 0.0,
+        };
+        assert_eq!(success_only.observe(&report), -1.0);
+        assert!(ObservationWeights::default().observe(&report) > 0.5);
+
+        // No usable weights: equal weighting
+        let none = ObservationWeights {
+            success: -1.0,
+            tool_fidelity: 0.0,
+            latency: 0.0,
+            refusal_consistency: 0.0,
+            robustness: f64::NAN,
+        };
+        assert_eq!(
+            none.observe(&report),
+            ObservationWeights::default().observe(&report)
+        );
+    }
+}
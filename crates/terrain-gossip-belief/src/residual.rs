@@ -0,0 +1,189 @@
+//! Prober residuals and correlated-prober clustering
+//!
+//! Shared by prober trust and gossipd's anomaly detection, so both map
+//! metrics and judge probers alike. Each report's metrics become components
+//! in [0, 1]; its residual is the signed distance from the component-wise
+//! median of the reports in its group (one target and epoch). Two
+//! probers are correlated when, over enough shared groups, both sit clear
+//! of consensus and their residuals point the same way. Correlated probers
+//! form clusters through transitive links.
+
+use crate::field::median;
+use crate::observation::LATENCY_SCALE_MS;
+use std::collections::{BTreeMap, BTreeSet};
+use terrain_gossip_core::types::MetricsVector;
+
+/// Metric components compared against consensus
+pub const COMPONENTS: usize = 6;
+
+/// Index of each component
+pub const SUCCESS: usize = 0;
+pub const REFUSAL_CONSISTENCY: usize = 1;
+pub const TOOL_FIDELITY: usize = 2;
+pub const ROBUSTNESS: usize = 3;
+pub const DRIFT: usize = 4;
+pub const LATENCY: usize = 5;
+
+/// Signed distance of a report's components from consensus
+pub type Residual = [f64; COMPONENTS];
+
+/// Metrics as comparable components in [0, 1]
+///
+/// Latency maps to `LATENCY_SCALE_MS / (LATENCY_SCALE_MS + p50)`, so faster
+/// is higher like the other quality components. Non-finite values count as
+/// zero.
+pub fn components(metrics: &MetricsVector) -> [f64; COMPONENTS] {
+    let p50 = metrics.latency_p50_ms as f64;
+    let mut values = [0.0; COMPONENTS];
+    values[SUCCESS] = metrics.success_rate;
+    values[REFUSAL_CONSISTENCY] = metrics.refusal_consistency;
+    values[TOOL_FIDELITY] = metrics.tool_fidelity;
+    values[ROBUSTNESS] = metrics.robustness_score;
+    values[DRIFT] = metrics.drift_indicator;
+    values[LATENCY] = LATENCY_SCALE_MS / (LATENCY_SCALE_MS + p50);
+    values.map(|x| {
+        if x.is_finite() {
+            x.clamp(0.0, 1.0)
+        } else {
+            0.0
+        }
+    })
+}
+
+/// Component-wise median of `reports`
+pub fn consensus(reports: &[[f64; COMPONENTS]]) -> [f64; COMPONENTS] {
+    let mut center = [0.0; COMPONENTS];
+    for (i, value) in center.iter_mut().enumerate() {
+        let mut column: Vec<f64> = reports.iter().map(|r| r[i]).collect();
+        *value = median(&mut column);
+    }
+    center
+}
+
+/// Residual of each report in a group from the group's consensus
+pub fn residuals(reports: &[[f64; COMPONENTS]]) -> Vec<Residual> {
+    let center = consensus(reports);
+    reports
+        .iter()
+        .map(|report| std::array::from_fn(|i| report[i] - center[i]))
+        .collect()
+}
+
+/// Largest component distance from consensus
+pub fn deviation(residual: &Residual) -> f64 {
+    residual.iter().fold(0.0f64, |max, x| max.max(x.abs()))
+}
+
+/// When two probers' residuals are correlated
+#[derive(Debug, Clone)]
+pub struct CorrelationConfig {
+    /// Cosine similarity of residuals that links two probers
+    pub threshold: f64,
+    /// Groups two probers must share before they are compared
+    pub min_shared: usize,
+    /// Mean deviation both probers need; honest probers hover near
+    /// consensus and their noise is not a signal
+    pub noise_floor: f64,
+}
+
+impl CorrelationConfig {
+    /// Correlation of two probers' residuals on the groups they share, if
+    /// it links them
+    pub fn correlation(&self, shared: &[(Residual, Residual)]) -> Option<f64> {
+        if shared.is_empty() || shared.len() < self.min_shared {
+            return None;
+        }
+        let (mut dot, mut norm_a, mut norm_b) = (0.0, 0.0, 0.0);
+        let (mut dev_a, mut dev_b) = (0.0, 0.0);
+        for (a, b) in shared {
+            for i in 0..COMPONENTS {
+                dot += a[i] * b[i];
+                norm_a += a[i] * a[i];
+                norm_b += b[i] * b[i];
+            }
+            dev_a += deviation(a);
+            dev_b += deviation(b);
+        }
+        let n = shared.len() as f64;
+        if dev_a / n < self.noise_floor || dev_b / n < self.noise_floor {
+            return None;
+        }
+        let correlation = dot / (norm_a.sqrt() * norm_b.sqrt());
+        (correlation >= self.threshold).then_some(correlation)
+    }
+}
+
+/// Clusters of probers connected by `links`, each sorted, in order
+pub fn clusters<P: Ord + Clone>(links: &[(P, P)]) -> Vec<Vec<P>> {
+    let mut neighbors: BTreeMap<&P, Vec<&P>> = BTreeMap::new();
+    for (a, b) in links {
+        neighbors.entry(a).or_default().push(b);
+        neighbors.entry(b).or_default().push(a);
+    }
+    let mut seen: BTreeSet<&P> = BTreeSet::new();
+    let mut clusters = Vec::new();
+    for start in neighbors.keys() {
+        if !seen.insert(start) {
+            continue;
+        }
+        let mut members = vec![(*start).clone()];
+        let mut frontier = vec![*start];
+        while let Some(current) = frontier.pop() {
+            for next in &neighbors[current] {
+                if seen.insert(next) {
+                    members.push((*next).clone());
+                    frontier.push(next);
+                }
+            }
+        }
+        members.sort();
+        clusters.push(members);
+    }
+    clusters
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shifted(shift: f64) -> Residual {
+        [shift, shift, 0.0, 0.0, 0.0, 0.0]
+    }
+
+    #[test]
+    fn test_correlation_and_clusters() {
+        let config = CorrelationConfig {
+            threshold: 0.9,
+            min_shared: 3,
+            noise_floor: 0.1,
+        };
+        let together: Vec<_> = [0.2, 0.3, 0.25]
+            .iter()
+            .map(|s| (shifted(*s), shifted(s + 0.01)))
+            .collect();
+        assert!(config.correlation(&together).unwrap() > 0.99);
+        assert!(config.correlation(&together[..2]).is_none());
+
+        // Opposite pulls and noise near consensus do not link
+        let opposed: Vec<_> = together.iter().map(|(a, b)| (*a, b.map(|x| -x))).collect();
+        assert!(config.correlation(&opposed).is_none());
+        let quiet: Vec<_> = together
+            .iter()
+            .map(|(a, b)| (a.map(|x| x / 10.0), b.map(|x| x / 10.0)))
+            .collect();
+        assert!(config.correlation(&quiet).is_none());
+
+        assert_eq!(
+            clusters(&[(3, 4), (1, 2), (2, 5)]),
+            vec![vec![1, 2, 5], vec![3, 4]]
+        );
+    }
+
+    #[test]
+    fn test_residuals_from_median() {
+        let reports = [[0.9; COMPONENTS], [0.8; COMPONENTS], [0.1; COMPONENTS]];
+        let residuals = residuals(&reports);
+        assert!(residuals[1].iter().all(|x| x.abs() < 1e-9));
+        assert!((deviation(&residuals[2]) - 0.7).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,379 @@
+//! Link hints from behavioral similarity
+//!
+//! Each observer keeps a local fingerprint of every target it talks to:
+//! response latencies, response lengths (style), refusals and the outputs of
+//! deterministic canary prompts. Two targets are compared with two-sample
+//! tests on each part; if no test rejects them as different backends, the
+//! pair's `compatibility_score` is the mean similarity shrunk by the number
+//! of samples behind it, so a few lucky samples cannot claim a link.
+//!
+//! Hints are re-sent when a pair's score moves or ages, and a pair that no
+//! longer looks alike gets one low-scored hint to replace the earlier one.
+
+use parking_lot::Mutex;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use terrain_gossip_core::types::*;
+
+const DOMAIN_EVIDENCE: &[u8] = b"linkhint-evidence";
+
+/// One observation of a target
+#[derive(Debug, Clone, PartialEq)]
+pub struct BehaviorSample {
+    /// Time to a complete response
+    pub latency_ms: f64,
+    /// Response length in tokens, when the response was read
+    pub response_tokens: Option<u32>,
+    /// Whether the target refused the request
+    pub refused: bool,
+    /// Canary prompt hash and output hash, for deterministic canaries
+    pub canary: Option<(Bytes32, Bytes32)>,
+}
+
+/// Similarity thresholds
+#[derive(Debug, Clone)]
+pub struct SimilarityConfig {
+    /// Samples kept per target
+    pub max_samples: usize,
+    /// Samples a target needs before it is compared
+    pub min_samples: usize,
+    /// KS coefficient for latency and length tests (1.36 is 5% significance)
+    pub ks_coefficient: f64,
+    /// Two-proportion z at which refusal rates differ
+    pub refusal_z: f64,
+    /// Share of shared canaries with matching outputs needed
+    pub canary_agreement: f64,
+    /// Samples at which the score is shrunk by half
+    pub prior_samples: f64,
+    /// Score a pair needs to be hinted
+    pub min_score: f64,
+    /// Score change that re-sends a hint
+    pub resend_delta: f64,
+    /// Epochs after which a hint is re-sent unchanged
+    pub refresh_epochs: u64,
+}
+
+impl Default for SimilarityConfig {
+    fn default() -> Self {
+        Self {
+            max_samples: 256,
+            min_samples: 20,
+            ks_coefficient: 1.36,
+            refusal_z: 1.96,
+            canary_agreement: 0.9,
+            prior_samples: 20.0,
+            min_score: 0.6,
+            resend_delta: 0.1,
+            refresh_epochs: 24,
+        }
+    }
+}
+
+/// A pair of targets to hint
+#[derive(Debug, Clone, PartialEq)]
+pub struct LinkFinding {
+    pub epoch_id: u64,
+    /// Smaller target by bytes
+    pub target_a: TargetRef,
+    pub target_b: TargetRef,
+    /// Calibrated probability-like score; 0 when a test rejects the pair
+    pub score: f64,
+    /// Commitment to both fingerprints the score was computed from
+    pub evidence_commitment: Bytes32,
+}
+
+impl LinkFinding {
+    /// Unsigned link hint for this finding
+    pub fn to_link_hint(&self, world: WorldId) -> EventBody {
+        EventBody::LinkHint(LinkHintEvent {
+            world,
+            epoch_id: self.epoch_id,
+            target_a: self.target_a,
+            target_b: self.target_b,
+            evidence_commitment: self.evidence_commitment,
+            compatibility_score: self.score,
+            signer_transport_pubkey: vec![],
+            signature: vec![],
+        })
+    }
+}
+
+/// Summary of a target's samples
+struct Fingerprint {
+    latencies: Vec<f64>,
+    lengths: Vec<f64>,
+    refusals: usize,
+    samples: usize,
+    /// Latest output per canary prompt
+    canaries: BTreeMap<Bytes32, Bytes32>,
+}
+
+impl Fingerprint {
+    fn new(samples: &VecDeque<BehaviorSample>) -> Self {
+        let mut latencies: Vec<f64> = samples.iter().map(|s| s.latency_ms).collect();
+        let mut lengths: Vec<f64> = samples
+            .iter()
+            .filter_map(|s| s.response_tokens.map(f64::from))
+            .collect();
+        latencies.sort_by(f64::total_cmp);
+        lengths.sort_by(f64::total_cmp);
+        Self {
+            latencies,
+            lengths,
+            refusals: samples.iter().filter(|s| s.refused).count(),
+            samples: samples.len(),
+            canaries: samples.iter().filter_map(|s| s.canary).collect(),
+        }
+    }
+
+    fn refusal_rate(&self) -> f64 {
+        self.refusals as f64 / self.samples as f64
+    }
+
+    /// Deciles, refusal counts and canaries, in a fixed byte layout
+    fn commit(&self, hasher: &mut blake3::Hasher) {
+        for sorted in [&self.latencies, &self.lengths] {
+            hasher.update(&(sorted.len() as u64).to_le_bytes());
+            if !sorted.is_empty() {
+                for decile in 0..=10 {
+                    let value = sorted[decile * (sorted.len() - 1) / 10];
+                    hasher.update(&value.to_le_bytes());
+                }
+            }
+        }
+        hasher.update(&(self.refusals as u64).to_le_bytes());
+        hasher.update(&(self.samples as u64).to_le_bytes());
+        for (prompt, output) in &self.canaries {
+            hasher.update(prompt);
+            hasher.update(output);
+        }
+    }
+}
+
+#[derive(Default)]
+struct State {
+    samples: HashMap<TargetRef, VecDeque<BehaviorSample>>,
+    /// Last (epoch, score) hinted per ordered pair
+    hinted: HashMap<(TargetRef, TargetRef), (u64, f64)>,
+}
+
+/// Compares target fingerprints and finds pairs worth hinting
+pub struct SimilarityDetector {
+    config: SimilarityConfig,
+    state: Mutex<State>,
+}
+
+impl SimilarityDetector {
+    /// Create a detector with no samples
+    pub fn new(config: SimilarityConfig) -> Self {
+        Self {
+            config,
+            state: Mutex::new(State::default()),
+        }
+    }
+
+    /// Record an observation of a target
+    pub fn record(&self, target: TargetRef, sample: BehaviorSample) {
+        if !sample.latency_ms.is_finite() {
+            return;
+        }
+        let mut state = self.state.lock();
+        let samples = state.samples.entry(target).or_default();
+        if samples.len() == self.config.max_samples {
+            samples.pop_front();
+        }
+        samples.push_back(sample);
+    }
+
+    /// Forget a target, e.g. when its descriptor is revoked
+    pub fn remove(&self, target: &TargetRef) {
+        let mut state = self.state.lock();
+        state.samples.remove(target);
+        state.hinted.retain(|(a, b), _| a != target && b != target);
+    }
+
+    /// Compare all pairs of targets, returning hints newly worth sending
+    pub fn detect(&self, epoch_id: u64) -> Vec<LinkFinding> {
+        let mut state = self.state.lock();
+        let mut fingerprints: Vec<(TargetRef, Fingerprint)> = state
+            .samples
+            .iter()
+            .filter(|(_, samples)| samples.len() >= self.config.min_samples)
+            .map(|(target, samples)| (*target, Fingerprint::new(samples)))
+            .collect();
+        fingerprints.sort_by_key(|(target, _)| target.0);
+
+        let mut findings = Vec::new();
+        for (i, (target_a, a)) in fingerprints.iter().enumerate() {
+            for (target_b, b) in &fingerprints[i + 1..] {
+                let pair = (*target_a, *target_b);
+                let score = self.score(a, b);
+                let resend = match state.hinted.get(&pair) {
+                    None => score >= self.config.min_score,
+                    Some((hinted_epoch, hinted_score)) => {
+                        (score - hinted_score).abs() >= self.config.resend_delta
+                            || epoch_id >= hinted_epoch + self.config.refresh_epochs
+                    }
+                };
+                if !resend {
+                    continue;
+                }
+                // A pair that stopped looking alike is hinted once more, low,
+                // and then forgotten
+                if score >= self.config.min_score {
+                    state.hinted.insert(pair, (epoch_id, score));
+                } else {
+                    state.hinted.remove(&pair);
+                }
+
+                let mut hasher = blake3::Hasher::new();
+                hasher.update(DOMAIN_EVIDENCE);
+                hasher.update(&target_a.0);
+                hasher.update(&target_b.0);
+                hasher.update(&epoch_id.to_le_bytes());
+                a.commit(&mut hasher);
+                b.commit(&mut hasher);
+                findings.push(LinkFinding {
+                    epoch_id,
+                    target_a: *target_a,
+                    target_b: *target_b,
+                    score,
+                    evidence_commitment: *hasher.finalize().as_bytes(),
+                });
+            }
+        }
+        findings
+    }
+
+    /// Calibrated compatibility of two fingerprints
+    fn score(&self, a: &Fingerprint, b: &Fingerprint) -> f64 {
+        let mut similarities = Vec::with_capacity(4);
+        let mut evidence = a.samples.min(b.samples) as f64;
+
+        for (x, y) in [(&a.latencies, &b.latencies), (&a.lengths, &b.lengths)] {
+            if x.len() < self.config.min_samples || y.len() < self.config.min_samples {
+                continue;
+            }
+            let (n, m) = (x.len() as f64, y.len() as f64);
+            let distance = ks_distance(x, y);
+            if distance > self.config.ks_coefficient * ((n + m) / (n * m)).sqrt() {
+                return 0.0;
+            }
+            similarities.push(1.0 - distance);
+        }
+
+        let (n, m) = (a.samples as f64, b.samples as f64);
+        let (rate_a, rate_b) = (a.refusal_rate(), b.refusal_rate());
+        let pooled = (a.refusals + b.refusals) as f64 / (n + m);
+        let spread = (pooled * (1.0 - pooled) * (1.0 / n + 1.0 / m)).sqrt();
+        if spread > 0.0 && (rate_a - rate_b).abs() / spread > self.config.refusal_z {
+            return 0.0;
+        }
+        similarities.push(1.0 - (rate_a - rate_b).abs());
+
+        // Identical outputs on deterministic canaries are the strongest
+        // evidence, so shared canaries count twice
+        let shared: Vec<bool> = a
+            .canaries
+            .iter()
+            .filter_map(|(prompt, output)| b.canaries.get(prompt).map(|other| other == output))
+            .collect();
+        if !shared.is_empty() {
+            let agreement = shared.iter().filter(|same| **same).count() as f64 / shared.len() as f64;
+            if agreement < self.config.canary_agreement {
+                return 0.0;
+            }
+            similarities.extend([agreement, agreement]);
+            evidence += shared.len() as f64;
+        }
+
+        let similarity = similarities.iter().sum::<f64>() / similarities.len() as f64;
+        similarity * evidence / (evidence + self.config.prior_samples)
+    }
+}
+
+/// Two-sample Kolmogorov-Smirnov statistic of two sorted samples
+fn ks_distance(x: &[f64], y: &[f64]) -> f64 {
+    let (mut i, mut j, mut distance) = (0, 0, 0.0f64);
+    while i < x.len() && j < y.len() {
+        let value = x[i].min(y[j]);
+        while i < x.len() && x[i] <= value {
+            i += 1;
+        }
+        while j < y.len() && y[j] <= value {
+            j += 1;
+        }
+        distance = distance.max((i as f64 / x.len() as f64 - j as f64 / y.len() as f64).abs());
+    }
+    distance
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(latency_ms: f64, tokens: u32, refused: bool, canary: Option<(u8, u8)>) -> BehaviorSample {
+        BehaviorSample {
+            latency_ms,
+            response_tokens: Some(tokens),
+            refused,
+            canary: canary.map(|(prompt, output)| ([prompt; 32], [output; 32])),
+        }
+    }
+
+    /// Same backend behind targets 1 and 2, a different one behind 3
+    fn observe(detector: &SimilarityDetector, rounds: u32) {
+        for i in 0..rounds {
+            let jitter = f64::from(i % 10);
+            let refused = i % 8 == 0;
+            let canary = (i % 5 == 0).then_some((i as u8, i as u8));
+            detector.record(TargetRef([1; 32]), sample(200.0 + jitter, 100 + i % 7, refused, canary));
+            detector.record(TargetRef([2; 32]), sample(201.0 + jitter, 101 + i % 7, refused, canary));
+            detector.record(TargetRef([3; 32]), sample(600.0 + jitter, 40 + i % 7, false, None));
+        }
+    }
+
+    #[test]
+    fn test_same_backend_is_hinted() {
+        let detector = SimilarityDetector::new(SimilarityConfig::default());
+        observe(&detector, 10);
+        assert!(detector.detect(1).is_empty());
+
+        observe(&detector, 100);
+        let findings = detector.detect(1);
+        assert_eq!(findings.len(), 1);
+        let finding = &findings[0];
+        assert_eq!(
+            (finding.target_a, finding.target_b),
+            (TargetRef([1; 32]), TargetRef([2; 32]))
+        );
+        assert!(finding.score > 0.7 && finding.score < 1.0);
+        let EventBody::LinkHint(hint) = finding.to_link_hint(WorldId([9; 32])) else {
+            panic!("expected a link hint");
+        };
+        assert_eq!(hint.evidence_commitment, finding.evidence_commitment);
+
+        // Unchanged pairs are not re-sent until the hint ages
+        assert!(detector.detect(2).is_empty());
+        assert_eq!(detector.detect(25).len(), 1);
+    }
+
+    #[test]
+    fn test_diverging_pair_is_retracted() {
+        let detector = SimilarityDetector::new(SimilarityConfig {
+            max_samples: 40,
+            ..SimilarityConfig::default()
+        });
+        observe(&detector, 40);
+        assert_eq!(detector.detect(1).len(), 1);
+
+        // Target 2 now answers canaries differently
+        for i in 0..40u8 {
+            detector.record(TargetRef([1; 32]), sample(200.0, 100, false, Some((i, i))));
+            detector.record(TargetRef([2; 32]), sample(200.0, 100, false, Some((i, !i))));
+        }
+        let findings = detector.detect(2);
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].score, 0.0);
+        assert!(detector.detect(3).is_empty());
+    }
+}
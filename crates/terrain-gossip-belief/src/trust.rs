@@ -0,0 +1,550 @@
+//! Prober trust from predictiveness (RFC-0001 §8.4, §12.4)
+//!
+//! A prober's report on a target is scored once `holdout_epochs` have passed
+//! since it was made: against the consensus of *other* probers' reports on
+//! that target in the following epochs. Reports close to that later
+//! consensus move trust up, distant ones move it down. A report is only
+//! scored when the consensus is diverse: enough other probers, from enough
+//! distinct correlation clusters.
+//!
+//! Probers whose metric residuals from consensus are strongly correlated
+//! are grouped into clusters (see [`crate::residual`]), and a cluster's
+//! combined trust is capped so that colluding or cloned probers cannot
+//! outweigh independent ones.
+
+use crate::field::{BeliefField, Report};
+use crate::residual::{self, CorrelationConfig, Residual};
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use terrain_gossip_core::types::TargetRef;
+use thiserror::Error;
+
+type Prober = [u8; 32];
+
+/// Trust persistence errors
+#[derive(Debug, Error)]
+pub enum TrustError {
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Invalid trust file: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+/// Trust engine configuration
+#[derive(Debug, Clone)]
+pub struct TrustConfig {
+    /// Trust of a prober before any report is scored
+    pub initial_trust: f64,
+    /// Epochs after a report whose consensus it is scored against
+    pub holdout_epochs: u64,
+    /// Distance from consensus at which a report scores 0.5
+    pub tolerance: f64,
+    /// Step of each trust update towards a report's score
+    pub learning_rate: f64,
+    /// Other probers a consensus needs
+    pub min_probers: usize,
+    /// Distinct clusters a consensus needs
+    pub min_clusters: usize,
+    /// Residual correlation that puts two probers in one cluster
+    pub correlation_threshold: f64,
+    /// Reports two probers must share before they are compared
+    pub min_shared: usize,
+    /// Mean residual deviation both probers need before they are compared
+    pub noise_floor: f64,
+    /// Most combined trust one cluster can hold
+    pub cluster_cap: f64,
+    /// Scored reports before a key's trust counts outside aggregation,
+    /// e.g. for its link hints
+    pub min_earned: u64,
+}
+
+impl Default for TrustConfig {
+    fn default() -> Self {
+        Self {
+            initial_trust: 0.5,
+            holdout_epochs: 2,
+            tolerance: 0.25,
+            learning_rate: 0.1,
+            min_probers: 2,
+            min_clusters: 2,
+            correlation_threshold: 0.9,
+            min_shared: 5,
+            noise_floor: 0.1,
+            cluster_cap: 1.0,
+            min_earned: 10,
+        }
+    }
+}
+
+/// A prober's trust record
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProberTrust {
+    pub prober: Prober,
+    /// Trust in [0, 1] before the cluster cap
+    pub trust: f64,
+    /// Reports scored
+    pub scored: u64,
+    /// EWMA of report scores
+    pub recent_accuracy: f64,
+}
+
+/// Persisted engine state
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct Snapshot {
+    /// Reports from epochs up to here are scored
+    scored_through: Option<u64>,
+    probers: Vec<ProberTrust>,
+}
+
+/// Summary of one update
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TrustUpdate {
+    /// Reports scored against later consensus
+    pub scored: usize,
+    /// Reports skipped for lack of a diverse consensus
+    pub skipped: usize,
+    /// Clusters of two or more correlated probers
+    pub clusters: usize,
+}
+
+#[derive(Default)]
+struct EngineState {
+    scored_through: Option<u64>,
+    probers: HashMap<Prober, ProberTrust>,
+    /// Cluster ID (smallest member) of each clustered prober
+    clusters: HashMap<Prober, Prober>,
+}
+
+/// Scores probers by how well their reports predict later consensus
+pub struct TrustEngine {
+    config: TrustConfig,
+    state: RwLock<EngineState>,
+}
+
+impl TrustEngine {
+    /// Create an engine with no history
+    pub fn new(config: TrustConfig) -> Self {
+        Self {
+            config,
+            state: RwLock::new(EngineState::default()),
+        }
+    }
+
+    /// Load trust saved by [`TrustEngine::save`], or start fresh if the
+    /// file does not exist
+    pub fn load(config: TrustConfig, path: &Path) -> Result<Self, TrustError> {
+        let engine = Self::new(config);
+        let snapshot: Snapshot = match std::fs::read(path) {
+            Ok(bytes) => serde_json::from_slice(&bytes)?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(engine),
+            Err(e) => return Err(e.into()),
+        };
+        {
+            let mut state = engine.state.write();
+            state.scored_through = snapshot.scored_through;
+            state.probers = snapshot
+                .probers
+                .into_iter()
+                .map(|record| (record.prober, record))
+                .collect();
+        }
+        Ok(engine)
+    }
+
+    /// Save trust records
+    pub fn save(&self, path: &Path) -> Result<(), TrustError> {
+        let snapshot = {
+            let state = self.state.read();
+            let mut probers: Vec<ProberTrust> = state.probers.values().cloned().collect();
+            probers.sort_by_key(|record| record.prober);
+            Snapshot {
+                scored_through: state.scored_through,
+                probers,
+            }
+        };
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        // Write then rename so a crash never leaves a truncated file
+        let tmp = path.with_extension("tmp");
+        std::fs::write(&tmp, serde_json::to_vec_pretty(&snapshot)?)?;
+        std::fs::rename(&tmp, path)?;
+        Ok(())
+    }
+
+    /// A prober's trust record
+    pub fn record(&self, prober: &Prober) -> Option<ProberTrust> {
+        self.state.read().probers.get(prober).cloned()
+    }
+
+    /// A prober's trust before the cluster cap
+    pub fn trust(&self, prober: &Prober) -> f64 {
+        self.record(prober)
+            .map_or(self.config.initial_trust, |record| record.trust)
+    }
+
+    /// Correlated prober clusters, each sorted, in byte order
+    pub fn clusters(&self) -> Vec<Vec<Prober>> {
+        let mut clusters: BTreeMap<Prober, Vec<Prober>> = BTreeMap::new();
+        for (prober, cluster) in &self.state.read().clusters {
+            clusters.entry(*cluster).or_default().push(*prober);
+        }
+        clusters
+            .into_values()
+            .map(|mut members| {
+                members.sort_unstable();
+                members
+            })
+            .collect()
+    }
+
+    /// Trust used for aggregation: trust scaled down so that no cluster
+    /// holds more than `cluster_cap` in total
+    pub fn effective_trust(&self) -> HashMap<Prober, f64> {
+        let state = self.state.read();
+        let mut totals: HashMap<Prober, f64> = HashMap::new();
+        for (prober, cluster) in &state.clusters {
+            *totals.entry(*cluster).or_default() += self.trust_in(&state, prober);
+        }
+        let mut probers: Vec<Prober> = state.probers.keys().copied().collect();
+        probers.extend(state.clusters.keys());
+        probers
+            .into_iter()
+            .map(|prober| {
+                let trust = self.trust_in(&state, &prober);
+                let total = state
+                    .clusters
+                    .get(&prober)
+                    .and_then(|cluster| totals.get(cluster))
+                    .copied()
+                    .unwrap_or(trust);
+                let scale = if total > self.config.cluster_cap {
+                    self.config.cluster_cap / total
+                } else {
+                    1.0
+                };
+                (prober, trust * scale)
+            })
+            .collect()
+    }
+
+    /// Effective trust of keys with at least `min_earned` scored reports;
+    /// keys without earned trust are absent and should count for nothing
+    pub fn earned_trust(&self) -> HashMap<Prober, f64> {
+        let mut effective = self.effective_trust();
+        let state = self.state.read();
+        effective.retain(|prober, _| {
+            state
+                .probers
+                .get(prober)
+                .is_some_and(|record| record.scored >= self.config.min_earned)
+        });
+        effective
+    }
+
+    fn trust_in(&self, state: &EngineState, prober: &Prober) -> f64 {
+        state
+            .probers
+            .get(prober)
+            .map_or(self.config.initial_trust, |record| record.trust)
+    }
+
+    /// Score reports whose holdout window has closed, recluster probers, and
+    /// push effective trust into the field
+    pub fn update(&self, field: &BeliefField) -> TrustUpdate {
+        let reports: Vec<(TargetRef, Vec<Report>)> = field
+            .targets()
+            .into_iter()
+            .map(|target| (target, field.reports(&target)))
+            .collect();
+        let mut update = TrustUpdate {
+            clusters: self.recluster(&reports),
+            ..TrustUpdate::default()
+        };
+
+        let closed = field.epoch_id().checked_sub(self.config.holdout_epochs);
+        let mut state = self.state.write();
+        if let Some(closed) = closed.filter(|closed| Some(*closed) > state.scored_through) {
+            let after = state.scored_through;
+            let weights: HashMap<Prober, f64> = reports
+                .iter()
+                .flat_map(|(_, reports)| reports.iter().map(|r| r.prober))
+                .map(|prober| (prober, self.trust_in(&state, &prober)))
+                .collect();
+
+            for (_, reports) in &reports {
+                let due = reports
+                    .iter()
+                    .filter(|r| r.epoch_id <= closed && Some(r.epoch_id) > after);
+                for report in due {
+                    let Some(consensus) = self.consensus(&state, &weights, reports, report) else {
+                        update.skipped += 1;
+                        continue;
+                    };
+                    let score = (1.0
+                        - (report.value - consensus).abs() / (2.0 * self.config.tolerance))
+                        .clamp(0.0, 1.0);
+                    let rate = self.config.learning_rate;
+                    let record = state.probers.entry(report.prober).or_insert(ProberTrust {
+                        prober: report.prober,
+                        trust: self.config.initial_trust,
+                        scored: 0,
+                        recent_accuracy: self.config.initial_trust,
+                    });
+                    record.trust += rate * (score - record.trust);
+                    record.recent_accuracy += rate * (score - record.recent_accuracy);
+                    record.scored += 1;
+                    update.scored += 1;
+                }
+            }
+            state.scored_through = Some(closed);
+        }
+        drop(state);
+
+        for (prober, trust) in self.effective_trust() {
+            field.set_trust(prober, trust);
+        }
+        update
+    }
+
+    /// Later consensus of other probers on the target of `report`, if it is
+    /// diverse enough
+    fn consensus(
+        &self,
+        state: &EngineState,
+        weights: &HashMap<Prober, f64>,
+        reports: &[Report],
+        report: &Report,
+    ) -> Option<f64> {
+        let own_cluster = state.clusters.get(&report.prober);
+        let later: Vec<&Report> = reports
+            .iter()
+            .filter(|r| {
+                r.epoch_id > report.epoch_id
+                    && r.epoch_id <= report.epoch_id + self.config.holdout_epochs
+                    && r.prober != report.prober
+                    // The prober's own cluster cannot vouch for it
+                    && (own_cluster.is_none() || state.clusters.get(&r.prober) != own_cluster)
+            })
+            .collect();
+
+        let mut probers: Vec<Prober> = later.iter().map(|r| r.prober).collect();
+        probers.sort_unstable();
+        probers.dedup();
+        let mut clusters: Vec<Prober> = probers
+            .iter()
+            .map(|p| state.clusters.get(p).copied().unwrap_or(*p))
+            .collect();
+        clusters.sort_unstable();
+        clusters.dedup();
+        if probers.len() < self.config.min_probers || clusters.len() < self.config.min_clusters {
+            return None;
+        }
+
+        let values: Vec<(f64, f64)> = later
+            .iter()
+            .map(|r| (r.value, weights.get(&r.prober).copied().unwrap_or(0.0)))
+            .collect();
+        Some(crate::field::weighted_median(&values))
+    }
+
+    /// Group probers whose residuals from per-epoch consensus are strongly
+    /// correlated, returning the number of clusters
+    fn recluster(&self, reports: &[(TargetRef, Vec<Report>)]) -> usize {
+        // Residual of each report from its (target, epoch) consensus
+        let mut residuals: HashMap<Prober, BTreeMap<([u8; 32], u64), Residual>> = HashMap::new();
+        for (target, reports) in reports {
+            let mut by_epoch: BTreeMap<u64, Vec<&Report>> = BTreeMap::new();
+            for report in reports {
+                by_epoch.entry(report.epoch_id).or_default().push(report);
+            }
+            for (epoch_id, group) in by_epoch {
+                if group.len() < 3 {
+                    continue;
+                }
+                let components: Vec<_> = group.iter().map(|r| r.components).collect();
+                for (report, residual) in group.iter().zip(residual::residuals(&components)) {
+                    residuals
+                        .entry(report.prober)
+                        .or_default()
+                        .insert((target.0, epoch_id), residual);
+                }
+            }
+        }
+
+        let correlation = CorrelationConfig {
+            threshold: self.config.correlation_threshold,
+            min_shared: self.config.min_shared,
+            noise_floor: self.config.noise_floor,
+        };
+        let mut probers: Vec<Prober> = residuals.keys().copied().collect();
+        probers.sort_unstable();
+        let mut links = Vec::new();
+        for (i, a) in probers.iter().enumerate() {
+            for b in &probers[i + 1..] {
+                let shared: Vec<(Residual, Residual)> = residuals[a]
+                    .iter()
+                    .filter_map(|(key, x)| Some((*x, *residuals[b].get(key)?)))
+                    .collect();
+                if correlation.correlation(&shared).is_some() {
+                    links.push((*a, *b));
+                }
+            }
+        }
+
+        let clusters = residual::clusters(&links);
+        // Smallest member is the cluster ID
+        self.state.write().clusters = clusters
+            .iter()
+            .flat_map(|members| members.iter().map(|m| (*m, members[0])))
+            .collect();
+        clusters.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::field::BeliefConfig;
+    use crate::observation::ObservationWeights;
+    use terrain_gossip_core::types::*;
+
+    /// Deterministic noise in [-0.05, 0.05)
+    fn noise(prober: u8, target: u8, epoch_id: u64) -> f64 {
+        let seed = (prober as u64 * 131 + target as u64 * 31 + epoch_id * 7)
+            .wrapping_mul(2_654_435_761)
+            % 1000;
+        seed as f64 / 10_000.0 - 0.05
+    }
+
+    fn attestation(prober: u8, target: u8, epoch_id: u64, quality: f64) -> Event {
+        let mut id = [prober; 32];
+        id[1] = target;
+        id[2..10].copy_from_slice(&epoch_id.to_le_bytes());
+        Event {
+            event_id: EventId(id),
+            world: WorldId([0; 32]),
+            epoch_id,
+            event_type: EventType::Attestation,
+            body: EventBody::Attestation(BehaviorAttestation {
+                attestation_id: AttestationId(id),
+                world: WorldId([0; 32]),
+                epoch_id,
+                challenge_id: ChallengeId(id),
+                target_ref: TargetRef([target; 32]),
+                target_fah: None,
+                metrics: MetricsVector {
+                    success_rate: quality,
+                    refusal_consistency: quality,
+                    tool_fidelity: quality,
+                    latency_p50_ms: 0,
+                    latency_p95_ms: 0,
+                    robustness_score: quality,
+                    drift_indicator: 0.0,
+                    freshness: FreshnessStrength::None,
+                },
+                evidence_commitment: [0; 32],
+                freshness_anchor: None,
+                prober_transport_pubkey: vec![prober; 32],
+                signature: vec![],
+            }),
+        }
+    }
+
+    /// Honest probers 1-4 report each target's true quality with noise;
+    /// `others` reports whatever `lie` returns
+    fn field(others: &[u8], lie: impl Fn(u8, u64) -> f64) -> BeliefField {
+        let field = BeliefField::new(ObservationWeights::default(), BeliefConfig::default());
+        for epoch_id in 1..=8 {
+            for target in 1..=4u8 {
+                let truth = 0.4 + 0.1 * target as f64;
+                for prober in 1..=4 {
+                    let quality = truth + noise(prober, target, epoch_id);
+                    field.apply(&attestation(prober, target, epoch_id, quality));
+                }
+                for prober in others {
+                    field.apply(&attestation(
+                        *prober,
+                        target,
+                        epoch_id,
+                        lie(target, epoch_id),
+                    ));
+                }
+            }
+        }
+        field
+    }
+
+    #[test]
+    fn test_predictive_probers_gain_trust() {
+        let field = field(&[9], |_, _| 0.0);
+        let engine = TrustEngine::new(TrustConfig::default());
+        let update = engine.update(&field);
+        // Epochs 1-6 are scored; the liar is outvoted everywhere
+        assert_eq!(update.scored, 6 * 4 * 5);
+        assert_eq!(update.clusters, 0);
+        for prober in 1..=4 {
+            assert!(engine.trust(&[prober; 32]) > 0.8);
+        }
+        assert!(engine.trust(&[9; 32]) < 0.1);
+        assert_eq!(field.trust(&[9; 32]), engine.trust(&[9; 32]));
+
+        // Only scored keys have earned trust
+        let earned = engine.earned_trust();
+        assert!(earned[&[1; 32]] > 0.8);
+        assert!(!earned.contains_key(&[42; 32]));
+        let strict = TrustEngine::new(TrustConfig {
+            min_earned: 100,
+            ..TrustConfig::default()
+        });
+        strict.update(&field);
+        assert!(strict.earned_trust().is_empty());
+
+        // Scored reports are not scored again
+        assert_eq!(engine.update(&field).scored, 0);
+    }
+
+    #[test]
+    fn test_correlated_probers_capped() {
+        // Three clones push every target up by a varying amount together
+        let field = field(&[7, 8, 9], |target, epoch_id| {
+            0.4 + 0.1 * target as f64 + 0.1 + 0.1 * ((target as u64 + epoch_id) % 3) as f64
+        });
+        let engine = TrustEngine::new(TrustConfig::default());
+        let update = engine.update(&field);
+        assert_eq!(update.clusters, 1);
+        assert_eq!(engine.clusters(), vec![vec![[7; 32], [8; 32], [9; 32]]]);
+
+        // The clones cannot vouch for each other, and together hold at most
+        // the cluster cap
+        let effective = engine.effective_trust();
+        let clones: f64 = [7u8, 8, 9].iter().map(|p| effective[&[*p; 32]]).sum();
+        assert!(clones <= 1.0 + 1e-9);
+        assert!(engine.trust(&[7; 32]) < engine.trust(&[1; 32]));
+        assert_eq!(effective[&[1; 32]], engine.trust(&[1; 32]));
+    }
+
+    #[test]
+    fn test_trust_persists() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("trust.json");
+        let field = field(&[9], |_, _| 0.0);
+
+        let engine = TrustEngine::load(TrustConfig::default(), &path).unwrap();
+        engine.update(&field);
+        engine.save(&path).unwrap();
+
+        // A restarted engine keeps trust and does not rescore the same log
+        let restarted = TrustEngine::load(TrustConfig::default(), &path).unwrap();
+        assert_eq!(restarted.record(&[9; 32]), engine.record(&[9; 32]));
+        assert_eq!(restarted.update(&field).scored, 0);
+
+        std::fs::write(&path, b"not json").unwrap();
+        assert!(matches!(
+            TrustEngine::load(TrustConfig::default(), &path),
+            Err(TrustError::Json(_))
+        ));
+    }
+}
@@ -48,6 +48,20 @@ pub fn normalize_rule_bundle(bundle: &mut RuleBundle) -> Result<()> {
     Ok(())
 }
 
+/// Canonical bytes of a RuleBundle for hashing.
+///
+/// An unset `descriptor_ttl_epochs` is left out, so bundles that predate it
+/// hash as before. This is only a hash input; the bundle's serde encoding
+/// always carries the field.
+pub fn rule_bundle_bytes(bundle: &RuleBundle) -> Result<Vec<u8>> {
+    let mut bytes = canonical_bytes(bundle)?;
+    if bundle.descriptor_ttl_epochs.is_none() {
+        // postcard encodes the trailing None as a single zero byte
+        bytes.pop();
+    }
+    Ok(bytes)
+}
+
 /// Validate and normalize a MetricsVector for canonical encoding.
 pub fn normalize_metrics_vector(metrics: &mut MetricsVector) -> Result<()> {
     metrics.success_rate = normalize_f64(metrics.success_rate)?;
@@ -3,10 +3,11 @@
 //! All hash derivations use BLAKE3 with domain separation prefixes.
 //! This module provides the normative implementations.
 
-use crate::canonical::{canonical_bytes, normalize_descriptor_unsigned};
+use crate::canonical::{canonical_bytes, normalize_descriptor_unsigned, rule_bundle_bytes};
 use crate::error::{Error, Result};
 use crate::types::*;
 use blake3::Hasher;
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
 
 // =============================================================================
 // DOMAIN SEPARATION PREFIXES
@@ -30,6 +31,8 @@ pub const DOMAIN_REPLICA: &[u8] = b"replica";
 pub const DOMAIN_CPK: &[u8] = b"cpk";
 /// Domain prefix for descriptor signature
 pub const DOMAIN_DESC_SIG: &[u8] = b"desc-sig";
+/// Domain prefix for event body signatures
+pub const DOMAIN_EVENT_SIG: &[u8] = b"event-sig";
 
 // =============================================================================
 // WORLD IDENTITY
@@ -49,21 +52,25 @@ pub fn normalize_world_phrase(phrase: &str) -> String {
 ///
 /// `WorldId = BLAKE3("world" || phrase_norm || rule_bundle_hash)`
 pub fn derive_world_id(phrase: &str, rule_bundle: &RuleBundle) -> Result<WorldId> {
+    Ok(derive_world_id_from_hash(phrase, &rule_bundle_hash(rule_bundle)?))
+}
+
+/// Derive WorldId from phrase and an already computed rule bundle hash,
+/// e.g. the successor world of an endorsed bundle.
+pub fn derive_world_id_from_hash(phrase: &str, rule_bundle_hash: &Bytes32) -> WorldId {
     let phrase_norm = normalize_world_phrase(phrase);
-    let rule_bundle_bytes = canonical_bytes(rule_bundle)?;
-    let rule_bundle_hash = blake3::hash(&rule_bundle_bytes);
 
     let mut hasher = Hasher::new();
     hasher.update(DOMAIN_WORLD);
     hasher.update(phrase_norm.as_bytes());
-    hasher.update(rule_bundle_hash.as_bytes());
+    hasher.update(rule_bundle_hash);
 
-    Ok(WorldId(*hasher.finalize().as_bytes()))
+    WorldId(*hasher.finalize().as_bytes())
 }
 
 /// Compute rule bundle hash (used in WorldId derivation).
 pub fn rule_bundle_hash(rule_bundle: &RuleBundle) -> Result<Bytes32> {
-    let bytes = canonical_bytes(rule_bundle)?;
+    let bytes = rule_bundle_bytes(rule_bundle)?;
     Ok(*blake3::hash(&bytes).as_bytes())
 }
 
@@ -263,6 +270,162 @@ pub fn verify_receipt_id(receipt: &ProbeReceipt) -> Result<()> {
     Ok(())
 }
 
+// =============================================================================
+// EVENT SIGNATURES
+// =============================================================================
+
+/// Event type carried by an event body.
+pub fn event_type_of(body: &EventBody) -> EventType {
+    match body {
+        EventBody::Receipt(_) => EventType::Receipt,
+        EventBody::Attestation(_) => EventType::Attestation,
+        EventBody::Dispute(_) => EventType::Dispute,
+        EventBody::LinkHint(_) => EventType::LinkHint,
+        EventBody::RuleEndorsement(_) => EventType::RuleEndorsement,
+        EventBody::DescriptorPublish(_) => EventType::DescriptorPublish,
+        EventBody::DescriptorRevoke(_) => EventType::DescriptorRevoke,
+    }
+}
+
+/// World and epoch declared by an event body.
+pub fn event_world_epoch(body: &EventBody) -> (WorldId, u64) {
+    match body {
+        EventBody::Receipt(b) => (b.world, b.epoch_id),
+        EventBody::Attestation(b) => (b.world, b.epoch_id),
+        EventBody::Dispute(b) => (b.world, b.epoch_id),
+        EventBody::LinkHint(b) => (b.world, b.epoch_id),
+        EventBody::RuleEndorsement(b) => (b.world, b.epoch_id),
+        EventBody::DescriptorPublish(b) => (b.world, b.epoch_id),
+        EventBody::DescriptorRevoke(b) => (b.world, b.epoch_id),
+    }
+}
+
+/// Signer public key and signature carried by an event body.
+pub fn event_signer(body: &EventBody) -> (&[u8], &[u8]) {
+    match body {
+        EventBody::Receipt(b) => (&b.prober_transport_pubkey, &b.signature),
+        EventBody::Attestation(b) => (&b.prober_transport_pubkey, &b.signature),
+        EventBody::Dispute(b) => (&b.disputer_transport_pubkey, &b.signature),
+        EventBody::LinkHint(b) => (&b.signer_transport_pubkey, &b.signature),
+        EventBody::RuleEndorsement(b) => (&b.signer_transport_pubkey, &b.signature),
+        EventBody::DescriptorPublish(b) => (
+            &b.descriptor.provider_transport_pubkey,
+            &b.descriptor.signature,
+        ),
+        EventBody::DescriptorRevoke(b) => (&b.provider_transport_pubkey, &b.signature),
+    }
+}
+
+fn set_event_signer(body: &mut EventBody, pubkey: Vec<u8>, signature: Vec<u8>) {
+    let (key_field, sig_field) = match body {
+        EventBody::Receipt(b) => (&mut b.prober_transport_pubkey, &mut b.signature),
+        EventBody::Attestation(b) => (&mut b.prober_transport_pubkey, &mut b.signature),
+        EventBody::Dispute(b) => (&mut b.disputer_transport_pubkey, &mut b.signature),
+        EventBody::LinkHint(b) => (&mut b.signer_transport_pubkey, &mut b.signature),
+        EventBody::RuleEndorsement(b) => (&mut b.signer_transport_pubkey, &mut b.signature),
+        EventBody::DescriptorPublish(b) => (
+            &mut b.descriptor.provider_transport_pubkey,
+            &mut b.descriptor.signature,
+        ),
+        EventBody::DescriptorRevoke(b) => (&mut b.provider_transport_pubkey, &mut b.signature),
+    };
+    *key_field = pubkey;
+    *sig_field = signature;
+}
+
+/// Compute the bytes to sign for an event body.
+///
+/// Descriptor publications are signed over `descriptor_sign_bytes`; all other
+/// bodies over `"event-sig" || canonical_bytes(body with empty signature)`.
+pub fn event_sign_bytes(body: &EventBody) -> Result<Vec<u8>> {
+    if let EventBody::DescriptorPublish(publish) = body {
+        let descriptor = &publish.descriptor;
+        return descriptor_sign_bytes(
+            &descriptor.unsigned.world,
+            &descriptor.descriptor_id,
+            &descriptor.unsigned,
+        );
+    }
+
+    let mut unsigned = body.clone();
+    let pubkey = event_signer(body).0.to_vec();
+    set_event_signer(&mut unsigned, pubkey, Vec::new());
+
+    let mut bytes = DOMAIN_EVENT_SIG.to_vec();
+    bytes.extend_from_slice(&canonical_bytes(&unsigned)?);
+    Ok(bytes)
+}
+
+/// Verify an Ed25519 signature.
+pub fn verify_signature(pubkey: &[u8], message: &[u8], signature: &[u8]) -> Result<()> {
+    let pubkey: [u8; 32] = pubkey
+        .try_into()
+        .map_err(|_| Error::InvalidPublicKey(format!("expected 32 bytes, got {}", pubkey.len())))?;
+    let key =
+        VerifyingKey::from_bytes(&pubkey).map_err(|e| Error::InvalidPublicKey(e.to_string()))?;
+    let signature = Signature::from_slice(signature).map_err(|_| Error::InvalidSignature)?;
+    key.verify(message, &signature)
+        .map_err(|_| Error::InvalidSignature)
+}
+
+/// Verify that an event's event_id matches `compute_event_id(body)`.
+pub fn verify_event_id(event: &Event) -> Result<()> {
+    let computed = compute_event_id(&event.body)?;
+    if computed != event.event_id {
+        return Err(Error::HashMismatch {
+            computed: hex::encode(computed.0),
+            transmitted: hex::encode(event.event_id.0),
+        });
+    }
+    Ok(())
+}
+
+/// Verify an event's body signature (and descriptor ID for publications).
+pub fn verify_event_signature(event: &Event) -> Result<()> {
+    if let EventBody::DescriptorPublish(publish) = &event.body {
+        verify_descriptor_id(&publish.descriptor)?;
+    }
+    let (pubkey, signature) = event_signer(&event.body);
+    verify_signature(pubkey, &event_sign_bytes(&event.body)?, signature)
+}
+
+/// Signs event bodies with a transport key.
+pub struct EventSigner {
+    key: SigningKey,
+}
+
+impl EventSigner {
+    /// Create a signer from an Ed25519 seed
+    pub fn from_seed(seed: &Bytes32) -> Self {
+        Self {
+            key: SigningKey::from_bytes(seed),
+        }
+    }
+
+    /// Signer public key
+    pub fn public_key(&self) -> Bytes32 {
+        self.key.verifying_key().to_bytes()
+    }
+
+    /// Sign a body and wrap it in an `Event` with its computed ID.
+    ///
+    /// The body's signer key field is overwritten with this signer's key.
+    pub fn sign(&self, mut body: EventBody) -> Result<Event> {
+        set_event_signer(&mut body, self.public_key().to_vec(), Vec::new());
+        let signature = self.key.sign(&event_sign_bytes(&body)?).to_bytes().to_vec();
+        set_event_signer(&mut body, self.public_key().to_vec(), signature);
+
+        let (world, epoch_id) = event_world_epoch(&body);
+        Ok(Event {
+            event_id: compute_event_id(&body)?,
+            world,
+            epoch_id,
+            event_type: event_type_of(&body),
+            body,
+        })
+    }
+}
+
 // =============================================================================
 // CONVENIENCE: Create normalized descriptor
 // =============================================================================
@@ -312,6 +475,7 @@ mod tests {
             w_latency: 0.3,
             w_refusal_consistency: 0.2,
             w_robustness: 0.4,
+            descriptor_ttl_epochs: None,
         }
     }
 
@@ -336,6 +500,30 @@ mod tests {
         assert_ne!(world_id, world_id3);
     }
 
+    #[test]
+    fn test_rule_bundle_encoding() {
+        // The bundle round-trips through postcard with or without a TTL
+        let mut bundle = test_rule_bundle();
+        for ttl in [None, Some(48)] {
+            bundle.descriptor_ttl_epochs = ttl;
+            let bytes = postcard::to_allocvec(&bundle).unwrap();
+            assert_eq!(postcard::from_bytes::<RuleBundle>(&bytes).unwrap(), bundle);
+        }
+
+        // A set TTL is hashed; an unset one leaves the hash as it was
+        // before the field existed
+        let full = canonical_bytes(&bundle).unwrap();
+        assert_eq!(
+            rule_bundle_hash(&bundle).unwrap(),
+            *blake3::hash(&full).as_bytes()
+        );
+        bundle.descriptor_ttl_epochs = None;
+        assert_eq!(
+            hex::encode(rule_bundle_hash(&bundle).unwrap()),
+            "d13fd49f76d179b1ac758e94810fe7eeeacdbc8cefaa98a1d1f63a3416299db6"
+        );
+    }
+
     #[test]
     fn test_fah_derivation() {
         let manifest = CapabilityManifest {
@@ -371,6 +559,36 @@ mod tests {
         assert_ne!(target_ref, other_ref);
     }
 
+    #[test]
+    fn test_event_sign_and_verify() {
+        let signer = EventSigner::from_seed(&[7u8; 32]);
+        let body = EventBody::RuleEndorsement(RuleEndorsementEvent {
+            world: WorldId([2u8; 32]),
+            epoch_id: 5,
+            rule_bundle_hash: [3u8; 32],
+            weight: 1.0,
+            signer_transport_pubkey: vec![],
+            signature: vec![],
+        });
+
+        let event = signer.sign(body).unwrap();
+        assert_eq!(event.event_type, EventType::RuleEndorsement);
+        assert_eq!(event.epoch_id, 5);
+        verify_event_id(&event).unwrap();
+        verify_event_signature(&event).unwrap();
+
+        // Tampering breaks both the signature and the ID
+        let mut tampered = event.clone();
+        if let EventBody::RuleEndorsement(b) = &mut tampered.body {
+            b.weight = 2.0;
+        }
+        assert!(matches!(
+            verify_event_signature(&tampered),
+            Err(Error::InvalidSignature)
+        ));
+        assert!(verify_event_id(&tampered).is_err());
+    }
+
     #[test]
     fn test_replica_id_rotation() {
         let pubkey = [1u8; 32];
@@ -2,7 +2,7 @@
 //!
 //! These vectors MUST be reproduced exactly by TypeScript implementation.
 
-use crate::canonical::canonical_bytes;
+use crate::canonical::{canonical_bytes, rule_bundle_bytes};
 use crate::crypto::*;
 use crate::types::*;
 use serde::Serialize;
@@ -45,6 +45,7 @@ fn world_id_vector() -> TestVector {
         w_latency: 0.3,
         w_refusal_consistency: 0.2,
         w_robustness: 0.4,
+        descriptor_ttl_epochs: None,
     };
 
     let phrase = "test world alpha";
@@ -75,7 +76,7 @@ fn world_id_vector() -> TestVector {
             },
             "rule_bundle_hash_hex": hex::encode(rule_hash),
         }),
-        canonical_bytes_hex: hex::encode(canonical_bytes(&bundle).unwrap()),
+        canonical_bytes_hex: hex::encode(rule_bundle_bytes(&bundle).unwrap()),
         hash_hex: hex::encode(world_id.0),
     }
 }
@@ -96,14 +97,15 @@ fn rule_bundle_hash_vector() -> TestVector {
         w_latency: 0.5,
         w_refusal_consistency: 0.3,
         w_robustness: 0.8,
+        descriptor_ttl_epochs: None,
     };
 
-    let bytes = canonical_bytes(&bundle).unwrap();
+    let bytes = rule_bundle_bytes(&bundle).unwrap();
     let hash = blake3::hash(&bytes);
 
     TestVector {
         name: "rule_bundle_hash".into(),
-        description: "BLAKE3(canonical_bytes(RuleBundle))".into(),
+        description: "BLAKE3(rule_bundle_bytes(RuleBundle))".into(),
         inputs: serde_json::json!({
             "version": bundle.version,
             "epoch_len_ms": bundle.epoch_len_ms,
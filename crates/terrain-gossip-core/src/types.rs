@@ -74,6 +74,11 @@ pub struct RuleBundle {
     pub w_latency: f64,
     pub w_refusal_consistency: f64,
     pub w_robustness: f64,
+    /// Epochs a descriptor stays current after publication (unset: until
+    /// superseded or revoked). Left out of the rule bundle hash when unset,
+    /// so existing WorldIds are unchanged.
+    #[serde(default)]
+    pub descriptor_ttl_epochs: Option<u64>,
 }
 
 // =============================================================================
@@ -252,8 +257,18 @@ pub struct DescriptorPublishEvent {
     pub descriptor: ProviderDescriptor,
 }
 
+/// Descriptor withdrawal, signed by the descriptor's provider key
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct DescriptorRevokeEvent {
+    pub world: WorldId,
+    pub epoch_id: u64,
+    pub descriptor_id: DescriptorId,
+    pub provider_transport_pubkey: Vec<u8>,
+    pub signature: Vec<u8>,
+}
+
 /// Event type discriminant (matches protobuf EventType)
-#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq, Hash)]
 #[repr(u8)]
 pub enum EventType {
     Unspecified = 0,
@@ -266,6 +281,7 @@ pub enum EventType {
     Verdict = 7,
     TrainingManifest = 8,
     DescriptorPublish = 9,
+    DescriptorRevoke = 10,
 }
 
 /// Union of all event bodies
@@ -277,6 +293,7 @@ pub enum EventBody {
     LinkHint(LinkHintEvent),
     RuleEndorsement(RuleEndorsementEvent),
     DescriptorPublish(DescriptorPublishEvent),
+    DescriptorRevoke(DescriptorRevokeEvent),
     // Shard, Verdict, TrainingManifest omitted (optional plugin)
 }
 
@@ -295,6 +312,26 @@ pub struct Event {
     pub body: EventBody,
 }
 
+// =============================================================================
+// SEMANTIC QUERY (local only)
+// =============================================================================
+
+/// Top-k search over event summary embeddings
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct SemanticQuery {
+    pub world: WorldId,
+    /// Little-endian f32 components
+    pub embedding: Vec<u8>,
+    pub top_k: u32,
+    pub filter_type: Option<EventType>,
+}
+
+/// Matching events, best first
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct SemanticQueryResult {
+    pub event_ids: Vec<EventId>,
+}
+
 // =============================================================================
 // DELTA SYNC
 // =============================================================================
@@ -321,6 +358,7 @@ pub struct CircuitCreate {
 
 /// Next hop specification for circuit extension
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[allow(clippy::large_enum_variant)]
 pub enum NextHop {
     DescriptorId(DescriptorId),
     DescriptorInline(ProviderDescriptor),
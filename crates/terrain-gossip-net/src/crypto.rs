@@ -10,6 +10,7 @@ use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
 use hkdf::Hkdf;
 use rand::rngs::OsRng;
 use sha2::Sha256;
+use std::path::Path;
 use thiserror::Error;
 use x25519_dalek::{EphemeralSecret, PublicKey as X25519Public, SharedSecret};
 
@@ -47,6 +48,33 @@ impl KeyPair {
         Self { signing_key }
     }
 
+    /// Load the keypair whose seed is stored at `path`, generating and
+    /// storing one on first use so the identity survives restarts
+    pub fn load_or_generate(path: &Path) -> std::io::Result<Self> {
+        if let Ok(bytes) = std::fs::read(path) {
+            let seed: [u8; 32] = bytes.as_slice().try_into().map_err(|_| {
+                std::io::Error::new(std::io::ErrorKind::InvalidData, "key file is not 32 bytes")
+            })?;
+            return Ok(Self::from_seed(&seed));
+        }
+
+        let keypair = Self::generate();
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mut options = std::fs::OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        std::io::Write::write_all(&mut options.open(path)?, &keypair.seed())?;
+        Ok(keypair)
+    }
+
+    /// Seed bytes, for signers that share this identity
+    pub fn seed(&self) -> [u8; 32] {
+        self.signing_key.to_bytes()
+    }
+
     /// Get the public key bytes
     pub fn public_key(&self) -> [u8; 32] {
         self.signing_key.verifying_key().to_bytes()
@@ -57,6 +85,17 @@ impl KeyPair {
         self.signing_key.verifying_key()
     }
 
+    /// PKCS#8 v1 encoding of the private key, for TLS certificates
+    pub(crate) fn to_pkcs8_der(&self) -> Vec<u8> {
+        const PREFIX: [u8; 16] = [
+            0x30, 0x2e, 0x02, 0x01, 0x00, 0x30, 0x05, 0x06, 0x03, 0x2b, 0x65, 0x70, 0x04, 0x22,
+            0x04, 0x20,
+        ];
+        let mut der = PREFIX.to_vec();
+        der.extend_from_slice(&self.signing_key.to_bytes());
+        der
+    }
+
     /// Sign a message
     pub fn sign(&self, message: &[u8]) -> [u8; 64] {
         self.signing_key.sign(message).to_bytes()
@@ -205,6 +244,17 @@ impl Default for EphemeralKeyExchange {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_keypair_persists() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("keys").join("node.key");
+        let kp = KeyPair::load_or_generate(&path).unwrap();
+        assert_eq!(KeyPair::load_or_generate(&path).unwrap().public_key(), kp.public_key());
+
+        std::fs::write(&path, b"short").unwrap();
+        assert!(KeyPair::load_or_generate(&path).is_err());
+    }
+
     #[test]
     fn test_keypair_sign_verify() {
         let kp = KeyPair::generate();
@@ -236,20 +286,19 @@ mod tests {
         let alice_pub = X25519Public::from(alice.public_key());
         let bob_pub = X25519Public::from(bob.public_key());
 
-        let shared = alice.exchange(&bob_pub.to_bytes());
+        let alice_shared = alice.exchange(&bob_pub.to_bytes());
+        let bob_shared = bob.exchange(&alice_pub.to_bytes());
 
-        let mut alice_keys = SessionKeys::derive(&shared, &alice_pub, &bob_pub, b"test").unwrap();
+        let mut alice_keys =
+            SessionKeys::derive(&alice_shared, &alice_pub, &bob_pub, b"test").unwrap();
+        // Bob derives keys with swapped roles
+        let bob_keys = SessionKeys::derive(&bob_shared, &bob_pub, &alice_pub, b"test").unwrap();
 
         let plaintext = b"secret message";
         let aad = b"context";
 
         let ciphertext = alice_keys.encrypt(plaintext, aad).unwrap();
-
-        // Bob would derive keys with swapped roles
-        let bob_exchange = EphemeralKeyExchange::new();
-        let shared2 = bob_exchange.exchange(&alice_pub.to_bytes());
-        let bob_keys = SessionKeys::derive(&shared2, &bob_pub, &alice_pub, b"test").unwrap();
-
-        // Note: This test shows the API; real usage requires same shared secret
+        let decrypted = bob_keys.decrypt(&ciphertext, aad, 0).unwrap();
+        assert_eq!(decrypted, plaintext);
     }
 }
@@ -5,6 +5,7 @@
 use bytes::{Buf, BufMut, BytesMut};
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
 use tokio_util::codec::{Decoder, Encoder};
 
 /// Maximum frame size (16 MB)
@@ -38,12 +39,30 @@ pub enum FrameType {
     Ping = 0,
     /// Pong response
     Pong = 1,
+    /// Connection handshake
+    Hello = 2,
+    /// Handshake proof, sent after HELLO under the agreed sealing
+    HelloAuth = 3,
     /// Delta sync request
     DeltaSyncRequest = 10,
     /// Delta sync response
     DeltaSyncResponse = 11,
     /// Event broadcast
     EventBroadcast = 12,
+    /// Lazy push announcement of event IDs
+    IHave = 13,
+    /// Request for announced events (also grafts the sender)
+    IWant = 14,
+    /// Demote the sender from eager to lazy push
+    GossipPrune = 15,
+    /// Known control-plane peer addresses
+    PeerExchange = 16,
+    /// AEAD-sealed control-plane frame
+    Sealed = 17,
+    /// Request for specific events by ID
+    EventFetch = 18,
+    /// Events held from an EventFetch
+    EventFetchResponse = 19,
     /// Descriptor query
     DescriptorQuery = 20,
     /// Descriptor response
@@ -60,6 +79,16 @@ pub enum FrameType {
     InferenceRequest = 40,
     /// Inference response (inside circuit)
     InferenceResponse = 41,
+    /// Query for the providers an inference node can reach
+    RouteQuery = 42,
+    /// Provider keys an inference node can reach
+    RouteResponse = 43,
+    /// Local API request
+    ApiRequest = 50,
+    /// Local API response
+    ApiResponse = 51,
+    /// Local API subscription event
+    ApiEvent = 52,
 }
 
 impl TryFrom<u8> for FrameType {
@@ -69,9 +98,18 @@ impl TryFrom<u8> for FrameType {
         match value {
             0 => Ok(Self::Ping),
             1 => Ok(Self::Pong),
+            2 => Ok(Self::Hello),
+            3 => Ok(Self::HelloAuth),
             10 => Ok(Self::DeltaSyncRequest),
             11 => Ok(Self::DeltaSyncResponse),
             12 => Ok(Self::EventBroadcast),
+            13 => Ok(Self::IHave),
+            14 => Ok(Self::IWant),
+            15 => Ok(Self::GossipPrune),
+            16 => Ok(Self::PeerExchange),
+            17 => Ok(Self::Sealed),
+            18 => Ok(Self::EventFetch),
+            19 => Ok(Self::EventFetchResponse),
             20 => Ok(Self::DescriptorQuery),
             21 => Ok(Self::DescriptorResponse),
             30 => Ok(Self::CircuitCreate),
@@ -80,11 +118,65 @@ impl TryFrom<u8> for FrameType {
             33 => Ok(Self::CircuitDestroy),
             40 => Ok(Self::InferenceRequest),
             41 => Ok(Self::InferenceResponse),
+            42 => Ok(Self::RouteQuery),
+            43 => Ok(Self::RouteResponse),
+            50 => Ok(Self::ApiRequest),
+            51 => Ok(Self::ApiResponse),
+            52 => Ok(Self::ApiEvent),
             _ => Err(FrameError::Serialization(format!("Unknown frame type: {}", value))),
         }
     }
 }
 
+/// Groups of frame types that share one transport stream
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum FrameClass {
+    /// Keepalive, handshake, peer exchange and sealed frames
+    Control,
+    /// Event dissemination and delta sync
+    Gossip,
+    /// Descriptor queries
+    Descriptor,
+    /// Onion circuit setup and cells
+    Circuit,
+    /// Inference traffic
+    Inference,
+    /// Local API
+    Api,
+}
+
+impl FrameType {
+    /// Stream class this frame type is carried on
+    pub fn class(self) -> FrameClass {
+        match self {
+            Self::Ping
+            | Self::Pong
+            | Self::Hello
+            | Self::HelloAuth
+            | Self::PeerExchange
+            | Self::Sealed => FrameClass::Control,
+            Self::DeltaSyncRequest
+            | Self::DeltaSyncResponse
+            | Self::EventBroadcast
+            | Self::IHave
+            | Self::IWant
+            | Self::GossipPrune
+            | Self::EventFetch
+            | Self::EventFetchResponse => FrameClass::Gossip,
+            Self::DescriptorQuery | Self::DescriptorResponse => FrameClass::Descriptor,
+            Self::CircuitCreate
+            | Self::CircuitExtend
+            | Self::CircuitCell
+            | Self::CircuitDestroy => FrameClass::Circuit,
+            Self::InferenceRequest
+            | Self::InferenceResponse
+            | Self::RouteQuery
+            | Self::RouteResponse => FrameClass::Inference,
+            Self::ApiRequest | Self::ApiResponse | Self::ApiEvent => FrameClass::Api,
+        }
+    }
+}
+
 /// Codec for length-prefixed frames
 ///
 /// Wire format:
@@ -197,6 +289,41 @@ impl Frame {
     }
 }
 
+/// Read a single frame from an async stream.
+///
+/// Returns `Ok(None)` if the stream ends cleanly before a new frame starts.
+pub async fn read_frame<R: AsyncRead + Unpin>(reader: &mut R) -> Result<Option<Frame>, FrameError> {
+    let mut len_buf = [0u8; 4];
+    match reader.read_exact(&mut len_buf).await {
+        Ok(_) => {}
+        Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e.into()),
+    }
+
+    let length = u32::from_be_bytes(len_buf) as usize;
+    if length > MAX_FRAME_SIZE {
+        return Err(FrameError::TooLarge(length));
+    }
+
+    let mut buf = BytesMut::with_capacity(4 + length);
+    buf.put_slice(&len_buf);
+    buf.resize(4 + length, 0);
+    reader.read_exact(&mut buf[4..]).await?;
+
+    FrameCodec::new().decode(&mut buf)
+}
+
+/// Write a single frame to an async stream.
+pub async fn write_frame<W: AsyncWrite + Unpin>(
+    writer: &mut W,
+    frame: Frame,
+) -> Result<(), FrameError> {
+    let mut buf = BytesMut::new();
+    FrameCodec::new().encode(frame, &mut buf)?;
+    writer.write_all(&buf).await?;
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -226,4 +353,21 @@ mod tests {
         assert_eq!(decoded.payload.len(), 512);
         assert_eq!(&decoded.payload[..3], &[1, 2, 3]);
     }
+
+    #[tokio::test]
+    async fn test_async_frame_io() {
+        let (mut client, mut server) = tokio::io::duplex(1024);
+
+        write_frame(&mut client, Frame::new(FrameType::IHave, vec![9, 8, 7]))
+            .await
+            .unwrap();
+        drop(client);
+
+        let frame = read_frame(&mut server).await.unwrap().unwrap();
+        assert_eq!(frame.frame_type, FrameType::IHave);
+        assert_eq!(frame.payload, vec![9, 8, 7]);
+
+        // Clean EOF yields no frame
+        assert!(read_frame(&mut server).await.unwrap().is_none());
+    }
 }
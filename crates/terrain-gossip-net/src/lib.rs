@@ -3,17 +3,22 @@
 //! This crate provides:
 //! - Transport keypair management
 //! - QUIC-based secure transport
+//! - Self-signed TLS identities bound to transport keys
 //! - Onion circuit construction and relay
 //! - Message framing and encryption
+//! - Sealed control-plane frames with replay protection
 
 pub mod circuit;
 pub mod crypto;
 pub mod framing;
 pub mod peer;
+pub mod sealed;
+pub mod tls;
 pub mod transport;
 
 pub use circuit::{Circuit, CircuitBuilder, CircuitHop};
 pub use crypto::{KeyPair, SessionKeys};
-pub use framing::{Frame, FrameCodec};
+pub use framing::{Frame, FrameClass, FrameCodec};
 pub use peer::{PeerId, PeerInfo};
-pub use transport::Transport;
+pub use sealed::{Opener, SealKey, SealOffer, Sealer};
+pub use transport::{Transport, TransportEvent};
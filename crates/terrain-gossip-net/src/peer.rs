@@ -42,7 +42,7 @@ pub struct PeerInfo {
 }
 
 /// Roles a peer can serve
-#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
 pub struct PeerRoles {
     /// Can relay inference traffic
     pub relay: bool,
@@ -0,0 +1,614 @@
+//! Sealed control-plane frames
+//!
+//! Control-plane traffic may cross provider-operated relays only if it is
+//! encrypted end to end, so relays never see `TargetRef`s. A sealed frame
+//! wraps any other frame: the inner type and payload are encrypted with
+//! XChaCha20-Poly1305 and the header is bound as associated data.
+//!
+//! Keys come from either the epoch control-plane key (shared by all members)
+//! or per-peer session keys from an X25519 exchange. Each `Sealer` picks a
+//! random session tag, so senders sharing an epoch key never reuse a nonce.
+//! An `Opener` rejects frames outside a sliding replay window per session and
+//! frames older than the allowed clock skew, which bounds how long a session
+//! must be remembered.
+//!
+//! Replay windows live only as long as their `Opener`, and epoch keys are
+//! shared by every connection, so a sealer and opener may be bound to a
+//! channel (e.g. a handshake transcript). The channel is authenticated with
+//! each frame, so frames recorded on one connection, or reflected back to
+//! their sender, fail to open on another.
+//!
+//! Sealed frames are ordinary `Frame`s and can be written to a TCP stream
+//! or carried in circuit cells unchanged.
+
+use crate::crypto::{CryptoError, EphemeralKeyExchange, KeyPair, SessionKeys};
+use crate::framing::{Frame, FrameType};
+use chacha20poly1305::{
+    aead::{Aead, KeyInit, Payload},
+    XChaCha20Poly1305, XNonce,
+};
+use hkdf::Hkdf;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::collections::HashMap;
+use terrain_gossip_core::types::WorldId;
+use thiserror::Error;
+
+/// Domain separator for sealed-frame associated data
+const DOMAIN_SEALED: &[u8] = b"terrain-sealed-v1";
+
+/// HKDF info for deriving a seal key from a control-plane key
+const INFO_EPOCH_KEY: &[u8] = b"terrain-seal-epoch";
+
+/// Domain separator for key IDs
+const DOMAIN_KEY_ID: &[u8] = b"terrain-seal-key-id";
+
+/// Domain separator for session offer signatures
+const DOMAIN_SEAL_OFFER: &[u8] = b"terrain-seal-offer";
+
+/// HKDF context for session keys
+const CONTEXT_SESSION: &[u8] = b"terrain-seal-session";
+
+/// Counters tracked behind the highest one seen
+pub const REPLAY_WINDOW: u64 = 64;
+
+/// Default allowed clock skew (2 minutes)
+pub const DEFAULT_MAX_SKEW_MS: u64 = 120_000;
+
+/// Sessions remembered per opener before the least recent is dropped
+const MAX_SESSIONS: usize = 4096;
+
+/// Sealing errors
+#[derive(Debug, Error)]
+pub enum SealError {
+    #[error("Crypto error: {0}")]
+    Crypto(#[from] CryptoError),
+    #[error("Codec error: {0}")]
+    Codec(#[from] postcard::Error),
+    #[error("Not a sealed frame: {0:?}")]
+    NotSealed(FrameType),
+    #[error("Unknown seal key {0:02x?}")]
+    UnknownKey([u8; 8]),
+    #[error("Replayed frame (counter {0})")]
+    Replay(u64),
+    #[error("Frame sent {0} ms outside the allowed clock skew")]
+    Stale(u64),
+    #[error("Invalid inner frame type {0}")]
+    InnerType(u8),
+    #[error("Invalid seal offer signature")]
+    BadOffer,
+}
+
+/// Symmetric key for sealing frames
+#[derive(Clone)]
+pub struct SealKey {
+    id: [u8; 8],
+    key: [u8; 32],
+}
+
+impl SealKey {
+    /// Use raw key bytes
+    pub fn new(key: [u8; 32]) -> Self {
+        let mut hasher = blake3::Hasher::new();
+        hasher.update(DOMAIN_KEY_ID);
+        hasher.update(&key);
+        let mut id = [0u8; 8];
+        id.copy_from_slice(&hasher.finalize().as_bytes()[..8]);
+        Self { id, key }
+    }
+
+    /// Derive from an epoch control-plane key
+    ///
+    /// The control-plane key also keys `TargetRef` derivation, so it is
+    /// never used for encryption directly.
+    pub fn from_control_plane_key(control_plane_key: &[u8; 32]) -> Self {
+        let hkdf = Hkdf::<Sha256>::new(None, control_plane_key);
+        let mut key = [0u8; 32];
+        hkdf.expand(INFO_EPOCH_KEY, &mut key)
+            .expect("32 bytes is a valid HKDF-SHA256 output length");
+        Self::new(key)
+    }
+
+    /// Sealing and opening keys for one side of a session
+    pub fn from_session(keys: &SessionKeys) -> (SealKey, SealKey) {
+        (Self::new(keys.encrypt_key), Self::new(keys.decrypt_key))
+    }
+
+    /// Public identifier of the key
+    pub fn id(&self) -> [u8; 8] {
+        self.id
+    }
+}
+
+impl std::fmt::Debug for SealKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SealKey")
+            .field("id", &self.id)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Sealing proposed in a connection handshake
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SealOffer {
+    /// Seal under the epoch control-plane key with this ID
+    Epoch { key_id: [u8; 8] },
+    /// Derive session keys from this X25519 key, signed with the sender's
+    /// transport key
+    Session {
+        ephemeral: [u8; 32],
+        signature: Vec<u8>,
+    },
+}
+
+impl SealOffer {
+    /// Offer session sealing with `exchange`
+    pub fn session(keypair: &KeyPair, world: &WorldId, exchange: &EphemeralKeyExchange) -> Self {
+        let ephemeral = exchange.public_key();
+        SealOffer::Session {
+            ephemeral,
+            signature: keypair
+                .sign(&session_offer_bytes(world, &ephemeral))
+                .to_vec(),
+        }
+    }
+}
+
+fn session_offer_bytes(world: &WorldId, ephemeral: &[u8; 32]) -> Vec<u8> {
+    let mut bytes = DOMAIN_SEAL_OFFER.to_vec();
+    bytes.extend_from_slice(&world.0);
+    bytes.extend_from_slice(ephemeral);
+    bytes
+}
+
+/// Sealing and opening keys from our exchange and the peer's session offer
+///
+/// The offer must be signed by `their_transport_key`, which ties the
+/// session to the identity admitted in the handshake.
+pub fn session_keys(
+    exchange: EphemeralKeyExchange,
+    offer: &SealOffer,
+    their_transport_key: &[u8; 32],
+    world: &WorldId,
+) -> Result<(SealKey, SealKey), SealError> {
+    let SealOffer::Session {
+        ephemeral,
+        signature,
+    } = offer
+    else {
+        return Err(SealError::BadOffer);
+    };
+    let signature: [u8; 64] = signature
+        .as_slice()
+        .try_into()
+        .map_err(|_| SealError::BadOffer)?;
+    KeyPair::verify(
+        their_transport_key,
+        &session_offer_bytes(world, ephemeral),
+        &signature,
+    )
+    .map_err(|_| SealError::BadOffer)?;
+
+    let ours = x25519_dalek::PublicKey::from(exchange.public_key());
+    let theirs = x25519_dalek::PublicKey::from(*ephemeral);
+    let shared = exchange.exchange(ephemeral);
+    let mut context = CONTEXT_SESSION.to_vec();
+    context.extend_from_slice(&world.0);
+    let keys = SessionKeys::derive(&shared, &ours, &theirs, &context)?;
+    Ok(SealKey::from_session(&keys))
+}
+
+/// Authenticated header of a sealed frame
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SealedHeader {
+    pub key_id: [u8; 8],
+    /// Random per-sealer tag; with the counter it forms the nonce
+    pub session: [u8; 16],
+    pub counter: u64,
+    /// Sender's Unix time in milliseconds
+    pub sent_at: u64,
+}
+
+impl SealedHeader {
+    fn nonce(&self) -> XNonce {
+        let mut nonce = [0u8; 24];
+        nonce[..16].copy_from_slice(&self.session);
+        nonce[16..].copy_from_slice(&self.counter.to_le_bytes());
+        XNonce::from(nonce)
+    }
+
+    fn associated_data(&self, channel: &[u8; 32]) -> Result<Vec<u8>, SealError> {
+        let mut aad = DOMAIN_SEALED.to_vec();
+        aad.extend_from_slice(channel);
+        aad.extend_from_slice(&postcard::to_allocvec(self)?);
+        Ok(aad)
+    }
+}
+
+/// Payload of a `Sealed` frame
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SealedFrame {
+    pub header: SealedHeader,
+    /// Encrypted inner frame type byte followed by its payload
+    pub ciphertext: Vec<u8>,
+}
+
+/// Seals outgoing frames under one key
+pub struct Sealer {
+    key: SealKey,
+    session: [u8; 16],
+    counter: u64,
+    channel: [u8; 32],
+}
+
+impl Sealer {
+    /// Create a sealer with a fresh random session
+    pub fn new(key: SealKey) -> Self {
+        let mut session = [0u8; 16];
+        rand::rngs::OsRng.fill_bytes(&mut session);
+        Self {
+            key,
+            session,
+            counter: 0,
+            channel: [0; 32],
+        }
+    }
+
+    /// Bind sealed frames to `channel`; only an opener bound to the same
+    /// channel accepts them
+    pub fn with_channel(mut self, channel: [u8; 32]) -> Self {
+        self.channel = channel;
+        self
+    }
+
+    /// ID of the current key
+    pub fn key_id(&self) -> [u8; 8] {
+        self.key.id
+    }
+
+    /// Switch to a new key (e.g. at an epoch boundary), starting a new
+    /// session on the same channel
+    pub fn rekey(&mut self, key: SealKey) {
+        *self = Self::new(key).with_channel(self.channel);
+    }
+
+    /// Seal a frame sent at `now_ms`
+    pub fn seal(&mut self, frame: &Frame, now_ms: u64) -> Result<Frame, SealError> {
+        let header = SealedHeader {
+            key_id: self.key.id,
+            session: self.session,
+            counter: self.counter,
+            sent_at: now_ms,
+        };
+        self.counter += 1;
+
+        let mut plaintext = Vec::with_capacity(frame.payload.len() + 1);
+        plaintext.push(frame.frame_type as u8);
+        plaintext.extend_from_slice(&frame.payload);
+
+        let cipher = XChaCha20Poly1305::new_from_slice(&self.key.key)
+            .map_err(|_| CryptoError::InvalidKeyLength)?;
+        let aad = header.associated_data(&self.channel)?;
+        let ciphertext = cipher
+            .encrypt(
+                &header.nonce(),
+                Payload {
+                    msg: &plaintext,
+                    aad: &aad,
+                },
+            )
+            .map_err(|_| CryptoError::EncryptionFailed)?;
+
+        let sealed = SealedFrame { header, ciphertext };
+        Ok(Frame::new(
+            FrameType::Sealed,
+            postcard::to_allocvec(&sealed)?,
+        ))
+    }
+}
+
+/// Sliding window of counters seen in one session
+#[derive(Clone, Debug, Default)]
+struct ReplayWindow {
+    /// Highest counter accepted
+    highest: u64,
+    /// Bit `i` set if `highest - i` was accepted
+    seen: u64,
+    /// Last time a frame was accepted (Unix millis)
+    last_used: u64,
+}
+
+impl ReplayWindow {
+    /// Whether `counter` is new and inside the window
+    fn check(&self, counter: u64) -> Result<(), SealError> {
+        if self.seen == 0 || counter > self.highest {
+            return Ok(());
+        }
+        let behind = self.highest - counter;
+        if behind >= REPLAY_WINDOW || self.seen & (1 << behind) != 0 {
+            return Err(SealError::Replay(counter));
+        }
+        Ok(())
+    }
+
+    /// Record `counter` as accepted
+    fn accept(&mut self, counter: u64, now_ms: u64) {
+        if self.seen == 0 {
+            self.highest = counter;
+            self.seen = 1;
+        } else if counter > self.highest {
+            let shift = counter - self.highest;
+            self.seen = if shift >= REPLAY_WINDOW {
+                1
+            } else {
+                (self.seen << shift) | 1
+            };
+            self.highest = counter;
+        } else {
+            self.seen |= 1 << (self.highest - counter);
+        }
+        self.last_used = now_ms;
+    }
+}
+
+/// Opens incoming sealed frames and rejects replays
+pub struct Opener {
+    keys: Vec<SealKey>,
+    windows: HashMap<[u8; 16], ReplayWindow>,
+    max_skew_ms: u64,
+    channel: [u8; 32],
+}
+
+impl Opener {
+    /// Create an opener accepting any of `keys`
+    pub fn new(keys: Vec<SealKey>) -> Self {
+        Self {
+            keys,
+            windows: HashMap::new(),
+            max_skew_ms: DEFAULT_MAX_SKEW_MS,
+            channel: [0; 32],
+        }
+    }
+
+    /// Accept only frames sealed for `channel`
+    pub fn with_channel(mut self, channel: [u8; 32]) -> Self {
+        self.channel = channel;
+        self
+    }
+
+    /// Set the allowed clock skew
+    pub fn with_max_skew(mut self, max_skew_ms: u64) -> Self {
+        self.max_skew_ms = max_skew_ms;
+        self
+    }
+
+    /// Replace the accepted keys (e.g. current and previous epoch)
+    pub fn set_keys(&mut self, keys: Vec<SealKey>) {
+        self.keys = keys;
+    }
+
+    /// IDs of the accepted keys
+    pub fn key_ids(&self) -> Vec<[u8; 8]> {
+        self.keys.iter().map(|k| k.id).collect()
+    }
+
+    /// Open a sealed frame received at `now_ms`
+    pub fn open(&mut self, frame: &Frame, now_ms: u64) -> Result<Frame, SealError> {
+        if frame.frame_type != FrameType::Sealed {
+            return Err(SealError::NotSealed(frame.frame_type));
+        }
+        let sealed: SealedFrame = postcard::from_bytes(&frame.payload)?;
+        let header = &sealed.header;
+
+        let skew = now_ms.abs_diff(header.sent_at);
+        if skew > self.max_skew_ms {
+            return Err(SealError::Stale(skew));
+        }
+        let key = self
+            .keys
+            .iter()
+            .find(|k| k.id == header.key_id)
+            .ok_or(SealError::UnknownKey(header.key_id))?;
+        if let Some(window) = self.windows.get(&header.session) {
+            window.check(header.counter)?;
+        }
+
+        // Authenticate before touching the window so forgeries cannot
+        // advance it
+        let cipher = XChaCha20Poly1305::new_from_slice(&key.key)
+            .map_err(|_| CryptoError::InvalidKeyLength)?;
+        let aad = header.associated_data(&self.channel)?;
+        let plaintext = cipher
+            .decrypt(
+                &header.nonce(),
+                Payload {
+                    msg: &sealed.ciphertext,
+                    aad: &aad,
+                },
+            )
+            .map_err(|_| CryptoError::DecryptionFailed)?;
+
+        let (&type_byte, payload) = plaintext.split_first().ok_or(SealError::InnerType(0))?;
+        let frame_type =
+            FrameType::try_from(type_byte).map_err(|_| SealError::InnerType(type_byte))?;
+        if frame_type == FrameType::Sealed {
+            return Err(SealError::InnerType(type_byte));
+        }
+
+        self.remember(header.session, header.counter, now_ms);
+        Ok(Frame::new(frame_type, payload.to_vec()))
+    }
+
+    /// Record an accepted counter, forgetting sessions that can no longer
+    /// produce fresh frames
+    fn remember(&mut self, session: [u8; 16], counter: u64, now_ms: u64) {
+        if !self.windows.contains_key(&session) && self.windows.len() >= MAX_SESSIONS {
+            let horizon = now_ms.saturating_sub(2 * self.max_skew_ms);
+            self.windows.retain(|_, w| w.last_used >= horizon);
+            if self.windows.len() >= MAX_SESSIONS {
+                if let Some(oldest) = self
+                    .windows
+                    .iter()
+                    .min_by_key(|(_, w)| w.last_used)
+                    .map(|(s, _)| *s)
+                {
+                    self.windows.remove(&oldest);
+                }
+            }
+        }
+        self.windows
+            .entry(session)
+            .or_default()
+            .accept(counter, now_ms);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn control_frame() -> Frame {
+        Frame::new(FrameType::PeerExchange, b"target refs".to_vec())
+    }
+
+    #[test]
+    fn test_seal_open_roundtrip() {
+        let key = SealKey::from_control_plane_key(&[7; 32]);
+        let mut sealer = Sealer::new(key.clone());
+        let mut opener = Opener::new(vec![key]);
+
+        let sealed = sealer.seal(&control_frame(), 1_000).unwrap();
+        assert_eq!(sealed.frame_type, FrameType::Sealed);
+        assert!(!sealed
+            .payload
+            .windows(b"target refs".len())
+            .any(|w| w == b"target refs"));
+
+        let opened = opener.open(&sealed, 1_500).unwrap();
+        assert_eq!(opened.frame_type, FrameType::PeerExchange);
+        assert_eq!(opened.payload, b"target refs");
+
+        // Wrong key, tampered header and stale frames are rejected
+        let mut other = Opener::new(vec![SealKey::new([8; 32])]);
+        assert!(matches!(
+            other.open(&sealer.seal(&control_frame(), 1_000).unwrap(), 1_000),
+            Err(SealError::UnknownKey(_))
+        ));
+        let mut sealed_frame: SealedFrame =
+            postcard::from_bytes(&sealer.seal(&control_frame(), 1_000).unwrap().payload).unwrap();
+        sealed_frame.header.sent_at += 1;
+        let tampered = Frame::new(
+            FrameType::Sealed,
+            postcard::to_allocvec(&sealed_frame).unwrap(),
+        );
+        assert!(matches!(
+            opener.open(&tampered, 1_000),
+            Err(SealError::Crypto(CryptoError::DecryptionFailed))
+        ));
+        let stale = sealer.seal(&control_frame(), 1_000).unwrap();
+        assert!(matches!(
+            opener.open(&stale, 1_000 + DEFAULT_MAX_SKEW_MS + 1),
+            Err(SealError::Stale(_))
+        ));
+    }
+
+    #[test]
+    fn test_session_keys() {
+        let world = WorldId([3; 32]);
+        let (alice, bob) = (KeyPair::from_seed(&[1; 32]), KeyPair::from_seed(&[2; 32]));
+        let (alice_x, bob_x) = (EphemeralKeyExchange::new(), EphemeralKeyExchange::new());
+        let alice_offer = SealOffer::session(&alice, &world, &alice_x);
+        let bob_offer = SealOffer::session(&bob, &world, &bob_x);
+
+        // An offer is only accepted from the key that signed it
+        assert!(matches!(
+            session_keys(
+                EphemeralKeyExchange::new(),
+                &bob_offer,
+                &alice.public_key(),
+                &world
+            ),
+            Err(SealError::BadOffer)
+        ));
+
+        let (alice_seal, alice_open) =
+            session_keys(alice_x, &bob_offer, &bob.public_key(), &world).unwrap();
+        let (bob_seal, bob_open) =
+            session_keys(bob_x, &alice_offer, &alice.public_key(), &world).unwrap();
+
+        let frame = Sealer::new(alice_seal).seal(&control_frame(), 0).unwrap();
+        assert_eq!(
+            Opener::new(vec![bob_open]).open(&frame, 0).unwrap().payload,
+            b"target refs"
+        );
+        let frame = Sealer::new(bob_seal).seal(&control_frame(), 0).unwrap();
+        Opener::new(vec![alice_open]).open(&frame, 0).unwrap();
+    }
+
+    #[test]
+    fn test_replay_window() {
+        let key = SealKey::new([1; 32]);
+        let mut sealer = Sealer::new(key.clone());
+        let mut opener = Opener::new(vec![key]);
+
+        let frames: Vec<Frame> = (0..100)
+            .map(|_| sealer.seal(&control_frame(), 0).unwrap())
+            .collect();
+
+        // Out-of-order delivery inside the window is fine, replays are not
+        opener.open(&frames[5], 0).unwrap();
+        opener.open(&frames[3], 0).unwrap();
+        assert!(matches!(
+            opener.open(&frames[5], 0),
+            Err(SealError::Replay(5))
+        ));
+        assert!(matches!(
+            opener.open(&frames[3], 0),
+            Err(SealError::Replay(3))
+        ));
+        opener.open(&frames[4], 0).unwrap();
+
+        // Frames that fell behind the window are refused
+        opener.open(&frames[99], 0).unwrap();
+        assert!(matches!(
+            opener.open(&frames[10], 0),
+            Err(SealError::Replay(10))
+        ));
+        opener.open(&frames[40], 0).unwrap();
+
+        // A rekeyed sealer starts a new session
+        sealer.rekey(SealKey::new([2; 32]));
+        opener.set_keys(vec![SealKey::new([1; 32]), SealKey::new([2; 32])]);
+        opener
+            .open(&sealer.seal(&control_frame(), 0).unwrap(), 0)
+            .unwrap();
+    }
+
+    #[test]
+    fn test_channel_binding() {
+        let key = SealKey::from_control_plane_key(&[5; 32]);
+        let mut sealer = Sealer::new(key.clone()).with_channel([1; 32]);
+        let frame = sealer.seal(&control_frame(), 0).unwrap();
+
+        // A fresh opener on another channel (a new connection) has no
+        // replay window for the frame, but still refuses it
+        for channel in [[0; 32], [2; 32]] {
+            assert!(matches!(
+                Opener::new(vec![key.clone()])
+                    .with_channel(channel)
+                    .open(&frame, 0),
+                Err(SealError::Crypto(CryptoError::DecryptionFailed))
+            ));
+        }
+        let mut opener = Opener::new(vec![key.clone()]).with_channel([1; 32]);
+        opener.open(&frame, 0).unwrap();
+
+        // Rekeying keeps the channel
+        sealer.rekey(SealKey::new([6; 32]));
+        opener.set_keys(vec![key, SealKey::new([6; 32])]);
+        opener
+            .open(&sealer.seal(&control_frame(), 0).unwrap(), 0)
+            .unwrap();
+    }
+}
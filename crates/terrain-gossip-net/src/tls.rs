@@ -0,0 +1,249 @@
+//! Self-signed TLS identities bound to transport keys
+//!
+//! Every node presents a self-signed certificate for its Ed25519 transport
+//! key. There is no CA: a certificate is acceptable when its handshake
+//! signature verifies, and the peer's identity is the key inside it. Dialers
+//! additionally require that key to match the `PeerId` they meant to reach.
+
+use crate::crypto::KeyPair;
+use crate::peer::PeerId;
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::crypto::{ring, CryptoProvider, WebPkiSupportedAlgorithms};
+use rustls::pki_types::{CertificateDer, PrivateKeyDer, PrivatePkcs8KeyDer, ServerName, UnixTime};
+use rustls::server::danger::{ClientCertVerified, ClientCertVerifier};
+use rustls::{DigitallySignedStruct, DistinguishedName, SignatureScheme};
+use std::sync::Arc;
+use thiserror::Error;
+
+/// ALPN protocol identifier for the transport
+pub const ALPN: &[u8] = b"terrain-gossip/1";
+
+/// DER encoding of the Ed25519 algorithm identifier (OID 1.3.101.112)
+const ED25519_OID: [u8; 5] = [0x06, 0x03, 0x2b, 0x65, 0x70];
+
+/// TLS setup errors
+#[derive(Debug, Error)]
+pub enum TlsError {
+    #[error("Certificate error: {0}")]
+    Certificate(#[from] rcgen::Error),
+    #[error("Rustls error: {0}")]
+    Rustls(#[from] rustls::Error),
+}
+
+/// Self-signed certificate and private key for a transport keypair
+pub fn self_signed(
+    keypair: &KeyPair,
+) -> Result<(CertificateDer<'static>, PrivateKeyDer<'static>), TlsError> {
+    let pkcs8 = keypair.to_pkcs8_der();
+    let signing_key = rcgen::KeyPair::try_from(pkcs8.as_slice())?;
+    let name = hex::encode(keypair.public_key());
+    let cert = rcgen::CertificateParams::new(vec![name])?.self_signed(&signing_key)?;
+    let key = PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(pkcs8));
+    Ok((cert.der().clone(), key))
+}
+
+/// Identity of the peer holding a certificate: its Ed25519 subject key
+pub fn peer_id(cert: &[u8]) -> Option<PeerId> {
+    let (_, cert, _) = der_next(cert, 0x30)?;
+    let (_, mut tbs, _) = der_next(cert, 0x30)?;
+    // Optional explicit version
+    if tbs.first() == Some(&0xa0) {
+        tbs = der_next(tbs, 0xa0)?.2;
+    }
+    // Serial, signature algorithm, issuer, validity, subject
+    for tag in [0x02, 0x30, 0x30, 0x30, 0x30] {
+        tbs = der_next(tbs, tag)?.2;
+    }
+    let (_, spki, _) = der_next(tbs, 0x30)?;
+    let (_, algorithm, rest) = der_next(spki, 0x30)?;
+    if algorithm != ED25519_OID {
+        return None;
+    }
+    let (_, bits, _) = der_next(rest, 0x03)?;
+    match bits {
+        [0, key @ ..] => Some(PeerId::from_public_key(key.try_into().ok()?)),
+        _ => None,
+    }
+}
+
+/// Split one DER element with the expected tag off `input`, returning
+/// (tag, contents, rest)
+fn der_next(input: &[u8], tag: u8) -> Option<(u8, &[u8], &[u8])> {
+    let (&found, input) = input.split_first()?;
+    if found != tag {
+        return None;
+    }
+    let (&first, mut input) = input.split_first()?;
+    let len = if first < 0x80 {
+        first as usize
+    } else {
+        let count = (first & 0x7f) as usize;
+        if count == 0 || count > 4 || input.len() < count {
+            return None;
+        }
+        let (bytes, rest) = input.split_at(count);
+        input = rest;
+        bytes.iter().fold(0usize, |len, b| (len << 8) | *b as usize)
+    };
+    if input.len() < len {
+        return None;
+    }
+    let (contents, rest) = input.split_at(len);
+    Some((found, contents, rest))
+}
+
+fn provider() -> Arc<CryptoProvider> {
+    Arc::new(ring::default_provider())
+}
+
+/// Accepts any self-signed Ed25519 certificate, or only the one for
+/// `expected` when set
+#[derive(Debug)]
+struct PeerVerifier {
+    expected: Option<PeerId>,
+    algorithms: WebPkiSupportedAlgorithms,
+}
+
+impl PeerVerifier {
+    fn new(expected: Option<PeerId>) -> Self {
+        Self {
+            expected,
+            algorithms: provider().signature_verification_algorithms,
+        }
+    }
+
+    fn check(&self, end_entity: &CertificateDer<'_>) -> Result<(), rustls::Error> {
+        let id = peer_id(end_entity).ok_or(rustls::Error::InvalidCertificate(
+            rustls::CertificateError::BadEncoding,
+        ))?;
+        match self.expected {
+            Some(expected) if expected != id => Err(rustls::Error::InvalidCertificate(
+                rustls::CertificateError::ApplicationVerificationFailure,
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl ServerCertVerifier for PeerVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        self.check(end_entity)?;
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        rustls::crypto::verify_tls12_signature(message, cert, dss, &self.algorithms)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        rustls::crypto::verify_tls13_signature(message, cert, dss, &self.algorithms)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        vec![SignatureScheme::ED25519]
+    }
+}
+
+impl ClientCertVerifier for PeerVerifier {
+    fn root_hint_subjects(&self) -> &[DistinguishedName] {
+        &[]
+    }
+
+    fn verify_client_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _now: UnixTime,
+    ) -> Result<ClientCertVerified, rustls::Error> {
+        self.check(end_entity)?;
+        Ok(ClientCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        rustls::crypto::verify_tls12_signature(message, cert, dss, &self.algorithms)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        rustls::crypto::verify_tls13_signature(message, cert, dss, &self.algorithms)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        vec![SignatureScheme::ED25519]
+    }
+}
+
+/// TLS 1.3 server config requiring a client certificate from every peer
+pub fn server_config(keypair: &KeyPair) -> Result<rustls::ServerConfig, TlsError> {
+    let (cert, key) = self_signed(keypair)?;
+    let mut config = rustls::ServerConfig::builder_with_provider(provider())
+        .with_protocol_versions(&[&rustls::version::TLS13])?
+        .with_client_cert_verifier(Arc::new(PeerVerifier::new(None)))
+        .with_single_cert(vec![cert], key)?;
+    config.alpn_protocols = vec![ALPN.to_vec()];
+    Ok(config)
+}
+
+/// TLS 1.3 client config that only accepts `expected` as the server
+pub fn client_config(
+    keypair: &KeyPair,
+    expected: PeerId,
+) -> Result<rustls::ClientConfig, TlsError> {
+    let (cert, key) = self_signed(keypair)?;
+    let mut config = rustls::ClientConfig::builder_with_provider(provider())
+        .with_protocol_versions(&[&rustls::version::TLS13])?
+        .dangerous()
+        .with_custom_certificate_verifier(Arc::new(PeerVerifier::new(Some(expected))))
+        .with_client_auth_cert(vec![cert], key)?;
+    config.alpn_protocols = vec![ALPN.to_vec()];
+    Ok(config)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_certificate_binds_transport_key() {
+        let keypair = KeyPair::from_seed(&[7; 32]);
+        let (cert, _) = self_signed(&keypair).unwrap();
+        assert_eq!(
+            peer_id(&cert),
+            Some(PeerId::from_public_key(&keypair.public_key()))
+        );
+
+        // Truncated or foreign encodings yield no identity
+        assert_eq!(peer_id(&cert[..cert.len() / 2]), None);
+        assert_eq!(peer_id(b"not a certificate"), None);
+
+        let verifier = PeerVerifier::new(Some(PeerId([9; 32])));
+        assert!(verifier.check(&cert).is_err());
+        assert!(PeerVerifier::new(None).check(&cert).is_ok());
+    }
+}
@@ -1,17 +1,38 @@
 //! QUIC-based transport layer
 //!
-//! Provides secure, multiplexed connections between nodes.
+//! Provides secure, multiplexed connections between nodes. Peers
+//! authenticate with self-signed certificates for their transport keys (see
+//! [`crate::tls`]), and each [`FrameClass`] travels on its own unidirectional
+//! stream so bulk gossip never stalls control or circuit traffic.
 
 use crate::crypto::KeyPair;
-use crate::framing::{Frame, FrameError};
-use crate::peer::{PeerId, PeerInfo};
-use parking_lot::RwLock;
+use crate::framing::{read_frame, Frame, FrameClass, FrameCodec, FrameError};
+use crate::peer::{PeerId, PeerInfo, PeerRoles};
+use crate::tls::{self, TlsError};
+use bytes::BytesMut;
+use parking_lot::{Mutex, RwLock};
+use quinn::crypto::rustls::{QuicClientConfig, QuicServerConfig};
+use rustls::pki_types::CertificateDer;
 use std::collections::HashMap;
 use std::net::SocketAddr;
 use std::sync::Arc;
+use std::time::Duration;
 use thiserror::Error;
 use tokio::sync::mpsc;
-use tracing::{error, info, warn};
+use tokio_util::codec::Encoder;
+use tracing::{debug, info, warn};
+
+/// Server name sent when dialing; peers are identified by key, not name
+const SERVER_NAME: &str = "terrain-gossip";
+
+/// Frames queued per stream before `send` waits
+const STREAM_QUEUE_DEPTH: usize = 64;
+
+/// Keepalive interval for idle connections
+const KEEP_ALIVE: Duration = Duration::from_secs(15);
+
+/// Idle time after which a connection is dropped (millis)
+const IDLE_TIMEOUT_MS: u32 = 60_000;
 
 /// Transport errors
 #[derive(Debug, Error)]
@@ -20,6 +41,8 @@ pub enum TransportError {
     Io(#[from] std::io::Error),
     #[error("QUIC error: {0}")]
     Quic(String),
+    #[error("TLS error: {0}")]
+    Tls(#[from] TlsError),
     #[error("Connection closed")]
     ConnectionClosed,
     #[error("Frame error: {0}")]
@@ -28,14 +51,26 @@ pub enum TransportError {
     PeerNotFound(PeerId),
     #[error("Already connected to peer: {0}")]
     AlreadyConnected(PeerId),
+    #[error("Transport is not bound")]
+    NotBound,
+}
+
+fn quic_error(e: impl std::fmt::Display) -> TransportError {
+    TransportError::Quic(e.to_string())
 }
 
 /// Connection to a peer
 pub struct Connection {
     /// Peer info
     pub peer: PeerInfo,
-    /// Send channel
-    tx: mpsc::Sender<Frame>,
+    /// Underlying QUIC connection
+    quic: quinn::Connection,
+    /// Whether we dialed this connection
+    outbound: bool,
+    /// Fixed cell size for circuit cells (0 = no padding)
+    fixed_cell_bytes: usize,
+    /// Send queue of each stream opened so far
+    streams: Mutex<HashMap<FrameClass, mpsc::Sender<Frame>>>,
     /// Is the connection open
     open: Arc<RwLock<bool>>,
 }
@@ -46,12 +81,30 @@ impl Connection {
         if !*self.open.read() {
             return Err(TransportError::ConnectionClosed);
         }
-        self.tx
+        self.stream(frame.frame_type.class())
             .send(frame)
             .await
             .map_err(|_| TransportError::ConnectionClosed)
     }
 
+    /// Send queue for a frame class, opening its stream on first use
+    fn stream(&self, class: FrameClass) -> mpsc::Sender<Frame> {
+        let mut streams = self.streams.lock();
+        if let Some(tx) = streams.get(&class).filter(|tx| !tx.is_closed()) {
+            return tx.clone();
+        }
+        let (tx, rx) = mpsc::channel(STREAM_QUEUE_DEPTH);
+        let codec = FrameCodec::with_fixed_cells(self.fixed_cell_bytes);
+        tokio::spawn(write_stream(self.quic.clone(), rx, codec));
+        streams.insert(class, tx.clone());
+        tx
+    }
+
+    /// Remote address of the connection
+    pub fn remote_addr(&self) -> SocketAddr {
+        self.quic.remote_address()
+    }
+
     /// Check if connection is open
     pub fn is_open(&self) -> bool {
         *self.open.read()
@@ -60,7 +113,36 @@ impl Connection {
     /// Close the connection
     pub fn close(&self) {
         *self.open.write() = false;
+        self.quic.close(0u32.into(), b"closed");
+    }
+}
+
+/// Drain a send queue onto a fresh unidirectional stream
+async fn write_stream(
+    quic: quinn::Connection,
+    mut rx: mpsc::Receiver<Frame>,
+    mut codec: FrameCodec,
+) {
+    let mut stream = match quic.open_uni().await {
+        Ok(stream) => stream,
+        Err(e) => {
+            debug!("Failed to open stream: {}", e);
+            return;
+        }
+    };
+    let mut buf = BytesMut::new();
+    while let Some(frame) = rx.recv().await {
+        buf.clear();
+        if let Err(e) = codec.encode(frame, &mut buf) {
+            warn!("Dropping unencodable frame: {}", e);
+            continue;
+        }
+        if let Err(e) = stream.write_all(&buf).await {
+            debug!("Stream write failed: {}", e);
+            return;
+        }
     }
+    let _ = stream.finish();
 }
 
 /// Event from the transport layer
@@ -82,6 +164,10 @@ pub struct Transport {
     our_id: PeerId,
     /// Listen address
     listen_addr: SocketAddr,
+    /// QUIC endpoint, once bound
+    endpoint: RwLock<Option<quinn::Endpoint>>,
+    /// Where transport events are delivered, once bound
+    events: RwLock<Option<mpsc::Sender<TransportEvent>>>,
     /// Connected peers
     connections: RwLock<HashMap<PeerId, Arc<Connection>>>,
     /// Known peers (may not be connected)
@@ -98,6 +184,8 @@ impl Transport {
             keypair,
             our_id,
             listen_addr,
+            endpoint: RwLock::new(None),
+            events: RwLock::new(None),
             connections: RwLock::new(HashMap::new()),
             known_peers: RwLock::new(HashMap::new()),
             fixed_cell_bytes: 0,
@@ -156,7 +244,7 @@ impl Transport {
             .read()
             .get(to)
             .cloned()
-            .ok_or_else(|| TransportError::PeerNotFound(*to))?;
+            .ok_or(TransportError::PeerNotFound(*to))?;
 
         conn.send(frame).await
     }
@@ -171,35 +259,279 @@ impl Transport {
         }
     }
 
-    /// Start the transport layer (placeholder for QUIC implementation)
+    /// Bind the QUIC endpoint, returning the address it listens on
+    ///
+    /// Events from all connections are delivered to `event_tx`. Binding
+    /// twice keeps the first endpoint.
+    pub fn bind(
+        &self,
+        event_tx: mpsc::Sender<TransportEvent>,
+    ) -> Result<SocketAddr, TransportError> {
+        let mut endpoint = self.endpoint.write();
+        if let Some(endpoint) = endpoint.as_ref() {
+            return Ok(endpoint.local_addr()?);
+        }
+
+        let crypto =
+            QuicServerConfig::try_from(tls::server_config(&self.keypair)?).map_err(quic_error)?;
+        let mut config = quinn::ServerConfig::with_crypto(Arc::new(crypto));
+        config.transport_config(transport_config());
+        let bound = quinn::Endpoint::server(config, self.listen_addr)?;
+        let addr = bound.local_addr()?;
+
+        *endpoint = Some(bound);
+        *self.events.write() = Some(event_tx);
+        info!("Transport listening on {}", addr);
+        Ok(addr)
+    }
+
+    /// Dial a peer, authenticating it against its `PeerId`
+    ///
+    /// Each of the peer's addresses is tried in turn.
+    pub async fn connect(
+        self: &Arc<Self>,
+        peer: &PeerInfo,
+    ) -> Result<Arc<Connection>, TransportError> {
+        if self.is_connected(&peer.id) {
+            return Err(TransportError::AlreadyConnected(peer.id));
+        }
+        let endpoint = self
+            .endpoint
+            .read()
+            .clone()
+            .ok_or(TransportError::NotBound)?;
+        let crypto = QuicClientConfig::try_from(tls::client_config(&self.keypair, peer.id)?)
+            .map_err(quic_error)?;
+        let mut config = quinn::ClientConfig::new(Arc::new(crypto));
+        config.transport_config(transport_config());
+        self.add_peer(peer.clone());
+
+        let mut last_error = TransportError::PeerNotFound(peer.id);
+        for addr in &peer.addrs {
+            let connecting = match endpoint.connect_with(config.clone(), *addr, SERVER_NAME) {
+                Ok(connecting) => connecting,
+                Err(e) => {
+                    last_error = quic_error(e);
+                    continue;
+                }
+            };
+            match connecting.await {
+                Ok(quic) => return self.establish(quic, true),
+                Err(e) => {
+                    debug!("Failed to connect to {} at {}: {}", peer.id, addr, e);
+                    last_error = quic_error(e);
+                }
+            }
+        }
+        Err(last_error)
+    }
+
+    /// Start the transport layer, accepting connections until the endpoint
+    /// closes
+    ///
+    /// Binds first if [`Transport::bind`] has not been called.
     pub async fn run(
         self: Arc<Self>,
         event_tx: mpsc::Sender<TransportEvent>,
     ) -> Result<(), TransportError> {
-        info!("Transport listening on {}", self.listen_addr);
-
-        // TODO: Implement QUIC server/client
-        // For now, this is a placeholder that shows the API
-        // Real implementation would:
-        // 1. Create QUIC endpoint
-        // 2. Accept incoming connections
-        // 3. Handle connection establishment with key exchange
-        // 4. Spawn tasks for reading/writing frames
-        // 5. Send events via event_tx
-
-        // Keep running
+        self.bind(event_tx)?;
+        let endpoint = self
+            .endpoint
+            .read()
+            .clone()
+            .ok_or(TransportError::NotBound)?;
+
+        while let Some(incoming) = endpoint.accept().await {
+            let transport = self.clone();
+            tokio::spawn(async move {
+                let result = match incoming.await {
+                    Ok(quic) => transport.establish(quic, false).map(|_| ()),
+                    Err(e) => Err(quic_error(e)),
+                };
+                if let Err(e) = result {
+                    debug!("Rejected inbound connection: {}", e);
+                }
+            });
+        }
+        Ok(())
+    }
+
+    /// Close the endpoint and every connection
+    pub fn shutdown(&self) {
+        for conn in self.connections.write().drain().map(|(_, conn)| conn) {
+            conn.close();
+        }
+        if let Some(endpoint) = self.endpoint.read().as_ref() {
+            endpoint.close(0u32.into(), b"shutdown");
+        }
+    }
+
+    /// Register a handshaken connection and start reading its streams
+    fn establish(
+        self: &Arc<Self>,
+        quic: quinn::Connection,
+        outbound: bool,
+    ) -> Result<Arc<Connection>, TransportError> {
+        let events = self.events.read().clone().ok_or(TransportError::NotBound)?;
+        let Some(id) = peer_identity(&quic) else {
+            quic.close(0u32.into(), b"no identity");
+            return Err(TransportError::Quic("peer presented no identity".into()));
+        };
+
+        let mut peer = self
+            .get_peer(&id)
+            .unwrap_or_else(|| PeerInfo::new(id.0, vec![]));
+        // Inbound connections come from ephemeral ports, not listen addresses
+        if outbound && !peer.addrs.contains(&quic.remote_address()) {
+            peer.addrs.push(quic.remote_address());
+        }
+        peer.touch();
+        self.add_peer(peer.clone());
+
+        let conn = Arc::new(Connection {
+            peer,
+            quic,
+            outbound,
+            fixed_cell_bytes: self.fixed_cell_bytes,
+            streams: Mutex::new(HashMap::new()),
+            open: Arc::new(RwLock::new(true)),
+        });
+        {
+            let mut connections = self.connections.write();
+            if let Some(existing) = connections.get(&id).filter(|c| c.is_open()) {
+                if self.dialer(existing).0 < self.dialer(&conn).0 {
+                    conn.close();
+                    return Err(TransportError::AlreadyConnected(id));
+                }
+                existing.close();
+            }
+            connections.insert(id, conn.clone());
+        }
+
+        debug!(
+            "Connected to {} ({})",
+            id,
+            if outbound { "outbound" } else { "inbound" }
+        );
+        tokio::spawn(self.clone().read_streams(conn.clone(), events));
+        Ok(conn)
+    }
+
+    /// Which side dialed a connection
+    ///
+    /// When two peers dial each other at once, both keep the connection
+    /// dialed by the smaller ID.
+    fn dialer(&self, conn: &Connection) -> PeerId {
+        if conn.outbound {
+            self.our_id
+        } else {
+            conn.peer.id
+        }
+    }
+
+    /// Surface frames from every stream the peer opens until it disconnects
+    async fn read_streams(
+        self: Arc<Self>,
+        conn: Arc<Connection>,
+        events: mpsc::Sender<TransportEvent>,
+    ) {
+        let from = conn.peer.id;
+        if events
+            .send(TransportEvent::PeerConnected(from))
+            .await
+            .is_err()
+        {
+            return;
+        }
+
         loop {
-            tokio::time::sleep(tokio::time::Duration::from_secs(60)).await;
+            match conn.quic.accept_uni().await {
+                Ok(recv) => {
+                    tokio::spawn(read_stream(from, recv, events.clone()));
+                }
+                Err(e) => {
+                    debug!("Connection to {} closed: {}", from, e);
+                    break;
+                }
+            }
+        }
+
+        *conn.open.write() = false;
+        let removed = {
+            let mut connections = self.connections.write();
+            let current = connections
+                .get(&from)
+                .is_some_and(|c| c.quic.stable_id() == conn.quic.stable_id());
+            current && connections.remove(&from).is_some()
+        };
+        if removed {
+            let _ = events.send(TransportEvent::PeerDisconnected(from)).await;
+        }
+    }
+}
+
+/// Forward frames from one stream, which must carry a single frame class
+async fn read_stream(
+    from: PeerId,
+    mut recv: quinn::RecvStream,
+    events: mpsc::Sender<TransportEvent>,
+) {
+    let mut class = None;
+    loop {
+        let frame = match read_frame(&mut recv).await {
+            Ok(Some(frame)) => frame,
+            Ok(None) => return,
+            Err(e) => {
+                debug!("Stream from {} failed: {}", from, e);
+                return;
+            }
+        };
+        let frame_class = frame.frame_type.class();
+        if *class.get_or_insert(frame_class) != frame_class {
+            warn!("Peer {} mixed frame classes on one stream", from);
+            let _ = recv.stop(0u32.into());
+            return;
+        }
+        if events
+            .send(TransportEvent::FrameReceived { from, frame })
+            .await
+            .is_err()
+        {
+            return;
         }
     }
 }
 
+/// Peer identity from the certificate presented during the handshake
+fn peer_identity(quic: &quinn::Connection) -> Option<PeerId> {
+    let certs = quic
+        .peer_identity()?
+        .downcast::<Vec<CertificateDer<'static>>>()
+        .ok()?;
+    tls::peer_id(certs.first()?)
+}
+
+fn transport_config() -> Arc<quinn::TransportConfig> {
+    let mut config = quinn::TransportConfig::default();
+    config
+        .keep_alive_interval(Some(KEEP_ALIVE))
+        .max_idle_timeout(Some(quinn::VarInt::from_u32(IDLE_TIMEOUT_MS).into()));
+    Arc::new(config)
+}
+
 /// Message serialization helpers
 pub mod messages {
     use super::*;
+    use crate::sealed::SealOffer;
     use serde::{Deserialize, Serialize};
     use terrain_gossip_core::types::*;
 
+    /// Domain separator for handshake transcripts
+    const DOMAIN_HELLO_TRANSCRIPT: &[u8] = b"terrain-hello-transcript";
+
+    /// Domain separator for handshake signatures
+    const DOMAIN_HELLO_AUTH: &[u8] = b"terrain-hello-auth";
+
     /// Delta sync request message
     #[derive(Clone, Debug, Serialize, Deserialize)]
     pub struct DeltaSyncRequest {
@@ -214,6 +546,8 @@ pub mod messages {
         pub world: WorldId,
         pub events: Vec<Event>,
         pub now: Vec<VersionVectorEntry>,
+        /// Whether the sender has more events after this page
+        pub has_more: bool,
     }
 
     /// Descriptor query message
@@ -229,25 +563,288 @@ pub mod messages {
         pub descriptor: Option<ProviderDescriptor>,
     }
 
-    impl DeltaSyncRequest {
-        pub fn to_frame(&self) -> Result<Frame, postcard::Error> {
-            let payload = postcard::to_allocvec(self)?;
-            Ok(Frame::new(crate::framing::FrameType::DeltaSyncRequest, payload))
+    /// Connection handshake, sent by both sides before any other frame
+    ///
+    /// HELLO travels in the clear, so it carries no secrets: knowledge of
+    /// the world phrase is proven afterwards in `HelloAuth`.
+    #[derive(Clone, Debug, Serialize, Deserialize)]
+    pub struct Hello {
+        pub world: WorldId,
+        pub transport_pubkey: [u8; 32],
+        /// Fresh random value binding the handshake to this connection
+        pub nonce: [u8; 32],
+        /// Address the sender accepts connections on, if any
+        pub listen_addr: Option<SocketAddr>,
+        pub roles: PeerRoles,
+        /// Sealing the sender requires for all later frames
+        pub seal: Option<SealOffer>,
+    }
+
+    impl Hello {
+        /// Transcript of a handshake from this HELLO's sender to `peer`
+        ///
+        /// Covers both HELLOs, so a proof over it is bound to this
+        /// connection and direction.
+        pub fn transcript(&self, peer: &Hello) -> Result<[u8; 32], postcard::Error> {
+            let mut hasher = blake3::Hasher::new();
+            hasher.update(DOMAIN_HELLO_TRANSCRIPT);
+            hasher.update(&postcard::to_allocvec(self)?);
+            hasher.update(&postcard::to_allocvec(peer)?);
+            Ok(*hasher.finalize().as_bytes())
         }
+    }
 
-        pub fn from_frame(frame: &Frame) -> Result<Self, postcard::Error> {
-            postcard::from_bytes(&frame.payload)
+    /// Second handshake frame, sent under the sealing agreed in HELLO
+    #[derive(Clone, Debug, Serialize, Deserialize)]
+    pub struct HelloAuth {
+        /// MAC of the sender's transcript keyed by the world phrase
+        pub phrase_proof: [u8; 32],
+        /// Signature over the sender's transcript by its transport key,
+        /// proving it holds the key announced in HELLO
+        pub signature: Vec<u8>,
+    }
+
+    impl HelloAuth {
+        /// Sign the sender's `transcript` with its transport key
+        pub fn new(keypair: &KeyPair, transcript: &[u8; 32], phrase_proof: [u8; 32]) -> Self {
+            Self {
+                phrase_proof,
+                signature: keypair.sign(&auth_sign_bytes(transcript)).to_vec(),
+            }
         }
+
+        /// Whether `transport_pubkey` signed `transcript`
+        pub fn verify(&self, transport_pubkey: &[u8; 32], transcript: &[u8; 32]) -> bool {
+            let Ok(signature) = <[u8; 64]>::try_from(self.signature.as_slice()) else {
+                return false;
+            };
+            KeyPair::verify(transport_pubkey, &auth_sign_bytes(transcript), &signature).is_ok()
+        }
+    }
+
+    fn auth_sign_bytes(transcript: &[u8; 32]) -> Vec<u8> {
+        let mut bytes = DOMAIN_HELLO_AUTH.to_vec();
+        bytes.extend_from_slice(transcript);
+        bytes
+    }
+
+    /// Eager push of full events
+    #[derive(Clone, Debug, Serialize, Deserialize)]
+    pub struct EventBroadcast {
+        pub world: WorldId,
+        pub events: Vec<Event>,
+    }
+
+    /// Lazy push: announce event IDs without their bodies
+    #[derive(Clone, Debug, Serialize, Deserialize)]
+    pub struct IHave {
+        pub world: WorldId,
+        pub event_ids: Vec<EventId>,
+    }
+
+    /// Request events previously announced via IHave
+    #[derive(Clone, Debug, Serialize, Deserialize)]
+    pub struct IWant {
+        pub world: WorldId,
+        pub event_ids: Vec<EventId>,
+    }
+
+    /// Request specific events by ID, whether or not they were announced
+    #[derive(Clone, Debug, Serialize, Deserialize)]
+    pub struct EventFetch {
+        pub world: WorldId,
+        pub event_ids: Vec<EventId>,
+    }
+
+    /// The requested events the responder holds; absent ones are omitted
+    #[derive(Clone, Debug, Serialize, Deserialize)]
+    pub struct EventFetchResponse {
+        pub world: WorldId,
+        pub events: Vec<Event>,
+    }
+
+    /// Ask the receiver to stop eagerly pushing to the sender
+    #[derive(Clone, Debug, Serialize, Deserialize)]
+    pub struct GossipPrune {
+        pub world: WorldId,
     }
 
-    impl DeltaSyncResponse {
-        pub fn to_frame(&self) -> Result<Frame, postcard::Error> {
-            let payload = postcard::to_allocvec(self)?;
-            Ok(Frame::new(crate::framing::FrameType::DeltaSyncResponse, payload))
+    /// A control-plane peer advertised via peer exchange
+    #[derive(Clone, Debug, Serialize, Deserialize)]
+    pub struct PeerAdvert {
+        pub transport_pubkey: [u8; 32],
+        pub addr: SocketAddr,
+        pub roles: PeerRoles,
+        /// Last time the advertiser saw this peer (unix millis)
+        pub last_seen: u64,
+    }
+
+    /// Peer exchange: admitted peers the sender has connected to
+    #[derive(Clone, Debug, Serialize, Deserialize)]
+    pub struct PeerExchange {
+        pub world: WorldId,
+        pub peers: Vec<PeerAdvert>,
+    }
+
+    macro_rules! impl_frame_message {
+        ($($msg:ident),* $(,)?) => {
+            $(
+                impl $msg {
+                    pub fn to_frame(&self) -> Result<Frame, postcard::Error> {
+                        let payload = postcard::to_allocvec(self)?;
+                        Ok(Frame::new(crate::framing::FrameType::$msg, payload))
+                    }
+
+                    pub fn from_frame(frame: &Frame) -> Result<Self, postcard::Error> {
+                        postcard::from_bytes(&frame.payload)
+                    }
+                }
+            )*
+        };
+    }
+
+    impl_frame_message!(
+        Hello,
+        HelloAuth,
+        DeltaSyncRequest,
+        DeltaSyncResponse,
+        EventBroadcast,
+        IHave,
+        IWant,
+        GossipPrune,
+        EventFetch,
+        EventFetchResponse,
+        PeerExchange,
+        DescriptorQuery,
+        DescriptorResponse,
+    );
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::framing::FrameType;
+
+        #[test]
+        fn test_hello_auth_proves_transport_key() {
+            let keypair = KeyPair::from_seed(&[1; 32]);
+            let auth = HelloAuth::new(&keypair, &[2; 32], [0; 32]);
+            assert!(auth.verify(&keypair.public_key(), &[2; 32]));
+            assert!(!auth.verify(&keypair.public_key(), &[3; 32]));
+            assert!(!auth.verify(&KeyPair::from_seed(&[4; 32]).public_key(), &[2; 32]));
         }
 
-        pub fn from_frame(frame: &Frame) -> Result<Self, postcard::Error> {
-            postcard::from_bytes(&frame.payload)
+        #[test]
+        fn test_message_frame_roundtrip() {
+            let ihave = IHave {
+                world: WorldId([1; 32]),
+                event_ids: vec![EventId([2; 32]), EventId([3; 32])],
+            };
+
+            let frame = ihave.to_frame().unwrap();
+            assert_eq!(frame.frame_type, FrameType::IHave);
+
+            let decoded = IHave::from_frame(&frame).unwrap();
+            assert_eq!(decoded.world, ihave.world);
+            assert_eq!(decoded.event_ids, ihave.event_ids);
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::framing::FrameType;
+
+    async fn start(seed: u8) -> (Arc<Transport>, SocketAddr, mpsc::Receiver<TransportEvent>) {
+        let keypair = KeyPair::from_seed(&[seed; 32]);
+        let transport =
+            Arc::new(Transport::new(keypair, "127.0.0.1:0".parse().unwrap()).with_fixed_cells(64));
+        let (tx, rx) = mpsc::channel(16);
+        let addr = transport.bind(tx.clone()).unwrap();
+        tokio::spawn(transport.clone().run(tx));
+        (transport, addr, rx)
+    }
+
+    async fn next_event(rx: &mut mpsc::Receiver<TransportEvent>) -> TransportEvent {
+        tokio::time::timeout(Duration::from_secs(5), rx.recv())
+            .await
+            .expect("timed out waiting for transport event")
+            .expect("event channel closed")
+    }
+
+    #[tokio::test]
+    async fn test_loopback_frames() {
+        let (a, _, mut a_events) = start(1).await;
+        let (b, b_addr, mut b_events) = start(2).await;
+
+        let conn = a
+            .connect(&PeerInfo::new(b.public_key(), vec![b_addr]))
+            .await
+            .unwrap();
+        assert_eq!(conn.peer.id, b.our_id());
+        assert!(matches!(
+            a.connect(&PeerInfo::new(b.public_key(), vec![b_addr]))
+                .await,
+            Err(TransportError::AlreadyConnected(_))
+        ));
+
+        assert!(matches!(
+            next_event(&mut a_events).await,
+            TransportEvent::PeerConnected(id) if id == b.our_id()
+        ));
+        assert!(matches!(
+            next_event(&mut b_events).await,
+            TransportEvent::PeerConnected(id) if id == a.our_id()
+        ));
+
+        // Frames of one class arrive in order; circuit cells are padded
+        for frame in [
+            Frame::new(FrameType::IHave, vec![1]),
+            Frame::new(FrameType::IWant, vec![2]),
+            Frame::new(FrameType::CircuitCell, vec![3]),
+        ] {
+            a.send(&b.our_id(), frame).await.unwrap();
+        }
+        let mut gossip = Vec::new();
+        for _ in 0..3 {
+            match next_event(&mut b_events).await {
+                TransportEvent::FrameReceived { from, frame } => {
+                    assert_eq!(from, a.our_id());
+                    if frame.frame_type == FrameType::CircuitCell {
+                        assert_eq!(frame.payload.len(), 64);
+                    } else {
+                        gossip.push(frame.frame_type);
+                    }
+                }
+                other => panic!("unexpected event {:?}", other),
+            }
+        }
+        assert_eq!(gossip, vec![FrameType::IHave, FrameType::IWant]);
+
+        // The accepting side can answer over the same connection
+        b.send(&a.our_id(), Frame::pong()).await.unwrap();
+        assert!(matches!(
+            next_event(&mut a_events).await,
+            TransportEvent::FrameReceived { frame, .. } if frame.frame_type == FrameType::Pong
+        ));
+
+        a.shutdown();
+        assert!(matches!(
+            next_event(&mut b_events).await,
+            TransportEvent::PeerDisconnected(id) if id == a.our_id()
+        ));
+        assert!(!b.is_connected(&a.our_id()));
+    }
+
+    #[tokio::test]
+    async fn test_wrong_identity_rejected() {
+        let (a, _, _a_events) = start(1).await;
+        let (b, b_addr, _b_events) = start(2).await;
+
+        // B's address, but a different expected key
+        let impostor = PeerInfo::new([3; 32], vec![b_addr]);
+        assert!(a.connect(&impostor).await.is_err());
+        assert!(!a.is_connected(&impostor.id));
+        assert!(!a.is_connected(&b.our_id()));
+    }
+}